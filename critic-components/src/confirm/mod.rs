@@ -0,0 +1,112 @@
+//! A modal dialog asking the user to confirm a destructive action
+//!
+//! While the dialog is open, Tab only moves between its two buttons and Escape cancels. Escape
+//! does not reach the listeners on the document (e.g. the one closing the help overlay), so one
+//! press only closes the dialog.
+
+use leptos::{ev::KeyboardEvent, html::Button, prelude::*};
+
+use crate::DEFAULT_BUTTON_CLASSES;
+
+/// Ask whether `message` should really be done while `open` is set
+///
+/// `open` is reset when the user decides. `on_confirm` runs when they confirm, `on_cancel` when
+/// they cancel (by button, Escape or clicking next to the dialog). Cancel is focused initially, so
+/// pressing Enter right away does no harm.
+#[component]
+pub fn ConfirmDialog(
+    open: RwSignal<bool>,
+    #[prop(into)] message: String,
+    #[prop(into, default = "Confirm".to_string())] confirm_label: String,
+    #[prop(into, default = "Cancel".to_string())] cancel_label: String,
+    #[prop(into)] on_confirm: Callback<()>,
+    #[prop(into, optional)] on_cancel: Option<Callback<()>>,
+) -> impl IntoView {
+    let cancel_ref = NodeRef::<Button>::new();
+    let confirm_ref = NodeRef::<Button>::new();
+    // which of the two buttons has the focus
+    let confirm_focused = RwSignal::new(false);
+
+    let cancel = move || {
+        open.set(false);
+        if let Some(on_cancel) = on_cancel {
+            on_cancel.run(());
+        };
+    };
+    let confirm = move || {
+        open.set(false);
+        on_confirm.run(());
+    };
+
+    // focus the dialog as soon as it is shown
+    Effect::new(move || {
+        if open.get() {
+            if let Some(button) = cancel_ref.get() {
+                let _ = button.focus();
+            };
+        };
+    });
+
+    let on_keydown = move |ev: KeyboardEvent| match ev.key().as_str() {
+        "Escape" => {
+            ev.prevent_default();
+            ev.stop_propagation();
+            cancel();
+        }
+        // keep the focus inside the dialog
+        "Tab" => {
+            ev.prevent_default();
+            let target = if confirm_focused.get_untracked() {
+                cancel_ref
+            } else {
+                confirm_ref
+            };
+            if let Some(button) = target.get_untracked() {
+                let _ = button.focus();
+            };
+        }
+        _ => {}
+    };
+
+    let message = StoredValue::new(message);
+    let confirm_label = StoredValue::new(confirm_label);
+    let cancel_label = StoredValue::new(cancel_label);
+    view! {
+        <Show when=move || open.get()>
+            <div
+                class="fixed inset-0 z-20 flex items-center justify-center bg-slate-900/80"
+                on:click=move |_| cancel()
+            >
+                <div
+                    class="m-4 max-w-lg rounded-lg border border-slate-500 bg-slate-800 p-4 text-slate-50"
+                    role="alertdialog"
+                    aria-modal="true"
+                    on:click=|ev| ev.stop_propagation()
+                    on:keydown=on_keydown
+                >
+                    <p class="mb-4">{message.get_value()}</p>
+                    <div class="flex justify-around">
+                        <button
+                            node_ref=cancel_ref
+                            class=format!("w-2/5 {DEFAULT_BUTTON_CLASSES}")
+                            type="button"
+                            on:focus=move |_| confirm_focused.set(false)
+                            on:click=move |_| cancel()
+                        >
+                            {cancel_label.get_value()}
+                        </button>
+                        <button
+                            node_ref=confirm_ref
+                            class=format!("w-2/5 bg-red-700/60 {DEFAULT_BUTTON_CLASSES}")
+                            type="button"
+                            on:focus=move |_| confirm_focused.set(true)
+                            on:click=move |_| confirm()
+                        >
+                            {confirm_label.get_value()}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,147 @@
+//! Checking the anchors of a transcription before it is saved
+//!
+//! Anchor ids look like `A_V_<shorthand>_<raw id>`. The raw id is what the user types in the
+//! anchor block, so it has to be checked for characters that would break this format. Every id may
+//! only be used once per page.
+
+use std::collections::HashSet;
+
+use critic_format::streamed::Block;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Everything before the shorthand of the versification scheme
+const ANCHOR_ID_PREFIX: &str = "A_V_";
+
+/// The most anchors [`verse_anchor_ids`] creates at once
+///
+/// Verse ranges are typed in by hand, so a typo like 100000 instead of 1000 would otherwise create
+/// a hundred thousand blocks.
+pub const MAX_SEEDED_VERSES: usize = 500;
+
+/// What is wrong with an anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorProblemKind {
+    /// An earlier anchor already has the same id
+    Duplicate,
+    /// The id is not `A_V_<shorthand>_<raw id>`
+    Malformed,
+    /// No raw id was entered
+    EmptyRawId,
+    /// The raw id contains something other than ASCII letters, digits, `.` and `-`
+    InvalidCharacters,
+}
+
+/// An anchor that may not be saved like this
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorProblem {
+    /// The position of the block in the transcription (0-based)
+    pub block: usize,
+    pub anchor_id: String,
+    pub kind: AnchorProblemKind,
+}
+impl core::fmt::Display for AnchorProblem {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let block = self.block + 1;
+        let id = &self.anchor_id;
+        match self.kind {
+            AnchorProblemKind::Duplicate => {
+                write!(f, "Block {block}: the anchor {id} is used more than once.")
+            }
+            AnchorProblemKind::Malformed => {
+                write!(
+                    f,
+                    "Block {block}: the anchor {id} does not look like {ANCHOR_ID_PREFIX}<scheme>_<id>."
+                )
+            }
+            AnchorProblemKind::EmptyRawId => {
+                write!(f, "Block {block}: the anchor {id} has no id.")
+            }
+            AnchorProblemKind::InvalidCharacters => {
+                write!(
+                    f,
+                    "Block {block}: the id of anchor {id} may only contain ASCII letters, digits, '.' and '-'."
+                )
+            }
+        }
+    }
+}
+
+/// Check a single anchor id, ignoring duplicates
+fn check_anchor_id(anchor_id: &str) -> Option<AnchorProblemKind> {
+    let Some((shorthand, raw_id)) = anchor_id
+        .strip_prefix(ANCHOR_ID_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+    else {
+        return Some(AnchorProblemKind::Malformed);
+    };
+    if shorthand.is_empty() || !shorthand.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some(AnchorProblemKind::Malformed);
+    };
+    if raw_id.is_empty() {
+        return Some(AnchorProblemKind::EmptyRawId);
+    };
+    if !raw_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    {
+        return Some(AnchorProblemKind::InvalidCharacters);
+    };
+    None
+}
+
+/// The anchor ids for every verse from `start` to `end` (both included) in the scheme `shorthand`
+///
+/// The verse range of a page (`verse_start`/`verse_end`) is a plain number counting the verses of
+/// the whole text, not book, chapter and verse. That number is used as the raw id, so verse 1234 in
+/// the scheme `P` becomes `A_V_P_1234`. At most [`MAX_SEEDED_VERSES`] ids are returned.
+pub fn verse_anchor_ids(shorthand: &str, start: i64, end: i64) -> Vec<String> {
+    (start..=end)
+        .take(MAX_SEEDED_VERSES)
+        .map(|verse| format!("{ANCHOR_ID_PREFIX}{shorthand}_{verse}"))
+        .collect()
+}
+
+/// Find all anchors in `blocks` that are malformed or reuse the id of an earlier anchor
+///
+/// The problems are in the order of the blocks.
+pub fn find_anchor_problems(blocks: &[Block]) -> Vec<AnchorProblem> {
+    let mut seen = HashSet::new();
+    let mut problems = Vec::new();
+    for (block, anchor) in blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, block)| match block {
+            Block::Anchor(anchor) => Some((idx, anchor)),
+            _ => None,
+        })
+    {
+        let kind = check_anchor_id(&anchor.anchor_id).or_else(|| {
+            (!seen.insert(anchor.anchor_id.as_str())).then_some(AnchorProblemKind::Duplicate)
+        });
+        if let Some(kind) = kind {
+            problems.push(AnchorProblem {
+                block,
+                anchor_id: anchor.anchor_id.clone(),
+                kind,
+            });
+        };
+    }
+    problems
+}
+
+/// All problems in one message, or None if there are none
+pub fn describe_anchor_problems(problems: &[AnchorProblem]) -> Option<String> {
+    if problems.is_empty() {
+        return None;
+    };
+    Some(
+        problems
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
@@ -0,0 +1,116 @@
+use critic_format::streamed::{Anchor, Block, BlockType, FromTypeLangAndContent};
+
+use super::{
+    describe_anchor_problems, find_anchor_problems, verse_anchor_ids, AnchorProblem,
+    AnchorProblemKind, MAX_SEEDED_VERSES,
+};
+
+fn anchor(anchor_id: &str) -> Block {
+    Block::Anchor(Anchor {
+        anchor_id: anchor_id.to_string(),
+        anchor_type: "Masoretic".to_string(),
+    })
+}
+
+fn text(content: &str) -> Block {
+    Block::from_type_lang_and_content(BlockType::Text, "hbo-Hebr".to_string(), content.to_string())
+}
+
+#[test]
+fn valid_anchors_have_no_problems() {
+    let blocks = vec![
+        anchor("A_V_M_1.1"),
+        text("בְּרֵאשִׁית"),
+        anchor("A_V_M_1.2"),
+        // the same raw id in another scheme is a different anchor
+        anchor("A_V_LXX_1.2"),
+        anchor("A_V_M_1-3a"),
+    ];
+    assert_eq!(find_anchor_problems(&blocks), vec![]);
+    assert_eq!(describe_anchor_problems(&[]), None);
+}
+
+#[test]
+fn duplicate_anchors_are_flagged_after_the_first() {
+    let blocks = vec![
+        anchor("A_V_M_1.1"),
+        text("a"),
+        anchor("A_V_M_1.1"),
+        anchor("A_V_M_1.2"),
+        anchor("A_V_M_1.1"),
+    ];
+    assert_eq!(
+        find_anchor_problems(&blocks),
+        vec![
+            AnchorProblem {
+                block: 2,
+                anchor_id: "A_V_M_1.1".to_string(),
+                kind: AnchorProblemKind::Duplicate,
+            },
+            AnchorProblem {
+                block: 4,
+                anchor_id: "A_V_M_1.1".to_string(),
+                kind: AnchorProblemKind::Duplicate,
+            },
+        ]
+    );
+}
+
+#[test]
+fn malformed_anchors_are_flagged() {
+    let kinds = |blocks: Vec<Block>| {
+        find_anchor_problems(&blocks)
+            .into_iter()
+            .map(|p| (p.block, p.kind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        kinds(vec![
+            anchor("A_V_M_"),
+            anchor("A_V_M_1 1"),
+            anchor("A_V_M_1_1"),
+            anchor("verse-1"),
+            anchor("A_V__1"),
+            anchor(""),
+        ]),
+        vec![
+            (0, AnchorProblemKind::EmptyRawId),
+            (1, AnchorProblemKind::InvalidCharacters),
+            (2, AnchorProblemKind::InvalidCharacters),
+            (3, AnchorProblemKind::Malformed),
+            (4, AnchorProblemKind::Malformed),
+            (5, AnchorProblemKind::Malformed),
+        ]
+    );
+    // the same empty id twice is reported as empty both times
+    assert_eq!(
+        kinds(vec![anchor("A_V_M_"), anchor("A_V_M_")]),
+        vec![
+            (0, AnchorProblemKind::EmptyRawId),
+            (1, AnchorProblemKind::EmptyRawId)
+        ]
+    );
+}
+
+#[test]
+fn problems_name_the_block() {
+    let problems = find_anchor_problems(&[anchor("A_V_M_1"), anchor("A_V_M_1")]);
+    assert_eq!(
+        describe_anchor_problems(&problems),
+        Some("Block 2: the anchor A_V_M_1 is used more than once.".to_string())
+    );
+}
+
+#[test]
+fn verse_ranges_expand_to_valid_anchor_ids() {
+    let ids = verse_anchor_ids("P", 1233, 1235);
+    assert_eq!(ids, vec!["A_V_P_1233", "A_V_P_1234", "A_V_P_1235"]);
+    let anchors = ids.iter().map(|id| anchor(id)).collect::<Vec<_>>();
+    assert_eq!(find_anchor_problems(&anchors), vec![]);
+}
+
+#[test]
+fn verse_ranges_are_capped() {
+    assert_eq!(verse_anchor_ids("P", 5, 4), Vec::<String>::new());
+    assert_eq!(verse_anchor_ids("P", 1, 1_000_000).len(), MAX_SEEDED_VERSES);
+}
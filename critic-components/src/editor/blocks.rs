@@ -10,8 +10,8 @@ use critic_format::streamed::{
 use leptos::{html::Textarea, prelude::*};
 use serde::{Deserialize, Serialize};
 
-use super::{UnReStack, UnReStep};
-use critic_shared::VersificationScheme;
+use super::{spellcheck::spellcheck_attribute, UnReStack, UnReStep};
+use critic_shared::{lang::text_direction, VersificationScheme, STATIC_VERSIFICATION_SCHEMES};
 
 use crate::{
     accordion::{Accordion, Align, Item, List},
@@ -30,6 +30,60 @@ pub struct EditorBlock {
     pub focus_on_load: bool,
 }
 
+/// The largest extent a lacuna or space may have
+pub const MAX_EXTENT: u32 = 10_000;
+
+/// Reasons an extent typed into the editor is not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtentError {
+    NotANumber,
+    NotPositive,
+    TooLarge,
+}
+impl core::fmt::Display for ExtentError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::NotANumber => {
+                write!(f, "The extent must be a whole number")
+            }
+            Self::NotPositive => {
+                write!(f, "The extent must be at least 1")
+            }
+            Self::TooLarge => {
+                write!(f, "The extent must be at most {MAX_EXTENT}")
+            }
+        }
+    }
+}
+impl core::error::Error for ExtentError {}
+
+/// Parse the extent of a lacuna or space, which must be in 1..=[`MAX_EXTENT`]
+pub fn parse_extent<T: TryFrom<u32>>(value: &str) -> Result<T, ExtentError> {
+    let n = match value.trim().parse::<i64>() {
+        Ok(n) => n,
+        Err(e) => {
+            return Err(match e.kind() {
+                core::num::IntErrorKind::PosOverflow => ExtentError::TooLarge,
+                core::num::IntErrorKind::NegOverflow => ExtentError::NotPositive,
+                _ => ExtentError::NotANumber,
+            });
+        }
+    };
+    if n < 1 {
+        return Err(ExtentError::NotPositive);
+    };
+    u32::try_from(n)
+        .ok()
+        .filter(|&n| n <= MAX_EXTENT)
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or(ExtentError::TooLarge)
+}
+
+/// Help text for the extent fields
+fn extent_help() -> String {
+    format!("A whole number from 1 to {MAX_EXTENT}")
+}
+
 fn inner_text_view(
     undo_stack: RwSignal<UnReStack>,
     paragraph: RwSignal<Paragraph>,
@@ -43,11 +97,13 @@ fn inner_text_view(
             <div>
                 <p class="font-light text-xs">"Raw Text: "</p>
                 <textarea
-                    class="text-right font-serif text-3xl bg-yellow-100 text-black font-mono"
+                    class="text-start font-serif text-3xl bg-yellow-100 text-black font-mono"
                     id=format!("block-input-{id}")
                     node_ref=focus_element
                     autocomplete="false"
-                    spellcheck="false"
+                    spellcheck=spellcheck_attribute(move || paragraph.read().lang.clone())
+                    lang=move || paragraph.read().lang.clone()
+                    dir=move || text_direction(&paragraph.read().lang)
                     rows=TEXTAREA_DEFAULT_ROWS
                     cols=TEXTAREA_DEFAULT_COLS * 2
                     // reactive, so undo/redo actions can change the view
@@ -124,6 +180,7 @@ fn inner_lacuna_view(
     // `lacuna` itself will contain the displayed setting, `current_lacuna`
     // will contain the value from the last savepoint (of the Undo-stack)
     let current_lacuna = RwSignal::new(lacuna.get_untracked());
+    let extent_error = RwSignal::new(None::<ExtentError>);
     let config_expanded = signal(false);
     view! {
         <div class="flex justify-between">
@@ -166,17 +223,34 @@ fn inner_lacuna_view(
                             prop:value=move || lacuna.read().n
                             class="text-sm"
                             placeholder="n"
+                            title=extent_help()
                             autocomplete="false"
                             spellcheck="false"
                             id=format!("block-input-{id}-extent")
                             on:input:target=move |ev| {
                                 let x = ev.target().value();
                                 if x.is_empty() {} else {
-                                    lacuna.write().n = ev.target().value().parse().unwrap_or(1);
+                                    match parse_extent(&x) {
+                                        Ok(n) => {
+                                            lacuna.write().n = n;
+                                            extent_error.set(None);
+                                        }
+                                        Err(e) => extent_error.set(Some(e)),
+                                    }
                                 }
                             }
                             on:change:target=move |ev| {
-                                lacuna.write().n = ev.target().value().parse().unwrap_or(1);
+                                match parse_extent(&ev.target().value()) {
+                                    Ok(n) => {
+                                        lacuna.write().n = n;
+                                        extent_error.set(None);
+                                    }
+                                    Err(e) => {
+                                        // keep the last valid extent, the error tells the user
+                                        extent_error.set(Some(e));
+                                        return;
+                                    }
+                                };
                                 undo_stack
                                     .write()
                                     .push_undo(
@@ -189,6 +263,14 @@ fn inner_lacuna_view(
                                 current_lacuna.write().n = lacuna.get_untracked().n;
                             }
                         />
+                        <span class="font-light text-xs">{extent_help()}</span>
+                        {move || {
+                            extent_error
+                                .get()
+                                .map(|e| {
+                                    view! { <p class="text-xs text-red-500">{e.to_string()}</p> }
+                                })
+                        }}
                     </Item>
                     <Item align=Align::Left>
                         <span class="font-light text-xs">"Unit of Extent: "</span>
@@ -281,12 +363,14 @@ fn inner_uncertain_view(
                 <br />
                 // proposed (reconstructed) content
                 <textarea
-                    class="text-right font-serif text-3xl bg-orange-100 text-black font-mono"
+                    class="text-start font-serif text-3xl bg-orange-100 text-black font-mono"
                     id=format!("block-input-{id}")
                     node_ref=focus_element
                     prop:value=move || uncertain.read().content.clone()
                     autocomplete="false"
-                    spellcheck="false"
+                    spellcheck=spellcheck_attribute(move || uncertain.read().lang.clone())
+                    lang=move || uncertain.read().lang.clone()
+                    dir=move || text_direction(&uncertain.read().lang)
                     rows=TEXTAREA_DEFAULT_ROWS
                     cols=TEXTAREA_DEFAULT_COLS
                     on:input:target=move |ev| {
@@ -389,6 +473,7 @@ fn inner_space_view(
     id: usize,
 ) -> impl IntoView {
     let current_space = RwSignal::new(space.get_untracked());
+    let extent_error = RwSignal::new(None::<ExtentError>);
     view! {
         <div class="flex justify-between">
             <span class="font-light text-xs">"Space: "</span>
@@ -397,17 +482,34 @@ fn inner_space_view(
                 prop:value=move || space.read().quantity
                 class="text-sm"
                 placeholder="extent"
+                title=extent_help()
                 autocomplete="false"
                 spellcheck="false"
                 id=format!("block-input-{id}-extent")
                 on:input:target=move |ev| {
                     let x = ev.target().value();
                     if x.is_empty() {} else {
-                        space.write().quantity = ev.target().value().parse().unwrap_or(1);
+                        match parse_extent(&x) {
+                            Ok(n) => {
+                                space.write().quantity = n;
+                                extent_error.set(None);
+                            }
+                            Err(e) => extent_error.set(Some(e)),
+                        }
                     }
                 }
                 on:change:target=move |ev| {
-                    space.write().quantity = ev.target().value().parse().unwrap_or(1);
+                    match parse_extent(&ev.target().value()) {
+                        Ok(n) => {
+                            space.write().quantity = n;
+                            extent_error.set(None);
+                        }
+                        Err(e) => {
+                            // keep the last valid extent, the error tells the user
+                            extent_error.set(Some(e));
+                            return;
+                        }
+                    };
                     undo_stack
                         .write()
                         .push_undo(
@@ -420,6 +522,11 @@ fn inner_space_view(
                     current_space.write().quantity = space.get_untracked().quantity;
                 }
             />
+            {move || {
+                extent_error
+                    .get()
+                    .map(|e| view! { <span class="text-xs text-red-500">{e.to_string()}</span> })
+            }}
             <span class="font-light text-xs">"Unit of Extent: "</span>
             <select
                 id=format!("block-input-{id}-unit")
@@ -525,10 +632,10 @@ fn inner_anchor_view(
     let raw_id = RwSignal::new(if !anchor.read_untracked().anchor_id.starts_with("A_V_") {
         String::default()
     } else {
+        // A_V_<shorthand>_<raw id>
         anchor.read_untracked().anchor_id[4..]
-            .split("_")
-            .nth(2)
-            .map_or(String::default(), |substr| substr.to_string())
+            .split_once('_')
+            .map_or(String::default(), |(_, raw_id)| raw_id.to_string())
     });
 
     // The shorthand associated to the currently selected versification scheme, which is needed
@@ -595,7 +702,11 @@ fn inner_anchor_view(
                     <Item align=Align::Left>
                         <span class="font-light text-xs">"Versification Scheme: "</span>
                         <select
-                            prop:value=move || anchor.read().anchor_type.clone()
+                            prop:value=move || {
+                                // set the value again once all options are present
+                                versification_schemes_res.track();
+                                anchor.read().anchor_type.clone()
+                            }
                             on:input:target=move |ev| {
                                 anchor.write().anchor_type = ev.target().value();
                             }
@@ -621,17 +732,18 @@ fn inner_anchor_view(
                                     .anchor_type;
                             }
                         >
-                            // these two schemes are static and can always be show.
-                            // please change if you change the static schemes in
-                            // `202507071848_versification_scheme.up.sql`
+                            // the static schemes can always be shown, all others (including
+                            // the ones added by maintainers) once the server responds
                             <Suspense fallback=move || {
-                                view! {
-                                    <option value="Present">Present</option>
-                                    <option value="Common">Common</option>
-                                }
+                                STATIC_VERSIFICATION_SCHEMES
+                                    .iter()
+                                    .map(|full_name| {
+                                        view! { <option value=*full_name>{*full_name}</option> }
+                                    })
+                                    .collect::<Vec<_>>()
                             }>
-                                {versification_schemes_res
-                                    .get_untracked()
+                                {move || versification_schemes_res
+                                    .get()
                                     .map(|scheme_res| match scheme_res {
                                         Ok(schemes) => {
                                             leptos::either::Either::Left(
@@ -683,11 +795,13 @@ fn inner_abbreviation_view(
             <div>
                 // surface form
                 <textarea
-                    class="text-right font-serif text-3xl bg-orange-100 text-black font-mono"
+                    class="text-start font-serif text-3xl bg-orange-100 text-black font-mono"
                     node_ref=focus_element
                     prop:value=move || abbreviation.read().surface.clone()
                     autocomplete="false"
-                    spellcheck="false"
+                    spellcheck=spellcheck_attribute(move || abbreviation.read().surface_lang.clone())
+                    lang=move || abbreviation.read().surface_lang.clone()
+                    dir=move || text_direction(&abbreviation.read().surface_lang)
                     id=format!("block-input-{id}-surface")
                     rows=1
                     cols=TEXTAREA_DEFAULT_COLS
@@ -754,12 +868,14 @@ fn inner_abbreviation_view(
             <div>
                 // expanded form
                 <textarea
-                    class="text-right font-serif text-3xl bg-orange-100 text-black font-mono"
+                    class="text-start font-serif text-3xl bg-orange-100 text-black font-mono"
                     id=format!("block-input-{id}")
                     node_ref=focus_element
                     prop:value=move || abbreviation.read().expansion.clone()
                     autocomplete="false"
-                    spellcheck="false"
+                    spellcheck=spellcheck_attribute(move || abbreviation.read().expansion_lang.clone())
+                    lang=move || abbreviation.read().expansion_lang.clone()
+                    dir=move || text_direction(&abbreviation.read().expansion_lang)
                     rows=1
                     cols=TEXTAREA_DEFAULT_COLS
                     on:input:target=move |ev| {
@@ -879,12 +995,14 @@ fn inner_correction_view(
                         <span class="font-light text-xs">"Version "{dyn_v.0}":"</span>
                         <div>
                             <textarea
-                                class="text-right font-serif text-3xl bg-orange-100 text-black font-mono"
+                                class="text-start font-serif text-3xl bg-orange-100 text-black font-mono"
                                 id=format!("block-input-{id}-v-{}", dyn_v.0)
                                 node_ref=focus_element
                                 prop:value=move || memo_val.read().content.clone()
                                 autocomplete="false"
-                                spellcheck="false"
+                                spellcheck=spellcheck_attribute(move || memo_val.read().lang.clone())
+                                lang=move || memo_val.read().lang.clone()
+                                dir=move || text_direction(&memo_val.read().lang)
                                 rows=1
                                 cols=TEXTAREA_DEFAULT_COLS
                                 on:input:target=move |ev| {
@@ -1256,6 +1374,20 @@ pub enum InnerBlock {
     Space(RwSignal<Space>),
 }
 impl InnerBlock {
+    /// Subscribe the running effect or memo to changes of the data in this block
+    pub fn track(&self) {
+        match self {
+            Self::Text(x) => x.track(),
+            Self::Break(x) => x.track(),
+            Self::Lacuna(x) => x.track(),
+            Self::Space(x) => x.track(),
+            Self::Uncertain(x) => x.track(),
+            Self::Anchor(x) => x.track(),
+            Self::Correction(x) => x.track(),
+            Self::Abbreviation(x) => x.track(),
+        }
+    }
+
     /// overwrite own data with that given from new_block, but only if the types are the same
     fn overwrite_with(&mut self, new_block: Block) {
         match self {
@@ -1435,15 +1567,33 @@ impl InnerBlock {
         if let Some(content) = before_part {
             res.push((self.clone_with_new_content(content.to_owned()), false));
         };
-        res.push((
+        let new_block = if matches!(new_block_type, BlockType::Correction) {
+            // the selection is what was written originally, and the starting point for the
+            // corrected version
+            let lang = self.lang().unwrap_or_default();
+            InnerBlock::Correction(RwSignal::new(Correction {
+                versions: vec![
+                    Version {
+                        lang: lang.clone(),
+                        hand: None,
+                        content: new_part.to_owned(),
+                    },
+                    Version {
+                        lang,
+                        hand: None,
+                        content: new_part.to_owned(),
+                    },
+                ],
+            }))
+        } else {
             InnerBlock::from_type_lang_and_content(
                 new_block_type,
                 self.lang().unwrap_or_default(),
                 new_part.to_owned(),
-            ),
-            // we do want to autofocus on the middle block
-            true,
-        ));
+            )
+        };
+        // we do want to autofocus on the middle block
+        res.push((new_block, true));
         if let Some(content) = after_part {
             res.push((self.clone_with_new_content(content.to_owned()), false));
         };
@@ -2,19 +2,33 @@
 //!
 //! This is the GUI-area and directly related APIs/server functions to save its data.
 
-use critic_format::streamed::BlockType;
+use critic_format::streamed::{Anchor, Block, BlockType};
+use critic_shared::VersificationScheme;
 use leptos::{ev::keydown, logging::log, prelude::*};
 use leptos_use::{use_document, use_event_listener};
 use undo::{UnReStack, UnReStep};
 use web_sys::{wasm_bindgen::JsCast, HtmlTextAreaElement};
 
+use crate::confirm::ConfirmDialog;
+
+pub mod anchors;
+
 pub mod blocks;
 use blocks::*;
 
+pub mod spellcheck;
+
+mod stats;
+use stats::EditorStats;
+
 mod undo;
 
 mod versification_scheme;
 
+// include tests
+#[cfg(test)]
+mod test;
+
 /// Add a new Block to the editor
 ///
 /// `blocks`: the blocks currently present
@@ -23,6 +37,9 @@ mod versification_scheme;
 /// `undo_stack`: add an undo-action for the block creation to this [`UnReStack`]
 /// `default_language`: use this language for the new block if its language cannot be determined
 /// automatically
+///
+/// The block is inserted at the block currently focused. If no block is focused (e.g. right after
+/// loading or after deleting the last block), it is appended to the end instead.
 fn new_node(
     blocks: RwSignal<Vec<EditorBlock>>,
     next_id: RwSignal<usize>,
@@ -30,28 +47,41 @@ fn new_node(
     undo_stack: RwSignal<UnReStack>,
     default_language: &str,
 ) {
+    let append = || {
+        let step = append_block(
+            &mut blocks.write(),
+            &mut next_id.write(),
+            block_type,
+            default_language,
+        );
+        undo_stack.write().push_undo(step);
+    };
     // first find out the id of the block currently selected
     let active_element = match use_document().active_element() {
         Some(el) => el,
         None => {
+            append();
             return;
         }
     };
     let primary_input = match active_element.dyn_into::<HtmlTextAreaElement>() {
         Ok(el) => el,
         Err(_) => {
+            append();
             return;
         }
     };
     // get the block index we are in right now
-    // break if this is an ID which we do not know
+    // append if this is an ID which we do not know
     if !primary_input.id().starts_with("block-input-") {
+        append();
         return;
     };
     let id_stripped = &primary_input.id()[12..];
     let id = match id_stripped.parse::<usize>() {
         Ok(el) => el,
         Err(_) => {
+            append();
             return;
         }
     };
@@ -126,18 +156,192 @@ fn new_node(
                 *next_id.write() += 1;
             }
         };
+    } else {
+        // the focused block is gone (e.g. it was just deleted)
+        append();
     };
 }
 
+/// Append a new empty block to the end of `blocks`
+///
+/// The block gets `next_id` (which is then incremented) and focus once it is shown. Returns the
+/// [`UnReStep`] undoing the insertion.
+fn append_block(
+    blocks: &mut Vec<EditorBlock>,
+    next_id: &mut usize,
+    block_type: BlockType,
+    default_language: &str,
+) -> UnReStep {
+    let new_block = EditorBlock::new(
+        *next_id,
+        block_type,
+        default_language.to_string(),
+        String::default(),
+        true,
+    );
+    *next_id += 1;
+    let physical_index = blocks.len();
+    blocks.push(new_block.clone());
+    UnReStep::new_insertion(physical_index, new_block)
+}
+
+/// `block` with all of its languages set to `lang`
+///
+/// Only text, uncertain, abbreviation and correction blocks have a language, all other blocks are
+/// returned unchanged.
+fn with_language(block: Block, lang: &str) -> Block {
+    match block {
+        Block::Text(mut paragraph) => {
+            paragraph.lang = lang.to_string();
+            Block::Text(paragraph)
+        }
+        Block::Uncertain(mut uncertain) => {
+            uncertain.lang = lang.to_string();
+            Block::Uncertain(uncertain)
+        }
+        Block::Abbreviation(mut abbreviation) => {
+            abbreviation.surface_lang = lang.to_string();
+            abbreviation.expansion_lang = lang.to_string();
+            Block::Abbreviation(abbreviation)
+        }
+        Block::Correction(mut correction) => {
+            for version in correction.versions.iter_mut() {
+                version.lang = lang.to_string();
+            }
+            Block::Correction(correction)
+        }
+        other => other,
+    }
+}
+
+/// Set the language of all `blocks` to `lang`
+///
+/// Returns the [`UnReStep`] restoring the previous language of every changed block, or None if no
+/// block changed.
+fn set_language_of_all_blocks(blocks: &mut [EditorBlock], lang: &str) -> Option<UnReStep> {
+    let mut changes = Vec::new();
+    for block in blocks.iter_mut() {
+        let old: Block = block.inner.clone().into();
+        let new = with_language(old.clone(), lang);
+        if new != old {
+            block.overwrite_inner(&old, &new)?;
+            changes.push((block.id(), old, new));
+        };
+    }
+    if changes.is_empty() {
+        None
+    } else {
+        Some(UnReStep::new_data_changes(changes))
+    }
+}
+
+/// Append an anchor followed by an empty text block for each of `anchor_ids` to `blocks`
+///
+/// The anchors are in the versification `scheme`. Anchors that are already in `blocks` are skipped,
+/// so seeding twice does not create duplicates. Returns the [`UnReStep`] removing all new blocks
+/// at once, or None if nothing was added.
+fn seed_verse_anchors(
+    blocks: &mut Vec<EditorBlock>,
+    next_id: &mut usize,
+    scheme: &VersificationScheme,
+    anchor_ids: Vec<String>,
+    lang: &str,
+) -> Option<UnReStep> {
+    let existing = blocks
+        .iter()
+        .filter_map(|b| match Block::from(b.inner.clone()) {
+            Block::Anchor(anchor) => Some(anchor.anchor_id),
+            _ => None,
+        })
+        .collect::<std::collections::HashSet<_>>();
+    let mut new_blocks = Vec::new();
+    for anchor_id in anchor_ids {
+        if existing.contains(&anchor_id) {
+            continue;
+        };
+        new_blocks.push(EditorBlock {
+            id: *next_id,
+            inner: Block::Anchor(Anchor {
+                anchor_id,
+                anchor_type: scheme.full_name.clone(),
+            })
+            .into(),
+            focus_on_load: false,
+        });
+        new_blocks.push(EditorBlock::new(
+            *next_id + 1,
+            BlockType::Text,
+            lang.to_string(),
+            String::default(),
+            false,
+        ));
+        *next_id += 2;
+    }
+    if new_blocks.is_empty() {
+        return None;
+    };
+    let physical_index = blocks.len();
+    blocks.extend(new_blocks.iter().cloned());
+    Some(UnReStep::new_block_change(
+        physical_index,
+        vec![],
+        new_blocks,
+    ))
+}
+
+/// Move the block with the logical `id` to `target_index`, shifting the blocks in between
+///
+/// Targets past the end move the block to the end. Returns the [`UnReStep`] undoing the move, or
+/// None if nothing was moved (unknown `id` or a block moved onto itself).
+fn move_block(blocks: &mut Vec<EditorBlock>, id: usize, target_index: usize) -> Option<UnReStep> {
+    let from = blocks.iter().position(|b| b.id() == id)?;
+    let to = target_index.min(blocks.len() - 1);
+    if from == to {
+        return None;
+    };
+    let block = blocks.remove(from);
+    blocks.insert(to, block);
+    Some(UnReStep::new_move(from, to))
+}
+
 /// The raw block-editor (i.e. not containing XML and such)
+///
+/// `language` is the language new blocks are created with. It can be changed in the editor
+/// without touching existing blocks.
+///
+/// If `verses` (first and last verse on the page) is given, the user can add anchors for these
+/// verses in one go.
 #[component]
 pub fn Editor(
     blocks: RwSignal<Vec<EditorBlock>>,
-    default_language: String,
+    language: RwSignal<String>,
     on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
+    verses: Option<(i64, i64)>,
 ) -> impl IntoView {
     let undo_stack = RwSignal::new(UnReStack::new());
 
+    // logical ID of the block that is currently being dragged
+    let dragged = RwSignal::new(None::<usize>);
+    // physical index the dragged block would be moved to when dropped now
+    let drop_target = RwSignal::new(None::<usize>);
+    let end_drag = move || {
+        dragged.set(None);
+        drop_target.set(None);
+    };
+    let drop_at = move |target_index: usize| {
+        if let Some(id) = dragged.get_untracked() {
+            let mut step = None;
+            blocks.maybe_update(|b| {
+                step = move_block(b, id, target_index);
+                step.is_some()
+            });
+            if let Some(step) = step {
+                undo_stack.write().push_undo(step);
+            };
+        };
+        end_drag();
+    };
+
     // logical ID (insertion order) of blocks, 1-based
     let next_id = RwSignal::new(blocks.read_untracked().len() + 1);
 
@@ -253,7 +457,6 @@ pub fn Editor(
     };
 
     // the keyboard-shortcut listener
-    let cloned_default_language = default_language.clone();
     let _cleanup = use_event_listener(use_document(), keydown, move |evt| {
         // <ctrl>-<alt>-S - Save
         if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 83 {
@@ -282,7 +485,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Text,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         // <ctrl>-<alt>-A (new Abbreviation)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 65 {
@@ -291,7 +494,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Abbreviation,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             )
         // <ctrl>-<alt>-U (new Uncertain)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 85 {
@@ -300,7 +503,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Uncertain,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             )
         // <ctrl>-<alt>-L (new Lacuna)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 76 {
@@ -309,7 +512,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Lacuna,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         // <ctrl>-<alt>-V (new Anchor/Verse)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 86 {
@@ -318,7 +521,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Anchor,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         // <ctrl>-<alt>-C (new Correction)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 67 {
@@ -327,7 +530,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Correction,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         // <ctrl>-<alt>-<space> (new Space)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 32 {
@@ -336,7 +539,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Space,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         // <ctrl>-<alt>-<ENTER> (new Break)
         } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 13 {
@@ -345,7 +548,7 @@ pub fn Editor(
                 next_id,
                 BlockType::Break,
                 undo_stack,
-                &cloned_default_language,
+                &language.read_untracked(),
             );
         };
     });
@@ -360,24 +563,85 @@ pub fn Editor(
         OnceResource::new(versification_scheme::get_versification_schemes());
     provide_context(versification_schemes);
 
+    // Only the text fields of the blocks read these, to decide whether to enable spellchecking
+    let spellcheck_languages: spellcheck::SpellcheckLanguages =
+        OnceResource::new(spellcheck::get_spellcheck_languages());
+    provide_context(spellcheck_languages);
+
     view! {
         <EditorEditButtons
-            default_language=default_language
+            language=language
             blocks=blocks
             next_id=next_id
             undo_stack=undo_stack
             on_save=on_save
         />
+        {verses
+            .map(|(start, end)| {
+                view! {
+                    <SeedVerseAnchors
+                        start=start
+                        end=end
+                        blocks=blocks
+                        next_id=next_id
+                        undo_stack=undo_stack
+                        language=language
+                        versification_schemes=versification_schemes
+                    />
+                }
+            })}
         <div id="editor-blocks" class="h-0 grow overflow-y-auto">
             <For
                 each=move || blocks.get()
                 key=|block| block.id()
                 children=move |outer_block| {
                     let outer_id = outer_block.id();
+                    let index_untracked = move || {
+                        blocks.read_untracked().iter().position(|b| b.id() == outer_id)
+                    };
                     view! {
                         <br />
-                        <div class="flex justify-between">
+                        <div
+                            class="flex justify-between"
+                            class=(
+                                "bg-sky-700/30",
+                                move || {
+                                    drop_target.get().is_some()
+                                        && drop_target.get() == physical_index_maybe(outer_id)
+                                },
+                            )
+                            on:dragover=move |ev| {
+                                if dragged.get_untracked().is_some() {
+                                    // allow dropping here
+                                    ev.prevent_default();
+                                    drop_target.set(index_untracked());
+                                }
+                            }
+                            on:drop=move |ev| {
+                                ev.prevent_default();
+                                if let Some(target_index) = index_untracked() {
+                                    drop_at(target_index);
+                                }
+                            }
+                        >
                             <span>
+                                <span
+                                    draggable="true"
+                                    class="cursor-grab select-none px-1"
+                                    title="Drag to move this block"
+                                    on:dragstart=move |ev| {
+                                        dragged.set(Some(outer_id));
+                                        if let Some(data_transfer) = ev.data_transfer() {
+                                            // firefox only starts dragging if there is some data
+                                            let _ = data_transfer
+                                                .set_data("text/plain", &outer_id.to_string());
+                                            data_transfer.set_effect_allowed("move");
+                                        }
+                                    }
+                                    on:dragend=move |_| end_drag()
+                                >
+                                    "⠿"
+                                </span>
                                 {move || move_up_button(outer_id)}
                                 {move || move_down_button(outer_id)}
                             </span>
@@ -421,7 +685,23 @@ pub fn Editor(
                     }
                 }
             ></For>
+            <Show when=move || dragged.get().is_some()>
+                <div
+                    class="m-2 border-2 border-dashed border-slate-500 p-2 text-center text-slate-400"
+                    on:dragover=move |ev| {
+                        ev.prevent_default();
+                        drop_target.set(None);
+                    }
+                    on:drop=move |ev| {
+                        ev.prevent_default();
+                        drop_at(usize::MAX);
+                    }
+                >
+                    "Drop here to move the block to the end"
+                </div>
+            </Show>
         </div>
+        <EditorStats blocks=blocks />
     }
 }
 
@@ -430,22 +710,14 @@ fn EditorEditButtons(
     blocks: RwSignal<Vec<EditorBlock>>,
     next_id: RwSignal<usize>,
     undo_stack: RwSignal<UnReStack>,
-    default_language: String,
+    language: RwSignal<String>,
     on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
 ) -> impl IntoView {
     const BUTTON_DEFAULT_CLASS: &str = "rounded-md bg-slate-700 p-1 hover:bg-slate-500";
+    let language_confirm_open = RwSignal::new(false);
 
-    // each on click handler needs to own the default language, so we arc-clone it :/
-    let default_language = std::sync::Arc::new(default_language);
-    let text_lang = default_language.clone();
-    let uncertain_lang = default_language.clone();
-    let lacuna_lang = default_language.clone();
-    let abbr_lang = default_language.clone();
-    let corr_lang = default_language.clone();
-    let space_lang = default_language.clone();
-    let break_lang = default_language.clone();
     view! {
-        <div class="grid grid-cols-11 gap-1 border-b border-slate-600 p-1" id="editor-tab-header">
+        <div class="grid grid-cols-12 gap-1 border-b border-slate-600 p-1" id="editor-tab-header">
             <span class="text-orange-400 flex flex-col justify-center">ctrl + alt +</span>
             <button class=BUTTON_DEFAULT_CLASS>
                 <span
@@ -495,7 +767,13 @@ fn EditorEditButtons(
                 class=BUTTON_DEFAULT_CLASS
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Text, undo_stack, &text_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Text,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="text-orange-400">"T: "</span>
@@ -505,7 +783,13 @@ fn EditorEditButtons(
                 class=BUTTON_DEFAULT_CLASS
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Uncertain, undo_stack, &uncertain_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Uncertain,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="text-orange-400">"U: "</span>
@@ -515,7 +799,13 @@ fn EditorEditButtons(
                 class=BUTTON_DEFAULT_CLASS
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Lacuna, undo_stack, &lacuna_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Lacuna,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="text-orange-400">"L: "</span>
@@ -525,7 +815,13 @@ fn EditorEditButtons(
                 class=BUTTON_DEFAULT_CLASS
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Abbreviation, undo_stack, &abbr_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Abbreviation,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="text-orange-400">"A: "</span>
@@ -535,7 +831,13 @@ fn EditorEditButtons(
                 class=BUTTON_DEFAULT_CLASS
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Correction, undo_stack, &corr_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Correction,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="text-orange-400">"C: "</span>
@@ -545,7 +847,13 @@ fn EditorEditButtons(
                 class="inline-flex rounded-md bg-slate-700 p-1 hover:bg-slate-500"
                 on:mousedown=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Space, undo_stack, &space_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Space,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="inline-flex text-orange-400">
@@ -570,7 +878,13 @@ fn EditorEditButtons(
                 class="inline-flex rounded-md bg-slate-700 p-1 hover:bg-slate-500"
                 on:click=move |ev| {
                     ev.prevent_default();
-                    new_node(blocks, next_id, BlockType::Break, undo_stack, &break_lang);
+                    new_node(
+                        blocks,
+                        next_id,
+                        BlockType::Break,
+                        undo_stack,
+                        &language.read_untracked(),
+                    );
                 }
             >
                 <span class="inline-flex text-orange-400">
@@ -592,6 +906,122 @@ fn EditorEditButtons(
                 </span>
                 enter
             </button>
+            <button
+                class=BUTTON_DEFAULT_CLASS
+                on:mousedown=move |ev| {
+                    ev.prevent_default();
+                    let step = append_block(
+                        &mut blocks.write(),
+                        &mut next_id.write(),
+                        BlockType::Text,
+                        &language.read_untracked(),
+                    );
+                    undo_stack.write().push_undo(step);
+                }
+            >
+                "text at end"
+            </button>
+        </div>
+        <div class="flex gap-2 border-b border-slate-600 p-1 text-sm">
+            <label for="editor-new-block-language" class="flex flex-col justify-center">
+                "Language of new blocks:"
+            </label>
+            <input
+                id="editor-new-block-language"
+                class="bg-slate-700 px-1"
+                placeholder="language"
+                autocomplete="false"
+                spellcheck="false"
+                prop:value=move || language.get()
+                on:change:target=move |ev| language.set(ev.target().value())
+            />
+            <button class=BUTTON_DEFAULT_CLASS on:click=move |_| language_confirm_open.set(true)>
+                "apply to all blocks"
+            </button>
+            <ConfirmDialog
+                open=language_confirm_open
+                message="Set the language of all blocks to the language of new blocks? This can be undone."
+                confirm_label="Apply to all"
+                on_confirm=move |()| {
+                    let step = set_language_of_all_blocks(
+                        &mut blocks.write(),
+                        &language.read_untracked(),
+                    );
+                    if let Some(step) = step {
+                        undo_stack.write().push_undo(step);
+                    };
+                }
+            />
+        </div>
+    }
+}
+
+/// Adds anchors for the verses `start` to `end` in a versification scheme the user picks
+///
+/// This never happens on its own, the user has to click the button. It can be undone as one step.
+#[component]
+fn SeedVerseAnchors(
+    start: i64,
+    end: i64,
+    blocks: RwSignal<Vec<EditorBlock>>,
+    next_id: RwSignal<usize>,
+    undo_stack: RwSignal<UnReStack>,
+    language: RwSignal<String>,
+    versification_schemes: OnceResource<Result<Vec<VersificationScheme>, ServerFnError>>,
+) -> impl IntoView {
+    let schemes = move || {
+        versification_schemes
+            .get()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+    };
+    // shorthand of the chosen scheme, the first one until the user picks another
+    let chosen = RwSignal::new(None::<String>);
+    let seed = move |_| {
+        let available = schemes();
+        let Some(scheme) = available
+            .iter()
+            .find(|s| Some(&s.shorthand) == chosen.read_untracked().as_ref())
+            .or(available.first())
+        else {
+            return;
+        };
+        let step = seed_verse_anchors(
+            &mut blocks.write(),
+            &mut next_id.write(),
+            scheme,
+            anchors::verse_anchor_ids(&scheme.shorthand, start, end),
+            &language.read_untracked(),
+        );
+        if let Some(step) = step {
+            undo_stack.write().push_undo(step);
+        };
+    };
+
+    view! {
+        <div class="flex gap-2 border-b border-slate-600 p-1 text-sm">
+            <label for="editor-seed-scheme" class="flex flex-col justify-center">
+                {format!("Add anchors for the verses {start} to {end} in")}
+            </label>
+            <select
+                id="editor-seed-scheme"
+                class="bg-slate-700 px-1"
+                on:change:target=move |ev| chosen.set(Some(ev.target().value()))
+            >
+                {move || {
+                    schemes()
+                        .into_iter()
+                        .map(|scheme| {
+                            view! {
+                                <option value=scheme.shorthand.clone()>{scheme.full_name}</option>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </select>
+            <button class="rounded-md bg-slate-700 p-1 hover:bg-slate-500" on:click=seed>
+                "add verse anchors"
+            </button>
         </div>
     }
 }
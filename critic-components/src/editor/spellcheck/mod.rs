@@ -0,0 +1,53 @@
+//! Enabling the browsers spellcheck for some languages
+//!
+//! Spellchecking is off for all text fields unless the language of the block is one of the
+//! `spellcheck_languages` from the `[editor]` section of the server config.
+
+use leptos::{prelude::*, server};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// The languages the server enabled spellchecking for, provided as context by the editor
+pub type SpellcheckLanguages = OnceResource<Result<Vec<String>, ServerFnError>>;
+
+#[server]
+pub async fn get_spellcheck_languages() -> Result<Vec<String>, ServerFnError> {
+    let config: std::sync::Arc<critic_server::config::Config> =
+        use_context().ok_or(ServerFnError::new("Unable to get config from context"))?;
+    Ok(config.editor.spellcheck_languages.clone())
+}
+
+/// Should text in `lang` be spellchecked?
+///
+/// A configured language matches itself and every more specific tag, so `en` matches `en-GB`,
+/// but not `eng`. Case is ignored, as it is for language tags in general.
+pub fn spellcheck_enabled(lang: &str, languages: &[String]) -> bool {
+    let lang = lang.trim();
+    !lang.is_empty()
+        && languages.iter().any(|configured| {
+            let configured = configured.trim();
+            !configured.is_empty()
+                && lang.len() >= configured.len()
+                && lang.is_char_boundary(configured.len())
+                && lang[..configured.len()].eq_ignore_ascii_case(configured)
+                && lang[configured.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c == '-')
+        })
+}
+
+/// The value of the `spellcheck` attribute for a text field whose language is given by `lang`
+///
+/// Stays `"false"` until the languages are loaded from the server, and if they cannot be loaded.
+pub fn spellcheck_attribute(
+    lang: impl Fn() -> String + Send + Sync + 'static,
+) -> impl Fn() -> &'static str + Send + Sync + 'static {
+    let languages = use_context::<SpellcheckLanguages>();
+    move || match languages.as_ref().and_then(|res| res.get()) {
+        Some(Ok(languages)) if spellcheck_enabled(&lang(), &languages) => "true",
+        _ => "false",
+    }
+}
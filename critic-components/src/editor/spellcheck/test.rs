@@ -0,0 +1,31 @@
+use super::spellcheck_enabled;
+
+fn languages(langs: &[&str]) -> Vec<String> {
+    langs.iter().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn nothing_is_spellchecked_by_default() {
+    assert!(!spellcheck_enabled("en", &[]));
+    assert!(!spellcheck_enabled("hbo-Hebr", &[]));
+}
+
+#[test]
+fn configured_languages_match_more_specific_tags() {
+    let langs = languages(&["en", "grc"]);
+    assert!(spellcheck_enabled("en", &langs));
+    assert!(spellcheck_enabled("en-GB", &langs));
+    assert!(spellcheck_enabled("EN-gb", &langs));
+    assert!(spellcheck_enabled(" grc ", &langs));
+    assert!(!spellcheck_enabled("eng", &langs));
+    assert!(!spellcheck_enabled("hbo", &langs));
+    assert!(!spellcheck_enabled("", &langs));
+}
+
+#[test]
+fn specific_configured_tags_do_not_match_general_ones() {
+    let langs = languages(&["en-GB", ""]);
+    assert!(spellcheck_enabled("en-GB", &langs));
+    assert!(!spellcheck_enabled("en", &langs));
+    assert!(!spellcheck_enabled("en-US", &langs));
+}
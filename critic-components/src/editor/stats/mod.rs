@@ -0,0 +1,119 @@
+//! Statistics about the transcription shown below the editor
+//!
+//! Counting blocks by type only depends on which blocks exist, while counting characters depends
+//! on the content of text blocks. These are kept in separate memos, so that typing only
+//! recomputes the text counts.
+
+use leptos::prelude::*;
+
+use super::blocks::{EditorBlock, InnerBlock};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Number of blocks of every type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCounts {
+    pub text: usize,
+    pub uncertain: usize,
+    pub lacuna: usize,
+    pub abbreviation: usize,
+    pub correction: usize,
+    pub anchor: usize,
+    pub space: usize,
+    pub line_or_column_break: usize,
+}
+impl BlockCounts {
+    /// Count the blocks without reading (or tracking) their content
+    pub fn from_blocks(blocks: &[EditorBlock]) -> Self {
+        let mut counts = Self::default();
+        for block in blocks {
+            match block.inner {
+                InnerBlock::Text(_) => counts.text += 1,
+                InnerBlock::Uncertain(_) => counts.uncertain += 1,
+                InnerBlock::Lacuna(_) => counts.lacuna += 1,
+                InnerBlock::Abbreviation(_) => counts.abbreviation += 1,
+                InnerBlock::Correction(_) => counts.correction += 1,
+                InnerBlock::Anchor(_) => counts.anchor += 1,
+                InnerBlock::Space(_) => counts.space += 1,
+                InnerBlock::Break(_) => counts.line_or_column_break += 1,
+            }
+        }
+        counts
+    }
+
+    /// Human-readable names and counts, leaving out types that do not occur
+    fn entries(&self) -> Vec<(&'static str, usize)> {
+        [
+            ("Text", self.text),
+            ("Uncertain", self.uncertain),
+            ("Lacuna", self.lacuna),
+            ("Abbreviation", self.abbreviation),
+            ("Correction", self.correction),
+            ("Verse", self.anchor),
+            ("Space", self.space),
+            ("Break", self.line_or_column_break),
+        ]
+        .into_iter()
+        .filter(|(_name, count)| *count > 0)
+        .collect()
+    }
+}
+
+/// Size of the text in `Text` and `Uncertain` blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextCounts {
+    /// Characters that are not whitespace
+    pub characters: usize,
+    /// Whitespace-separated words
+    pub words: usize,
+}
+impl TextCounts {
+    /// Count the text, reading (and tracking) only the content of `Text` and `Uncertain` blocks
+    pub fn from_blocks(blocks: &[EditorBlock]) -> Self {
+        let mut counts = Self::default();
+        let mut add = |content: &str| {
+            counts.characters += content.chars().filter(|c| !c.is_whitespace()).count();
+            counts.words += content.split_whitespace().count();
+        };
+        for block in blocks {
+            match &block.inner {
+                InnerBlock::Text(paragraph) => paragraph.with(|p| add(&p.content)),
+                InnerBlock::Uncertain(uncertain) => uncertain.with(|u| add(&u.content)),
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+/// A status bar with the size of the transcription and the number of blocks by type
+#[component]
+pub fn EditorStats(blocks: RwSignal<Vec<EditorBlock>>) -> impl IntoView {
+    let block_counts = Memo::new(move |_| blocks.with(|b| BlockCounts::from_blocks(b)));
+    let text_counts = Memo::new(move |_| blocks.with(|b| TextCounts::from_blocks(b)));
+
+    view! {
+        <div class="flex flex-wrap gap-4 border-t border-slate-600 p-1 text-sm text-slate-400">
+            <span>
+                {move || {
+                    let counts = text_counts.get();
+                    format!("{} characters, {} words", counts.characters, counts.words)
+                }}
+            </span>
+            <span>{move || format!("{} lacunae", block_counts.get().lacuna)}</span>
+            <span>
+                {move || {
+                    block_counts
+                        .get()
+                        .entries()
+                        .into_iter()
+                        .map(|(name, count)| format!("{name}: {count}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }}
+            </span>
+        </div>
+    }
+}
@@ -0,0 +1,64 @@
+use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+
+use super::{BlockCounts, TextCounts};
+use crate::editor::blocks::EditorBlock;
+
+fn blocks(blocks: Vec<(BlockType, &str)>) -> Vec<EditorBlock> {
+    blocks
+        .into_iter()
+        .enumerate()
+        .map(|(id, (block_type, content))| EditorBlock {
+            id,
+            inner: Block::from_type_lang_and_content(
+                block_type,
+                "hbo-Hebr".to_string(),
+                content.to_string(),
+            )
+            .into(),
+            focus_on_load: false,
+        })
+        .collect()
+}
+
+#[test]
+fn empty_editor() {
+    assert_eq!(BlockCounts::from_blocks(&[]), BlockCounts::default());
+    assert_eq!(TextCounts::from_blocks(&[]), TextCounts::default());
+    assert!(BlockCounts::default().entries().is_empty());
+}
+
+#[test]
+fn blocks_are_counted_by_type() {
+    let counts = BlockCounts::from_blocks(&blocks(vec![
+        (BlockType::Text, "a"),
+        (BlockType::Lacuna, ""),
+        (BlockType::Text, "b"),
+        (BlockType::Anchor, ""),
+        (BlockType::Break, ""),
+        (BlockType::Lacuna, ""),
+    ]));
+    assert_eq!(counts.text, 2);
+    assert_eq!(counts.lacuna, 2);
+    assert_eq!(counts.anchor, 1);
+    assert_eq!(counts.line_or_column_break, 1);
+    assert_eq!(
+        counts.entries(),
+        vec![("Text", 2), ("Lacuna", 2), ("Verse", 1), ("Break", 1)]
+    );
+}
+
+#[test]
+fn only_text_and_uncertain_content_is_counted() {
+    let counts = TextCounts::from_blocks(&blocks(vec![
+        (BlockType::Text, "בְּרֵאשִׁית בָּרָא"),
+        (BlockType::Uncertain, " ab  c "),
+        (BlockType::Abbreviation, "not counted"),
+        (BlockType::Correction, "not counted"),
+        (BlockType::Space, ""),
+    ]));
+    assert_eq!(counts.words, 4);
+    assert_eq!(
+        counts.characters,
+        "בְּרֵאשִׁיתבָּרָא".chars().count() + "abc".chars().count()
+    );
+}
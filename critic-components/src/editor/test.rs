@@ -0,0 +1,339 @@
+use critic_format::streamed::{
+    Abbreviation, Anchor, Block, BlockType, Correction, Paragraph, Version,
+};
+use critic_shared::VersificationScheme;
+
+use super::{
+    anchors::verse_anchor_ids,
+    append_block,
+    blocks::{parse_extent, ExtentError, MAX_EXTENT},
+    move_block, seed_verse_anchors, set_language_of_all_blocks, EditorBlock, UnReStack, UnReStep,
+};
+
+fn text(content: &str) -> Block {
+    Block::Text(Paragraph {
+        lang: "hbo-Hebr".to_string(),
+        content: content.to_string(),
+    })
+}
+
+fn dehydrate(blocks: &[EditorBlock]) -> Vec<Block> {
+    blocks.iter().map(|b| b.inner.clone().into()).collect()
+}
+
+#[test]
+fn correction_from_selection_has_two_versions() {
+    let block = EditorBlock::new(
+        1,
+        BlockType::Text,
+        "hbo-Hebr".to_string(),
+        "abcdef".to_string(),
+        false,
+    );
+    let mut next_id = 2;
+    let split = block.split_at_selection(2, 4, BlockType::Correction, &mut next_id);
+    let version = |content: &str| Version {
+        lang: "hbo-Hebr".to_string(),
+        hand: None,
+        content: content.to_string(),
+    };
+    assert_eq!(
+        dehydrate(&split),
+        vec![
+            text("ab"),
+            Block::Correction(Correction {
+                versions: vec![version("cd"), version("cd")],
+            }),
+            text("ef"),
+        ]
+    );
+    assert_eq!(next_id, 5);
+}
+
+#[test]
+fn three_way_split_can_be_undone_and_redone() {
+    let original = EditorBlock::new(
+        1,
+        BlockType::Text,
+        "hbo-Hebr".to_string(),
+        "abcdef".to_string(),
+        false,
+    );
+    let mut blocks = vec![original.clone()];
+    let mut next_id = 2;
+    let split = original.split_at_selection(2, 4, BlockType::Correction, &mut next_id);
+    let removed = blocks.splice(0..1, split.clone()).collect();
+    let mut stack = UnReStack::new();
+    stack.push_undo(UnReStep::new_block_change(0, removed, split));
+    let after_split = dehydrate(&blocks);
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), vec![text("abcdef")]);
+    stack.redo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), after_split);
+}
+
+#[test]
+fn without_focus_blocks_are_appended() {
+    let mut blocks = vec![];
+    let mut next_id = 1;
+    let mut stack = UnReStack::new();
+    stack.push_undo(append_block(
+        &mut blocks,
+        &mut next_id,
+        BlockType::Text,
+        "hbo-Hebr",
+    ));
+    stack.push_undo(append_block(
+        &mut blocks,
+        &mut next_id,
+        BlockType::Lacuna,
+        "hbo-Hebr",
+    ));
+    assert_eq!(next_id, 3);
+    assert_eq!(
+        blocks.iter().map(|b| b.id()).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(dehydrate(&blocks)[0], text(""));
+    assert!(matches!(dehydrate(&blocks)[1], Block::Lacuna(_)));
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), vec![text("")]);
+    stack.undo(&mut blocks).unwrap();
+    assert!(blocks.is_empty());
+    stack.redo(&mut blocks).unwrap();
+    stack.redo(&mut blocks).unwrap();
+    assert_eq!(blocks.len(), 2);
+}
+
+#[test]
+fn language_of_all_blocks_is_undone_per_block() {
+    let version = |lang: &str| Version {
+        lang: lang.to_string(),
+        hand: None,
+        content: "x".to_string(),
+    };
+    let original = vec![
+        text("a"),
+        Block::Text(Paragraph {
+            lang: "grc".to_string(),
+            content: "b".to_string(),
+        }),
+        Block::Abbreviation(Abbreviation {
+            surface_lang: "hbo-Hebr".to_string(),
+            expansion_lang: "arc".to_string(),
+            surface: "c".to_string(),
+            expansion: "cc".to_string(),
+        }),
+        Block::Correction(Correction {
+            versions: vec![version("hbo-Hebr"), version("grc")],
+        }),
+    ];
+    let mut blocks = original
+        .iter()
+        .enumerate()
+        .map(|(id, b)| EditorBlock {
+            id,
+            inner: b.clone().into(),
+            focus_on_load: false,
+        })
+        .collect::<Vec<_>>();
+    // a block without language
+    blocks.push(EditorBlock::new(
+        4,
+        BlockType::Lacuna,
+        "hbo-Hebr".to_string(),
+        String::default(),
+        false,
+    ));
+    let original = dehydrate(&blocks);
+    let mut stack = UnReStack::new();
+
+    stack.push_undo(set_language_of_all_blocks(&mut blocks, "grc").unwrap());
+    let changed = dehydrate(&blocks);
+    assert_eq!(
+        changed[0],
+        Block::Text(Paragraph {
+            lang: "grc".to_string(),
+            content: "a".to_string(),
+        })
+    );
+    assert_eq!(changed[1], original[1]);
+    assert!(matches!(&changed[2], Block::Abbreviation(a)
+        if a.surface_lang == "grc" && a.expansion_lang == "grc"));
+    assert_eq!(
+        changed[3],
+        Block::Correction(Correction {
+            versions: vec![version("grc"), version("grc")],
+        })
+    );
+    assert_eq!(changed[4], original[4]);
+    // nothing left to change
+    assert!(set_language_of_all_blocks(&mut blocks, "grc").is_none());
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), original);
+    stack.redo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), changed);
+}
+
+#[test]
+fn extents_are_validated_instead_of_replaced() {
+    assert_eq!(parse_extent::<u32>("3"), Ok(3));
+    assert_eq!(parse_extent::<i32>(" 12 "), Ok(12));
+    assert_eq!(parse_extent::<u32>(&MAX_EXTENT.to_string()), Ok(MAX_EXTENT));
+    assert_eq!(parse_extent::<u32>("0"), Err(ExtentError::NotPositive));
+    assert_eq!(parse_extent::<i32>("-4"), Err(ExtentError::NotPositive));
+    assert_eq!(
+        parse_extent::<u32>(&(MAX_EXTENT + 1).to_string()),
+        Err(ExtentError::TooLarge)
+    );
+    assert_eq!(
+        parse_extent::<u32>("99999999999999999999999"),
+        Err(ExtentError::TooLarge)
+    );
+    assert_eq!(parse_extent::<u32>("2.5"), Err(ExtentError::NotANumber));
+    assert_eq!(parse_extent::<u32>("three"), Err(ExtentError::NotANumber));
+    // a value that does not fit the target type is too large as well
+    assert_eq!(parse_extent::<u8>("300"), Err(ExtentError::TooLarge));
+}
+
+#[test]
+fn seeding_verse_anchors_is_one_undo_step() {
+    let scheme = VersificationScheme {
+        id: 1,
+        full_name: "Present".to_string(),
+        shorthand: "P".to_string(),
+    };
+    let anchor = |id: &str| {
+        Block::Anchor(Anchor {
+            anchor_id: id.to_string(),
+            anchor_type: "Present".to_string(),
+        })
+    };
+    let mut blocks = vec![EditorBlock {
+        id: 1,
+        inner: anchor("A_V_P_11").into(),
+        focus_on_load: false,
+    }];
+    let mut next_id = 2;
+    let mut stack = UnReStack::new();
+    let step = seed_verse_anchors(
+        &mut blocks,
+        &mut next_id,
+        &scheme,
+        verse_anchor_ids("P", 10, 12),
+        "hbo-Hebr",
+    )
+    .unwrap();
+    stack.push_undo(step);
+    // the anchor that was already there is not added again
+    assert_eq!(
+        dehydrate(&blocks),
+        vec![
+            anchor("A_V_P_11"),
+            anchor("A_V_P_10"),
+            text(""),
+            anchor("A_V_P_12"),
+            text(""),
+        ]
+    );
+    assert_eq!(next_id, 6);
+    assert!(seed_verse_anchors(
+        &mut blocks,
+        &mut next_id,
+        &scheme,
+        verse_anchor_ids("P", 10, 12),
+        "hbo-Hebr",
+    )
+    .is_none());
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(dehydrate(&blocks), vec![anchor("A_V_P_11")]);
+}
+
+fn ids(blocks: &[EditorBlock]) -> Vec<usize> {
+    blocks.iter().map(|b| b.id()).collect()
+}
+
+fn numbered_blocks(n: usize) -> Vec<EditorBlock> {
+    (1..=n)
+        .map(|id| {
+            EditorBlock::new(
+                id,
+                BlockType::Text,
+                "hbo-Hebr".to_string(),
+                id.to_string(),
+                false,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn moved_blocks_can_be_undone_and_redone() {
+    let mut blocks = numbered_blocks(4);
+    let mut stack = UnReStack::new();
+    // forward: the blocks in between shift up
+    stack.push_undo(move_block(&mut blocks, 1, 2).unwrap());
+    assert_eq!(ids(&blocks), vec![2, 3, 1, 4]);
+    // backward: the blocks in between shift down
+    stack.push_undo(move_block(&mut blocks, 4, 0).unwrap());
+    assert_eq!(ids(&blocks), vec![4, 2, 3, 1]);
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![2, 3, 1, 4]);
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![1, 2, 3, 4]);
+    stack.redo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![2, 3, 1, 4]);
+    stack.redo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![4, 2, 3, 1]);
+    // the content moves with the block
+    assert_eq!(dehydrate(&blocks)[0], text("4"));
+}
+
+#[test]
+fn blocks_are_moved_to_the_ends() {
+    let mut blocks = numbered_blocks(3);
+    let mut stack = UnReStack::new();
+    stack.push_undo(move_block(&mut blocks, 1, 2).unwrap());
+    assert_eq!(ids(&blocks), vec![2, 3, 1]);
+    stack.push_undo(move_block(&mut blocks, 1, 0).unwrap());
+    assert_eq!(ids(&blocks), vec![1, 2, 3]);
+    // targets past the end move the block to the end
+    stack.push_undo(move_block(&mut blocks, 2, 17).unwrap());
+    assert_eq!(ids(&blocks), vec![1, 3, 2]);
+
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![1, 2, 3]);
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![2, 3, 1]);
+    stack.undo(&mut blocks).unwrap();
+    assert_eq!(ids(&blocks), vec![1, 2, 3]);
+    assert!(!stack.can_undo());
+}
+
+#[test]
+fn moves_that_change_nothing_are_not_recorded() {
+    let mut blocks = numbered_blocks(3);
+    // onto itself
+    assert!(move_block(&mut blocks, 2, 1).is_none());
+    // the last block past the end
+    assert!(move_block(&mut blocks, 3, 5).is_none());
+    // a block that does not exist
+    assert!(move_block(&mut blocks, 7, 0).is_none());
+    assert_eq!(ids(&blocks), vec![1, 2, 3]);
+    assert!(move_block(&mut vec![], 1, 0).is_none());
+}
+
+#[test]
+fn moves_on_inconsistent_state_are_rejected() {
+    let mut blocks = numbered_blocks(3);
+    let mut stack = UnReStack::new();
+    stack.push_undo(move_block(&mut blocks, 1, 2).unwrap());
+    blocks.truncate(2);
+    assert!(stack.undo(&mut blocks).is_err());
+    assert_eq!(ids(&blocks), vec![2, 3]);
+}
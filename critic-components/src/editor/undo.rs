@@ -75,8 +75,12 @@ impl std::error::Error for ReplayError {}
 pub(super) enum UnReStep {
     /// Data inside a block has changed (on:change of an input field)
     DataChange(DataChange),
+    /// Data inside several blocks has changed at once (e.g. setting the language of all blocks)
+    DataChanges(Vec<DataChange>),
     /// Two Blocks were exchanged
     BlockSwap(BlockSwap),
+    /// A Block was moved to another position, shifting the blocks in between
+    BlockMove(BlockMove),
     /// Any number of consecutive Blocks was exchanged for any other number of any other
     /// consecutive blocks.
     ///
@@ -99,6 +103,14 @@ impl UnReStep {
             new_inner_block,
         ))
     }
+    pub fn new_data_changes(changes: Vec<(usize, Block, Block)>) -> Self {
+        Self::DataChanges(
+            changes
+                .into_iter()
+                .map(|(id, old, new)| DataChange::new(id, old, new))
+                .collect(),
+        )
+    }
     pub fn new_block_change(
         physical_index_of_change: usize,
         old_blocks: Vec<EditorBlock>,
@@ -119,12 +131,27 @@ impl UnReStep {
     pub fn new_swap(physical_index_1: usize, physical_index_2: usize) -> Self {
         Self::BlockSwap(BlockSwap::new(physical_index_1, physical_index_2))
     }
+    pub fn new_move(physical_index_from: usize, physical_index_to: usize) -> Self {
+        Self::BlockMove(BlockMove::new(physical_index_from, physical_index_to))
+    }
 }
 impl Replay for UnReStep {
     fn replay(&self, blocks: &mut Vec<EditorBlock>) -> Result<(), ReplayError> {
         match self {
             Self::DataChange(x) => x.replay(blocks),
+            Self::DataChanges(x) => {
+                // check everything first, so that a failed replay does not leave half the blocks
+                // changed
+                for change in x {
+                    change.check(blocks)?;
+                }
+                for change in x {
+                    change.replay(blocks)?;
+                }
+                Ok(())
+            }
             Self::BlockSwap(x) => x.replay(blocks),
+            Self::BlockMove(x) => x.replay(blocks),
             Self::BlockChange(x) => x.replay(blocks),
         }
     }
@@ -133,7 +160,9 @@ impl Invert for UnReStep {
     fn invert(self) -> Self {
         match self {
             Self::DataChange(x) => Self::DataChange(x.invert()),
+            Self::DataChanges(x) => Self::DataChanges(x.into_iter().map(Invert::invert).collect()),
             Self::BlockSwap(x) => Self::BlockSwap(x.invert()),
+            Self::BlockMove(x) => Self::BlockMove(x.invert()),
             Self::BlockChange(x) => Self::BlockChange(x.invert()),
         }
     }
@@ -215,6 +244,18 @@ impl DataChange {
             new_inner,
         }
     }
+
+    /// Make sure that this change can be replayed on `blocks`
+    fn check(&self, blocks: &[EditorBlock]) -> Result<(), ReplayError> {
+        let block = blocks
+            .iter()
+            .find(|blck| blck.id() == self.id)
+            .ok_or(ReplayError::BlockNotFound(self.id))?;
+        if block.inner != self.old_inner {
+            return Err(ReplayError::OldStateInconsistent);
+        };
+        Ok(())
+    }
 }
 impl Invert for DataChange {
     fn invert(self) -> Self {
@@ -276,6 +317,44 @@ impl Replay for BlockSwap {
 }
 impl UnRe for BlockSwap {}
 
+/// The block at one physical position was removed and inserted at another one.
+#[derive(Debug, Clone)]
+pub(super) struct BlockMove {
+    /// physical position of the block before the move
+    from: usize,
+    /// physical position of the block after the move
+    to: usize,
+}
+impl BlockMove {
+    pub fn new(from: usize, to: usize) -> Self {
+        Self { from, to }
+    }
+}
+impl Invert for BlockMove {
+    fn invert(self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+        }
+    }
+}
+impl Replay for BlockMove {
+    fn replay(&self, blocks: &mut Vec<EditorBlock>) -> Result<(), ReplayError> {
+        // if the indices are the same, this is a noop
+        if self.from == self.to {
+            return Ok(());
+        };
+        if self.from >= blocks.len() || self.to >= blocks.len() {
+            Err(ReplayError::OldStateInconsistent)
+        } else {
+            let block = blocks.remove(self.from);
+            blocks.insert(self.to, block);
+            Ok(())
+        }
+    }
+}
+impl UnRe for BlockMove {}
+
 /// Any number of consecutive blocks was exchanged for any other number of consecutive blocks
 #[derive(Debug, Clone)]
 pub(super) struct BlockChange {
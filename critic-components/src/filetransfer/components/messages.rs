@@ -6,8 +6,12 @@ use crate::{
     icons::{CheckIcon, InfoIcon},
 };
 
+/// `skipped` are the notes on files that were deliberately not saved
 #[component]
-pub fn TransferComplete(on_continue: impl Fn(MouseEvent) + 'static) -> impl IntoView {
+pub fn TransferComplete(
+    skipped: Vec<String>,
+    on_continue: impl Fn(MouseEvent) + 'static,
+) -> impl IntoView {
     let (busy_reader, _) = signal(false);
 
     view! {
@@ -18,6 +22,17 @@ pub fn TransferComplete(on_continue: impl Fn(MouseEvent) + 'static) -> impl Into
                 <div class="flex flex-col items-center text-slate-800">
                     <div>Upload complete</div>
                     <div>Your files have been uploaded successfully.</div>
+                    {(!skipped.is_empty())
+                        .then(|| {
+                            view! {
+                                <ul class="mt-2">
+                                    {skipped
+                                        .into_iter()
+                                        .map(|note| view! { <li>{note}</li> })
+                                        .collect_view()}
+                                </ul>
+                            }
+                        })}
                 </div>
 
                 <Button busy_reader=busy_reader on_click=on_continue label="Continue" />
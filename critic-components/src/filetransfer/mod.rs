@@ -15,9 +15,12 @@ use components::{
     messages::{TransferComplete, TransferFailed},
 };
 
+use crate::toast::{use_toasts, ToastKind};
+
 #[component]
 pub fn TransferPage(msname: String) -> impl IntoView {
     let files = RwSignal::new(Vec::<SendWrapper<File>>::new());
+    let toasts = use_toasts();
 
     let transfer_action = Action::new_local(move |files: &Vec<SendWrapper<File>>| {
         let selected_files = files
@@ -25,7 +28,21 @@ pub fn TransferPage(msname: String) -> impl IntoView {
             .map(|wrapped| wrapped.clone().take())
             .collect::<Vec<_>>();
         let name = msname.clone();
-        async move { services::transfer_files(&selected_files, &name).await }
+        async move {
+            let reply = services::transfer_files(&selected_files, &name).await;
+            if let Some(toasts) = toasts {
+                let failed = reply.err.iter().filter(|e| e.is_some()).count();
+                if failed == 0 {
+                    toasts.push(ToastKind::Success, "Upload complete.");
+                } else {
+                    toasts.push(
+                        ToastKind::Error,
+                        format!("{failed} of {} files failed to upload.", reply.err.len()),
+                    );
+                };
+            };
+            reply
+        }
     });
     let transfer_pending = transfer_action.pending();
     let transfer_reply = transfer_action.value();
@@ -60,10 +77,13 @@ pub fn TransferPage(msname: String) -> impl IntoView {
                         }
                     }
                 >
-                    <TransferComplete on_continue=move |ev: MouseEvent| {
-                        ev.prevent_default();
-                        transfer_reply.set(None);
-                    } />
+                    <TransferComplete
+                        skipped=transfer_reply.get().unwrap().skipped
+                        on_continue=move |ev: MouseEvent| {
+                            ev.prevent_default();
+                            transfer_reply.set(None);
+                        }
+                    />
                 </Show>
             </Show>
         </div>
@@ -1,9 +1,14 @@
 //! The service actually uploading files (by sending POST requests to the server)
 
-use critic_shared::{FileTransferResponse, MAX_BODY_SIZE};
+use critic_shared::{FileTransferResponse, ARCHIVE_EXTENSION, MAX_BODY_SIZE, PDF_EXTENSION};
 use web_sys::FormData;
 
 pub async fn transfer_batch(files: &[web_sys::File], msname: &str) -> FileTransferResponse {
+    send_files(files, critic_shared::urls::PAGE_UPLOAD_API_ENDPOINT, msname).await
+}
+
+/// POST `files` to the upload `endpoint` for `msname`
+async fn send_files(files: &[web_sys::File], endpoint: &str, msname: &str) -> FileTransferResponse {
     let form_data = FormData::new().unwrap();
     for file in files.iter() {
         form_data
@@ -16,7 +21,7 @@ pub async fn transfer_batch(files: &[web_sys::File], msname: &str) -> FileTransf
     match reqwasm::http::Request::post(&format!(
         "{}{}/{}",
         critic_shared::urls::UPLOAD_BASE_URL,
-        critic_shared::urls::PAGE_UPLOAD_API_ENDPOINT,
+        endpoint,
         msname
     ))
     .body(form_data)
@@ -44,6 +49,41 @@ pub async fn transfer_batch(files: &[web_sys::File], msname: &str) -> FileTransf
     this_batch_response
 }
 
+/// The endpoint for files that the server expands into several pages (zip archives and PDFs)
+///
+/// None for plain page images.
+fn expanding_endpoint(file: &web_sys::File) -> Option<&'static str> {
+    let name = file.name();
+    if name.ends_with(&format!(".{ARCHIVE_EXTENSION}")) {
+        Some(critic_shared::urls::ARCHIVE_UPLOAD_API_ENDPOINT)
+    } else if name.ends_with(&format!(".{PDF_EXTENSION}")) {
+        Some(critic_shared::urls::PDF_UPLOAD_API_ENDPOINT)
+    } else {
+        None
+    }
+}
+
+/// Transfer a single file, which the server expands into individual pages
+///
+/// The server answers with one result per page created from the file. These are merged into a
+/// single result for the file, so the response stays aligned with the selected files.
+pub async fn transfer_expanding(
+    file: &web_sys::File,
+    endpoint: &str,
+    msname: &str,
+) -> FileTransferResponse {
+    let entry_response = send_files(std::slice::from_ref(file), endpoint, msname).await;
+    let entry_errors = entry_response.err.into_iter().flatten().collect::<Vec<_>>();
+    let mut response = FileTransferResponse::new();
+    if entry_errors.is_empty() {
+        response.push_ok();
+    } else {
+        response.push_err(entry_errors.join(" "));
+    };
+    response.skipped = entry_response.skipped;
+    response
+}
+
 /// Transfer files to the api endpoint on the server with a POST request
 pub async fn transfer_files(files: &[web_sys::File], msname: &str) -> FileTransferResponse {
     let mut response = FileTransferResponse::new();
@@ -54,18 +94,27 @@ pub async fn transfer_files(files: &[web_sys::File], msname: &str) -> FileTransf
     let file_iter = files.iter();
     let mut current_batch_size = 0_f64;
     for file in file_iter {
-        if file.size() + current_batch_size < MAX_BODY_SIZE as f64 {
+        if let Some(endpoint) = expanding_endpoint(file) {
+            // send the batch collected so far, so the responses stay in the order of the files
+            if batch_end > batch_start {
+                response.append(transfer_batch(&files[batch_start..batch_end], msname).await);
+            };
+            if file.size() > MAX_BODY_SIZE as f64 {
+                response.push_err("File is to large.".to_string());
+            } else {
+                response.append(transfer_expanding(file, endpoint, msname).await);
+            };
+            // the next batch starts after this file
+            batch_start = batch_end + 1;
+            batch_end = batch_start;
+            current_batch_size = 0_f64;
+        } else if file.size() + current_batch_size < MAX_BODY_SIZE as f64 {
             current_batch_size += file.size();
             batch_end += 1;
         // `file` would make this batch to large. send the last one
         } else {
             // send this batch
-            response.extend(
-                transfer_batch(&files[batch_start..batch_end], msname)
-                    .await
-                    .err
-                    .into_iter(),
-            );
+            response.append(transfer_batch(&files[batch_start..batch_end], msname).await);
             // start a new batch - this starts with (and contains) the file we are currently on
             batch_start = batch_end;
             batch_end = batch_start + 1;
@@ -81,12 +130,9 @@ pub async fn transfer_files(files: &[web_sys::File], msname: &str) -> FileTransf
         };
     }
     // send the final batch
-    response.extend(
-        transfer_batch(&files[batch_start..batch_end], msname)
-            .await
-            .err
-            .into_iter(),
-    );
+    if batch_end > batch_start {
+        response.append(transfer_batch(&files[batch_start..batch_end], msname).await);
+    };
     // and return the responses
     response
 }
@@ -0,0 +1,278 @@
+//! A viewer for manuscript images with zoom, pan and rotation
+//!
+//! The viewer starts out with the small `preview.webp` and only loads the full size image once the
+//! user zooms in. It knows nothing about transcriptions, so it can be put next to any editor.
+
+use critic_shared::urls::{IMAGE_BASE_LOCATION, STATIC_BASE_URL};
+use leptos::{html::Div, prelude::*};
+
+use crate::DEFAULT_BUTTON_CLASSES;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// The smallest scale the image can be shown at
+pub const MIN_SCALE: f64 = 0.5;
+/// The largest scale the image can be shown at
+pub const MAX_SCALE: f64 = 12.0;
+/// How much one step of the scroll wheel or the zoom buttons zooms
+const ZOOM_STEP: f64 = 1.25;
+
+/// How far the image is rotated clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+impl Rotation {
+    pub fn clockwise(self) -> Self {
+        match self {
+            Self::None => Self::Quarter,
+            Self::Quarter => Self::Half,
+            Self::Half => Self::ThreeQuarters,
+            Self::ThreeQuarters => Self::None,
+        }
+    }
+
+    pub fn counterclockwise(self) -> Self {
+        match self {
+            Self::None => Self::ThreeQuarters,
+            Self::Quarter => Self::None,
+            Self::Half => Self::Quarter,
+            Self::ThreeQuarters => Self::Half,
+        }
+    }
+
+    pub fn degrees(self) -> u16 {
+        match self {
+            Self::None => 0,
+            Self::Quarter => 90,
+            Self::Half => 180,
+            Self::ThreeQuarters => 270,
+        }
+    }
+}
+
+/// Where and how large the image is shown
+///
+/// Offsets are in pixels and relative to the center of the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewTransform {
+    pub scale: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub rotation: Rotation,
+}
+impl Default for ViewTransform {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            rotation: Rotation::None,
+        }
+    }
+}
+impl ViewTransform {
+    /// Zoom by `factor`, keeping the point (`x`, `y`) of the viewer where it is
+    ///
+    /// The scale is clamped to [`MIN_SCALE`]..=[`MAX_SCALE`].
+    pub fn zoom_at(&mut self, factor: f64, x: f64, y: f64) {
+        let new_scale = (self.scale * factor).clamp(MIN_SCALE, MAX_SCALE);
+        let effective_factor = new_scale / self.scale;
+        self.offset_x = x - effective_factor * (x - self.offset_x);
+        self.offset_y = y - effective_factor * (y - self.offset_y);
+        self.scale = new_scale;
+    }
+
+    /// Move the image by (`dx`, `dy`) pixels
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.offset_x += dx;
+        self.offset_y += dy;
+    }
+
+    /// The value for the css `transform` property of the image
+    pub fn css(&self) -> String {
+        format!(
+            "translate({}px, {}px) scale({}) rotate({}deg)",
+            self.offset_x,
+            self.offset_y,
+            self.scale,
+            self.rotation.degrees()
+        )
+    }
+}
+
+/// A pointer currently pressed on the viewer
+#[derive(Debug, Clone, Copy)]
+struct ActivePointer {
+    id: i32,
+    x: f64,
+    y: f64,
+}
+
+fn distance(a: &ActivePointer, b: &ActivePointer) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Shows the image of a page
+///
+/// - scroll or pinch to zoom
+/// - drag to pan
+/// - the buttons rotate the image by 90 degrees or reset the view
+///
+/// `placeholder` (the `data:` url from [`critic_shared::PageMeta`]) is shown until the image
+/// has loaded.
+#[component]
+pub fn ImageViewer(
+    msname: String,
+    pagename: String,
+    #[prop(optional_no_strip)] placeholder: Option<String>,
+) -> impl IntoView {
+    let image_base = format!("{STATIC_BASE_URL}{IMAGE_BASE_LOCATION}/{msname}/{pagename}");
+    let preview_src = format!("{image_base}/preview.webp");
+    // the server picks the best format the browser supports
+    let original_src = image_base.clone();
+
+    let transform = RwSignal::new(ViewTransform::default());
+    // once the user zoomed in, keep the original even when zooming out again
+    let show_original = RwSignal::new(false);
+    Effect::new(move |_| {
+        if transform.read().scale > 1.0 {
+            show_original.set(true);
+        };
+    });
+    let pointers = StoredValue::new(Vec::<ActivePointer>::new());
+    let viewer_ref = NodeRef::<Div>::new();
+
+    // position of the event relative to the center of the viewer
+    let relative_to_center = move |client_x: f64, client_y: f64| {
+        viewer_ref
+            .get_untracked()
+            .map(|viewer| {
+                let rect = viewer.get_bounding_client_rect();
+                (
+                    client_x - rect.left() - rect.width() / 2.0,
+                    client_y - rect.top() - rect.height() / 2.0,
+                )
+            })
+            .unwrap_or((0.0, 0.0))
+    };
+
+    view! {
+        <div class="flex flex-col h-full">
+            <div class="flex justify-center">
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    on:click=move |_| transform.update(|t| t.zoom_at(ZOOM_STEP, 0.0, 0.0))
+                >
+                    "Zoom in"
+                </button>
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    on:click=move |_| transform.update(|t| t.zoom_at(1.0 / ZOOM_STEP, 0.0, 0.0))
+                >
+                    "Zoom out"
+                </button>
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    on:click=move |_| {
+                        transform.update(|t| t.rotation = t.rotation.counterclockwise())
+                    }
+                >
+                    "Rotate left"
+                </button>
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    on:click=move |_| transform.update(|t| t.rotation = t.rotation.clockwise())
+                >
+                    "Rotate right"
+                </button>
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    on:click=move |_| transform.set(ViewTransform::default())
+                >
+                    "Reset"
+                </button>
+            </div>
+            <div
+                node_ref=viewer_ref
+                class="relative grow min-h-96 overflow-hidden bg-slate-900 cursor-grab touch-none select-none"
+                on:wheel=move |ev| {
+                    ev.prevent_default();
+                    let (x, y) = relative_to_center(ev.client_x().into(), ev.client_y().into());
+                    let factor = if ev.delta_y() < 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+                    transform.update(|t| t.zoom_at(factor, x, y));
+                }
+                on:pointerdown=move |ev| {
+                    if let Some(viewer) = viewer_ref.get_untracked() {
+                        let _ = viewer.set_pointer_capture(ev.pointer_id());
+                    };
+                    pointers
+                        .update_value(|p| {
+                            p.push(ActivePointer {
+                                id: ev.pointer_id(),
+                                x: ev.client_x().into(),
+                                y: ev.client_y().into(),
+                            })
+                        });
+                }
+                on:pointermove=move |ev| {
+                    let moved = ActivePointer {
+                        id: ev.pointer_id(),
+                        x: ev.client_x().into(),
+                        y: ev.client_y().into(),
+                    };
+                    let before = pointers.get_value();
+                    let Some(idx) = before.iter().position(|p| p.id == moved.id) else {
+                        // hovering without a pressed button
+                        return;
+                    };
+                    match before.as_slice() {
+                        // drag
+                        [only] => {
+                            transform.update(|t| t.pan(moved.x - only.x, moved.y - only.y));
+                        }
+                        // pinch
+                        [first, second] => {
+                            let other = if idx == 0 { second } else { first };
+                            let old_distance = distance(first, second);
+                            if old_distance > 0.0 {
+                                let factor = distance(&moved, other) / old_distance;
+                                let (x, y) = relative_to_center(
+                                    (moved.x + other.x) / 2.0,
+                                    (moved.y + other.y) / 2.0,
+                                );
+                                transform.update(|t| t.zoom_at(factor, x, y));
+                            };
+                        }
+                        // more than two fingers are not a gesture we know
+                        _ => {}
+                    };
+                    pointers.update_value(|p| p[idx] = moved);
+                }
+                on:pointerup=move |ev| {
+                    pointers.update_value(|p| p.retain(|p| p.id != ev.pointer_id()));
+                }
+                on:pointercancel=move |ev| {
+                    pointers.update_value(|p| p.retain(|p| p.id != ev.pointer_id()));
+                }
+            >
+                <img
+                    class="absolute inset-0 m-auto max-h-full max-w-full origin-center bg-contain bg-center bg-no-repeat"
+                    draggable="false"
+                    src=move || {
+                        if show_original.get() { original_src.clone() } else { preview_src.clone() }
+                    }
+                    style:transform=move || transform.read().css()
+                    style:background-image=placeholder.map(|url| format!("url({url})"))
+                    alt=format!("Page {pagename} of {msname}")
+                />
+            </div>
+        </div>
+    }
+}
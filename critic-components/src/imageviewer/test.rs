@@ -0,0 +1,51 @@
+use super::{Rotation, ViewTransform, MAX_SCALE, MIN_SCALE};
+
+#[test]
+fn zoom_keeps_point_under_cursor() {
+    let mut transform = ViewTransform::default();
+    transform.pan(10.0, -20.0);
+    transform.zoom_at(2.0, 50.0, 30.0);
+    assert_eq!(transform.scale, 2.0);
+    // the image point that was at (50, 30) is still there
+    assert_eq!(transform.offset_x, 50.0 - 2.0 * (50.0 - 10.0));
+    assert_eq!(transform.offset_y, 30.0 - 2.0 * (30.0 + 20.0));
+}
+
+#[test]
+fn zoom_is_clamped() {
+    let mut transform = ViewTransform::default();
+    transform.zoom_at(1000.0, 10.0, 10.0);
+    assert_eq!(transform.scale, MAX_SCALE);
+    let offset = (transform.offset_x, transform.offset_y);
+    // zooming further in changes nothing
+    transform.zoom_at(2.0, 10.0, 10.0);
+    assert_eq!((transform.offset_x, transform.offset_y), offset);
+
+    transform.zoom_at(0.0001, 0.0, 0.0);
+    assert_eq!(transform.scale, MIN_SCALE);
+}
+
+#[test]
+fn rotation_cycles() {
+    let mut rotation = Rotation::None;
+    for expected in [90, 180, 270, 0] {
+        rotation = rotation.clockwise();
+        assert_eq!(rotation.degrees(), expected);
+    }
+    assert_eq!(Rotation::None.counterclockwise(), Rotation::ThreeQuarters);
+    assert_eq!(
+        Rotation::Quarter.clockwise().counterclockwise(),
+        Rotation::Quarter
+    );
+}
+
+#[test]
+fn css_transform() {
+    let mut transform = ViewTransform::default();
+    transform.pan(3.0, 4.0);
+    transform.rotation = Rotation::Quarter;
+    assert_eq!(
+        transform.css(),
+        "translate(3px, 4px) scale(1) rotate(90deg)"
+    );
+}
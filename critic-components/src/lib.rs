@@ -1,9 +1,13 @@
 //! Icons shared across the app
 
 pub mod accordion;
+pub mod confirm;
 pub mod editor;
 pub mod filetransfer;
 pub mod icons;
+pub mod imageviewer;
+pub mod reconcile;
+pub mod toast;
 pub mod xmleditor;
 
 pub const TEXTAREA_DEFAULT_ROWS: i32 = 2;
@@ -0,0 +1,198 @@
+//! Comparing two transcriptions of the same page
+//!
+//! The transcriptions are aligned block by block using their longest common subsequence. Blocks
+//! that are not part of this subsequence are paired up as disagreements in the gaps between
+//! agreeing blocks.
+
+use critic_format::streamed::Block;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// What the transcriptions disagree on
+///
+/// Ordered by importance: when the two sides of a disagreement would be classified differently,
+/// the greater kind is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DisagreementKind {
+    /// Breaks, anchors or spaces
+    Other,
+    /// Text, including uncertain text and abbreviations
+    Text,
+    /// One side contains a correction
+    Correction,
+    /// One side contains a lacuna
+    Lacuna,
+}
+impl DisagreementKind {
+    fn of_block(block: &Block) -> Self {
+        match block {
+            Block::Lacuna(_) => Self::Lacuna,
+            Block::Correction(_) => Self::Correction,
+            Block::Text(_) | Block::Uncertain(_) | Block::Abbreviation(_) => Self::Text,
+            Block::Break(_) | Block::Anchor(_) | Block::Space(_) => Self::Other,
+        }
+    }
+
+    fn between(left: Option<&Block>, right: Option<&Block>) -> Self {
+        left.into_iter()
+            .chain(right)
+            .map(Self::of_block)
+            .max()
+            .unwrap_or(Self::Other)
+    }
+}
+impl core::fmt::Display for DisagreementKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Other => write!(f, "Structure"),
+            Self::Text => write!(f, "Text"),
+            Self::Correction => write!(f, "Correction"),
+            Self::Lacuna => write!(f, "Lacuna"),
+        }
+    }
+}
+
+/// One step in the alignment of two transcriptions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlignedBlock {
+    /// Both transcriptions contain this block
+    Agreement(Block),
+    /// The transcriptions differ here. A side is `None` if it has no block at this position.
+    Disagreement {
+        left: Option<Block>,
+        right: Option<Block>,
+        kind: DisagreementKind,
+    },
+}
+
+/// Align two transcriptions block by block
+pub fn align_blocks(left: &[Block], right: &[Block]) -> Vec<AlignedBlock> {
+    // lcs_len[i][j] is the length of the longest common subsequence of left[i..] and right[j..]
+    let mut lcs_len = vec![vec![0_usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs_len[i][j] = if left[i] == right[j] {
+                lcs_len[i + 1][j + 1] + 1
+            } else {
+                lcs_len[i + 1][j].max(lcs_len[i][j + 1])
+            };
+        }
+    }
+
+    let mut res = Vec::new();
+    let mut left_only = Vec::new();
+    let mut right_only = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if left[i] == right[j] {
+            push_disagreements(&mut res, &mut left_only, &mut right_only);
+            res.push(AlignedBlock::Agreement(left[i].clone()));
+            i += 1;
+            j += 1;
+        } else if lcs_len[i + 1][j] >= lcs_len[i][j + 1] {
+            left_only.push(left[i].clone());
+            i += 1;
+        } else {
+            right_only.push(right[j].clone());
+            j += 1;
+        };
+    }
+    left_only.extend_from_slice(&left[i..]);
+    right_only.extend_from_slice(&right[j..]);
+    push_disagreements(&mut res, &mut left_only, &mut right_only);
+    res
+}
+
+/// Pair up the blocks between two agreements and push them as disagreements
+fn push_disagreements(
+    res: &mut Vec<AlignedBlock>,
+    left_only: &mut Vec<Block>,
+    right_only: &mut Vec<Block>,
+) {
+    let len = left_only.len().max(right_only.len());
+    let mut left_iter = left_only.drain(..);
+    let mut right_iter = right_only.drain(..);
+    for _ in 0..len {
+        let left = left_iter.next();
+        let right = right_iter.next();
+        let kind = DisagreementKind::between(left.as_ref(), right.as_ref());
+        res.push(AlignedBlock::Disagreement { left, right, kind });
+    }
+}
+
+/// Short human readable rendering of a block for the reconciliation view
+fn block_summary(block: &Block) -> String {
+    match block {
+        Block::Text(x) => x.content.clone(),
+        Block::Uncertain(x) => format!("[{}]", x.content),
+        Block::Abbreviation(x) => format!("{} ({})", x.surface, x.expansion),
+        Block::Correction(x) => x
+            .versions
+            .iter()
+            .map(|v| v.content.as_str())
+            .collect::<Vec<_>>()
+            .join(" → "),
+        Block::Lacuna(_) => "[lacuna]".to_string(),
+        Block::Space(_) => "[space]".to_string(),
+        Block::Break(_) => "[break]".to_string(),
+        Block::Anchor(x) => format!("[{}]", x.anchor_id),
+    }
+}
+
+/// Show an alignment side by side, highlighting the disagreements
+#[component]
+pub fn AlignmentView(
+    alignment: Vec<AlignedBlock>,
+    left_name: String,
+    right_name: String,
+) -> impl IntoView {
+    view! {
+        <div class="table w-full text-lg">
+            <div class="table-header-group font-bold">
+                <div class="table-row">
+                    <div class="table-cell p-2">{left_name}</div>
+                    <div class="table-cell p-2">{right_name}</div>
+                    <div class="table-cell p-2"></div>
+                </div>
+            </div>
+            <div class="table-row-group">
+                {alignment
+                    .into_iter()
+                    .map(|aligned| match aligned {
+                        AlignedBlock::Agreement(block) => {
+                            let summary = block_summary(&block);
+                            view! {
+                                <div class="table-row border-b border-slate-600">
+                                    <div class="table-cell p-2">{summary.clone()}</div>
+                                    <div class="table-cell p-2">{summary}</div>
+                                    <div class="table-cell p-2"></div>
+                                </div>
+                            }
+                                .into_any()
+                        }
+                        AlignedBlock::Disagreement { left, right, kind } => {
+                            view! {
+                                <div class="table-row border-b border-slate-600 bg-red-700/40">
+                                    <div class="table-cell p-2">
+                                        {left.as_ref().map(block_summary)}
+                                    </div>
+                                    <div class="table-cell p-2">
+                                        {right.as_ref().map(block_summary)}
+                                    </div>
+                                    <div class="table-cell p-2 text-orange-400">
+                                        {kind.to_string()}
+                                    </div>
+                                </div>
+                            }
+                                .into_any()
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </div>
+    }
+}
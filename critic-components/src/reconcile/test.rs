@@ -0,0 +1,100 @@
+use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+
+use super::{align_blocks, AlignedBlock, DisagreementKind};
+
+fn block(block_type: BlockType, content: &str) -> Block {
+    Block::from_type_lang_and_content(block_type, "hbo-Hebr".to_string(), content.to_string())
+}
+
+fn text(content: &str) -> Block {
+    block(BlockType::Text, content)
+}
+
+#[test]
+fn identical_transcriptions_agree() {
+    let blocks = vec![text("a"), block(BlockType::Break, ""), text("b")];
+    let alignment = align_blocks(&blocks, &blocks);
+    assert_eq!(
+        alignment,
+        blocks
+            .into_iter()
+            .map(AlignedBlock::Agreement)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn empty_transcriptions() {
+    assert_eq!(align_blocks(&[], &[]), vec![]);
+    assert_eq!(
+        align_blocks(&[text("a")], &[]),
+        vec![AlignedBlock::Disagreement {
+            left: Some(text("a")),
+            right: None,
+            kind: DisagreementKind::Text,
+        }]
+    );
+}
+
+#[test]
+fn differing_text_is_paired() {
+    let left = vec![text("a"), text("b"), text("c")];
+    let right = vec![text("a"), text("x"), text("c")];
+    assert_eq!(
+        align_blocks(&left, &right),
+        vec![
+            AlignedBlock::Agreement(text("a")),
+            AlignedBlock::Disagreement {
+                left: Some(text("b")),
+                right: Some(text("x")),
+                kind: DisagreementKind::Text,
+            },
+            AlignedBlock::Agreement(text("c")),
+        ]
+    );
+}
+
+#[test]
+fn inserted_block_is_one_sided() {
+    let left = vec![text("a"), text("c")];
+    let right = vec![text("a"), text("b"), text("c")];
+    assert_eq!(
+        align_blocks(&left, &right),
+        vec![
+            AlignedBlock::Agreement(text("a")),
+            AlignedBlock::Disagreement {
+                left: None,
+                right: Some(text("b")),
+                kind: DisagreementKind::Text,
+            },
+            AlignedBlock::Agreement(text("c")),
+        ]
+    );
+}
+
+#[test]
+fn lacuna_and_correction_take_precedence() {
+    let lacuna = block(BlockType::Lacuna, "");
+    let correction = block(BlockType::Correction, "b");
+    let left = vec![text("a"), lacuna.clone(), text("c")];
+    let right = vec![text("a"), correction.clone(), text("c")];
+    assert_eq!(
+        align_blocks(&left, &right)[1],
+        AlignedBlock::Disagreement {
+            left: Some(lacuna),
+            right: Some(correction.clone()),
+            kind: DisagreementKind::Lacuna,
+        }
+    );
+
+    let left = vec![text("b")];
+    let right = vec![correction.clone()];
+    assert_eq!(
+        align_blocks(&left, &right),
+        vec![AlignedBlock::Disagreement {
+            left: Some(text("b")),
+            right: Some(correction),
+            kind: DisagreementKind::Correction,
+        }]
+    );
+}
@@ -0,0 +1,121 @@
+//! Short notifications about the outcome of an action
+//!
+//! The app calls [`provide_toasts`] once and renders a [`ToastStack`]. Components below it then
+//! report results with [`push_toast`]. Code running in a task has no reactive owner, so it has to
+//! get the [`Toasts`] with [`use_toasts`] before spawning.
+
+use std::time::Duration;
+
+use leptos::prelude::*;
+
+/// How long a toast stays up unless it is closed by hand
+const TOAST_TIMEOUT: Duration = Duration::from_secs(6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Success,
+    Error,
+    Info,
+}
+
+impl ToastKind {
+    fn classes(&self) -> &'static str {
+        match self {
+            ToastKind::Success => "border-green-600 bg-green-900",
+            ToastKind::Error => "border-red-600 bg-red-900",
+            ToastKind::Info => "border-sky-600 bg-slate-800",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Toast {
+    id: usize,
+    kind: ToastKind,
+    message: String,
+}
+
+/// Handle to the toasts currently shown
+#[derive(Debug, Clone, Copy)]
+pub struct Toasts {
+    toasts: RwSignal<Vec<Toast>>,
+    next_id: StoredValue<usize>,
+}
+
+impl Toasts {
+    fn new() -> Self {
+        Self {
+            toasts: RwSignal::new(Vec::new()),
+            next_id: StoredValue::new(0),
+        }
+    }
+
+    /// Show `message` until it times out or is closed
+    pub fn push(&self, kind: ToastKind, message: impl Into<String>) {
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id + 1);
+        self.toasts.update(|toasts| {
+            toasts.push(Toast {
+                id,
+                kind,
+                message: message.into(),
+            })
+        });
+        let this = *self;
+        set_timeout(move || this.dismiss(id), TOAST_TIMEOUT);
+    }
+
+    /// Remove the toast with `id` if it is still shown
+    pub fn dismiss(&self, id: usize) {
+        self.toasts
+            .try_update(|toasts| toasts.retain(|toast| toast.id != id));
+    }
+}
+
+/// Make toasts available to all components below the current one
+pub fn provide_toasts() -> Toasts {
+    let toasts = Toasts::new();
+    provide_context(toasts);
+    toasts
+}
+
+pub fn use_toasts() -> Option<Toasts> {
+    use_context::<Toasts>()
+}
+
+/// Show `message` as a toast
+///
+/// Does nothing when no toasts were provided.
+pub fn push_toast(kind: ToastKind, message: impl Into<String>) {
+    if let Some(toasts) = use_toasts() {
+        toasts.push(kind, message);
+    };
+}
+
+/// Render the provided toasts in the bottom right corner, newest last
+#[component]
+pub fn ToastStack() -> impl IntoView {
+    let toasts = use_toasts().expect("ToastStack needs provide_toasts to be called first");
+    view! {
+        <div class="fixed bottom-4 right-4 z-40 flex w-96 flex-col gap-2">
+            <For each=move || toasts.toasts.get() key=|toast| toast.id let:toast>
+                <div
+                    role="alert"
+                    class=format!(
+                        "flex flex-row items-start justify-between rounded-2xl border-2 p-3 text-slate-50 shadow-md {}",
+                        toast.kind.classes(),
+                    )
+                >
+                    <p class="grow">{toast.message}</p>
+                    <button
+                        class="ml-2 px-2 font-bold hover:text-slate-300"
+                        aria-label="Close"
+                        on:click=move |_| toasts.dismiss(toast.id)
+                    >
+                        "×"
+                    </button>
+                </div>
+            </For>
+        </div>
+    }
+}
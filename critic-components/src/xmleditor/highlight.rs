@@ -0,0 +1,144 @@
+//! Splitting XML into highlighted tokens and locating syntax errors in it
+
+use quick_xml::{events::Event, Reader};
+
+/// The kind of a piece of XML, used to color it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Tag names and the punctuation of tags
+    Tag,
+    /// Attribute names
+    Attribute,
+    /// Quoted attribute values
+    Value,
+    Comment,
+    /// Everything outside of tags
+    Text,
+}
+impl TokenKind {
+    /// Tailwind classes to color this kind of token
+    pub fn classes(self) -> &'static str {
+        match self {
+            Self::Tag => "text-sky-400",
+            Self::Attribute => "text-orange-300",
+            Self::Value => "text-green-300",
+            Self::Comment => "text-slate-400",
+            Self::Text => "text-slate-50",
+        }
+    }
+}
+
+/// Split `xml` into tokens for highlighting
+///
+/// This does not validate anything and never fails: concatenating all tokens gives back `xml`.
+pub fn tokenize(xml: &str) -> Vec<(TokenKind, String)> {
+    let mut tokens: Vec<(TokenKind, String)> = Vec::new();
+    let mut push = |kind: TokenKind, content: &str| {
+        if content.is_empty() {
+            return;
+        };
+        match tokens.last_mut() {
+            Some((last_kind, last_content)) if *last_kind == kind => {
+                last_content.push_str(content);
+            }
+            _ => tokens.push((kind, content.to_string())),
+        }
+    };
+
+    let mut rest = xml;
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            push(TokenKind::Comment, &rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            let mut tag = &rest[..end];
+            rest = &rest[end..];
+            // the name including any leading punctuation like `</` or `<?`
+            let name_start = if tag[1..].starts_with(['/', '?', '!']) {
+                2
+            } else {
+                1
+            };
+            let name_end = tag[name_start..]
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .map(|i| name_start + i)
+                .unwrap_or(tag.len());
+            push(TokenKind::Tag, &tag[..name_end]);
+            tag = &tag[name_end..];
+            while !tag.is_empty() {
+                if let Some(stripped) = tag.strip_prefix(['"', '\'']) {
+                    let quote = &tag[..1];
+                    let end = stripped.find(quote).map(|i| i + 2).unwrap_or(tag.len());
+                    push(TokenKind::Value, &tag[..end]);
+                    tag = &tag[end..];
+                } else if tag.starts_with(|c: char| c.is_whitespace()) {
+                    let end = tag.find(|c: char| !c.is_whitespace()).unwrap_or(tag.len());
+                    push(TokenKind::Text, &tag[..end]);
+                    tag = &tag[end..];
+                } else if tag.starts_with(['=', '/', '>', '?']) {
+                    push(TokenKind::Tag, &tag[..1]);
+                    tag = &tag[1..];
+                } else {
+                    let end = tag
+                        .find(|c: char| {
+                            c.is_whitespace() || ['=', '/', '>', '"', '\''].contains(&c)
+                        })
+                        .unwrap_or(tag.len());
+                    push(TokenKind::Attribute, &tag[..end]);
+                    tag = &tag[end..];
+                };
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push(TokenKind::Text, &rest[..end]);
+            rest = &rest[end..];
+        };
+    }
+    tokens
+}
+
+/// The 1-based line and column of the byte at `offset` in `text`
+pub fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let mut end = offset.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let before = &text[..end];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Find the first syntax error in `xml`, returning its 1-based line and column
+///
+/// Returns None if the XML is well-formed; it may still not be a valid transcription.
+pub fn locate_syntax_error(xml: &str) -> Option<(usize, usize)> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => return None,
+            Ok(_) => {}
+            Err(_) => {
+                let offset = usize::try_from(reader.error_position()).unwrap_or(usize::MAX);
+                return Some(line_and_column(xml, offset));
+            }
+        }
+    }
+}
+
+/// The start and end of the 1-based `line` in `text`, in UTF-16 code units as used by the DOM
+pub fn line_range_utf16(text: &str, line: usize) -> (u32, u32) {
+    let start = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let end = text[start..]
+        .find('\n')
+        .map(|i| start + i)
+        .unwrap_or(text.len());
+    let utf16 = |byte: usize| u32::try_from(text[..byte].encode_utf16().count()).unwrap_or(0);
+    (utf16(start), utf16(end))
+}
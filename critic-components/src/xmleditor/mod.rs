@@ -1,19 +1,111 @@
 //! The XML editor (Basically a glorified textarea.
+//!
+//! The textarea has transparent text and sits on top of a highlighted copy of its content, with
+//! line numbers to the side.
 
-use critic_format::{page_from_xml, page_to_xml};
-use leptos::{either::Either, ev::keydown, prelude::*};
+use critic_format::{page_from_xml, page_to_xml, streamed::Block};
+use leptos::{
+    either::Either,
+    ev::keydown,
+    html::{Div, Pre, Textarea},
+    prelude::*,
+};
 use leptos_use::use_event_listener;
 
-use crate::{editor::blocks::EditorBlock, DEFAULT_BUTTON_CLASSES};
+use crate::{
+    editor::{
+        anchors::{describe_anchor_problems, find_anchor_problems},
+        blocks::EditorBlock,
+    },
+    reconcile::{align_blocks, AlignedBlock, AlignmentView},
+    DEFAULT_BUTTON_CLASSES,
+};
+
+mod highlight;
+use highlight::{line_and_column, line_range_utf16, locate_syntax_error, tokenize};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// A problem with the XML, with its position if it is known
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlError {
+    pub message: String,
+    /// 1-based line and column
+    pub position: Option<(usize, usize)>,
+}
+impl core::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "Line {line}, column {column}: {}", self.message)
+            }
+            None => {
+                write!(f, "{}", self.message)
+            }
+        }
+    }
+}
+impl From<String> for XmlError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            position: None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum XmlState {
     /// We know that the XML state is currently OK
     Checked,
     /// We know that the XML state is currently BAD
-    Err(String),
+    Err(XmlError),
     /// We have to check first
     Unchecked,
+    /// The XML is valid, but changes these blocks. The user has to confirm before they are applied.
+    AwaitingConfirmation(Vec<Block>),
+}
+
+/// The 1-based line and column of the `occurrence`th (0-based) attribute with the value `anchor_id`
+fn locate_anchor(xml: &str, anchor_id: &str, occurrence: usize) -> Option<(usize, usize)> {
+    let needle = format!("\"{anchor_id}\"");
+    xml.match_indices(&needle)
+        .nth(occurrence)
+        // point at the id, not the quote
+        .map(|(offset, _)| line_and_column(xml, offset + 1))
+}
+
+/// How many blocks applying the XML would add, remove and change
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ChangeCounts {
+    added: usize,
+    removed: usize,
+    changed: usize,
+}
+impl ChangeCounts {
+    /// Count the changes in an alignment of the current blocks (left) with the parsed ones (right)
+    fn of_alignment(alignment: &[AlignedBlock]) -> Self {
+        let mut counts = Self::default();
+        for aligned in alignment {
+            match aligned {
+                AlignedBlock::Agreement(_) => {}
+                AlignedBlock::Disagreement {
+                    left: None,
+                    right: Some(_),
+                    ..
+                } => counts.added += 1,
+                AlignedBlock::Disagreement {
+                    left: Some(_),
+                    right: None,
+                    ..
+                } => counts.removed += 1,
+                AlignedBlock::Disagreement { .. } => counts.changed += 1,
+            }
+        }
+        counts
+    }
 }
 
 /// The XML Editor.
@@ -59,30 +151,68 @@ pub fn XmlEditor(
             Ok((blocks_from_xml, name)) => {
                 if name != check_name {
                     *xml_state.write() =
-                        XmlState::Err(format!("The name of this page must be {check_name}"));
+                        XmlState::Err(format!("The name of this page must be {check_name}").into());
+                    return false;
+                }
+                let problems = find_anchor_problems(&blocks_from_xml);
+                if let Some(message) = describe_anchor_problems(&problems) {
+                    // show the first offending anchor
+                    let position = problems.first().and_then(|first| {
+                        let occurrence = blocks_from_xml[..first.block]
+                            .iter()
+                            .filter(
+                                |b| matches!(b, Block::Anchor(a) if a.anchor_id == first.anchor_id),
+                            )
+                            .count();
+                        locate_anchor(
+                            &textarea_content.read_untracked(),
+                            &first.anchor_id,
+                            occurrence,
+                        )
+                    });
+                    *xml_state.write() = XmlState::Err(XmlError { message, position });
                     return false;
+                };
+                let unchanged = blocks.with_untracked(|current| {
+                    current.len() == blocks_from_xml.len()
+                        && current
+                            .iter()
+                            .zip(&blocks_from_xml)
+                            .all(|(c, b)| c.inner == *b)
+                });
+                if unchanged {
+                    *xml_state.write() = XmlState::Checked;
+                    true
+                } else {
+                    // the user has to confirm the changes before the blocks are replaced
+                    *xml_state.write() = XmlState::AwaitingConfirmation(blocks_from_xml);
+                    false
                 }
-                // check was ok
-                *xml_state.write() = XmlState::Checked;
-                // set blocks accordingly
-                *blocks.write() = blocks_from_xml
-                    .into_iter()
-                    .enumerate()
-                    .map(|(id, b)| EditorBlock {
-                        inner: b.into(),
-                        id,
-                        focus_on_load: false,
-                    })
-                    .collect();
-                true
             }
-            Err(e) => {
-                // check was bad
-                *xml_state.write() = XmlState::Err(e);
+            Err(message) => {
+                // check was bad - the deserializer does not report positions, but syntax errors
+                // can be located by reading the XML again
+                let position = locate_syntax_error(&textarea_content.read_untracked());
+                *xml_state.write() = XmlState::Err(XmlError { message, position });
                 false
             }
         }
     };
+    // replace the blocks with those parsed from the XML
+    let apply = move || {
+        if let XmlState::AwaitingConfirmation(blocks_from_xml) = xml_state.get_untracked() {
+            *blocks.write() = blocks_from_xml
+                .into_iter()
+                .enumerate()
+                .map(|(id, b)| EditorBlock {
+                    inner: b.into(),
+                    id,
+                    focus_on_load: false,
+                })
+                .collect();
+            xml_state.set(XmlState::Checked);
+        };
+    };
     let save_check = check.clone();
     let save = move || {
         if save_check() {
@@ -91,7 +221,37 @@ pub fn XmlEditor(
         };
     };
 
-    let textarea_ref = NodeRef::new();
+    let textarea_ref = NodeRef::<Textarea>::new();
+    let gutter_ref = NodeRef::<Div>::new();
+    let highlight_ref = NodeRef::<Pre>::new();
+
+    let error_line = move || match xml_state.read().clone() {
+        XmlState::Err(XmlError {
+            position: Some((line, _)),
+            ..
+        }) => Some(line),
+        _ => None,
+    };
+    // select the offending line, which also scrolls it into view
+    Effect::new(move |_| {
+        if let (Some(line), Some(textarea)) = (error_line(), textarea_ref.get_untracked()) {
+            let (start, end) = line_range_utf16(&textarea_content.read_untracked(), line);
+            let _ = textarea.focus();
+            let _ = textarea.set_selection_range(start, end);
+        };
+    });
+    // keep line numbers and highlighting aligned with the textarea
+    let sync_scroll = move || {
+        if let Some(textarea) = textarea_ref.get_untracked() {
+            if let Some(gutter) = gutter_ref.get_untracked() {
+                gutter.set_scroll_top(textarea.scroll_top());
+            };
+            if let Some(highlight) = highlight_ref.get_untracked() {
+                highlight.set_scroll_top(textarea.scroll_top());
+                highlight.set_scroll_left(textarea.scroll_left());
+            };
+        };
+    };
     let key_save = save.clone();
     let key_check = check.clone();
     let _cleanup = use_event_listener(textarea_ref, keydown, move |evt| {
@@ -106,20 +266,68 @@ pub fn XmlEditor(
 
     Either::Right(view! {
         <div id="xml-editor">
-            <textarea
-                node_ref=textarea_ref
-                id="xml-edit-content"
-                class="m-3 p-1 bg-slate-700"
-                rows=13
-                cols=90
-                prop:value=move || textarea_content.get()
-                autocomplete="false"
-                spellcheck="false"
-                on:input:target=move |ev| {
-                    *textarea_content.write() = ev.target().value();
-                    xml_state.set(XmlState::Unchecked);
-                }
-            />
+            <div class="m-3 flex bg-slate-700 font-mono text-sm leading-5">
+                <div
+                    node_ref=gutter_ref
+                    class="select-none overflow-hidden border-r border-slate-500 p-1 text-right text-slate-400"
+                >
+                    {move || {
+                        (1..=textarea_content.read().split('\n').count())
+                            .map(|line| {
+                                view! {
+                                    <div class=(
+                                        "bg-red-700",
+                                        move || error_line() == Some(line),
+                                    )>{line}</div>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                </div>
+                <div class="relative">
+                    <pre
+                        node_ref=highlight_ref
+                        aria-hidden="true"
+                        class="pointer-events-none absolute inset-0 m-0 overflow-hidden whitespace-pre p-1"
+                    >
+                        {move || {
+                            error_line()
+                                .map(|line| {
+                                    view! {
+                                        <div
+                                            class="absolute left-0 right-0 h-5 bg-red-700/50"
+                                            style=format!("top: calc({}rem + 0.25rem)", (line - 1) as f32 * 1.25)
+                                        />
+                                    }
+                                })
+                        }}
+                        {move || {
+                            tokenize(&textarea_content.read())
+                                .into_iter()
+                                .map(|(kind, content)| {
+                                    view! { <span class=format!("relative {}", kind.classes())>{content}</span> }
+                                })
+                                .collect::<Vec<_>>()
+                        }}
+                    </pre>
+                    <textarea
+                        node_ref=textarea_ref
+                        id="xml-edit-content"
+                        class="relative whitespace-pre bg-transparent p-1 text-transparent caret-white"
+                        rows=13
+                        cols=90
+                        wrap="off"
+                        prop:value=move || textarea_content.get()
+                        autocomplete="false"
+                        spellcheck="false"
+                        on:input:target=move |ev| {
+                            *textarea_content.write() = ev.target().value();
+                            xml_state.set(XmlState::Unchecked);
+                        }
+                        on:scroll=move |_| sync_scroll()
+                    />
+                </div>
+            </div>
             <div>
                 <button
                     on:click=move |_| {
@@ -138,6 +346,49 @@ pub fn XmlEditor(
                     Save
                 </button>
             </div>
+            {move || match xml_state.get() {
+                XmlState::AwaitingConfirmation(blocks_from_xml) => {
+                    let current = blocks
+                        .with_untracked(|b| {
+                            b.iter().map(|b| b.inner.clone().into()).collect::<Vec<Block>>()
+                        });
+                    let alignment = align_blocks(&current, &blocks_from_xml);
+                    let counts = ChangeCounts::of_alignment(&alignment);
+                    let changes = alignment
+                        .into_iter()
+                        .filter(|aligned| matches!(aligned, AlignedBlock::Disagreement { .. }))
+                        .collect::<Vec<_>>();
+                    Some(
+                        view! {
+                            <div class="m-3 border border-orange-400 p-2">
+                                <p>
+                                    {format!(
+                                        "Applying this XML changes the blocks: {} added, {} removed, {} changed.",
+                                        counts.added,
+                                        counts.removed,
+                                        counts.changed,
+                                    )}
+                                </p>
+                                <AlignmentView
+                                    alignment=changes
+                                    left_name="Current blocks".to_string()
+                                    right_name="From XML".to_string()
+                                />
+                                <button on:click=move |_| apply() class=DEFAULT_BUTTON_CLASSES>
+                                    "Apply changes"
+                                </button>
+                                <button
+                                    on:click=move |_| xml_state.set(XmlState::Unchecked)
+                                    class=DEFAULT_BUTTON_CLASSES
+                                >
+                                    "Keep editing"
+                                </button>
+                            </div>
+                        },
+                    )
+                }
+                _ => None,
+            }}
         </div>
     })
 }
@@ -0,0 +1,115 @@
+use super::highlight::{
+    line_and_column, line_range_utf16, locate_syntax_error, tokenize, TokenKind,
+};
+
+#[test]
+fn tokens_concatenate_to_the_input() {
+    for xml in [
+        "",
+        "plain text",
+        r#"<?xml version="1.0"?><div n='1'>text<!-- note --></div>"#,
+        "<unclosed attr=\"value",
+        "<p>\n  בְּרֵאשִׁית\n</p>\n",
+    ] {
+        assert_eq!(
+            tokenize(xml)
+                .into_iter()
+                .map(|(_, t)| t)
+                .collect::<String>(),
+            xml
+        );
+    }
+}
+
+#[test]
+fn tokens_are_classified() {
+    assert_eq!(
+        tokenize(r#"<seg xml:lang="hbo">a</seg><!-- c -->"#),
+        vec![
+            (TokenKind::Tag, "<seg".to_string()),
+            (TokenKind::Text, " ".to_string()),
+            (TokenKind::Attribute, "xml:lang".to_string()),
+            (TokenKind::Tag, "=".to_string()),
+            (TokenKind::Value, r#""hbo""#.to_string()),
+            (TokenKind::Tag, ">".to_string()),
+            (TokenKind::Text, "a".to_string()),
+            (TokenKind::Tag, "</seg>".to_string()),
+            (TokenKind::Comment, "<!-- c -->".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn positions_are_one_based() {
+    let text = "ab\ncd\nאב";
+    assert_eq!(line_and_column(text, 0), (1, 1));
+    assert_eq!(line_and_column(text, 4), (2, 2));
+    // the second hebrew letter starts at byte 8
+    assert_eq!(line_and_column(text, 8), (3, 2));
+    assert_eq!(line_and_column(text, 1000), (3, 3));
+}
+
+#[test]
+fn syntax_errors_are_located() {
+    assert_eq!(locate_syntax_error("<a><b></b></a>"), None);
+    let (line, _column) = locate_syntax_error("<a>\n<b>\n</c>\n</a>").unwrap();
+    assert_eq!(line, 3);
+}
+
+#[test]
+fn line_ranges_use_utf16() {
+    let text = "ab\nאב\ncd";
+    assert_eq!(line_range_utf16(text, 1), (0, 2));
+    assert_eq!(line_range_utf16(text, 2), (3, 5));
+    assert_eq!(line_range_utf16(text, 3), (6, 8));
+}
+
+#[test]
+fn changes_are_counted() {
+    use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+
+    use super::ChangeCounts;
+    use crate::reconcile::align_blocks;
+
+    let text = |content: &str| {
+        Block::from_type_lang_and_content(
+            BlockType::Text,
+            "hbo-Hebr".to_string(),
+            content.to_string(),
+        )
+    };
+    let current = vec![text("a"), text("b"), text("c")];
+    assert_eq!(
+        ChangeCounts::of_alignment(&align_blocks(&current, &current)),
+        ChangeCounts::default()
+    );
+    let from_xml = vec![text("a"), text("B"), text("c"), text("d")];
+    assert_eq!(
+        ChangeCounts::of_alignment(&align_blocks(&current, &from_xml)),
+        ChangeCounts {
+            added: 1,
+            removed: 0,
+            changed: 1,
+        }
+    );
+    assert_eq!(
+        ChangeCounts::of_alignment(&align_blocks(&current, &[])),
+        ChangeCounts {
+            added: 0,
+            removed: 3,
+            changed: 0,
+        }
+    );
+}
+
+#[test]
+fn anchors_are_located() {
+    use super::locate_anchor;
+
+    let xml = "<anchor xml:id=\"A_V_M_1\"/>\n<p>a</p>\n  <anchor xml:id=\"A_V_M_1\"/>";
+    assert_eq!(locate_anchor(xml, "A_V_M_1", 0), Some((1, 17)));
+    assert_eq!(locate_anchor(xml, "A_V_M_1", 1), Some((3, 19)));
+    assert_eq!(locate_anchor(xml, "A_V_M_1", 2), None);
+    // only whole attribute values count
+    assert_eq!(locate_anchor(xml, "A_V_M_", 0), None);
+}
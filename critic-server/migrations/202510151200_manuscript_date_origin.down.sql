@@ -0,0 +1 @@
+ALTER TABLE manuscript DROP COLUMN date, DROP COLUMN origin;
@@ -0,0 +1,6 @@
+--- more metainformation on manuscripts
+ALTER TABLE manuscript
+	--- when this manuscript was written (e.g. 10th century)
+	ADD COLUMN date TEXT,
+	--- where this manuscript was written (e.g. Tiberias)
+	ADD COLUMN origin TEXT;
@@ -0,0 +1,3 @@
+--- sessions without refresh token have to log in again
+DELETE FROM user_session WHERE refresh_token IS NULL;
+ALTER TABLE user_session ALTER COLUMN refresh_token SET NOT NULL;
@@ -0,0 +1,2 @@
+--- not all oauth providers issue refresh tokens
+ALTER TABLE user_session ALTER COLUMN refresh_token DROP NOT NULL;
@@ -0,0 +1,3 @@
+--- a tiny version of the page image as data url, shown while the preview loads
+--- pages minified before this was introduced do not have one
+ALTER TABLE page ADD COLUMN placeholder TEXT;
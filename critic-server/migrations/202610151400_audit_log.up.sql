@@ -0,0 +1,11 @@
+--- who changed what, kept as a compliance trail for the project
+CREATE TABLE audit_log (
+	id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	username TEXT NOT NULL,
+	action TEXT NOT NULL,
+	--- what was changed, e.g. the manuscript or page name
+	target TEXT NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	details JSONB NOT NULL DEFAULT '{}'
+);
+CREATE INDEX audit_log_created_at ON audit_log (created_at);
@@ -0,0 +1,2 @@
+--- incremented on every metadata update, so concurrent edits of the same manuscript are detected
+ALTER TABLE manuscript ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+--- manuscripts added before this migration all count as added now
+ALTER TABLE manuscript ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
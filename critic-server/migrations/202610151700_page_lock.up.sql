@@ -0,0 +1,7 @@
+--- a user claiming a page while transcribing it, only used if locking is enabled in the config
+CREATE TABLE page_lock (
+	page BIGINT PRIMARY KEY REFERENCES page(id) ON DELETE CASCADE,
+	username TEXT NOT NULL,
+	--- the lock is ignored after this, so a crashed editor does not block the page forever
+	expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,9 @@
+--- how much each user uploaded per day, to enforce the upload quotas from the config
+CREATE TABLE upload_usage (
+	username TEXT NOT NULL,
+	day DATE NOT NULL DEFAULT current_date,
+	--- sum of the sizes of the uploaded page images
+	bytes BIGINT NOT NULL DEFAULT 0,
+	pages BIGINT NOT NULL DEFAULT 0,
+	PRIMARY KEY (username, day)
+);
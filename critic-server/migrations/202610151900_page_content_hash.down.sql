@@ -0,0 +1,2 @@
+DROP INDEX page_content_hash;
+ALTER TABLE page DROP COLUMN content_hash;
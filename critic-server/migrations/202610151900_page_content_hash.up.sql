@@ -0,0 +1,4 @@
+--- SHA-256 (hex) of the uploaded image, to notice the same scan being uploaded twice
+--- NULL for pages uploaded before this was recorded
+ALTER TABLE page ADD COLUMN content_hash TEXT;
+CREATE UNIQUE INDEX page_content_hash ON page (manuscript, content_hash);
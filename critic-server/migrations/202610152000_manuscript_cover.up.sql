@@ -0,0 +1,2 @@
+--- the page representing the manuscript, NULL means the first page
+ALTER TABLE manuscript ADD COLUMN cover_page_id BIGINT REFERENCES page(id) ON DELETE SET NULL;
@@ -0,0 +1,9 @@
+DROP TRIGGER transcription_updated_at ON transcription;
+DROP TRIGGER page_updated_at ON page;
+DROP TRIGGER manuscript_updated_at ON manuscript;
+DROP FUNCTION set_updated_at();
+ALTER TABLE transcription DROP COLUMN updated_at;
+ALTER TABLE transcription DROP COLUMN created_at;
+ALTER TABLE page DROP COLUMN updated_at;
+ALTER TABLE page DROP COLUMN created_at;
+ALTER TABLE manuscript DROP COLUMN updated_at;
@@ -0,0 +1,23 @@
+--- when rows of the core tables were created and last changed
+--- rows created before this migration all count as created now
+ALTER TABLE manuscript ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE page ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE page ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE transcription ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE transcription ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+--- bump updated_at on every update and never change created_at, whatever the query says
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+	NEW.created_at = OLD.created_at;
+	NEW.updated_at = now();
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER manuscript_updated_at BEFORE UPDATE ON manuscript
+	FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+CREATE TRIGGER page_updated_at BEFORE UPDATE ON page
+	FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+CREATE TRIGGER transcription_updated_at BEFORE UPDATE ON transcription
+	FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1 @@
+ALTER TABLE manuscript DROP COLUMN public;
@@ -0,0 +1,2 @@
+--- published transcriptions of public manuscripts can be read without logging in
+ALTER TABLE manuscript ADD COLUMN public BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+ALTER TABLE user_session DROP COLUMN subject;
@@ -0,0 +1,3 @@
+--- users are identified by the subject their oauth provider reports, not by their username
+--- sessions from before this get their subject on the next login
+ALTER TABLE user_session ADD COLUMN subject TEXT;
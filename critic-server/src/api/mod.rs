@@ -0,0 +1,81 @@
+//! Read-only JSON api for external tools
+//!
+//! All endpoints return the same types the app uses internally, serialized as JSON.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    db::{get_manuscript_pages, get_manuscripts, DBError},
+};
+
+/// Body returned for all failed requests
+#[derive(Serialize)]
+struct ApiError {
+    error: String,
+}
+
+/// Turn a db error into the matching JSON response
+fn error_response(e: DBError) -> Response {
+    let status = match e {
+        DBError::ManuscriptDoesNotExist(_) => StatusCode::NOT_FOUND,
+        _ => {
+            tracing::warn!("Failed to answer api request: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (
+        status,
+        Json(ApiError {
+            error: e.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// The router for the JSON api
+pub fn api_router() -> axum::Router {
+    axum::Router::new()
+        .route("/manuscripts", axum::routing::get(manuscripts))
+        .route("/manuscripts/{msname}", axum::routing::get(manuscript))
+        .route("/manuscripts/{msname}/pages", axum::routing::get(pages))
+}
+
+/// Metainformation for all manuscripts
+async fn manuscripts(Extension(config): Extension<Arc<Config>>) -> Response {
+    match get_manuscripts(&config.db).await {
+        Ok(x) => Json(x).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Metainformation and pages for a single manuscript
+async fn manuscript(
+    Extension(config): Extension<Arc<Config>>,
+    Path(msname): Path<String>,
+) -> Response {
+    match config
+        .manuscript_cache
+        .manuscript(&config.db, &msname)
+        .await
+    {
+        Ok(x) => Json(x).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// The pages of a single manuscript
+async fn pages(Extension(config): Extension<Arc<Config>>, Path(msname): Path<String>) -> Response {
+    match get_manuscript_pages(&config.db, &msname).await {
+        Ok(x) => Json(x).into_response(),
+        Err(e) => error_response(e),
+    }
+}
@@ -0,0 +1,32 @@
+//! A compliance trail of who changed what
+//!
+//! Every mutating action of a user (adding, changing and deleting manuscripts, pages,
+//! versification schemes, publishing transcriptions, ...) is recorded with [`record_audit`].
+//! Failing to record an entry never fails the action itself.
+
+use sqlx::{Pool, Postgres};
+
+use crate::db::add_audit_entry;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Record that `username` did `action` to `target`
+///
+/// `details` should be a JSON object with whatever else is needed to understand the action
+/// later. Errors are logged, but not returned: the action already happened, so failing it now
+/// would only hide that from the user.
+pub async fn record_audit(
+    pool: &Pool<Postgres>,
+    username: &str,
+    action: &str,
+    target: &str,
+    details: serde_json::Value,
+) {
+    if let Err(e) = add_audit_entry(pool, username, action, target, &details.to_string()).await {
+        tracing::error!(
+            "AUDIT LOG INCOMPLETE: failed to record that {username} did {action} on {target} ({details}): {e}"
+        );
+    };
+}
@@ -0,0 +1,41 @@
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+
+use super::record_audit;
+use crate::db::get_audit_entries;
+
+#[sqlx::test]
+async fn records_entries(pool: Pool<Postgres>) {
+    record_audit(
+        &pool,
+        "alice",
+        "rename_page",
+        "Codex A/1r",
+        json!({"new_name": "2r"}),
+    )
+    .await;
+    record_audit(&pool, "bob", "delete_manuscript", "Codex B", json!({})).await;
+
+    let entries = get_audit_entries(&pool, 0).await.unwrap();
+    assert_eq!(entries.len(), 2);
+    // newest first
+    assert_eq!(entries[0].username, "bob");
+    assert_eq!(entries[0].action, "delete_manuscript");
+    assert_eq!(entries[0].target, "Codex B");
+    assert_eq!(entries[1].username, "alice");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&entries[1].details).unwrap(),
+        json!({"new_name": "2r"})
+    );
+    assert!(get_audit_entries(&pool, 1).await.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn failure_is_not_fatal(pool: Pool<Postgres>) {
+    sqlx::query!("DROP TABLE audit_log;")
+        .execute(&pool)
+        .await
+        .unwrap();
+    // must neither panic nor return an error
+    record_audit(&pool, "alice", "add_manuscript", "Codex A", json!({})).await;
+}
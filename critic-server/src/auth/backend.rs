@@ -12,7 +12,8 @@ use reqwest::StatusCode;
 use serde::Deserialize;
 use tracing::{error, warn};
 
-use super::{AuthSession, Credentials};
+use super::{AuthSession, BackendError, Credentials};
+use crate::db::DBError;
 
 pub fn auth_router() -> Router {
     Router::new()
@@ -106,6 +107,10 @@ pub async fn oauth_redirect_endpoint(
             warn!("Got Oauth Redirect but csrf state was invalid.");
             return StatusCode::UNAUTHORIZED.into_response();
         }
+        Err(axum_login::Error::Backend(BackendError::DB(e @ DBError::UsernameTaken(_)))) => {
+            warn!("Refusing login: {e}");
+            return (StatusCode::CONFLICT, e.to_string()).into_response();
+        }
         Err(e) => {
             error!("Tried to authenticate user in the oauth redirect, but got the following error: {e}");
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -0,0 +1,91 @@
+//! Logging in with github
+//!
+//! Members of the configured organization may use critic.
+
+use reqwest::{header::USER_AGENT, StatusCode};
+use serde::Deserialize;
+
+use super::{
+    http::ProviderClient,
+    provider::{OauthProvider, ProviderError, UserRole},
+    AuthenticatedUser, UserInfo,
+};
+
+const AUTH_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const USER_INFO_URL: &str = "https://api.github.com/user";
+/// github requires a user agent for all api requests
+/// See: https://docs.github.com/en/rest/overview/resources-in-the-rest-api?apiVersion=2022-11-28#user-agent-required
+pub(super) const API_USER_AGENT: &str = "axum-login";
+
+/// The JSON object returned from githubs get-user endpoint
+#[derive(Deserialize)]
+struct GithubUser {
+    id: i32,
+    login: String,
+}
+
+#[derive(Debug)]
+pub struct GithubProvider {
+    /// The organization, members of which have access to critic
+    org_name: String,
+}
+impl GithubProvider {
+    pub fn new(org_name: &str) -> Self {
+        Self {
+            org_name: org_name.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OauthProvider for GithubProvider {
+    fn auth_url(&self) -> &str {
+        AUTH_URL
+    }
+
+    fn token_url(&self) -> &str {
+        TOKEN_URL
+    }
+
+    fn user_info_url(&self) -> &str {
+        USER_INFO_URL
+    }
+
+    fn parse_user_info(&self, user_info: serde_json::Value) -> Result<UserInfo, ProviderError> {
+        let user: GithubUser =
+            serde_json::from_value(user_info).map_err(ProviderError::UserInfo)?;
+        Ok(UserInfo {
+            id: user.id,
+            subject: user.id.to_string(),
+            login: user.login,
+        })
+    }
+
+    async fn user_role(
+        &self,
+        client: &ProviderClient,
+        _user_info_url: &str,
+        user: &AuthenticatedUser,
+    ) -> Result<UserRole, ProviderError> {
+        let request_url = format!(
+            "https://api.github.com/orgs/{}/members/{}",
+            urlencoding::encode(&self.org_name),
+            user.username
+        );
+        let response = client
+            .send(
+                client
+                    .get(request_url)
+                    .header(USER_AGENT.as_str(), API_USER_AGENT)
+                    .bearer_auth(user.access_token.clone()),
+            )
+            .await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(UserRole::Member),
+            StatusCode::NOT_FOUND => Ok(UserRole::Outsider),
+            c => Err(ProviderError::BadStatusCode(c)),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Logging in with gitlab (gitlab.com or a self-hosted instance)
+//!
+//! Members of the configured group (including inherited members) may use critic.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use super::{
+    http::ProviderClient,
+    provider::{OauthProvider, ProviderError, UserRole},
+    AuthenticatedUser, UserInfo,
+};
+
+/// Used when `oauth.base_url` is not set
+pub const DEFAULT_BASE_URL: &str = "https://gitlab.com";
+
+/// The JSON object returned from gitlabs user endpoint
+#[derive(Deserialize)]
+struct GitlabUser {
+    id: i32,
+    username: String,
+}
+
+#[derive(Debug)]
+pub struct GitlabProvider {
+    base_url: String,
+    auth_url: String,
+    token_url: String,
+    user_info_url: String,
+    /// The group, members of which have access to critic
+    group: String,
+}
+impl GitlabProvider {
+    pub fn new(base_url: &str, group: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        Self {
+            base_url: base_url.to_string(),
+            auth_url: format!("{base_url}/oauth/authorize"),
+            token_url: format!("{base_url}/oauth/token"),
+            user_info_url: format!("{base_url}/api/v4/user"),
+            group: group.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OauthProvider for GitlabProvider {
+    fn auth_url(&self) -> &str {
+        &self.auth_url
+    }
+
+    fn token_url(&self) -> &str {
+        &self.token_url
+    }
+
+    fn user_info_url(&self) -> &str {
+        &self.user_info_url
+    }
+
+    fn parse_user_info(&self, user_info: serde_json::Value) -> Result<UserInfo, ProviderError> {
+        let user: GitlabUser =
+            serde_json::from_value(user_info).map_err(ProviderError::UserInfo)?;
+        Ok(UserInfo {
+            id: user.id,
+            subject: user.id.to_string(),
+            login: user.username,
+        })
+    }
+
+    async fn user_role(
+        &self,
+        client: &ProviderClient,
+        _user_info_url: &str,
+        user: &AuthenticatedUser,
+    ) -> Result<UserRole, ProviderError> {
+        let request_url = format!(
+            "{}/api/v4/groups/{}/members/all/{}",
+            self.base_url,
+            urlencoding::encode(&self.group),
+            user.id
+        );
+        let response = client
+            .send(
+                client
+                    .get(request_url)
+                    .bearer_auth(user.access_token.clone()),
+            )
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(UserRole::Member),
+            StatusCode::NOT_FOUND => Ok(UserRole::Outsider),
+            c => Err(ProviderError::BadStatusCode(c)),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! The HTTP client used for calls to the api of the oauth provider
+//!
+//! It is built once, so that connections are reused across requests. Requests are retried a few
+//! times when the provider cannot be reached, times out or answers with a 5xx status.
+//!
+//! The token exchange does not go through here: the authorization code can only be used once and
+//! redirects must not be followed there, see [`super::OauthBackend`].
+
+use std::time::Duration;
+
+use reqwest::{IntoUrl, RequestBuilder, Response};
+
+/// Pause before the first retry, doubled for every further one
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub struct ProviderClient {
+    client: reqwest::Client,
+    /// How often a failed request is repeated
+    retries: u8,
+}
+impl ProviderClient {
+    /// Give up on a single attempt after `timeout`, repeat failed requests `retries` times
+    pub fn new(timeout: Duration, retries: u8) -> Self {
+        Self {
+            client: reqwest::ClientBuilder::new()
+                .timeout(timeout)
+                .build()
+                .expect("static client"),
+            retries,
+        }
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send `request`, retrying on transient failures
+    ///
+    /// After the last retry, its result is returned as is, even for a 5xx status.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let mut delay = RETRY_DELAY;
+        for attempt in 0..self.retries {
+            // requests with streamed bodies cannot be sent twice
+            let Some(try_request) = request.try_clone() else {
+                break;
+            };
+            match try_request.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Err(e) if !is_transient(&e) => return Err(e),
+                Ok(response) => {
+                    tracing::debug!(
+                        "Attempt {} at the oauth provider failed with {}, retrying.",
+                        attempt + 1,
+                        response.status()
+                    );
+                }
+                Err(e) => {
+                    tracing::debug!(
+                        "Attempt {} at the oauth provider failed: {e}, retrying.",
+                        attempt + 1
+                    );
+                }
+            };
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        request.send().await
+    }
+}
+
+/// Could the request succeed when it is sent again?
+fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout()
+}
@@ -13,40 +13,68 @@ use crate::{
     db::{self, DBError},
 };
 
+pub mod github;
+pub mod gitlab;
+/// The client for requests to the api of the oauth provider
+pub mod http;
+pub mod oidc;
+/// The provider specific parts of the oauth flow
+pub mod provider;
+
+pub use provider::{user_may, user_profile, Action};
+use provider::{OauthProvider, ProviderError};
+
+// include tests
+#[cfg(test)]
+mod test;
+
 // some basic types used across the app
-/// The JSON object returned from githubs get-user endpoint
+/// The user as reported by the oauth provider
 #[derive(Debug, Deserialize)]
 pub struct UserInfo {
-    /// ID of the user in github - we use the same ID in the internal DB here
+    /// ID of the user in the oauth provider - we use the same ID in the internal DB here
     pub id: i32,
-    /// username of the user in github - we use the same here
+    /// The stable identifier of the user in the oauth provider
+    ///
+    /// A username is bound to the first subject logging in with it, see
+    /// [`db::insert_or_update_user_session`].
+    pub subject: String,
+    /// username of the user in the oauth provider - we use the same here
     pub login: String,
 }
-impl From<AuthenticatedUser> for UserInfo {
-    fn from(value: AuthenticatedUser) -> Self {
-        Self {
-            id: value.id,
-            login: value.username,
-        }
-    }
-}
 
 /// The full User with oauth2 credentials
 #[derive(Deserialize, Clone, sqlx::prelude::FromRow)]
 pub struct AuthenticatedUser {
     pub id: i32,
     pub username: String,
+    /// None for sessions from before subjects were stored, until the user logs in again
+    pub subject: Option<String>,
     pub access_token: String,
-    pub refresh_token: String,
+    /// Not all providers issue refresh tokens
+    pub refresh_token: Option<String>,
     pub expires_at: time::OffsetDateTime,
 }
+impl AuthenticatedUser {
+    /// Whether the user has to log in again to get a new access token
+    ///
+    /// This is the case once the access token expired and there is no refresh token to get a new
+    /// one with.
+    pub fn must_log_in_again(&self, now: time::OffsetDateTime) -> bool {
+        self.refresh_token.is_none() && self.expires_at <= now
+    }
+}
 impl std::fmt::Debug for AuthenticatedUser {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AuthenticatedUser")
             .field("id", &self.id)
             .field("username", &self.username)
+            .field("subject", &self.subject)
             .field("access_token", &"[redacted]")
-            .field("refresh_token", &"[redacted]")
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| "[redacted]"),
+            )
             .field("expires_at", &self.expires_at)
             .finish()
     }
@@ -54,15 +82,11 @@ impl std::fmt::Debug for AuthenticatedUser {
 
 #[derive(Debug)]
 pub enum NormalizeTokenResponseError {
-    NoRefresh,
     NoExpiresIn,
 }
 impl core::fmt::Display for NormalizeTokenResponseError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
-            Self::NoRefresh => {
-                write!(f, "No refresh token was given")
-            }
             Self::NoExpiresIn => {
                 write!(f, "No expires_in time was given")
             }
@@ -73,7 +97,7 @@ impl std::error::Error for NormalizeTokenResponseError {}
 #[derive(Debug)]
 pub struct NormalizedTokenResponse {
     pub access_token: String,
-    pub refresh_token: String,
+    pub refresh_token: Option<String>,
     pub expires_at: time::OffsetDateTime,
 }
 impl
@@ -97,9 +121,7 @@ impl
             access_token: value.access_token().clone().into_secret(),
             refresh_token: value
                 .refresh_token()
-                .ok_or(NormalizeTokenResponseError::NoRefresh)?
-                .clone()
-                .into_secret(),
+                .map(|token| token.clone().into_secret()),
             expires_at,
         })
     }
@@ -108,11 +130,14 @@ impl
 /// has all the backend APIs for auth flows
 pub mod backend;
 
+/// Sessions are keyed on the username, which is unique and bound to one subject
+///
+/// The numeric id is not unique for all providers.
 impl AuthUser for AuthenticatedUser {
-    type Id = i32;
+    type Id = String;
 
     fn id(&self) -> Self::Id {
-        self.id
+        self.username.clone()
     }
 
     fn session_auth_hash(&self) -> &[u8] {
@@ -138,10 +163,13 @@ pub struct Credentials {
 pub enum BackendError {
     /// failure while talking to our postgres
     DB(DBError),
-    /// failure while calling the /oauth/token endpoint in github - could not get token
+    /// failure while calling the token endpoint of the oauth provider - could not get token
     TokenExchange(String),
     Reqwest(reqwest::Error),
-    Github(reqwest::Error),
+    /// The user info endpoint did not return JSON
+    UserInfoJson(reqwest::Error),
+    /// The provider could not make sense of the user info
+    Provider(ProviderError),
     TokenResponse(NormalizeTokenResponseError),
 }
 impl core::fmt::Display for BackendError {
@@ -159,13 +187,19 @@ impl core::fmt::Display for BackendError {
             Self::Reqwest(e) => {
                 write!(f, "Failure sending http request: {e}")
             }
-            Self::Github(e) => {
-                write!(f, "Failure to parse response JSON from github API: {e}")
+            Self::UserInfoJson(e) => {
+                write!(
+                    f,
+                    "Failure to parse user info JSON from the oauth provider: {e}"
+                )
+            }
+            Self::Provider(e) => {
+                write!(f, "Failure to get the user from the oauth provider: {e}")
             }
             Self::TokenResponse(e) => {
                 write!(
                     f,
-                    "Token response from githubs api was not as expected: {e}"
+                    "Token response from the oauth provider was not as expected: {e}"
                 )
             }
         }
@@ -173,17 +207,63 @@ impl core::fmt::Display for BackendError {
 }
 impl std::error::Error for BackendError {}
 
+/// The client for the token exchange, which must not follow redirects
+fn token_client() -> reqwest::Client {
+    reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("static client")
+}
+
 #[derive(Debug, Clone)]
-pub struct GithubOauthBackend {
+pub struct OauthBackend {
     db: sqlx::Pool<sqlx::Postgres>,
     client: crate::config::OauthClient,
+    provider: std::sync::Arc<dyn OauthProvider>,
+    /// the scopes requested in [`Self::authorize_url`]
+    scopes: Vec<Scope>,
+    user_info_url: String,
+    /// Used for the token exchange and the user info, does not follow redirects
+    token_client: reqwest::Client,
 }
 
-impl GithubOauthBackend {
+impl OauthBackend {
     pub fn new(config: std::sync::Arc<Config>) -> Self {
         let db = config.db.clone();
         let client = config.oauth_client.clone();
-        Self { db, client }
+        let provider = config.oauth_provider.clone();
+        let scopes = config
+            .oauth_scopes
+            .iter()
+            .map(|scope| Scope::new(scope.clone()))
+            .collect();
+        let user_info_url = config.oauth_user_info_url.clone();
+        Self {
+            db,
+            client,
+            provider,
+            scopes,
+            user_info_url,
+            token_client: token_client(),
+        }
+    }
+
+    /// A backend that does not need a full [`Config`]
+    #[cfg(test)]
+    pub(crate) fn from_parts(
+        db: sqlx::Pool<sqlx::Postgres>,
+        client: crate::config::OauthClient,
+        provider: std::sync::Arc<dyn OauthProvider>,
+    ) -> Self {
+        let user_info_url = provider.user_info_url().to_string();
+        Self {
+            db,
+            client,
+            provider,
+            scopes: vec![],
+            user_info_url,
+            token_client: token_client(),
+        }
     }
 
     /// URL to show to the user to start the oauth flow
@@ -195,7 +275,7 @@ impl GithubOauthBackend {
         let (url, csrf_token) = self
             .client
             .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("api".to_string()))
+            .add_scopes(self.scopes.clone())
             .set_pkce_challenge(pkce_challenge)
             .url();
         (url, csrf_token, pkce_verifier)
@@ -203,7 +283,7 @@ impl GithubOauthBackend {
 }
 
 #[async_trait::async_trait]
-impl AuthnBackend for GithubOauthBackend {
+impl AuthnBackend for OauthBackend {
     type User = AuthenticatedUser;
     type Credentials = Credentials;
     type Error = BackendError;
@@ -218,24 +298,21 @@ impl AuthnBackend for GithubOauthBackend {
         };
 
         // Process authorization code, expecting a token response back.
-        let client = reqwest::ClientBuilder::new()
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
-            .expect("static client");
+        let client = &self.token_client;
         let token_res = self
             .client
             // authorization code is known from session
             .exchange_code(AuthorizationCode::new(creds.code))
             // PKCE code verifier is known from session
             .set_pkce_verifier(PkceCodeVerifier::new(creds.pkce_verifier))
-            .request_async(&client)
+            .request_async(client)
             .await
             .map_err(|e| BackendError::TokenExchange(e.to_string()))?;
 
         // Use access token to request user info.
         let user_info = client
-            .get("https://api.github.com/user")
-            .header(USER_AGENT.as_str(), "axum-login") // See: https://docs.github.com/en/rest/overview/resources-in-the-rest-api?apiVersion=2022-11-28#user-agent-required
+            .get(&self.user_info_url)
+            .header(USER_AGENT.as_str(), github::API_USER_AGENT)
             .header(
                 AUTHORIZATION.as_str(),
                 format!("Bearer {}", token_res.access_token().secret()),
@@ -244,9 +321,13 @@ impl AuthnBackend for GithubOauthBackend {
             .await
             .map_err(Self::Error::Reqwest)?;
         let user_info = user_info
-            .json::<UserInfo>()
+            .json::<serde_json::Value>()
             .await
-            .map_err(Self::Error::Github)?;
+            .map_err(Self::Error::UserInfoJson)?;
+        let user_info = self
+            .provider
+            .parse_user_info(user_info)
+            .map_err(Self::Error::Provider)?;
 
         // Persist user in our database so we can use `get_user`.
         let user = db::insert_or_update_user_session(
@@ -260,18 +341,20 @@ impl AuthnBackend for GithubOauthBackend {
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-        sqlx::query_as!(
+        let user = sqlx::query_as!(
             AuthenticatedUser,
-            "select * from user_session where id = $1",
+            "select * from user_session where username = $1",
             user_id,
         )
         .fetch_optional(&self.db)
         .await
-        .map_err(|e| Self::Error::DB(DBError::CannotGetUsersession(e)))
+        .map_err(|e| Self::Error::DB(DBError::CannotGetUsersession(e)))?;
+        // without a refresh token, an expired session can only be renewed by logging in again
+        Ok(user.filter(|user| !user.must_log_in_again(time::OffsetDateTime::now_utc())))
     }
 }
 
 // We use a type alias for convenience.
 //
 // Note that we've supplied our concrete backend here.
-pub type AuthSession = axum_login::AuthSession<GithubOauthBackend>;
+pub type AuthSession = axum_login::AuthSession<OauthBackend>;
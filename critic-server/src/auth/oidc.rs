@@ -0,0 +1,149 @@
+//! Logging in with any OpenID Connect provider (Keycloak, Authentik, ...)
+//!
+//! The endpoints are read from the discovery document of the issuer. Users whose `groups` claim
+//! contains the configured group may use critic, so the provider has to include that claim in the
+//! user info.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use super::{
+    http::ProviderClient,
+    provider::{OauthProvider, ProviderError, UserRole},
+    AuthenticatedUser, UserInfo,
+};
+use crate::db::validate_username;
+
+/// The parts of the discovery document we need
+#[derive(Deserialize)]
+struct DiscoveryDocument {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+/// The claims we need from the user info endpoint
+#[derive(Deserialize)]
+struct OidcUser {
+    sub: String,
+    preferred_username: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+/// The numeric id stored for a subject
+///
+/// Numeric subjects are used as they are. All others (e.g. UUIDs in Keycloak) are hashed (32 bit
+/// FNV-1a), which is stable across restarts. Different subjects may get the same id, so users are
+/// identified by their subject instead. This id is only compared to claim sessions from before the
+/// subject was stored.
+pub(super) fn id_from_subject(sub: &str) -> i32 {
+    if let Ok(id) = sub.parse::<i32>() {
+        return id;
+    };
+    let hash = sub.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    // keep ids positive, like those of the other providers
+    (hash & 0x7fff_ffff) as i32
+}
+
+/// The user from the user info of any OpenID Connect provider
+///
+/// The username is `preferred_username`, or the subject if there is none. Unlike on github and
+/// gitlab, it can be anything, so names that cannot be used as file names are rejected.
+pub(super) fn parse_oidc_user_info(
+    user_info: serde_json::Value,
+) -> Result<UserInfo, ProviderError> {
+    let user: OidcUser = serde_json::from_value(user_info).map_err(ProviderError::UserInfo)?;
+    let login = user.preferred_username.unwrap_or_else(|| user.sub.clone());
+    validate_username(&login).map_err(|e| ProviderError::InvalidUsername(login.clone(), e))?;
+    Ok(UserInfo {
+        id: id_from_subject(&user.sub),
+        login,
+        subject: user.sub,
+    })
+}
+
+#[derive(Debug)]
+pub struct OidcProvider {
+    auth_url: String,
+    token_url: String,
+    user_info_url: String,
+    /// The group, members of which have access to critic
+    group: String,
+}
+impl OidcProvider {
+    /// Get the endpoints from the discovery document of `issuer`
+    pub async fn discover(
+        client: &ProviderClient,
+        issuer: &str,
+        group: &str,
+    ) -> Result<Self, ProviderError> {
+        let response = client
+            .send(client.get(format!(
+                "{}/.well-known/openid-configuration",
+                issuer.trim_end_matches('/')
+            )))
+            .await?;
+        if response.status() != StatusCode::OK {
+            return Err(ProviderError::BadStatusCode(response.status()));
+        };
+        let discovery: DiscoveryDocument = response.json().await?;
+        Ok(Self {
+            auth_url: discovery.authorization_endpoint,
+            token_url: discovery.token_endpoint,
+            user_info_url: discovery.userinfo_endpoint,
+            group: group.to_string(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl OauthProvider for OidcProvider {
+    fn auth_url(&self) -> &str {
+        &self.auth_url
+    }
+
+    fn token_url(&self) -> &str {
+        &self.token_url
+    }
+
+    fn user_info_url(&self) -> &str {
+        &self.user_info_url
+    }
+
+    fn parse_user_info(&self, user_info: serde_json::Value) -> Result<UserInfo, ProviderError> {
+        parse_oidc_user_info(user_info)
+    }
+
+    async fn user_role(
+        &self,
+        client: &ProviderClient,
+        user_info_url: &str,
+        user: &AuthenticatedUser,
+    ) -> Result<UserRole, ProviderError> {
+        let response = client
+            .send(
+                client
+                    .get(user_info_url)
+                    .bearer_auth(user.access_token.clone()),
+            )
+            .await?;
+        if response.status() != StatusCode::OK {
+            return Err(ProviderError::BadStatusCode(response.status()));
+        };
+        let user: OidcUser = response.json().await?;
+        // keycloak reports groups as paths, e.g. `/critic`
+        let group = self.group.trim_start_matches('/');
+        if user
+            .groups
+            .iter()
+            .any(|g| g.trim_start_matches('/') == group)
+        {
+            Ok(UserRole::Member)
+        } else {
+            Ok(UserRole::Outsider)
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! The parts of the oauth flow that differ between providers
+//!
+//! The oauth2 flow itself and the session handling are the same for all providers. A provider only
+//! knows its endpoints, how its user info looks and how to find out whether a user is a member of
+//! the group that may use critic.
+
+use std::sync::Arc;
+
+use axum_login::tower_sessions::Session;
+use critic_shared::UserProfile;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use super::{http::ProviderClient, AuthenticatedUser, UserInfo};
+use crate::config::Config;
+
+/// The provider to log in with, as set in the config
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Github,
+    Gitlab,
+    /// Any OpenID Connect provider (Keycloak, Authentik, ...) configured via its discovery document
+    GenericOidc,
+}
+
+/// What a logged in user may do in critic
+///
+/// Ordered from least to most privileged.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    /// Logged in, but not member of the configured group
+    Outsider,
+    /// Member of the configured group
+    Member,
+}
+
+/// Things that need a minimum [`UserRole`], configured in [`crate::config::PermissionsConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Start, save and publish transcriptions and reconciliations
+    Transcribe,
+    /// Upload page images
+    Upload,
+    /// Add, change and delete manuscripts and pages, import transcriptions
+    Administer,
+}
+impl core::fmt::Display for Action {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Transcribe => write!(f, "transcribe"),
+            Self::Upload => write!(f, "upload"),
+            Self::Administer => write!(f, "administer"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProviderError {
+    /// Reqwest had problems making the request itself
+    Reqwest(reqwest::Error),
+    /// The status code from the providers api was not what we assumed
+    BadStatusCode(StatusCode),
+    /// The user info returned by the provider does not have the expected shape
+    UserInfo(serde_json::Error),
+    /// A setting required for this provider is missing in the config
+    MissingSetting(&'static str),
+    /// The provider reported a username critic cannot use - (username, problem)
+    InvalidUsername(String, crate::db::DBError),
+}
+impl From<reqwest::Error> for ProviderError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Reqwest(value)
+    }
+}
+impl core::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Reqwest(e) => {
+                write!(f, "Unable to complete HTTP request: {e}")
+            }
+            Self::BadStatusCode(code) => {
+                write!(
+                    f,
+                    "Got the following status code from the oauth provider: {code}"
+                )
+            }
+            Self::UserInfo(e) => {
+                write!(f, "Unable to parse user info from the oauth provider: {e}")
+            }
+            Self::MissingSetting(name) => {
+                write!(f, "oauth.{name} must be set for this provider")
+            }
+            Self::InvalidUsername(username, e) => {
+                write!(
+                    f,
+                    "The oauth provider reported the username {username:?}: {e}"
+                )
+            }
+        }
+    }
+}
+impl core::error::Error for ProviderError {}
+
+/// Everything that is specific to one oauth provider
+#[async_trait::async_trait]
+pub trait OauthProvider: Send + Sync + core::fmt::Debug {
+    /// Where users are sent to log in
+    fn auth_url(&self) -> &str;
+    /// Where authorization codes are exchanged for tokens
+    fn token_url(&self) -> &str;
+    /// Where to get information about the logged in user with their access token
+    fn user_info_url(&self) -> &str;
+    /// Turn the response from [`Self::user_info_url`] into the user we store
+    fn parse_user_info(&self, user_info: serde_json::Value) -> Result<UserInfo, ProviderError>;
+    /// What this user may do in critic
+    ///
+    /// `user_info_url` is the configured `oauth.user_info_url`, i.e. the endpoint users logged in
+    /// with. Providers that read the role from the user info have to ask the same endpoint.
+    async fn user_role(
+        &self,
+        client: &ProviderClient,
+        user_info_url: &str,
+        user: &AuthenticatedUser,
+    ) -> Result<UserRole, ProviderError>;
+}
+
+/// Create the provider selected in the config
+///
+/// `group` is the organization or group whose members may use critic. `base_url` is the gitlab
+/// instance, `issuer` the OpenID Connect issuer.
+pub async fn create_provider(
+    client: &ProviderClient,
+    kind: ProviderKind,
+    group: &str,
+    base_url: Option<&str>,
+    issuer: Option<&str>,
+) -> Result<Arc<dyn OauthProvider>, ProviderError> {
+    Ok(match kind {
+        ProviderKind::Github => Arc::new(super::github::GithubProvider::new(group)),
+        ProviderKind::Gitlab => Arc::new(super::gitlab::GitlabProvider::new(
+            base_url.unwrap_or(super::gitlab::DEFAULT_BASE_URL),
+            group,
+        )),
+        ProviderKind::GenericOidc => Arc::new(
+            super::oidc::OidcProvider::discover(
+                client,
+                issuer.ok_or(ProviderError::MissingSetting("issuer"))?,
+                group,
+            )
+            .await?,
+        ),
+    })
+}
+
+/// May this user do `action`, according to the configured permissions?
+///
+/// The provider is only asked when logging in is not enough for `action`.
+pub async fn user_may(
+    config: Arc<Config>,
+    user: &AuthenticatedUser,
+    action: Action,
+) -> Result<bool, ProviderError> {
+    if config.permissions.allows(action, UserRole::Outsider) {
+        return Ok(true);
+    };
+    let role = config
+        .oauth_provider
+        .user_role(&config.provider_client, &config.oauth_user_info_url, user)
+        .await?;
+    Ok(config.permissions.allows(action, role))
+}
+
+/// The key in the session store under which [`user_profile`] keeps its result
+const USER_PROFILE_KEY: &str = "user.profile";
+
+/// The logged in user and what they may do, asking the provider once per session
+///
+/// Changes to the group membership show up after logging in again.
+pub async fn user_profile(
+    config: Arc<Config>,
+    session: &Session,
+    user: &AuthenticatedUser,
+) -> Result<UserProfile, ProviderError> {
+    match session.get::<UserProfile>(USER_PROFILE_KEY).await {
+        Ok(Some(profile)) if profile.username == user.username => return Ok(profile),
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!(
+                "Failed to read the cached profile of {}: {e}",
+                user.username
+            );
+        }
+    };
+    let role = config
+        .oauth_provider
+        .user_role(&config.provider_client, &config.oauth_user_info_url, user)
+        .await?;
+    let profile = config.permissions.profile(user.username.clone(), role);
+    if let Err(e) = session.insert(USER_PROFILE_KEY, &profile).await {
+        tracing::warn!("Failed to cache the profile of {}: {e}", user.username);
+    };
+    Ok(profile)
+}
@@ -0,0 +1,201 @@
+use axum::response::IntoResponse;
+use serde_json::json;
+
+use super::{
+    github::GithubProvider,
+    gitlab::GitlabProvider,
+    http::ProviderClient,
+    oidc::{id_from_subject, parse_oidc_user_info},
+    provider::{OauthProvider, UserRole},
+    AuthenticatedUser,
+};
+
+#[test]
+fn github_user_info() {
+    let provider = GithubProvider::new("org");
+    let user = provider
+        .parse_user_info(json!({"id": 12, "login": "octocat", "name": "The Octocat"}))
+        .unwrap();
+    assert_eq!(
+        (user.id, user.subject.as_str(), user.login.as_str()),
+        (12, "12", "octocat")
+    );
+    assert!(provider.parse_user_info(json!({"id": 12})).is_err());
+}
+
+#[test]
+fn gitlab_endpoints_and_user_info() {
+    let provider = GitlabProvider::new("https://gitlab.example.com/", "group");
+    assert_eq!(
+        provider.auth_url(),
+        "https://gitlab.example.com/oauth/authorize"
+    );
+    assert_eq!(
+        provider.token_url(),
+        "https://gitlab.example.com/oauth/token"
+    );
+    assert_eq!(
+        provider.user_info_url(),
+        "https://gitlab.example.com/api/v4/user"
+    );
+    let user = provider
+        .parse_user_info(json!({"id": 7, "username": "tanakh"}))
+        .unwrap();
+    assert_eq!(
+        (user.id, user.subject.as_str(), user.login.as_str()),
+        (7, "7", "tanakh")
+    );
+}
+
+#[test]
+fn oidc_user_info() {
+    let user = parse_oidc_user_info(json!({"sub": "abc", "preferred_username": "tanakh"})).unwrap();
+    assert_eq!(
+        (user.subject.as_str(), user.login.as_str()),
+        ("abc", "tanakh")
+    );
+    assert_eq!(
+        parse_oidc_user_info(json!({"sub": "abc"})).unwrap().login,
+        "abc"
+    );
+    // usernames become file names
+    for login in ["../admin", "a/b", "..", ".hidden", ""] {
+        assert!(
+            parse_oidc_user_info(json!({"sub": "abc", "preferred_username": login})).is_err(),
+            "{login} was accepted"
+        );
+    }
+    assert!(parse_oidc_user_info(json!({"sub": "../abc"})).is_err());
+}
+
+#[test]
+fn oidc_subject_ids() {
+    assert_eq!(id_from_subject("1234"), 1234);
+    let uuid = "0f8fad5b-d9cb-469f-a165-70867728950e";
+    // stable and positive
+    assert_eq!(id_from_subject(uuid), id_from_subject(uuid));
+    assert!(id_from_subject(uuid) >= 0);
+    assert_ne!(id_from_subject(uuid), id_from_subject("another-subject"));
+}
+
+fn user(refresh_token: Option<&str>, expires_at: time::OffsetDateTime) -> AuthenticatedUser {
+    AuthenticatedUser {
+        id: 1,
+        username: "user".to_string(),
+        subject: Some("1".to_string()),
+        access_token: "access".to_string(),
+        refresh_token: refresh_token.map(str::to_string),
+        expires_at,
+    }
+}
+
+#[test]
+fn expired_session_without_refresh_token_needs_login() {
+    let now = time::OffsetDateTime::now_utc();
+    let hour = time::Duration::hours(1);
+    assert!(user(None, now - hour).must_log_in_again(now));
+    assert!(!user(None, now + hour).must_log_in_again(now));
+    assert!(!user(Some("refresh"), now - hour).must_log_in_again(now));
+}
+
+#[test]
+fn refresh_token_is_redacted() {
+    let now = time::OffsetDateTime::now_utc();
+    let debug = format!("{:?}", user(Some("secret-refresh"), now));
+    assert!(!debug.contains("secret-refresh"));
+    assert!(!debug.contains("access\""));
+}
+
+/// Serve `statuses` in order, one per request, and the last one forever after
+async fn flaky_server(statuses: Vec<u16>) -> String {
+    let served = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let app = axum::Router::new().route(
+        "/",
+        axum::routing::get(move || {
+            let served = served.clone();
+            let statuses = statuses.clone();
+            async move {
+                let idx = served.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let status = statuses[idx.min(statuses.len() - 1)];
+                reqwest::StatusCode::from_u16(status).unwrap()
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}/")
+}
+
+#[tokio::test]
+async fn provider_requests_are_retried_on_server_errors() {
+    let client = ProviderClient::new(std::time::Duration::from_secs(5), 2);
+    let url = flaky_server(vec![503, 502, 200]).await;
+    let response = client.send(client.get(&url)).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // the last answer is returned once the retries are used up
+    let url = flaky_server(vec![503, 503, 503, 200]).await;
+    let response = client.send(client.get(&url)).await.unwrap();
+    assert_eq!(response.status(), 503);
+
+    // client errors are not retried
+    let url = flaky_server(vec![404, 200]).await;
+    let response = client.send(client.get(&url)).await.unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+/// A gitlab whose group `parent/child` has user 7 as inherited member only
+async fn gitlab_with_inherited_member() -> String {
+    let app = axum::Router::new()
+        // direct members only - user 7 is not one of them
+        .route(
+            "/api/v4/groups/{group}/members/{id}",
+            axum::routing::get(|| async { reqwest::StatusCode::NOT_FOUND }),
+        )
+        .route(
+            "/api/v4/groups/{group}/members/all/{id}",
+            axum::routing::get(
+                |axum::extract::Path((group, id)): axum::extract::Path<(String, i32)>| async move {
+                    if group == "parent/child" && id == 7 {
+                        axum::Json(json!({"id": 7, "username": "tanakh", "access_level": 30}))
+                            .into_response()
+                    } else {
+                        reqwest::StatusCode::NOT_FOUND.into_response()
+                    }
+                },
+            ),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn gitlab_accepts_inherited_members_of_subgroups() {
+    let client = ProviderClient::new(std::time::Duration::from_secs(5), 0);
+    let base_url = gitlab_with_inherited_member().await;
+    let member = AuthenticatedUser {
+        id: 7,
+        ..user(None, time::OffsetDateTime::now_utc())
+    };
+    assert_eq!(
+        GitlabProvider::new(&base_url, "parent/child")
+            .user_role(&client, "", &member)
+            .await
+            .unwrap(),
+        UserRole::Member
+    );
+    let outsider = AuthenticatedUser {
+        id: 8,
+        ..user(None, time::OffsetDateTime::now_utc())
+    };
+    assert_eq!(
+        GitlabProvider::new(&base_url, "parent/child")
+            .user_role(&client, "", &outsider)
+            .await
+            .unwrap(),
+        UserRole::Outsider
+    );
+}
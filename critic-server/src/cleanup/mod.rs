@@ -0,0 +1,212 @@
+//! Finding (and optionally deleting) image files that have no page in the db
+//!
+//! These are left behind when an upload fails halfway or when the minifier cannot unlink the
+//! original after converting it. The cleanup service periodically compares the directories under
+//! [`IMAGE_BASE_LOCATION`] with the `page` table.
+//!
+//! By default, orphans are only logged. They are deleted only if `delete_orphans` is set in the
+//! config. Anything changed recently is left alone, because it may belong to an upload or rename
+//! that is still in progress.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use critic_shared::urls::IMAGE_BASE_LOCATION;
+
+use crate::{
+    config::Config,
+    db::{get_all_pages, DBError, PAGE_MOVES},
+    signal_handler::InShutdown,
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Problems that can occur while looking for orphans
+#[derive(Debug)]
+enum CleanupError {
+    /// Cannot get the pages from the db
+    Db(DBError),
+    /// Cannot read this directory
+    ReadDir(String, std::io::Error),
+    /// The blocking task looking for orphans panicked or was cancelled
+    Join(tokio::task::JoinError),
+}
+impl core::fmt::Display for CleanupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Db(e) => {
+                write!(f, "Cannot get pages from the db: {e}")
+            }
+            Self::ReadDir(path, e) => {
+                write!(f, "Cannot read directory {path}: {e}")
+            }
+            Self::Join(e) => {
+                write!(f, "Cannot join the task looking for orphans: {e}")
+            }
+        }
+    }
+}
+impl core::error::Error for CleanupError {}
+
+/// The pages that exist in the db: (manuscript name, page name) -> whether the page is minified
+type KnownPages = HashMap<(String, String), bool>;
+
+/// All entries of a directory with their paths and names
+fn entries(path: &Path) -> Result<Vec<(PathBuf, String)>, CleanupError> {
+    let read_dir = match std::fs::read_dir(path) {
+        Ok(x) => x,
+        // nothing was uploaded yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(vec![]);
+        }
+        Err(e) => {
+            return Err(CleanupError::ReadDir(path.to_string_lossy().to_string(), e));
+        }
+    };
+    read_dir
+        .map(|entry| {
+            let entry =
+                entry.map_err(|e| CleanupError::ReadDir(path.to_string_lossy().to_string(), e))?;
+            Ok((
+                entry.path(),
+                entry.file_name().to_string_lossy().to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Was this path changed less than `min_age` before `now`?
+///
+/// Paths whose age cannot be determined are treated as recent, so they are never deleted.
+fn is_recent(path: &Path, min_age: Duration, now: SystemTime) -> bool {
+    match std::fs::metadata(path).and_then(|m| m.modified()) {
+        Ok(modified) => now
+            .duration_since(modified)
+            .map(|age| age < min_age)
+            .unwrap_or(true),
+        Err(_) => true,
+    }
+}
+
+/// Find all files and directories in `image_directory` without a page in the db
+///
+/// These are:
+/// - manuscript directories without any page in the db
+/// - page directories without a page in the db
+/// - `original` files of pages that are already minified
+fn find_orphans(
+    image_directory: &Path,
+    known_pages: &KnownPages,
+    min_age: Duration,
+    now: SystemTime,
+) -> Result<Vec<PathBuf>, CleanupError> {
+    let known_manuscripts = known_pages
+        .keys()
+        .map(|(msname, _)| msname.as_str())
+        .collect::<HashSet<_>>();
+    let mut orphans = Vec::new();
+    for (ms_path, msname) in entries(image_directory)? {
+        if !ms_path.is_dir() {
+            continue;
+        };
+        if !known_manuscripts.contains(msname.as_str()) {
+            if !is_recent(&ms_path, min_age, now) {
+                orphans.push(ms_path);
+            };
+            continue;
+        };
+        for (page_path, pagename) in entries(&ms_path)? {
+            match known_pages.get(&(msname.clone(), pagename)) {
+                None => {
+                    if !is_recent(&page_path, min_age, now) {
+                        orphans.push(page_path);
+                    };
+                }
+                Some(true) => {
+                    let original = page_path.join("original");
+                    if original.exists() && !is_recent(&original, min_age, now) {
+                        orphans.push(original);
+                    };
+                }
+                Some(false) => {}
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+/// Remove a single orphan, logging failures
+fn remove_orphan(path: &Path) {
+    let res = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    match res {
+        Ok(()) => {
+            tracing::info!("Deleted orphaned {}.", path.to_string_lossy());
+        }
+        Err(e) => {
+            tracing::warn!("Failed to delete orphaned {}: {e}", path.to_string_lossy());
+        }
+    };
+}
+
+/// Look for orphans once, and delete them if configured to
+///
+/// No page is renamed while this runs, see [`PAGE_MOVES`].
+async fn cleanup(config: &Arc<Config>) -> Result<(), CleanupError> {
+    let _no_moves = PAGE_MOVES.write().await;
+    let known_pages = get_all_pages(&config.db)
+        .await
+        .map_err(CleanupError::Db)?
+        .into_iter()
+        .map(|(msname, pagename, minified)| ((msname, pagename), minified))
+        .collect::<KnownPages>();
+    let image_directory = PathBuf::from(format!("{}{IMAGE_BASE_LOCATION}", config.data_directory));
+    let min_age = Duration::from_secs(config.cleanup.min_age_seconds);
+    let delete = config.cleanup.delete_orphans;
+    tokio::task::spawn_blocking(move || {
+        let orphans = find_orphans(&image_directory, &known_pages, min_age, SystemTime::now())?;
+        for orphan in orphans {
+            if delete {
+                remove_orphan(&orphan);
+            } else {
+                tracing::info!(
+                    "Found orphaned {} (not deleting, delete_orphans is off).",
+                    orphan.to_string_lossy()
+                );
+            };
+        }
+        Ok(())
+    })
+    .await
+    .map_err(CleanupError::Join)?
+}
+
+/// Run the cleanup service
+pub async fn run_cleanup(
+    config: Arc<Config>,
+    mut watcher: tokio::sync::watch::Receiver<InShutdown>,
+) {
+    tracing::debug!("Starting the cleanup service");
+    loop {
+        if let Err(e) = cleanup(&config).await {
+            tracing::warn!("Failed to look for orphaned files: {e}");
+        };
+        // now wait until the next run, or cancel the service if we are in shutdown
+        tokio::select! {
+            _ = watcher.changed() => {
+                tracing::debug!("Shutting down cleanup service now.");
+                return;
+            }
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.cleanup.interval_seconds)) => {}
+        };
+    }
+}
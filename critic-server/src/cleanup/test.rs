@@ -0,0 +1,61 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use super::{find_orphans, KnownPages};
+
+/// A fresh image directory for a single test
+fn image_directory(test_name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("critic-cleanup-{}-{test_name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+fn known_pages(pages: &[(&str, &str, bool)]) -> KnownPages {
+    pages
+        .iter()
+        .map(|(ms, page, minified)| ((ms.to_string(), page.to_string()), *minified))
+        .collect()
+}
+
+#[test]
+fn missing_image_directory_has_no_orphans() {
+    let dir = image_directory("missing");
+    assert_eq!(
+        find_orphans(&dir, &known_pages(&[]), Duration::ZERO, SystemTime::now()).unwrap(),
+        Vec::<PathBuf>::new()
+    );
+}
+
+#[test]
+fn finds_unknown_directories_and_leftover_originals() {
+    let dir = image_directory("orphans");
+    for page in ["ms/p1", "ms/p2", "ms/unknown", "other/p1"] {
+        std::fs::create_dir_all(dir.join(page)).unwrap();
+    }
+    std::fs::write(dir.join("ms/p1/original"), b"").unwrap();
+    std::fs::write(dir.join("ms/p2/original"), b"").unwrap();
+    let pages = known_pages(&[("ms", "p1", true), ("ms", "p2", false)]);
+    // make sure everything is older than the minimum age
+    let later = SystemTime::now() + Duration::from_secs(10);
+
+    let mut orphans = find_orphans(&dir, &pages, Duration::from_secs(5), later).unwrap();
+    orphans.sort();
+    assert_eq!(
+        orphans,
+        vec![
+            dir.join("ms/p1/original"),
+            dir.join("ms/unknown"),
+            dir.join("other")
+        ]
+    );
+
+    // nothing is old enough yet
+    assert_eq!(
+        find_orphans(&dir, &pages, Duration::from_secs(3600), later).unwrap(),
+        Vec::<PathBuf>::new()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
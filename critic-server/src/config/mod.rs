@@ -1,21 +1,50 @@
 //! Parse Config from config file
 
-use std::{fs::read_to_string, path::Path, str::FromStr};
+use std::{fs::read_to_string, str::FromStr, sync::Arc, time::Duration};
 
+use critic_shared::{Branding, UserProfile};
 use leptos::config::LeptosOptions;
 use serde::Deserialize;
-use sqlx::{Pool, Postgres};
-use tracing::{error, level_filters::LevelFilter};
+use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use tracing::level_filters::LevelFilter;
 use tracing_subscriber::filter::LevelParseError;
 
+use crate::{
+    auth::{
+        http::ProviderClient,
+        provider::{create_provider, Action, OauthProvider, ProviderError, ProviderKind, UserRole},
+    },
+    manuscript_cache::ManuscriptCache,
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Where the config file is read from if nothing else is specified
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/critic/config.toml";
+/// Environment variable overriding [`DEFAULT_CONFIG_PATH`]
+pub const CONFIG_PATH_ENV_VAR: &str = "CRITIC_CONFIG";
+
 #[derive(Debug)]
 pub enum ConfigError {
     TomlParse(toml::de::Error),
-    ConfigFileRead(std::io::Error),
+    /// The config file at this path cannot be read
+    ConfigFileRead(String, std::io::Error),
     PoolCreate(sqlx::Error),
     LogLevel(LevelParseError),
-    GithubAddrParse(oauth2::url::ParseError),
+    ProviderAddrParse(oauth2::url::ParseError),
+    /// The oauth provider cannot be set up
+    OauthProvider(ProviderError),
+    UserInfoUrlParse(oauth2::url::ParseError),
     PublicAddrParse(oauth2::url::ParseError),
+    MetricsAddrParse(std::net::AddrParseError),
+    /// This entry of `allowed_image_extensions` is not an image format critic can read
+    ImageExtension(String),
+    /// `db.max_connections` is 0
+    NoDbConnections,
+    /// `session.same_site` is `none`, but `session.cookie_secure` is off
+    InsecureSameSiteNone,
 }
 impl core::fmt::Display for ConfigError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -23,8 +52,8 @@ impl core::fmt::Display for ConfigError {
             Self::TomlParse(e) => {
                 write!(f, "Unable to parse config file as toml: {e}")
             }
-            Self::ConfigFileRead(e) => {
-                write!(f, "Unable to read config file: {e}")
+            Self::ConfigFileRead(path, e) => {
+                write!(f, "Unable to read config file {path}: {e}")
             }
             Self::PoolCreate(e) => {
                 write!(f, "Unable to create postgres DB pool: {e}")
@@ -32,18 +61,42 @@ impl core::fmt::Display for ConfigError {
             Self::LogLevel(e) => {
                 write!(f, "Unable to parse log_level: {e}")
             }
-            Self::GithubAddrParse(e) => {
+            Self::ProviderAddrParse(e) => {
                 write!(
                     f,
-                    "Unable to interpret github_addr as addr while using it to build a url: {e}"
+                    "Unable to use an endpoint of the oauth provider as url: {e}"
                 )
             }
+            Self::OauthProvider(e) => {
+                write!(f, "Unable to set up the oauth provider: {e}")
+            }
+            Self::UserInfoUrlParse(e) => {
+                write!(f, "Unable to parse oauth.user_info_url as url: {e}")
+            }
             Self::PublicAddrParse(e) => {
                 write!(
                     f,
                     "Unable to interpret public_addr as addr while using it to build a url: {e}"
                 )
             }
+            Self::MetricsAddrParse(e) => {
+                write!(f, "Unable to parse metrics_addr as socket addr: {e}")
+            }
+            Self::ImageExtension(ext) => {
+                write!(
+                    f,
+                    "allowed_image_extensions contains {ext}, which is not an image format critic can read"
+                )
+            }
+            Self::NoDbConnections => {
+                write!(f, "db.max_connections must be at least 1")
+            }
+            Self::InsecureSameSiteNone => {
+                write!(
+                    f,
+                    "session.same_site = \"none\" needs session.cookie_secure = true, browsers drop the session cookie otherwise"
+                )
+            }
         }
     }
 }
@@ -66,6 +119,99 @@ struct DbConfigData {
     host: String,
     port: Option<u16>,
     database: String,
+    /// How many connections to postgres critic keeps open at most
+    #[serde(default = "default_db_max_connections")]
+    max_connections: u32,
+    /// How many seconds to wait for a free connection before failing the request
+    #[serde(default = "default_db_acquire_timeout_seconds")]
+    acquire_timeout_seconds: u64,
+    /// Close connections that were not used for this many seconds
+    #[serde(default = "default_db_idle_timeout_seconds")]
+    idle_timeout_seconds: u64,
+    /// How often to try connecting at startup before giving up
+    #[serde(default = "default_db_connect_attempts")]
+    connect_attempts: u32,
+    /// How many seconds to wait after the first failed attempt, doubled after every further one
+    #[serde(default = "default_db_connect_retry_seconds")]
+    connect_retry_seconds: u64,
+}
+fn default_db_max_connections() -> u32 {
+    10
+}
+fn default_db_acquire_timeout_seconds() -> u64 {
+    30
+}
+fn default_db_idle_timeout_seconds() -> u64 {
+    10 * 60
+}
+fn default_db_connect_attempts() -> u32 {
+    10
+}
+fn default_db_connect_retry_seconds() -> u64 {
+    1
+}
+/// The longest wait between two attempts to connect, no matter how many attempts failed
+const MAX_DB_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How long to wait after `failed_attempts` attempts to connect have failed
+///
+/// The wait doubles after every failed attempt, up to [`MAX_DB_CONNECT_RETRY_DELAY`].
+fn db_connect_retry_delay(retry_seconds: u64, failed_attempts: u32) -> Duration {
+    let factor = 1_u64
+        .checked_shl(failed_attempts.saturating_sub(1))
+        .unwrap_or(u64::MAX);
+    Duration::from_secs(retry_seconds.saturating_mul(factor)).min(MAX_DB_CONNECT_RETRY_DELAY)
+}
+impl DbConfigData {
+    /// The options for the connection pool, without connecting yet
+    fn pool_options(&self) -> Result<PgPoolOptions, ConfigError> {
+        if self.max_connections == 0 {
+            return Err(ConfigError::NoDbConnections);
+        };
+        Ok(PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_seconds))
+            .idle_timeout(Duration::from_secs(self.idle_timeout_seconds)))
+    }
+
+    /// Connect to postgres, retrying with exponential backoff while it is not up yet
+    ///
+    /// When critic and postgres are started together (docker compose, k8s), postgres usually is
+    /// not ready when critic starts. Gives up after `connect_attempts` attempts.
+    async fn connect(&self) -> Result<Pool<Postgres>, ConfigError> {
+        let url = format!(
+            "postgres://{}:{}@{}:{}/{}",
+            self.user,
+            self.password,
+            self.host,
+            self.port.unwrap_or(5432),
+            self.database
+        );
+        let options = self.pool_options()?;
+        let attempts = self.connect_attempts.max(1);
+        let mut failed_attempts = 0;
+        loop {
+            match options.clone().connect(&url).await {
+                Ok(x) => return Ok(x),
+                Err(e) => {
+                    failed_attempts += 1;
+                    // logging is only set up once the config is read, so this goes to stderr
+                    if failed_attempts >= attempts {
+                        eprintln!(
+                            "Could not connect to postgres (attempt {failed_attempts}/{attempts}), giving up: {e}"
+                        );
+                        return Err(e.into());
+                    };
+                    let delay = db_connect_retry_delay(self.connect_retry_seconds, failed_attempts);
+                    eprintln!(
+                        "Could not connect to postgres (attempt {failed_attempts}/{attempts}), retrying in {}s: {e}",
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            };
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -73,22 +219,57 @@ struct WebConfigData {
     /// The address to host the website on (e.g. 127.0.0.1:8080)
     site_addr: String,
     /// Where is this website called from on the internet (including any reverse-proxies, NAT etc.)
-    /// Github must be able to communicate with critic via this FQDN, using https
+    /// The oauth provider must be able to communicate with critic via this FQDN, using https
     public_addr: String,
+    /// Serve the prometheus metrics on this separate address
+    ///
+    /// They are never served together with the website, so this defaults to a loopback address.
+    #[serde(default = "default_metrics_addr")]
+    metrics_addr: String,
+}
+fn default_metrics_addr() -> String {
+    "127.0.0.1:9090".to_string()
 }
 
 #[derive(Deserialize)]
 struct OauthConfigData {
     client_id: String,
     client_secret: String,
+    /// Which service users log in with
+    #[serde(default)]
+    provider: ProviderKind,
+    /// The gitlab instance to use (only for gitlab, defaults to gitlab.com)
+    base_url: Option<String>,
+    /// The issuer whose discovery document is used (only for generic_oidc)
+    issuer: Option<String>,
+    /// The scopes to request from the oauth provider
+    #[serde(default = "default_oauth_scopes")]
+    scopes: Vec<String>,
+    /// Where to get information about the logged in user (defaults to the providers endpoint)
+    user_info_url: Option<String>,
+    /// Give up on a single request to the providers api after this many seconds
+    #[serde(default = "default_oauth_request_timeout_seconds")]
+    request_timeout_seconds: u64,
+    /// Repeat requests to the providers api this often when they fail transiently
+    #[serde(default = "default_oauth_request_retries")]
+    request_retries: u8,
+}
+fn default_oauth_scopes() -> Vec<String> {
+    vec!["api".to_string()]
+}
+fn default_oauth_request_timeout_seconds() -> u64 {
+    10
+}
+fn default_oauth_request_retries() -> u8 {
+    2
 }
 
 /// The OauthConfig that will be usable to create clients on the server side
 #[derive(Deserialize)]
 struct OauthConfig {
-    /// the client ID we use to authenticate to github
+    /// the client ID we use to authenticate to the oauth provider
     client_id: oauth2::ClientId,
-    /// the client secret we use to authenticate to github
+    /// the client secret we use to authenticate to the oauth provider
     client_secret: oauth2::ClientSecret,
     auth_url: oauth2::AuthUrl,
     token_url: oauth2::TokenUrl,
@@ -97,17 +278,16 @@ struct OauthConfig {
 impl OauthConfig {
     fn try_from_config_data(
         value: OauthConfigData,
+        provider: &dyn OauthProvider,
         public_addr: &str,
     ) -> Result<Self, ConfigError> {
         Ok(Self {
             client_id: oauth2::ClientId::new(value.client_id),
             client_secret: oauth2::ClientSecret::new(value.client_secret),
-            auth_url: oauth2::AuthUrl::new("https://github.com/login/oauth/authorize".to_string())
-                .map_err(ConfigError::GithubAddrParse)?,
-            token_url: oauth2::TokenUrl::new(
-                "https://github.com/login/oauth/access_token".to_string(),
-            )
-            .map_err(ConfigError::GithubAddrParse)?,
+            auth_url: oauth2::AuthUrl::new(provider.auth_url().to_string())
+                .map_err(ConfigError::ProviderAddrParse)?,
+            token_url: oauth2::TokenUrl::new(provider.token_url().to_string())
+                .map_err(ConfigError::ProviderAddrParse)?,
             redirect_url: oauth2::RedirectUrl::new(format!("https://{public_addr}/oauth/redirect"))
                 .map_err(ConfigError::PublicAddrParse)?,
         })
@@ -141,18 +321,306 @@ impl From<OauthConfig> for OauthClient {
 /// Config partaining to the github instance
 #[derive(Deserialize, Debug)]
 pub struct GithubConfig {
-    /// The name of the organization, members of which have access to critic.
+    /// The name of the organization (or group for other oauth providers), members of which have
+    /// access to critic.
     pub org_name: String,
     /// The url-encoded name of the main repository to interact with
     pub repository_name: String,
 }
 
+/// Config for keeping the history of all transcriptions in a git repository
+#[derive(Deserialize, Debug)]
+pub struct HistoryConfig {
+    /// Commit saved transcriptions to a git repository in the transcription directory
+    #[serde(default)]
+    pub enabled: bool,
+    /// How many seconds to collect saves before committing them
+    #[serde(default = "default_history_batch_seconds")]
+    pub batch_seconds: u64,
+}
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_seconds: default_history_batch_seconds(),
+        }
+    }
+}
+fn default_history_batch_seconds() -> u64 {
+    300
+}
+
+/// The `SameSite` attribute of the session cookie
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    Strict,
+    #[default]
+    Lax,
+    /// Only allowed together with `cookie_secure = true`
+    None,
+}
+impl From<SameSite> for axum_login::tower_sessions::cookie::SameSite {
+    fn from(value: SameSite) -> Self {
+        match value {
+            SameSite::Strict => Self::Strict,
+            SameSite::Lax => Self::Lax,
+            SameSite::None => Self::None,
+        }
+    }
+}
+
+/// How log lines are written
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One short line per event, for humans
+    #[default]
+    Compact,
+    /// One JSON object per event, for log aggregation
+    Json,
+}
+
+/// Config for the login session cookie
+#[derive(Deserialize, Debug)]
+pub struct SessionConfig {
+    /// Only send the session cookie over https
+    ///
+    /// The public address must be https for the oauth redirect anyway, so this should only be
+    /// turned off for local development without a reverse proxy.
+    #[serde(default = "default_cookie_secure")]
+    pub cookie_secure: bool,
+    #[serde(default)]
+    pub same_site: SameSite,
+    /// Log users out after this many days of inactivity
+    #[serde(default = "default_session_expiry_days")]
+    pub session_expiry_days: i64,
+}
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            cookie_secure: default_cookie_secure(),
+            same_site: SameSite::default(),
+            session_expiry_days: default_session_expiry_days(),
+        }
+    }
+}
+impl SessionConfig {
+    /// Make sure browsers accept the session cookie
+    fn check(&self) -> Result<(), ConfigError> {
+        if self.same_site == SameSite::None && !self.cookie_secure {
+            return Err(ConfigError::InsecureSameSiteNone);
+        };
+        Ok(())
+    }
+}
+fn default_cookie_secure() -> bool {
+    true
+}
+fn default_session_expiry_days() -> i64 {
+    1
+}
+
+/// Config for the job that looks for files without a page in the db
+#[derive(Deserialize, Debug)]
+pub struct CleanupConfig {
+    /// Actually delete orphaned files instead of only logging them
+    #[serde(default)]
+    pub delete_orphans: bool,
+    /// How many seconds to wait between two runs
+    #[serde(default = "default_cleanup_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Files changed less than this many seconds ago are never considered orphaned, so that
+    /// uploads and renames in progress are left alone
+    #[serde(default = "default_cleanup_min_age_seconds")]
+    pub min_age_seconds: u64,
+}
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            delete_orphans: false,
+            interval_seconds: default_cleanup_interval_seconds(),
+            min_age_seconds: default_cleanup_min_age_seconds(),
+        }
+    }
+}
+fn default_cleanup_interval_seconds() -> u64 {
+    24 * 60 * 60
+}
+fn default_cleanup_min_age_seconds() -> u64 {
+    60 * 60
+}
+
+/// Config for claiming pages while transcribing them
+///
+/// Off by default: two transcribers working on the same page in parallel is the normal workflow.
+#[derive(Deserialize, Debug)]
+pub struct LockingConfig {
+    /// Only the user who opened a page first may save a transcription for it
+    #[serde(default)]
+    pub enabled: bool,
+    /// How many seconds a lock lasts without the editor refreshing it
+    #[serde(default = "default_lock_ttl_seconds")]
+    pub ttl_seconds: u64,
+}
+impl Default for LockingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_seconds: default_lock_ttl_seconds(),
+        }
+    }
+}
+fn default_lock_ttl_seconds() -> u64 {
+    120
+}
+
+/// Config for how often the minification service looks for new pages
+#[derive(Deserialize, Debug)]
+pub struct MinificationConfig {
+    /// How many milliseconds to wait before looking again when there was nothing to minify
+    #[serde(default = "default_minification_idle_ms")]
+    pub idle_ms: u64,
+    /// How many milliseconds to wait between two batches of pages
+    #[serde(default = "default_minification_batch_pause_ms")]
+    pub batch_pause_ms: u64,
+    /// How many milliseconds to wait after the db failed to hand out pages
+    #[serde(default = "default_minification_error_backoff_ms")]
+    pub error_backoff_ms: u64,
+    /// Wake up as soon as a page is added instead of waiting out `idle_ms`
+    #[serde(default = "default_minification_listen")]
+    pub listen: bool,
+}
+impl Default for MinificationConfig {
+    fn default() -> Self {
+        Self {
+            idle_ms: default_minification_idle_ms(),
+            batch_pause_ms: default_minification_batch_pause_ms(),
+            error_backoff_ms: default_minification_error_backoff_ms(),
+            listen: default_minification_listen(),
+        }
+    }
+}
+fn default_minification_idle_ms() -> u64 {
+    1000
+}
+fn default_minification_batch_pause_ms() -> u64 {
+    10
+}
+fn default_minification_error_backoff_ms() -> u64 {
+    5000
+}
+fn default_minification_listen() -> bool {
+    true
+}
+
+/// Config for how much a single user may upload per day
+///
+/// Both limits are off by default. Failed uploads of files that were already counted still count.
+#[derive(Deserialize, Debug, Default)]
+pub struct UploadQuotaConfig {
+    /// How many bytes of page images a user may upload per day
+    #[serde(default)]
+    pub daily_bytes: Option<u64>,
+    /// How many pages a user may upload per day
+    #[serde(default)]
+    pub daily_pages: Option<u64>,
+}
+
+/// The least privileged role that may do each [`Action`]
+///
+/// `outsider` lets every logged in user do it, `member` only members of the configured group.
+#[derive(Deserialize, Debug)]
+pub struct PermissionsConfig {
+    #[serde(default = "default_transcribe_role")]
+    pub transcribe: UserRole,
+    #[serde(default = "default_upload_role")]
+    pub upload: UserRole,
+    #[serde(default = "default_administer_role")]
+    pub administer: UserRole,
+}
+impl Default for PermissionsConfig {
+    fn default() -> Self {
+        Self {
+            transcribe: default_transcribe_role(),
+            upload: default_upload_role(),
+            administer: default_administer_role(),
+        }
+    }
+}
+impl PermissionsConfig {
+    pub fn minimum_role(&self, action: Action) -> UserRole {
+        match action {
+            Action::Transcribe => self.transcribe,
+            Action::Upload => self.upload,
+            Action::Administer => self.administer,
+        }
+    }
+
+    /// May a user with `role` do `action`?
+    pub fn allows(&self, action: Action, role: UserRole) -> bool {
+        role >= self.minimum_role(action)
+    }
+
+    /// Everything a user with `role` may do, for showing only the controls they can use
+    pub fn profile(&self, username: String, role: UserRole) -> UserProfile {
+        UserProfile {
+            username,
+            member: role == UserRole::Member,
+            may_transcribe: self.allows(Action::Transcribe, role),
+            may_upload: self.allows(Action::Upload, role),
+            may_administer: self.allows(Action::Administer, role),
+        }
+    }
+}
+fn default_transcribe_role() -> UserRole {
+    UserRole::Outsider
+}
+fn default_upload_role() -> UserRole {
+    UserRole::Member
+}
+fn default_administer_role() -> UserRole {
+    UserRole::Member
+}
+
+/// Config for the in-memory cache of manuscript metadata
+#[derive(Deserialize, Debug)]
+pub struct ManuscriptCacheConfig {
+    /// How many manuscripts to keep in memory, 0 disables the cache
+    #[serde(default = "default_manuscript_cache_size")]
+    pub size: usize,
+}
+impl Default for ManuscriptCacheConfig {
+    fn default() -> Self {
+        Self {
+            size: default_manuscript_cache_size(),
+        }
+    }
+}
+fn default_manuscript_cache_size() -> usize {
+    256
+}
+
+/// Config for the transcription editor
+#[derive(Deserialize, Debug, Default)]
+pub struct EditorConfig {
+    /// Enable the browsers spellcheck on text fields whose language is one of these
+    ///
+    /// `en` also matches more specific tags like `en-GB`. Empty (i.e. spellcheck always off) by
+    /// default.
+    #[serde(default)]
+    pub spellcheck_languages: Vec<String>,
+}
+
 /// The config data as it is present in (a well-formed) toml config file
 #[derive(Deserialize)]
 struct ConfigData {
     db: DbConfigData,
     web: WebConfigData,
     log_level: Option<String>,
+    /// `compact` (default) or `json`
+    #[serde(default)]
+    log_format: LogFormat,
     oauth: OauthConfigData,
     /// used as server part for determining where to communicate to github
     github: GithubConfig,
@@ -167,42 +635,217 @@ struct ConfigData {
     /// from time to time
     #[serde(default = "default_worker_threads")]
     worker_threads: u8,
+    /// The minimum width and height (in pixels) an uploaded page image must have
+    #[serde(default = "default_min_image_dimension")]
+    min_image_dimension: u32,
+    /// The largest single page image (in bytes) that may be uploaded
+    ///
+    /// The whole request is additionally limited by [`critic_shared::MAX_BODY_SIZE`]
+    #[serde(default = "default_max_image_size")]
+    max_image_size: usize,
+    /// The extensions uploaded page images may have
+    #[serde(default = "default_allowed_image_extensions")]
+    allowed_image_extensions: Vec<String>,
+    /// The resolution at which uploaded PDFs are rasterized into page images
+    #[serde(default = "default_pdf_dpi")]
+    pdf_dpi: u16,
+    /// Only serve page images to logged in users
+    #[serde(default = "default_require_login_for_images")]
+    require_login_for_images: bool,
+    /// How many seconds browsers may cache minified page images
+    #[serde(default = "default_image_cache_max_age_seconds")]
+    image_cache_max_age_seconds: u64,
+    /// How many seconds to wait for running uploads and requests when shutting down
+    #[serde(default = "default_shutdown_grace_seconds")]
+    shutdown_grace_seconds: u64,
+    /// Optional git history of transcriptions (off by default)
+    #[serde(default)]
+    history: HistoryConfig,
+    /// Looking for (and optionally deleting) orphaned image files
+    #[serde(default)]
+    cleanup: CleanupConfig,
+    /// Security and lifetime of the session cookie
+    #[serde(default)]
+    session: SessionConfig,
+    /// Settings for the transcription editor
+    #[serde(default)]
+    editor: EditorConfig,
+    /// Claiming pages while transcribing (off by default)
+    #[serde(default)]
+    locking: LockingConfig,
+    /// Polling and waking up of the minification service
+    #[serde(default)]
+    minification: MinificationConfig,
+    /// Daily upload limits per user (off by default)
+    #[serde(default)]
+    upload_quota: UploadQuotaConfig,
+    /// Keeping manuscript metadata in memory
+    #[serde(default)]
+    manuscript_cache: ManuscriptCacheConfig,
+    /// Who may do what
+    #[serde(default)]
+    permissions: PermissionsConfig,
+    /// Title, logo and welcome message of the site
+    #[serde(default)]
+    branding: Branding,
 }
 fn default_worker_threads() -> u8 {
     4
 }
+fn default_min_image_dimension() -> u32 {
+    100
+}
+fn default_max_image_size() -> usize {
+    50 * 1024 * 1024
+}
+fn default_allowed_image_extensions() -> Vec<String> {
+    critic_shared::ALLOWED_IMAGE_EXTENSIONS
+        .iter()
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+/// Make sure every extension belongs to an image format we can decode
+///
+/// Returns the extensions in lowercase.
+fn check_image_extensions(extensions: Vec<String>) -> Result<Vec<String>, ConfigError> {
+    extensions
+        .into_iter()
+        .map(|ext| {
+            let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+            match image::ImageFormat::from_extension(&ext) {
+                Some(format) if format.reading_enabled() => Ok(ext),
+                _ => Err(ConfigError::ImageExtension(ext)),
+            }
+        })
+        .collect()
+}
+fn default_pdf_dpi() -> u16 {
+    300
+}
+fn default_require_login_for_images() -> bool {
+    true
+}
+fn default_image_cache_max_age_seconds() -> u64 {
+    30 * 24 * 60 * 60
+}
+fn default_shutdown_grace_seconds() -> u64 {
+    60
+}
+
+/// Environment variables that take precedence over single values from the config file
+///
+/// Each entry is (variable name, setter on the parsed config file).
+const ENV_OVERRIDES: &[(&str, fn(&mut ConfigData, String))] = &[
+    ("CRITIC_DB_USER", |c, v| c.db.user = v),
+    ("CRITIC_DB_PASSWORD", |c, v| c.db.password = v),
+    ("CRITIC_DB_HOST", |c, v| c.db.host = v),
+    ("CRITIC_DB_DATABASE", |c, v| c.db.database = v),
+    ("CRITIC_OAUTH_CLIENT_ID", |c, v| c.oauth.client_id = v),
+    ("CRITIC_OAUTH_CLIENT_SECRET", |c, v| {
+        c.oauth.client_secret = v
+    }),
+    ("CRITIC_DATA_DIRECTORY", |c, v| c.data_directory = v),
+];
+
+impl ConfigData {
+    /// Replace values from the config file with those set in [`ENV_OVERRIDES`]
+    ///
+    /// `lookup` returns the value of an environment variable, if it is set.
+    fn apply_env_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) {
+        for (name, set) in ENV_OVERRIDES {
+            if let Some(value) = lookup(name) {
+                set(self, value);
+            };
+        }
+    }
+}
 
 /// The main config object that will be available across the Serverside application
-#[derive(Debug)]
 pub struct Config {
     // DB pool to use
     pub db: Pool<Postgres>,
     pub leptos_options: LeptosOptions,
+    /// Separate address for the prometheus metrics, which are not served on `site_addr`
+    pub metrics_addr: std::net::SocketAddr,
     pub log_level: LevelFilter,
+    pub log_format: LogFormat,
     pub oauth_client: OauthClient,
+    /// The provider users log in with
+    pub oauth_provider: Arc<dyn OauthProvider>,
+    /// Shared by all requests to the api of the provider
+    pub provider_client: ProviderClient,
+    /// The scopes requested when users log in
+    pub oauth_scopes: Vec<String>,
+    /// Where to get information about the logged in user
+    pub oauth_user_info_url: String,
     /// used as server part for determining where to communicate to github
     pub github: GithubConfig,
     pub data_directory: String,
     pub worker_threads: u8,
+    pub min_image_dimension: u32,
+    pub max_image_size: usize,
+    pub allowed_image_extensions: Vec<String>,
+    pub pdf_dpi: u16,
+    pub require_login_for_images: bool,
+    pub image_cache_max_age_seconds: u64,
+    pub shutdown_grace_seconds: u64,
+    pub history: HistoryConfig,
+    pub cleanup: CleanupConfig,
+    pub session: SessionConfig,
+    pub editor: EditorConfig,
+    pub locking: LockingConfig,
+    pub minification: MinificationConfig,
+    pub upload_quota: UploadQuotaConfig,
+    pub manuscript_cache: ManuscriptCache,
+    pub permissions: PermissionsConfig,
+    pub branding: Branding,
+}
+// written by hand so that the db url and oauth secret never end up in logs
+impl core::fmt::Debug for Config {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Config")
+            .field("db", &"[redacted]")
+            .field("leptos_options", &self.leptos_options)
+            .field("metrics_addr", &self.metrics_addr)
+            .field("log_level", &self.log_level)
+            .field("log_format", &self.log_format)
+            .field("oauth_client", &"[redacted]")
+            .field("oauth_provider", &self.oauth_provider)
+            .field("provider_client", &self.provider_client)
+            .field("oauth_scopes", &self.oauth_scopes)
+            .field("oauth_user_info_url", &self.oauth_user_info_url)
+            .field("github", &self.github)
+            .field("data_directory", &self.data_directory)
+            .field("worker_threads", &self.worker_threads)
+            .field("min_image_dimension", &self.min_image_dimension)
+            .field("max_image_size", &self.max_image_size)
+            .field("allowed_image_extensions", &self.allowed_image_extensions)
+            .field("pdf_dpi", &self.pdf_dpi)
+            .field("require_login_for_images", &self.require_login_for_images)
+            .field(
+                "image_cache_max_age_seconds",
+                &self.image_cache_max_age_seconds,
+            )
+            .field("shutdown_grace_seconds", &self.shutdown_grace_seconds)
+            .field("history", &self.history)
+            .field("cleanup", &self.cleanup)
+            .field("session", &self.session)
+            .field("editor", &self.editor)
+            .field("locking", &self.locking)
+            .field("minification", &self.minification)
+            .field("upload_quota", &self.upload_quota)
+            .field("manuscript_cache", &self.manuscript_cache)
+            .field("permissions", &self.permissions)
+            .field("branding", &self.branding)
+            .finish()
+    }
 }
 impl Config {
-    async fn try_from_config_data(value: ConfigData) -> Result<Self, ConfigError> {
-        // postgres settings
-        let url = format!(
-            "postgres://{}:{}@{}:{}/{}",
-            value.db.user,
-            value.db.password,
-            value.db.host,
-            value.db.port.unwrap_or(5432),
-            value.db.database
-        );
-        let db = match sqlx::postgres::PgPool::connect(&url).await {
-            Ok(x) => x,
-            Err(e) => {
-                error!("Could not connect to postgres: {e}");
-                return Err(e.into());
-            }
-        };
+    async fn try_from_config_data(mut value: ConfigData) -> Result<Self, ConfigError> {
+        value.apply_env_overrides(|name| std::env::var(name).ok());
+        value.session.check()?;
+        let db = value.db.connect().await?;
 
         let addr = std::net::SocketAddr::from_str(&value.web.site_addr)
             .expect("Should be able to parse socket addr");
@@ -213,26 +856,91 @@ impl Config {
             .site_pkg_dir("pkg")
             .site_addr(addr)
             .build();
+        let metrics_addr = std::net::SocketAddr::from_str(&value.web.metrics_addr)
+            .map_err(ConfigError::MetricsAddrParse)?;
         let log_level = tracing_subscriber::filter::LevelFilter::from_str(
             &value.log_level.unwrap_or("INFO".to_string()),
         )?;
 
+        let provider_client = ProviderClient::new(
+            Duration::from_secs(value.oauth.request_timeout_seconds),
+            value.oauth.request_retries,
+        );
+        let oauth_provider = create_provider(
+            &provider_client,
+            value.oauth.provider,
+            &value.github.org_name,
+            value.oauth.base_url.as_deref(),
+            value.oauth.issuer.as_deref(),
+        )
+        .await
+        .map_err(ConfigError::OauthProvider)?;
+        let allowed_image_extensions = check_image_extensions(value.allowed_image_extensions)?;
+        let oauth_scopes = value.oauth.scopes.clone();
+        let oauth_user_info_url = value
+            .oauth
+            .user_info_url
+            .clone()
+            .unwrap_or_else(|| oauth_provider.user_info_url().to_string());
+        // fail now instead of at the first login
+        oauth2::url::Url::parse(&oauth_user_info_url).map_err(ConfigError::UserInfoUrlParse)?;
+        let oauth_client = OauthConfig::try_from_config_data(
+            value.oauth,
+            oauth_provider.as_ref(),
+            &value.web.public_addr,
+        )?
+        .into();
+
         Ok(Self {
             db,
             leptos_options,
+            metrics_addr,
             log_level,
-            oauth_client: OauthConfig::try_from_config_data(value.oauth, &value.web.public_addr)?
-                .into(),
+            log_format: value.log_format,
+            oauth_client,
+            oauth_provider,
+            provider_client,
+            oauth_scopes,
+            oauth_user_info_url,
             github: value.github,
             data_directory: value.data_directory,
             worker_threads: value.worker_threads,
+            min_image_dimension: value.min_image_dimension,
+            max_image_size: value.max_image_size,
+            allowed_image_extensions,
+            pdf_dpi: value.pdf_dpi,
+            require_login_for_images: value.require_login_for_images,
+            image_cache_max_age_seconds: value.image_cache_max_age_seconds,
+            shutdown_grace_seconds: value.shutdown_grace_seconds,
+            history: value.history,
+            cleanup: value.cleanup,
+            session: value.session,
+            editor: value.editor,
+            locking: value.locking,
+            minification: value.minification,
+            upload_quota: value.upload_quota,
+            manuscript_cache: ManuscriptCache::new(value.manuscript_cache.size),
+            permissions: value.permissions,
+            branding: value.branding,
         })
     }
 
-    pub async fn try_create() -> Result<Self, ConfigError> {
-        let path = Path::new("/etc/critic/config.toml");
-        let content = read_to_string(path).map_err(ConfigError::ConfigFileRead)?;
+    /// Read the config file and set up everything from it
+    ///
+    /// The file is taken from `path_override` (the `--config` argument), then from
+    /// [`CONFIG_PATH_ENV_VAR`], then [`DEFAULT_CONFIG_PATH`].
+    pub async fn try_create(path_override: Option<String>) -> Result<Self, ConfigError> {
+        let path = config_path(path_override, std::env::var(CONFIG_PATH_ENV_VAR).ok());
+        let content = read_to_string(&path).map_err(|e| ConfigError::ConfigFileRead(path, e))?;
         let config_data: ConfigData = toml::from_str(&content).map_err(ConfigError::TomlParse)?;
         Self::try_from_config_data(config_data).await
     }
 }
+
+/// The config file to use: the cli argument wins over the environment variable, which wins over
+/// [`DEFAULT_CONFIG_PATH`]
+fn config_path(cli: Option<String>, env: Option<String>) -> String {
+    cli.filter(|path| !path.is_empty())
+        .or(env.filter(|path| !path.is_empty()))
+        .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string())
+}
@@ -0,0 +1,348 @@
+use super::{
+    check_image_extensions, config_path, db_connect_retry_delay, ConfigData, ConfigError,
+    LogFormat, SameSite, DEFAULT_CONFIG_PATH, MAX_DB_CONNECT_RETRY_DELAY,
+};
+use crate::auth::{provider::UserRole, Action};
+
+#[test]
+fn config_path_precedence() {
+    assert_eq!(config_path(None, None), DEFAULT_CONFIG_PATH);
+    assert_eq!(
+        config_path(None, Some("/env.toml".to_string())),
+        "/env.toml"
+    );
+    assert_eq!(
+        config_path(Some("/cli.toml".to_string()), Some("/env.toml".to_string())),
+        "/cli.toml"
+    );
+    // an empty variable is treated as unset
+    assert_eq!(config_path(None, Some(String::new())), DEFAULT_CONFIG_PATH);
+}
+
+const CONFIG_FILE: &str = r#"
+data_directory = "/file/data"
+
+[db]
+user = "critic"
+password = "file-password"
+host = "localhost"
+database = "critic"
+
+[web]
+site_addr = "127.0.0.1:8080"
+public_addr = "critic.example.com"
+
+[oauth]
+client_id = "file-id"
+client_secret = "file-secret"
+
+[github]
+org_name = "org"
+repository_name = "repo"
+"#;
+
+#[test]
+fn env_overrides_file_values() {
+    let mut config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    config.apply_env_overrides(|name| match name {
+        "CRITIC_DB_PASSWORD" => Some("env-password".to_string()),
+        "CRITIC_OAUTH_CLIENT_SECRET" => Some("env-secret".to_string()),
+        "CRITIC_DATA_DIRECTORY" => Some("/env/data".to_string()),
+        _ => None,
+    });
+    assert_eq!(config.db.password, "env-password");
+    assert_eq!(config.oauth.client_secret, "env-secret");
+    assert_eq!(config.data_directory, "/env/data");
+    // values without a variable are kept from the file
+    assert_eq!(config.db.user, "critic");
+    assert_eq!(config.oauth.client_id, "file-id");
+}
+
+#[test]
+fn no_env_keeps_file_values() {
+    let mut config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    config.apply_env_overrides(|_| None);
+    assert_eq!(config.db.password, "file-password");
+    assert_eq!(config.oauth.client_secret, "file-secret");
+    assert_eq!(config.data_directory, "/file/data");
+}
+
+#[test]
+fn metrics_are_on_loopback_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.web.metrics_addr, "127.0.0.1:9090");
+    let config: ConfigData = toml::from_str(
+        &CONFIG_FILE.replace("[web]\n", "[web]\nmetrics_addr = \"10.0.0.5:9090\"\n"),
+    )
+    .unwrap();
+    assert_eq!(config.web.metrics_addr, "10.0.0.5:9090");
+}
+
+#[test]
+fn session_defaults_to_secure_cookies() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert!(config.session.cookie_secure);
+    assert_eq!(config.session.same_site, SameSite::Lax);
+    assert_eq!(config.session.session_expiry_days, 1);
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[session]\ncookie_secure = false\nsame_site = \"strict\"\nsession_expiry_days = 7\n"
+    ))
+    .unwrap();
+    assert!(!config.session.cookie_secure);
+    assert_eq!(config.session.same_site, SameSite::Strict);
+    assert_eq!(config.session.session_expiry_days, 7);
+}
+
+#[test]
+fn same_site_none_needs_secure_cookies() {
+    let config: ConfigData =
+        toml::from_str(&format!("{CONFIG_FILE}\n[session]\nsame_site = \"none\"\n")).unwrap();
+    assert!(config.session.check().is_ok());
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[session]\ncookie_secure = false\nsame_site = \"none\"\n"
+    ))
+    .unwrap();
+    assert!(matches!(
+        config.session.check(),
+        Err(ConfigError::InsecureSameSiteNone)
+    ));
+}
+
+#[test]
+fn spellcheck_is_off_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert!(config.editor.spellcheck_languages.is_empty());
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[editor]\nspellcheck_languages = [\"en\", \"de\"]\n"
+    ))
+    .unwrap();
+    assert_eq!(
+        config.editor.spellcheck_languages,
+        vec!["en".to_string(), "de".to_string()]
+    );
+}
+
+#[test]
+fn image_extensions_must_be_readable() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.allowed_image_extensions, vec!["png", "jpg", "jpeg"]);
+
+    let extensions = |exts: &[&str]| exts.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        check_image_extensions(extensions(&["png", ".TIF", "tiff"])).unwrap(),
+        vec!["png", "tif", "tiff"]
+    );
+    assert!(matches!(
+        check_image_extensions(extensions(&["png", "docx"])),
+        Err(ConfigError::ImageExtension(ext)) if ext == "docx"
+    ));
+}
+
+#[test]
+fn max_image_size_defaults_to_50_mib() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.max_image_size, 50 * 1024 * 1024);
+
+    let config: ConfigData =
+        toml::from_str(&format!("max_image_size = 5_000_000\n{CONFIG_FILE}")).unwrap();
+    assert_eq!(config.max_image_size, 5_000_000);
+}
+
+#[test]
+fn oauth_scopes_default_to_api() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.oauth.scopes, vec!["api".to_string()]);
+    assert_eq!(config.oauth.user_info_url, None);
+
+    let config: ConfigData = toml::from_str(&CONFIG_FILE.replace(
+        "client_secret = \"file-secret\"",
+        "client_secret = \"file-secret\"\nscopes = [\"read_user\"]\nuser_info_url = \"https://gitlab.example.com/api/v4/user\"",
+    ))
+    .unwrap();
+    assert_eq!(config.oauth.scopes, vec!["read_user".to_string()]);
+    assert_eq!(
+        config.oauth.user_info_url.as_deref(),
+        Some("https://gitlab.example.com/api/v4/user")
+    );
+}
+
+#[test]
+fn oauth_requests_are_retried_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.oauth.request_timeout_seconds, 10);
+    assert_eq!(config.oauth.request_retries, 2);
+
+    let config: ConfigData = toml::from_str(&CONFIG_FILE.replace(
+        "client_secret = \"file-secret\"",
+        "client_secret = \"file-secret\"\nrequest_timeout_seconds = 3\nrequest_retries = 0",
+    ))
+    .unwrap();
+    assert_eq!(config.oauth.request_timeout_seconds, 3);
+    assert_eq!(config.oauth.request_retries, 0);
+}
+
+#[test]
+fn db_pool_is_bounded_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.db.max_connections, 10);
+    assert_eq!(config.db.acquire_timeout_seconds, 30);
+    assert_eq!(config.db.idle_timeout_seconds, 600);
+    let options = config.db.pool_options().unwrap();
+    assert_eq!(options.get_max_connections(), 10);
+}
+
+#[test]
+fn db_pool_needs_a_connection() {
+    let config: ConfigData = toml::from_str(&CONFIG_FILE.replace(
+        "database = \"critic\"",
+        "database = \"critic\"\nmax_connections = 0",
+    ))
+    .unwrap();
+    assert!(matches!(
+        config.db.pool_options(),
+        Err(ConfigError::NoDbConnections)
+    ));
+}
+
+#[test]
+fn db_connect_retries_back_off_exponentially() {
+    let delays = (1..=5)
+        .map(|failed| db_connect_retry_delay(2, failed).as_secs())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, vec![2, 4, 8, 16, 32]);
+    // the wait is capped, even after absurdly many attempts
+    assert_eq!(db_connect_retry_delay(2, 7), MAX_DB_CONNECT_RETRY_DELAY);
+    assert_eq!(db_connect_retry_delay(2, 500), MAX_DB_CONNECT_RETRY_DELAY);
+}
+
+#[test]
+fn logs_are_compact_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.log_format, LogFormat::Compact);
+
+    let config: ConfigData =
+        toml::from_str(&format!("log_format = \"json\"\n{CONFIG_FILE}")).unwrap();
+    assert_eq!(config.log_format, LogFormat::Json);
+}
+
+#[test]
+fn locking_is_off_by_default() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert!(!config.locking.enabled);
+    assert_eq!(config.locking.ttl_seconds, 120);
+}
+
+#[test]
+fn minification_intervals() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.minification.idle_ms, 1000);
+    assert_eq!(config.minification.batch_pause_ms, 10);
+    assert_eq!(config.minification.error_backoff_ms, 5000);
+    assert!(config.minification.listen);
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[minification]\nidle_ms = 30000\nlisten = false\n"
+    ))
+    .unwrap();
+    assert_eq!(config.minification.idle_ms, 30000);
+    assert_eq!(config.minification.batch_pause_ms, 10);
+    assert!(!config.minification.listen);
+}
+
+#[test]
+fn upload_quota() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.upload_quota.daily_bytes, None);
+    assert_eq!(config.upload_quota.daily_pages, None);
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[upload_quota]\ndaily_pages = 500\n"
+    ))
+    .unwrap();
+    assert_eq!(config.upload_quota.daily_bytes, None);
+    assert_eq!(config.upload_quota.daily_pages, Some(500));
+}
+
+#[test]
+fn manuscript_cache_size() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.manuscript_cache.size, 256);
+
+    let config: ConfigData =
+        toml::from_str(&format!("{CONFIG_FILE}\n[manuscript_cache]\nsize = 0\n")).unwrap();
+    assert_eq!(config.manuscript_cache.size, 0);
+}
+
+#[test]
+fn permissions_default_to_members_for_upload_and_administration() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    let permissions = config.permissions;
+    assert!(permissions.allows(Action::Transcribe, UserRole::Outsider));
+    for action in [Action::Upload, Action::Administer] {
+        assert!(!permissions.allows(action, UserRole::Outsider));
+        assert!(permissions.allows(action, UserRole::Member));
+    }
+}
+
+#[test]
+fn permissions_can_be_changed_per_action() {
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[permissions]\ntranscribe = \"member\"\nupload = \"outsider\"\n"
+    ))
+    .unwrap();
+    let permissions = config.permissions;
+    // just below and at the threshold
+    assert!(!permissions.allows(Action::Transcribe, UserRole::Outsider));
+    assert!(permissions.allows(Action::Transcribe, UserRole::Member));
+    assert!(permissions.allows(Action::Upload, UserRole::Outsider));
+    assert!(permissions.allows(Action::Upload, UserRole::Member));
+    // not set, so still the default
+    assert_eq!(
+        permissions.minimum_role(Action::Administer),
+        UserRole::Member
+    );
+
+    assert!(toml::from_str::<ConfigData>(&format!(
+        "{CONFIG_FILE}\n[permissions]\nupload = \"maintainer\"\n"
+    ))
+    .is_err());
+}
+
+#[test]
+fn profile_lists_allowed_actions() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    let outsider = config
+        .permissions
+        .profile("alice".to_string(), UserRole::Outsider);
+    assert_eq!(outsider.username, "alice");
+    assert!(!outsider.member);
+    assert!(outsider.may_transcribe);
+    assert!(!outsider.may_upload);
+    assert!(!outsider.may_administer);
+
+    let member = config
+        .permissions
+        .profile("bob".to_string(), UserRole::Member);
+    assert!(member.member);
+    assert!(member.may_transcribe && member.may_upload && member.may_administer);
+}
+
+#[test]
+fn branding_defaults_to_critic() {
+    let config: ConfigData = toml::from_str(CONFIG_FILE).unwrap();
+    assert_eq!(config.branding.title, "critic - textual criticism");
+    assert_eq!(config.branding.logo_url, "/logo.webp");
+    assert_eq!(config.branding.welcome_message, "Welcome to Critic");
+
+    let config: ConfigData = toml::from_str(&format!(
+        "{CONFIG_FILE}\n[branding]\ntitle = \"Our Edition\"\nlogo_url = \"/static/logo.png\"\n"
+    ))
+    .unwrap();
+    assert_eq!(config.branding.title, "Our Edition");
+    assert_eq!(config.branding.logo_url, "/static/logo.png");
+    // not set, so still the default
+    assert_eq!(config.branding.welcome_message, "Welcome to Critic");
+}
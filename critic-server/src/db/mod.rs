@@ -1,10 +1,18 @@
 //! Communication with the postgres database
 
-use sqlx::{prelude::FromRow, query_as, Pool, Postgres, QueryBuilder};
+use futures_util::StreamExt;
+use sqlx::{prelude::FromRow, query_as, PgConnection, Pool, Postgres, QueryBuilder};
 
-use critic_shared::{ManuscriptMeta, OwnStatus, PageMeta, PageTodo, VersificationScheme};
+use critic_shared::{
+    urls::{IMAGE_BASE_LOCATION, TRANSCRIPTION_BASE_LOCATION},
+    AdjacentPages, AuditEntry, FailedMinification, ManuscriptMeta, ManuscriptOrder, OwnStatus,
+    PageMeta, PageTodo, PageTranscriber, TodoOrder, UserProgress, VersificationScheme,
+    STATIC_VERSIFICATION_SCHEMES,
+};
 
 use crate::auth::{AuthenticatedUser, NormalizedTokenResponse, UserInfo};
+use crate::config::UploadQuotaConfig;
+use crate::transcription_store::{versification_scheme_in_use, TranscriptionStoreError};
 
 // include tests
 #[cfg(test)]
@@ -19,6 +27,15 @@ pub async fn migrate(pool: &Pool<Postgres>) {
     }
 }
 
+/// Check that the db answers at all
+pub async fn ping(pool: &Pool<Postgres>) -> Result<(), DBError> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .map_err(DBError::CannotPing)?;
+    Ok(())
+}
+
 #[derive(Debug)]
 pub enum DBError {
     CannotStartTransaction(sqlx::Error),
@@ -33,19 +50,85 @@ pub enum DBError {
     CannotAddManuscript(sqlx::Error),
     /// Unable to get versification schemes
     CannotGetVersificationSchemes(sqlx::Error),
+    /// Unable to add a versification scheme
+    CannotAddVersificationScheme(sqlx::Error),
+    /// Unable to update a versification scheme
+    CannotUpdateVersificationScheme(sqlx::Error),
+    /// Unable to delete a versification scheme
+    CannotDeleteVersificationScheme(sqlx::Error),
+    /// The versification scheme with this id does not exist
+    VersificationSchemeDoesNotExist(i64),
+    /// The versification scheme with this full name is used by anchors or verse mappings
+    VersificationSchemeInUse(String),
+    /// The versification scheme with this full name is one of [`STATIC_VERSIFICATION_SCHEMES`]
+    StaticVersificationScheme(String),
+    /// Unable to check whether transcriptions use a versification scheme
+    CannotCheckVersificationSchemeUse(TranscriptionStoreError),
+    /// The versification scheme with this full name changed while checking whether it is in use
+    VersificationSchemeChanged(String),
     /// failed to insert a page
     CannotInsertPage(sqlx::Error),
+    /// failed to delete a page
+    CannotDeletePage(sqlx::Error),
+    /// The page still has this many transcriptions and deleting was not forced
+    PageHasTranscriptions(i64),
+    /// failed to rename a page
+    CannotRenamePage(sqlx::Error),
+    /// failed to move the files of a page to the new name
+    CannotMovePageFiles(String, std::io::Error),
+    /// failed to delete a manuscript
+    CannotDeleteManuscript(sqlx::Error),
+    /// The manuscript still has this many published transcriptions and deleting was not forced
+    ManuscriptHasPublishedTranscriptions(i64),
     /// failed to get a page to minify
     CannotGetMinificationCandidate(sqlx::Error),
     CannotMarkPageMinificationFailed(sqlx::Error),
     CannotMarkPageMinified(sqlx::Error),
     CannotGetPage(sqlx::Error),
     PageAlreadyExists,
+    /// The page we looked for does not exist in this manuscript
+    PageDoesNotExist(String),
     CannotUpdateManuscript(sqlx::Error),
+    /// The manuscript was changed by someone else since its metadata was read
+    StaleUpdate(String),
+    CannotSetVerseRanges(sqlx::Error),
+    /// The page with this id would end before it starts
+    InvalidVerseRange(i64),
+    /// The page with this id exists, but the minification service did not fail on it
+    MinificationNotFailed(i64),
     CannotGetPagesByQuery(sqlx::Error),
     CannotGetEditorInitialValue(sqlx::Error),
     CannotInsertTranscription(sqlx::Error),
     CannotPublish(sqlx::Error),
+    CannotUnpublish(sqlx::Error),
+    CannotGetTranscriptions(sqlx::Error),
+    CannotGetUserProgress(sqlx::Error),
+    CannotStartReconciliation(sqlx::Error),
+    /// The page is already being reconciled by this user
+    PageAlreadyUnderReconciliation(String),
+    /// The db does not answer a trivial query
+    CannotPing(sqlx::Error),
+    CannotAddAuditEntry(sqlx::Error),
+    CannotGetAuditEntries(sqlx::Error),
+    CannotLockPage(sqlx::Error),
+    /// The page is claimed by this user
+    PageLocked(String),
+    CannotListen(sqlx::Error),
+    CannotRecordUploadUsage(sqlx::Error),
+    /// The daily upload quota (as described here) of the user is used up
+    UploadQuotaExceeded(String),
+    /// The manuscript already has a page (with this name) with the same image
+    DuplicatePageImage(String),
+    /// The manuscript title cannot be used, for the reason given
+    InvalidManuscriptTitle(String),
+    /// This manuscript title differs from the one requested only in case
+    ManuscriptTitleTaken(String),
+    /// The page name cannot be used, for the reason given
+    InvalidPageName(String),
+    /// This username was used by another account (subject) of the oauth provider before
+    UsernameTaken(String),
+    /// The username cannot be used, for the reason given
+    InvalidUsername(String),
 }
 impl core::fmt::Display for DBError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -77,9 +160,69 @@ impl core::fmt::Display for DBError {
             Self::CannotGetVersificationSchemes(e) => {
                 write!(f, "Unable to get versification schemes: {e}")
             }
+            Self::CannotAddVersificationScheme(e) => {
+                write!(f, "Unable to add versification scheme: {e}")
+            }
+            Self::CannotUpdateVersificationScheme(e) => {
+                write!(f, "Unable to update versification scheme: {e}")
+            }
+            Self::CannotDeleteVersificationScheme(e) => {
+                write!(f, "Unable to delete versification scheme: {e}")
+            }
+            Self::VersificationSchemeDoesNotExist(id) => {
+                write!(f, "There is no versification scheme with id {id}")
+            }
+            Self::VersificationSchemeInUse(full_name) => {
+                write!(
+                    f,
+                    "The versification scheme {full_name} is still used by anchors or verse mappings."
+                )
+            }
+            Self::StaticVersificationScheme(full_name) => {
+                write!(
+                    f,
+                    "The versification scheme {full_name} is built in and cannot be changed."
+                )
+            }
+            Self::CannotCheckVersificationSchemeUse(e) => {
+                write!(
+                    f,
+                    "Unable to check whether transcriptions use the versification scheme: {e}"
+                )
+            }
+            Self::VersificationSchemeChanged(full_name) => {
+                write!(
+                    f,
+                    "The versification scheme {full_name} was changed in the meantime. Please try again."
+                )
+            }
             Self::CannotInsertPage(e) => {
                 write!(f, "Unable to insert page: {e}")
             }
+            Self::CannotDeletePage(e) => {
+                write!(f, "Unable to delete page: {e}")
+            }
+            Self::PageHasTranscriptions(count) => {
+                write!(
+                    f,
+                    "This page has {count} transcriptions. Force deletion to remove them as well."
+                )
+            }
+            Self::CannotRenamePage(e) => {
+                write!(f, "Unable to rename page: {e}")
+            }
+            Self::CannotMovePageFiles(path, e) => {
+                write!(f, "Unable to move page files in {path}: {e}")
+            }
+            Self::CannotDeleteManuscript(e) => {
+                write!(f, "Unable to delete manuscript: {e}")
+            }
+            Self::ManuscriptHasPublishedTranscriptions(count) => {
+                write!(
+                    f,
+                    "This manuscript has {count} published transcriptions. Force deletion to remove them as well."
+                )
+            }
             Self::CannotGetMinificationCandidate(e) => {
                 write!(f, "Unable to get next page to minify: {e}")
             }
@@ -98,9 +241,18 @@ impl core::fmt::Display for DBError {
                     "A page with this name already exists for this manuscript."
                 )
             }
+            Self::PageDoesNotExist(pagename) => {
+                write!(f, "This page does not exist: {pagename}")
+            }
             Self::CannotUpdateManuscript(e) => {
                 write!(f, "Unable to update manuscript metadata: {e}")
             }
+            Self::StaleUpdate(msname) => {
+                write!(
+                    f,
+                    "The metadata of {msname} was changed by someone else in the meantime."
+                )
+            }
             Self::CannotGetPagesByQuery(e) => {
                 write!(f, "Unable to get pages from query: {e}")
             }
@@ -116,11 +268,86 @@ impl core::fmt::Display for DBError {
             Self::CannotPublish(e) => {
                 write!(f, "Unable to publish a transcription: {e}")
             }
+            Self::CannotUnpublish(e) => {
+                write!(f, "Unable to unpublish a transcription: {e}")
+            }
+            Self::CannotGetTranscriptions(e) => {
+                write!(f, "Unable to get transcriptions: {e}")
+            }
+            Self::CannotGetUserProgress(e) => {
+                write!(f, "Unable to get the progress of this user: {e}")
+            }
+            Self::CannotStartReconciliation(e) => {
+                write!(f, "Unable to start reconciliation: {e}")
+            }
+            Self::PageAlreadyUnderReconciliation(username) => {
+                write!(f, "This page is already being reconciled by {username}.")
+            }
+            Self::CannotSetVerseRanges(e) => {
+                write!(f, "Unable to set verse ranges: {e}")
+            }
+            Self::InvalidVerseRange(id) => {
+                write!(f, "The verse range of page {id} ends before it starts.")
+            }
+            Self::MinificationNotFailed(id) => {
+                write!(f, "The minification of page {id} did not fail.")
+            }
+            Self::CannotPing(e) => {
+                write!(f, "Unable to reach the db: {e}")
+            }
+            Self::CannotAddAuditEntry(e) => {
+                write!(f, "Unable to add an audit log entry: {e}")
+            }
+            Self::CannotGetAuditEntries(e) => {
+                write!(f, "Unable to get audit log entries: {e}")
+            }
+            Self::CannotLockPage(e) => {
+                write!(f, "Unable to lock or unlock page: {e}")
+            }
+            Self::PageLocked(username) => {
+                write!(f, "This page is currently claimed by {username}.")
+            }
+            Self::CannotListen(e) => {
+                write!(f, "Unable to listen for new pages: {e}")
+            }
+            Self::CannotRecordUploadUsage(e) => {
+                write!(f, "Unable to record the upload usage: {e}")
+            }
+            Self::UploadQuotaExceeded(quota) => {
+                write!(f, "Your daily upload quota of {quota} is used up.")
+            }
+            Self::DuplicatePageImage(pagename) => {
+                write!(f, "The same image was already uploaded as page {pagename}.")
+            }
+            Self::InvalidManuscriptTitle(reason) => {
+                write!(f, "Invalid manuscript title: {reason}")
+            }
+            Self::ManuscriptTitleTaken(existing) => {
+                write!(f, "There already is a manuscript called {existing}.")
+            }
+            Self::InvalidPageName(reason) => {
+                write!(f, "Invalid page name: {reason}")
+            }
+            Self::InvalidUsername(reason) => {
+                write!(f, "Invalid username: {reason}")
+            }
+            Self::UsernameTaken(username) => {
+                write!(
+                    f,
+                    "The username {username} belongs to another account of the oauth provider."
+                )
+            }
         }
     }
 }
 impl std::error::Error for DBError {}
 
+/// Store the session of a user who just logged in
+///
+/// A username belongs to the subject that first logged in with it. Others reporting the same
+/// username (e.g. after renaming themselves in an OpenID Connect provider) are rejected with
+/// [`DBError::UsernameTaken`]. Sessions from before subjects were stored are claimed by the subject
+/// with the same numeric id.
 pub async fn insert_or_update_user_session(
     pool: &Pool<Postgres>,
     user_info: UserInfo,
@@ -131,25 +358,32 @@ pub async fn insert_or_update_user_session(
         .await
         .map_err(DBError::CannotStartTransaction)?;
 
-    let authenticated_user = query_as!(
+    // the row of another account is not updated, so nothing is returned
+    let Some(authenticated_user) = query_as!(
         AuthenticatedUser,
-        "insert into user_session (id, username, access_token, refresh_token, expires_at)
-            values ($1, $2, $3, $4, $5)
+        "insert into user_session (id, username, subject, access_token, refresh_token, expires_at)
+            values ($1, $2, $3, $4, $5, $6)
             on conflict(username) do update
             set access_token = excluded.access_token,
             refresh_token = excluded.refresh_token,
             expires_at = excluded.expires_at,
-            id = excluded.id
+            subject = excluded.subject
+            where user_session.subject = excluded.subject
+                or (user_session.subject is null and user_session.id = excluded.id)
             returning *",
         user_info.id,
         user_info.login,
+        user_info.subject,
         token_res.access_token,
         token_res.refresh_token,
         token_res.expires_at,
     )
-    .fetch_one(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await
-    .map_err(DBError::CannotInsertOrUpdateUsersession)?;
+    .map_err(DBError::CannotInsertOrUpdateUsersession)?
+    else {
+        return Err(DBError::UsernameTaken(user_info.login));
+    };
 
     tx.commit()
         .await
@@ -158,13 +392,25 @@ pub async fn insert_or_update_user_session(
     Ok(authenticated_user)
 }
 
-async fn get_manuscript_meta(
+pub(crate) async fn get_manuscript_meta(
     pool: &Pool<Postgres>,
     msname: &str,
 ) -> Result<critic_shared::ManuscriptMeta, DBError> {
     sqlx::query_as!(
         critic_shared::ManuscriptMeta,
-        "SELECT * FROM manuscript WHERE title = $1;",
+        r#"SELECT id, title, institution, collection, hand_desc, script_desc, lang, date, origin, version,
+            COALESCE(
+                (SELECT page.name FROM page WHERE page.id = manuscript.cover_page_id),
+                (SELECT page.name FROM page WHERE page.manuscript = manuscript.id ORDER BY page.id LIMIT 1)
+            ) as "cover_page",
+            COALESCE(
+                (SELECT page.minified FROM page WHERE page.id = manuscript.cover_page_id),
+                (SELECT page.minified FROM page WHERE page.manuscript = manuscript.id ORDER BY page.id LIMIT 1),
+                false
+            ) as "cover_minified!",
+            updated_at::TEXT as "updated_at!",
+            public
+            FROM manuscript WHERE title = $1;"#,
         msname
     )
     .fetch_optional(pool)
@@ -173,13 +419,14 @@ async fn get_manuscript_meta(
     .ok_or(DBError::ManuscriptDoesNotExist(msname.to_string()))
 }
 
-async fn get_manuscript_page_rows(
+/// The pages of a manuscript in reading order, see [`page_name_order`]
+pub(crate) async fn get_manuscript_page_rows(
     pool: &Pool<Postgres>,
     msid: i64,
 ) -> Result<Vec<PageMeta>, DBError> {
-    sqlx::query_as!(
+    let mut pages = sqlx::query_as!(
         PageMeta,
-        "SELECT page.id, manuscript.id as manuscript_id, page.name, page.verse_start, page.verse_end
+        "SELECT page.id, manuscript.id as manuscript_id, page.name, page.verse_start, page.verse_end, page.placeholder, page.minified, page.minification_failed
             FROM manuscript
             INNER JOIN page on page.manuscript = manuscript.id
             WHERE manuscript.id = $1
@@ -188,7 +435,88 @@ async fn get_manuscript_page_rows(
     )
     .fetch_all(pool)
     .await
-    .map_err(DBError::CannotGetManuscript)
+    .map_err(DBError::CannotGetManuscript)?;
+    pages.sort_by(|a, b| page_name_order(&a.name, &b.name));
+    Ok(pages)
+}
+
+/// Get the pages of a manuscript
+pub async fn get_manuscript_pages(
+    pool: &Pool<Postgres>,
+    msname: &str,
+) -> Result<Vec<PageMeta>, DBError> {
+    let meta = get_manuscript_meta(pool, msname).await?;
+    get_manuscript_page_rows(pool, meta.id).await
+}
+
+/// Compare two page names in the order they appear in a manuscript
+///
+/// Page names are not strictly numeric (`folio3-recto`, `2v`, ...), so they are compared in
+/// chunks: runs of ASCII digits compare by their numeric value (`folio2` before `folio10`),
+/// everything else compares bytewise. Names that only differ in leading zeros fall back to a
+/// plain bytewise comparison, so this is a total order.
+pub fn page_name_order(a: &str, b: &str) -> std::cmp::Ordering {
+    fn chunks(s: &str) -> impl Iterator<Item = &str> {
+        let mut rest = s;
+        core::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let is_digit = first.is_ascii_digit();
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            rest = tail;
+            Some(chunk)
+        })
+    }
+    let numeric_cmp = |x: &str, y: &str| {
+        let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+        x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+    };
+
+    let mut a_chunks = chunks(a);
+    let mut b_chunks = chunks(b);
+    loop {
+        let ord = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) => {
+                if x.as_bytes()[0].is_ascii_digit() && y.as_bytes()[0].is_ascii_digit() {
+                    numeric_cmp(x, y)
+                } else {
+                    x.cmp(y)
+                }
+            }
+        };
+        if ord.is_ne() {
+            return ord;
+        };
+    }
+}
+
+/// Get the names of the pages before and after `pagename` in the manuscript
+///
+/// Pages are ordered by [`page_name_order`], like everywhere else. The first page has no previous page, the last page
+/// has no next page.
+pub async fn get_adjacent_pages(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+) -> Result<AdjacentPages, DBError> {
+    let meta = get_manuscript_meta(pool, msname).await?;
+    let names = get_manuscript_page_rows(pool, meta.id)
+        .await?
+        .into_iter()
+        .map(|page| page.name)
+        .collect::<Vec<_>>();
+    let Some(idx) = names.iter().position(|name| name == pagename) else {
+        return Err(DBError::PageDoesNotExist(pagename.to_string()));
+    };
+    Ok(AdjacentPages {
+        previous: idx.checked_sub(1).map(|i| names[i].clone()),
+        next: names.get(idx + 1).cloned(),
+    })
 }
 
 /// Get the metainformation for a manuscript from the db
@@ -201,32 +529,188 @@ pub async fn get_manuscript(
     Ok(critic_shared::Manuscript { meta, pages })
 }
 
+/// The columns of `manuscript` that make up a [`ManuscriptMeta`]
+///
+/// The cover page falls back to the page uploaded first.
+const MANUSCRIPT_META_COLUMNS: &str =
+    "id, title, institution, collection, hand_desc, script_desc, lang, date, origin, version,
+    COALESCE(
+        (SELECT page.name FROM page WHERE page.id = manuscript.cover_page_id),
+        (SELECT page.name FROM page WHERE page.manuscript = manuscript.id ORDER BY page.id LIMIT 1)
+    ) as cover_page,
+    COALESCE(
+        (SELECT page.minified FROM page WHERE page.id = manuscript.cover_page_id),
+        (SELECT page.minified FROM page WHERE page.manuscript = manuscript.id ORDER BY page.id LIMIT 1),
+        false
+    ) as cover_minified,
+    updated_at::TEXT as updated_at,
+    public";
+
+/// The `ORDER BY` clause for `order`
+///
+/// Ties are broken by title, so the order is always the same.
+fn manuscript_order_by(order: ManuscriptOrder) -> &'static str {
+    match order {
+        ManuscriptOrder::TitleAsc => " ORDER BY title ASC",
+        ManuscriptOrder::TitleDesc => " ORDER BY title DESC",
+        ManuscriptOrder::NewestFirst => " ORDER BY created_at DESC, title ASC",
+    }
+}
+
 /// Get the metainformation for all manuscripts, excluding the page information
 pub async fn get_manuscripts_by_name(
     pool: &Pool<Postgres>,
     msname: Option<String>,
+    order: ManuscriptOrder,
 ) -> Result<Vec<critic_shared::ManuscriptMeta>, DBError> {
+    let mut builder =
+        QueryBuilder::new(format!("SELECT {MANUSCRIPT_META_COLUMNS} FROM manuscript"));
     if let Some(name) = msname {
-        sqlx::query_as!(
-            critic_shared::ManuscriptMeta,
-            "SELECT * FROM manuscript WHERE title LIKE $1;",
-            format!("%{name}%")
-        )
+        builder.push(" WHERE title LIKE ");
+        builder.push_bind(format!("%{name}%"));
+    };
+    builder.push(manuscript_order_by(order));
+    builder
+        .build_query_as::<critic_shared::ManuscriptMeta>()
         .fetch_all(pool)
         .await
         .map_err(DBError::CannotGetManuscript)
-    } else {
-        sqlx::query_as!(critic_shared::ManuscriptMeta, "SELECT * FROM manuscript;",)
-            .fetch_all(pool)
-            .await
-            .map_err(DBError::CannotGetManuscript)
-    }
 }
 
 pub async fn get_manuscripts(
     pool: &Pool<Postgres>,
 ) -> Result<Vec<critic_shared::ManuscriptMeta>, DBError> {
-    get_manuscripts_by_name(pool, None).await
+    get_manuscripts_by_name(pool, None, ManuscriptOrder::TitleAsc).await
+}
+
+/// Choose the page representing the manuscript, or go back to the first page with `None`
+pub async fn set_manuscript_cover(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: Option<&str>,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(DBError::CannotUpdateManuscript)?;
+    let cover_page_id = match pagename {
+        Some(pagename) => Some(
+            page_id(&mut *conn, ms_meta.id, pagename)
+                .await?
+                .ok_or(DBError::PageDoesNotExist(pagename.to_string()))?,
+        ),
+        None => None,
+    };
+    sqlx::query!(
+        "UPDATE manuscript SET cover_page_id = $1 WHERE id = $2;",
+        cover_page_id,
+        ms_meta.id
+    )
+    .execute(&mut *conn)
+    .await
+    .map(|_| ())
+    .map_err(DBError::CannotUpdateManuscript)
+}
+
+/// Allow or forbid reading the published transcriptions of a manuscript without logging in
+pub async fn set_manuscript_public(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    public: bool,
+) -> Result<(), DBError> {
+    let res = sqlx::query!(
+        "UPDATE manuscript SET public = $1 WHERE title = $2;",
+        public,
+        msname
+    )
+    .execute(pool)
+    .await
+    .map_err(DBError::CannotUpdateManuscript)?;
+    if res.rows_affected() == 0 {
+        return Err(DBError::ManuscriptDoesNotExist(msname.to_string()));
+    };
+    Ok(())
+}
+
+/// Why `name` cannot be used as a single directory name below the data directory, if it cannot
+fn path_segment_problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("it must not be empty.")
+    } else if name.contains(['/', '\\']) {
+        Some("it must not contain '/' or '\\'.")
+    } else if name == "." || name.contains("..") {
+        Some("it must not be '.' or contain '..'.")
+    } else if name.chars().any(char::is_control) {
+        Some("it must not contain control characters.")
+    } else {
+        None
+    }
+}
+
+/// Make sure `title` can be used as manuscript title
+///
+/// Titles are used as directory names under the data directory, so anything that could leave that
+/// directory or trip up the file system is rejected.
+pub fn validate_manuscript_title(title: &str) -> Result<(), DBError> {
+    let reason = if title.trim() != title {
+        Some("it must not start or end with whitespace.")
+    } else {
+        path_segment_problem(title)
+    };
+    match reason {
+        Some(reason) => Err(DBError::InvalidManuscriptTitle(reason.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Make sure `pagename` can be used as page name
+///
+/// Like manuscript titles, page names are used as directory names for images and transcriptions.
+pub fn validate_page_name(pagename: &str) -> Result<(), DBError> {
+    match path_segment_problem(pagename) {
+        Some(reason) => Err(DBError::InvalidPageName(reason.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Make sure `username` can be used as username
+///
+/// Usernames are the file names of transcriptions. Hidden names are rejected as well, they are
+/// kept for files that are not transcriptions (e.g. the published ones).
+pub fn validate_username(username: &str) -> Result<(), DBError> {
+    let reason = if username.starts_with('.') {
+        Some("it must not start with '.'.")
+    } else {
+        path_segment_problem(username)
+    };
+    match reason {
+        Some(reason) => Err(DBError::InvalidUsername(reason.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Fail if a manuscript other than `except_id` has `title` in any capitalization
+///
+/// Two manuscripts differing only in case would share their directories on case-insensitive file
+/// systems.
+async fn ensure_manuscript_title_free(
+    pool: &Pool<Postgres>,
+    title: &str,
+    except_id: Option<i64>,
+) -> Result<(), DBError> {
+    let existing = sqlx::query_scalar!(
+        "SELECT title FROM manuscript WHERE lower(title) = lower($1) AND ($2::BIGINT IS NULL OR id <> $2);",
+        title,
+        except_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotGetManuscript)?;
+    match existing {
+        Some(existing) => Err(DBError::ManuscriptTitleTaken(existing)),
+        None => Ok(()),
+    }
 }
 
 pub async fn add_manuscript(
@@ -234,6 +718,8 @@ pub async fn add_manuscript(
     msname: &str,
     lang: Option<&str>,
 ) -> Result<(), DBError> {
+    validate_manuscript_title(msname)?;
+    ensure_manuscript_title_free(pool, msname, None).await?;
     sqlx::query!(
         "INSERT INTO manuscript (title, lang) VALUES ($1, $2);",
         msname,
@@ -245,6 +731,84 @@ pub async fn add_manuscript(
     .map_err(DBError::CannotAddManuscript)
 }
 
+/// Delete a manuscript with all its pages, transcriptions and reconciliations
+///
+/// This refuses to delete manuscripts with published transcriptions unless `force` is set.
+/// After the db is updated, the image and transcription directories for the manuscript are removed
+/// from `data_directory`.
+pub async fn delete_manuscript(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    msname: &str,
+    force: bool,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+
+    let published = sqlx::query_scalar!(
+        "SELECT COUNT(*)
+         FROM transcription
+         INNER JOIN page ON page.id = transcription.page
+         WHERE page.manuscript = $1 AND transcription.published;",
+        ms_meta.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(DBError::CannotDeleteManuscript)?
+    .unwrap_or_default();
+    if published > 0 && !force {
+        return Err(DBError::ManuscriptHasPublishedTranscriptions(published));
+    };
+
+    sqlx::query!(
+        "DELETE FROM transcription
+         USING page
+         WHERE page.id = transcription.page AND page.manuscript = $1;",
+        ms_meta.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(DBError::CannotDeleteManuscript)?;
+    sqlx::query!(
+        "DELETE FROM reconciliation
+         USING page
+         WHERE page.id = reconciliation.page AND page.manuscript = $1;",
+        ms_meta.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(DBError::CannotDeleteManuscript)?;
+    sqlx::query!("DELETE FROM page WHERE manuscript = $1;", ms_meta.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeleteManuscript)?;
+    sqlx::query!("DELETE FROM manuscript WHERE id = $1;", ms_meta.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeleteManuscript)?;
+
+    tx.commit()
+        .await
+        .map_err(DBError::CannotCommitTransaction)?;
+
+    // the db is consistent now - files that cannot be removed are merely orphaned
+    for base_location in [IMAGE_BASE_LOCATION, TRANSCRIPTION_BASE_LOCATION] {
+        let path = format!("{data_directory}{base_location}/{msname}");
+        match std::fs::remove_dir_all(&path) {
+            Ok(()) => {}
+            // manuscripts without pages or transcriptions have no directory
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!("Failed to remove {path} after deleting manuscript {msname}: {e}. Will not retry and leave the files orphaned.");
+            }
+        };
+    }
+    Ok(())
+}
+
 pub async fn get_versification_schemes(
     pool: &Pool<Postgres>,
 ) -> Result<Vec<VersificationScheme>, DBError> {
@@ -258,6 +822,300 @@ pub async fn get_versification_schemes(
     )
 }
 
+pub async fn add_versification_scheme(
+    pool: &Pool<Postgres>,
+    full_name: &str,
+    shorthand: &str,
+) -> Result<(), DBError> {
+    sqlx::query!(
+        "INSERT INTO versification_scheme (full_name, shorthand) VALUES ($1, $2);",
+        full_name,
+        shorthand
+    )
+    .execute(pool)
+    .await
+    .map(|_| {})
+    .map_err(DBError::CannotAddVersificationScheme)
+}
+
+/// Make sure no transcription on disk has an anchor in the versification scheme `id`
+///
+/// Returns the shorthand that was checked. Scanning the transcriptions is blocking, so it runs on a
+/// blocking task and has to happen before a transaction is opened.
+async fn ensure_versification_scheme_unused_on_disk(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    id: i64,
+) -> Result<String, DBError> {
+    let Some(scheme) = query_as!(
+        VersificationScheme,
+        "SELECT * FROM versification_scheme WHERE id = $1;",
+        id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotGetVersificationSchemes)?
+    else {
+        return Err(DBError::VersificationSchemeDoesNotExist(id));
+    };
+    let data_directory = data_directory.to_string();
+    let shorthand = scheme.shorthand.clone();
+    // anchors only live in the transcriptions on disk and refer to the scheme by its shorthand
+    let in_use = tokio::task::spawn_blocking(move || {
+        versification_scheme_in_use(&data_directory, &shorthand)
+    })
+    .await
+    .map_err(|e| DBError::CannotCheckVersificationSchemeUse(TranscriptionStoreError::Join(e)))?
+    .map_err(DBError::CannotCheckVersificationSchemeUse)?;
+    if in_use {
+        return Err(DBError::VersificationSchemeInUse(scheme.full_name));
+    };
+    Ok(scheme.shorthand)
+}
+
+/// Get a versification scheme that may be changed, i.e. one that exists, is not static and is not
+/// used anywhere
+///
+/// The scheme is locked for the rest of the transaction. `checked_shorthand` is the shorthand
+/// [`ensure_versification_scheme_unused_on_disk`] checked, so that the check cannot be outdated.
+async fn get_unused_versification_scheme(
+    conn: &mut PgConnection,
+    id: i64,
+    checked_shorthand: &str,
+) -> Result<VersificationScheme, DBError> {
+    let Some(scheme) = query_as!(
+        VersificationScheme,
+        "SELECT * FROM versification_scheme WHERE id = $1 FOR UPDATE;",
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(DBError::CannotGetVersificationSchemes)?
+    else {
+        return Err(DBError::VersificationSchemeDoesNotExist(id));
+    };
+    if STATIC_VERSIFICATION_SCHEMES.contains(&scheme.full_name.as_str()) {
+        return Err(DBError::StaticVersificationScheme(scheme.full_name));
+    };
+    if scheme.shorthand != checked_shorthand {
+        return Err(DBError::VersificationSchemeChanged(scheme.full_name));
+    };
+
+    let mappings = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM verse_map WHERE versification_scheme = $1;",
+        id
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(DBError::CannotGetVersificationSchemes)?
+    .unwrap_or_default();
+    if mappings > 0 {
+        return Err(DBError::VersificationSchemeInUse(scheme.full_name));
+    };
+    Ok(scheme)
+}
+
+/// Change the names of a versification scheme
+///
+/// Anchors refer to the scheme by its names, so this is refused for schemes that are in use.
+pub async fn update_versification_scheme(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    scheme: &VersificationScheme,
+) -> Result<(), DBError> {
+    let checked_shorthand =
+        ensure_versification_scheme_unused_on_disk(pool, data_directory, scheme.id).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+    get_unused_versification_scheme(&mut *tx, scheme.id, &checked_shorthand).await?;
+    sqlx::query!(
+        "UPDATE versification_scheme SET full_name = $1, shorthand = $2 WHERE id = $3;",
+        scheme.full_name,
+        scheme.shorthand,
+        scheme.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(DBError::CannotUpdateVersificationScheme)?;
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
+
+/// Delete a versification scheme
+///
+/// This is refused for schemes that are in use.
+pub async fn delete_versification_scheme(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    id: i64,
+) -> Result<(), DBError> {
+    let checked_shorthand =
+        ensure_versification_scheme_unused_on_disk(pool, data_directory, id).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+    get_unused_versification_scheme(&mut *tx, id, &checked_shorthand).await?;
+    sqlx::query!("DELETE FROM versification_scheme WHERE id = $1;", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeleteVersificationScheme)?;
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
+
+/// Get the id of the page `pagename` in the manuscript with id `ms_id` if it exists
+async fn page_id(
+    conn: &mut PgConnection,
+    ms_id: i64,
+    pagename: &str,
+) -> Result<Option<i64>, DBError> {
+    Ok(sqlx::query!(
+        "SELECT id FROM page WHERE manuscript = $1 AND name = $2;",
+        ms_id,
+        pagename
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(DBError::CannotGetPage)?
+    .map(|row| row.id))
+}
+
+/// Has `username` ever logged in?
+pub async fn user_exists(pool: &Pool<Postgres>, username: &str) -> Result<bool, DBError> {
+    Ok(
+        sqlx::query!("SELECT id FROM user_session WHERE username = $1;", username)
+            .fetch_optional(pool)
+            .await
+            .map_err(DBError::CannotGetUsersession)?
+            .is_some(),
+    )
+}
+
+/// Does the page `pagename` exist in the manuscript `msname`?
+pub async fn page_exists(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+) -> Result<bool, DBError> {
+    Ok(sqlx::query!(
+        "SELECT page.id
+         FROM page
+         INNER JOIN manuscript ON page.manuscript = manuscript.id
+         WHERE manuscript.title = $1 AND page.name = $2;",
+        msname,
+        pagename
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotGetPage)?
+    .is_some())
+}
+
+/// Claim this page for `username` for the next `ttl_seconds`
+///
+/// Call this again before the lock expires to keep it. A lock of another user is taken over once
+/// it expired; while it has not, this fails with [`DBError::PageLocked`].
+pub async fn acquire_lock(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+    ttl_seconds: u64,
+) -> Result<(), DBError> {
+    let acquired = sqlx::query_scalar!(
+        "INSERT INTO page_lock (page, username, expires_at)
+         SELECT page.id, $3, now() + make_interval(secs => $4::FLOAT8)
+         FROM page
+         INNER JOIN manuscript ON page.manuscript = manuscript.id
+         WHERE manuscript.title = $1 AND page.name = $2
+         ON CONFLICT (page) DO UPDATE
+         SET username = excluded.username, expires_at = excluded.expires_at
+         WHERE page_lock.username = excluded.username OR page_lock.expires_at < now()
+         RETURNING page;",
+        msname,
+        pagename,
+        username,
+        ttl_seconds as f64
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotLockPage)?;
+    if acquired.is_some() {
+        return Ok(());
+    };
+    // either someone else holds the lock or the page does not exist
+    let holder = sqlx::query_scalar!(
+        "SELECT page_lock.username
+         FROM page_lock
+         INNER JOIN page ON page_lock.page = page.id
+         INNER JOIN manuscript ON page.manuscript = manuscript.id
+         WHERE manuscript.title = $1 AND page.name = $2;",
+        msname,
+        pagename
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotLockPage)?;
+    match holder {
+        Some(holder) => Err(DBError::PageLocked(holder)),
+        None => Err(DBError::PageDoesNotExist(pagename.to_string())),
+    }
+}
+
+/// Fail with [`DBError::PageLocked`] if someone other than `username` holds the claim on this page
+///
+/// Unlike [`acquire_lock`], this does not claim the page.
+pub async fn check_lock(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), DBError> {
+    let holder = sqlx::query_scalar!(
+        "SELECT page_lock.username
+         FROM page_lock
+         INNER JOIN page ON page_lock.page = page.id
+         INNER JOIN manuscript ON page.manuscript = manuscript.id
+         WHERE manuscript.title = $1 AND page.name = $2
+            AND page_lock.username <> $3 AND page_lock.expires_at >= now();",
+        msname,
+        pagename,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotLockPage)?;
+    match holder {
+        Some(holder) => Err(DBError::PageLocked(holder)),
+        None => Ok(()),
+    }
+}
+
+/// Give up the claim of `username` on this page
+///
+/// Locks of other users are left alone.
+pub async fn release_lock(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), DBError> {
+    sqlx::query!(
+        "DELETE FROM page_lock
+         USING page, manuscript
+         WHERE page_lock.page = page.id AND page.manuscript = manuscript.id
+            AND manuscript.title = $1 AND page.name = $2 AND page_lock.username = $3;",
+        msname,
+        pagename,
+        username
+    )
+    .execute(pool)
+    .await
+    .map_err(DBError::CannotLockPage)
+    .map(|_| {})
+}
+
 pub async fn add_page(pool: &Pool<Postgres>, pagename: &str, msname: &str) -> Result<(), DBError> {
     // get manuscript id
     let ms_meta = get_manuscript_meta(pool, msname).await?;
@@ -265,31 +1123,252 @@ pub async fn add_page(pool: &Pool<Postgres>, pagename: &str, msname: &str) -> Re
         .begin()
         .await
         .map_err(DBError::CannotStartTransaction)?;
+    insert_page(&mut *tx, ms_meta.id, pagename, None).await?;
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
 
-    if sqlx::query!(
-        "SELECT id FROM page WHERE manuscript = $1 AND name = $2;",
+/// Add a page uploaded by `username` and count it towards their upload usage of today
+///
+/// `content_hash` identifies the image. If the manuscript already has a page with the same image,
+/// this fails with [`DBError::DuplicatePageImage`].
+///
+/// If the page takes the user over their `quota`, nothing is changed and this fails with
+/// [`DBError::UploadQuotaExceeded`]. The usage is counted in the same transaction as the page, so
+/// parallel uploads cannot sneak past the quota.
+pub async fn add_uploaded_page(
+    pool: &Pool<Postgres>,
+    pagename: &str,
+    msname: &str,
+    username: &str,
+    bytes: u64,
+    content_hash: &str,
+    quota: &UploadQuotaConfig,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+    // a unique index catches parallel uploads of the same image, this is only for the message
+    if let Some(existing) = sqlx::query_scalar!(
+        "SELECT name FROM page WHERE manuscript = $1 AND content_hash = $2;",
         ms_meta.id,
-        pagename
+        content_hash
     )
     .fetch_optional(&mut *tx)
     .await
     .map_err(DBError::CannotGetPage)?
-    .is_some()
     {
+        return Err(DBError::DuplicatePageImage(existing));
+    };
+    insert_page(&mut *tx, ms_meta.id, pagename, Some(content_hash)).await?;
+
+    // this locks the row of the user until the transaction ends
+    let usage = sqlx::query!(
+        "INSERT INTO upload_usage (username, bytes, pages)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (username, day) DO UPDATE
+            SET bytes = upload_usage.bytes + EXCLUDED.bytes, pages = upload_usage.pages + 1
+        RETURNING bytes, pages;",
+        username,
+        i64::try_from(bytes).unwrap_or(i64::MAX),
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(DBError::CannotRecordUploadUsage)?;
+    // usage is never negative
+    if let Some(max) = quota.daily_bytes.filter(|max| usage.bytes as u64 > *max) {
+        return Err(DBError::UploadQuotaExceeded(format!("{max} bytes")));
+    };
+    if let Some(max) = quota.daily_pages.filter(|max| usage.pages as u64 > *max) {
+        return Err(DBError::UploadQuotaExceeded(format!("{max} pages")));
+    };
+
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
+
+/// Insert a new page into the manuscript with id `ms_id`
+async fn insert_page(
+    conn: &mut PgConnection,
+    ms_id: i64,
+    pagename: &str,
+    content_hash: Option<&str>,
+) -> Result<(), DBError> {
+    validate_page_name(pagename)?;
+    if page_id(&mut *conn, ms_id, pagename).await?.is_some() {
         return Err(DBError::PageAlreadyExists);
     };
 
     sqlx::query!(
-        "INSERT INTO page (manuscript, name) VALUES ($1, $2);",
-        ms_meta.id,
+        "INSERT INTO page (manuscript, name, content_hash) VALUES ($1, $2, $3);",
+        ms_id,
         pagename,
+        content_hash,
     )
-    .execute(&mut *tx)
+    .execute(&mut *conn)
     .await
     .map(|_| {})
     .map_err(DBError::CannotInsertPage)?;
 
-    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+    // wake the minification service - postgres only delivers this once the transaction commits
+    sqlx::query!("SELECT pg_notify($1, '');", NEW_PAGE_CHANNEL)
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::CannotInsertPage)?;
+    Ok(())
+}
+
+/// The channel notified whenever a page is added
+const NEW_PAGE_CHANNEL: &str = "critic_new_page";
+
+/// Start listening for new pages
+///
+/// Each [`PgListener::recv`](sqlx::postgres::PgListener::recv) returns once a page was added.
+pub async fn listen_for_new_pages(
+    pool: &Pool<Postgres>,
+) -> Result<sqlx::postgres::PgListener, DBError> {
+    let mut listener = sqlx::postgres::PgListener::connect_with(pool)
+        .await
+        .map_err(DBError::CannotListen)?;
+    listener
+        .listen(NEW_PAGE_CHANNEL)
+        .await
+        .map_err(DBError::CannotListen)?;
+    Ok(listener)
+}
+
+/// Held (shared) while [`rename_page`] moves page directories and held exclusively by the cleanup
+///
+/// Between moving the directories and committing, the db still has the old page name. The cleanup
+/// would see the moved directory as an orphan in that window.
+pub(crate) static PAGE_MOVES: tokio::sync::RwLock<()> = tokio::sync::RwLock::const_new(());
+
+/// Rename a page
+///
+/// Fails with [`DBError::PageAlreadyExists`] if `new_pagename` is already taken in this manuscript.
+/// The image and transcription directories for the page under `data_directory` are moved as well.
+pub async fn rename_page(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    msname: &str,
+    old_pagename: &str,
+    new_pagename: &str,
+) -> Result<(), DBError> {
+    validate_page_name(new_pagename)?;
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let _moving = PAGE_MOVES.read().await;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+
+    let Some(id) = page_id(&mut *tx, ms_meta.id, old_pagename).await? else {
+        return Err(DBError::PageDoesNotExist(old_pagename.to_string()));
+    };
+    if page_id(&mut *tx, ms_meta.id, new_pagename).await?.is_some() {
+        return Err(DBError::PageAlreadyExists);
+    };
+    sqlx::query!("UPDATE page SET name = $1 WHERE id = $2;", new_pagename, id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotRenamePage)?;
+
+    // move the files before committing, so that a failure leaves the db untouched
+    let mut moved = Vec::new();
+    for base_location in [IMAGE_BASE_LOCATION, TRANSCRIPTION_BASE_LOCATION] {
+        let base_path = format!("{data_directory}{base_location}/{msname}");
+        let old_path = format!("{base_path}/{old_pagename}");
+        let new_path = format!("{base_path}/{new_pagename}");
+        match std::fs::rename(&old_path, &new_path) {
+            Ok(()) => moved.push((old_path, new_path)),
+            // pages without transcriptions have no transcription directory
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                undo_page_moves(&moved);
+                return Err(DBError::CannotMovePageFiles(old_path, e));
+            }
+        };
+    }
+
+    if let Err(e) = tx.commit().await {
+        undo_page_moves(&moved);
+        return Err(DBError::CannotCommitTransaction(e));
+    };
+    Ok(())
+}
+
+/// Move page directories back to where they were before a failed [`rename_page`]
+fn undo_page_moves(moved: &[(String, String)]) {
+    for (old_path, new_path) in moved {
+        if let Err(e) = std::fs::rename(new_path, old_path) {
+            tracing::error!(
+                "Failed to move {new_path} back to {old_path} after renaming a page failed: {e}"
+            );
+        };
+    }
+}
+
+/// Delete a page
+///
+/// This refuses to delete pages with transcriptions unless `force` is set.
+/// After the db is updated, the image and transcription directories for the page are removed from
+/// `data_directory`. This is also used to clean up after [`add_page`] when storing the page image
+/// fails.
+pub async fn delete_page(
+    pool: &Pool<Postgres>,
+    data_directory: &str,
+    pagename: &str,
+    msname: &str,
+    force: bool,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+
+    let Some(id) = page_id(&mut *tx, ms_meta.id, pagename).await? else {
+        return Err(DBError::PageDoesNotExist(pagename.to_string()));
+    };
+    let transcriptions =
+        sqlx::query_scalar!("SELECT COUNT(*) FROM transcription WHERE page = $1;", id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(DBError::CannotDeletePage)?
+            .unwrap_or_default();
+    if transcriptions > 0 && !force {
+        return Err(DBError::PageHasTranscriptions(transcriptions));
+    };
+
+    sqlx::query!("DELETE FROM transcription WHERE page = $1;", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeletePage)?;
+    sqlx::query!("DELETE FROM reconciliation WHERE page = $1;", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeletePage)?;
+    sqlx::query!("DELETE FROM page WHERE id = $1;", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(DBError::CannotDeletePage)?;
+
+    tx.commit()
+        .await
+        .map_err(DBError::CannotCommitTransaction)?;
+
+    // the db is consistent now - files that cannot be removed are merely orphaned
+    for base_location in [IMAGE_BASE_LOCATION, TRANSCRIPTION_BASE_LOCATION] {
+        let path = format!("{data_directory}{base_location}/{msname}/{pagename}");
+        match std::fs::remove_dir_all(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!("Failed to remove {path} after deleting page {pagename} of {msname}: {e}. Will not retry and leave the files orphaned.");
+            }
+        };
+    }
+    Ok(())
 }
 
 /// page information plus the name of the MS it belongs to
@@ -301,6 +1380,9 @@ struct _PageMetaWithMsName {
     name: String,
     verse_start: Option<i64>,
     verse_end: Option<i64>,
+    placeholder: Option<String>,
+    minified: bool,
+    minification_failed: bool,
 }
 impl From<_PageMetaWithMsName> for (String, PageMeta) {
     fn from(value: _PageMetaWithMsName) -> Self {
@@ -312,6 +1394,9 @@ impl From<_PageMetaWithMsName> for (String, PageMeta) {
                 name: value.name,
                 verse_start: value.verse_start,
                 verse_end: value.verse_end,
+                placeholder: value.placeholder,
+                minified: value.minified,
+                minification_failed: value.minification_failed,
             },
         )
     }
@@ -325,7 +1410,7 @@ pub async fn get_page_to_minify(
     how_many: u8,
 ) -> Result<Vec<(String, PageMeta)>, DBError> {
     Ok(sqlx::query_as!(_PageMetaWithMsName,
-        "SELECT manuscript.title as manuscript_name, page.id, manuscript as manuscript_id, name, verse_start, verse_end
+        "SELECT manuscript.title as manuscript_name, page.id, manuscript as manuscript_id, name, verse_start, verse_end, placeholder, minified, minification_failed
          FROM page
          INNER JOIN manuscript on page.manuscript = manuscript.id
          WHERE minified = false AND minification_failed = false
@@ -339,15 +1424,42 @@ pub async fn get_page_to_minify(
     )
 }
 
-pub async fn mark_page_minifcation_failed(
+/// How many pages are still waiting to be minified
+pub async fn count_pages_to_minify(pool: &Pool<Postgres>) -> Result<i64, DBError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM page WHERE minified = false AND minification_failed = false;"
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(DBError::CannotGetMinificationCandidate)?
+    .unwrap_or(0))
+}
+
+/// All pages as (manuscript name, page name, whether the page is minified)
+pub async fn get_all_pages(pool: &Pool<Postgres>) -> Result<Vec<(String, String, bool)>, DBError> {
+    Ok(sqlx::query!(
+        "SELECT manuscript.title, page.name, page.minified
+         FROM page
+         INNER JOIN manuscript on page.manuscript = manuscript.id;"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetPage)?
+    .into_iter()
+    .map(|row| (row.title, row.name, row.minified))
+    .collect())
+}
+
+/// Mark all these pages as failed to minify in a single query
+pub async fn mark_pages_minification_failed(
     pool: &Pool<Postgres>,
-    page_id: i64,
+    page_ids: &[i64],
 ) -> Result<(), DBError> {
     sqlx::query!(
         "UPDATE page
          SET minification_failed = true
-         WHERE id = $1;",
-        page_id
+         WHERE id = ANY($1);",
+        page_ids
     )
     .execute(pool)
     .await
@@ -355,33 +1467,182 @@ pub async fn mark_page_minifcation_failed(
     .map(|_| {})
 }
 
-pub async fn mark_page_minified(pool: &Pool<Postgres>, page_id: i64) -> Result<(), DBError> {
+/// Let the minification service try again for all pages it failed to minify
+///
+/// Returns how many pages will be tried again.
+pub async fn retry_failed_minifications(pool: &Pool<Postgres>) -> Result<u64, DBError> {
     sqlx::query!(
         "UPDATE page
-         SET minified = true
-         WHERE id = $1;",
+         SET minification_failed = false
+         WHERE minification_failed = true AND minified = false;"
+    )
+    .execute(pool)
+    .await
+    .map_err(DBError::CannotMarkPageMinificationFailed)
+    .map(|res| res.rows_affected())
+}
+
+/// Let the minification service try again for a single page it failed to minify
+///
+/// Returns the manuscript and name of the page.
+pub async fn retry_page_minification(
+    pool: &Pool<Postgres>,
+    page_id: i64,
+) -> Result<FailedMinification, DBError> {
+    let retried = sqlx::query_as!(
+        FailedMinification,
+        "UPDATE page
+         SET minification_failed = false
+         FROM manuscript
+         WHERE page.id = $1 AND page.manuscript = manuscript.id
+         AND page.minification_failed = true AND page.minified = false
+         RETURNING page.id as page_id, manuscript.title as manuscript, page.name as page;",
+        page_id
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(DBError::CannotMarkPageMinificationFailed)?;
+    if let Some(retried) = retried {
+        return Ok(retried);
+    };
+    let exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM page WHERE id = $1) as \"exists!\";",
         page_id
     )
+    .fetch_one(pool)
+    .await
+    .map_err(DBError::CannotGetPage)?;
+    if exists {
+        Err(DBError::MinificationNotFailed(page_id))
+    } else {
+        Err(DBError::PageDoesNotExist(page_id.to_string()))
+    }
+}
+
+/// One page of the pages that failed to minify, ordered by manuscript and page name
+pub async fn get_failed_minifications(
+    pool: &Pool<Postgres>,
+    page: i32,
+) -> Result<Vec<FailedMinification>, DBError> {
+    sqlx::query_as!(
+        FailedMinification,
+        "SELECT page.id as page_id, manuscript.title as manuscript, page.name as page
+         FROM page
+         INNER JOIN manuscript ON page.manuscript = manuscript.id
+         WHERE page.minification_failed = true AND page.minified = false
+         ORDER BY manuscript.title, page.name
+         LIMIT $1 OFFSET $2;",
+        i64::from(DEFAULT_PAGINATION_SIZE),
+        i64::from(page.max(0) * DEFAULT_PAGINATION_SIZE)
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetPage)
+}
+
+/// Mark all these pages as minified in a single query
+///
+/// `pages` are (page id, placeholder) pairs
+pub async fn mark_pages_minified(
+    pool: &Pool<Postgres>,
+    pages: &[(i64, String)],
+) -> Result<(), DBError> {
+    let (page_ids, placeholders): (Vec<i64>, Vec<String>) = pages.iter().cloned().unzip();
+    sqlx::query!(
+        "UPDATE page
+         SET minified = true, placeholder = minified_page.placeholder
+         FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS minified_page(id, placeholder)
+         WHERE page.id = minified_page.id;",
+        &page_ids,
+        &placeholders
+    )
     .execute(pool)
     .await
     .map_err(DBError::CannotMarkPageMinified)
     .map(|_| {})
 }
 
-pub async fn update_ms_meta(pool: &Pool<Postgres>, data: &ManuscriptMeta) -> Result<(), DBError> {
-    sqlx::query!(
-            "UPDATE manuscript SET title = $1, institution = $2, collection = $3, hand_desc = $4, script_desc = $5 WHERE id = $6;",
+/// Update the metadata of a manuscript and return its new version
+///
+/// The update only succeeds if `data.version` is still the version in the db, otherwise someone
+/// else has changed the manuscript since `data` was read and [`DBError::StaleUpdate`] is returned.
+pub async fn update_ms_meta(pool: &Pool<Postgres>, data: &ManuscriptMeta) -> Result<i64, DBError> {
+    validate_manuscript_title(&data.title)?;
+    ensure_manuscript_title_free(pool, &data.title, Some(data.id)).await?;
+    let new_version = sqlx::query_scalar!(
+            "UPDATE manuscript SET title = $1, institution = $2, collection = $3, hand_desc = $4, script_desc = $5, lang = $6, date = $7, origin = $8, version = version + 1 WHERE id = $9 AND version = $10 RETURNING version;",
             data.title,
             data.institution,
             data.collection,
             data.hand_desc,
             data.script_desc,
+            data.lang,
+            data.date,
+            data.origin,
             data.id,
+            data.version,
         )
-        .execute(pool)
+        .fetch_optional(pool)
+        .await
+        .map_err(DBError::CannotUpdateManuscript)?;
+    if let Some(new_version) = new_version {
+        return Ok(new_version);
+    };
+    // report the manuscript under the title it is stored with, data.title may be a new one
+    Err(DBError::StaleUpdate(
+        get_manuscript_title(pool, data.id).await?,
+    ))
+}
+
+/// The title of the manuscript with this id
+pub async fn get_manuscript_title(pool: &Pool<Postgres>, id: i64) -> Result<String, DBError> {
+    sqlx::query_scalar!("SELECT title FROM manuscript WHERE id = $1;", id)
+        .fetch_optional(pool)
+        .await
+        .map_err(DBError::CannotGetManuscript)?
+        .ok_or(DBError::ManuscriptDoesNotExist(id.to_string()))
+}
+
+/// Set the first and last verse for many pages of the manuscript `msname` at once
+///
+/// `updates` contains (page id, verse start, verse end). Either all updates are applied or none.
+/// Pages of other manuscripts are reported as [`DBError::PageDoesNotExist`].
+pub async fn set_page_verse_ranges(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    updates: &[(i64, Option<i64>, Option<i64>)],
+) -> Result<(), DBError> {
+    for (id, verse_start, verse_end) in updates {
+        if let (Some(start), Some(end)) = (verse_start, verse_end) {
+            if start > end {
+                return Err(DBError::InvalidVerseRange(*id));
+            };
+        };
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+    for (id, verse_start, verse_end) in updates {
+        // dropping tx on error rolls back everything done so far
+        let updated = sqlx::query!(
+            "UPDATE page SET verse_start = $2, verse_end = $3
+            WHERE id = $1 AND manuscript = (SELECT id FROM manuscript WHERE title = $4);",
+            id,
+            *verse_start,
+            *verse_end,
+            msname
+        )
+        .execute(&mut *tx)
         .await
-        .map(|_| {})
-        .map_err(DBError::CannotUpdateManuscript)
+        .map_err(DBError::CannotSetVerseRanges)?
+        .rows_affected();
+        if updated == 0 {
+            return Err(DBError::PageDoesNotExist(id.to_string()));
+        };
+    }
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
 }
 
 struct QueryTerm<'a> {
@@ -397,6 +1658,18 @@ enum QueryType {
     PageEqual,
     PageContains,
 }
+impl QueryType {
+    /// Can this be checked on the manuscript table alone?
+    fn is_manuscript_level(&self) -> bool {
+        match self {
+            Self::ManuscriptEqual
+            | Self::ManuscriptContains
+            | Self::LanguageEqual
+            | Self::LanguageContains => true,
+            Self::PageEqual | Self::PageContains => false,
+        }
+    }
+}
 
 /// Decompose a query such as
 /// ```text
@@ -491,6 +1764,48 @@ fn query_term_to_sql_filter<'a>(
 
 const DEFAULT_PAGINATION_SIZE: i32 = 50;
 
+/// Get the manuscripts matching a query in the same syntax as [`get_pages_by_query`]
+///
+/// Only `ms` and `lang` terms are used, page terms are ignored. Words without any prefix search
+/// in the title like `ms:` does.
+pub async fn get_manuscripts_by_query(
+    pool: &Pool<Postgres>,
+    query: &str,
+    order: ManuscriptOrder,
+) -> Result<Vec<critic_shared::ManuscriptMeta>, DBError> {
+    let mut terms = Vec::<QueryTerm>::new();
+    for item in query.split_whitespace() {
+        let item_terms = decompose_query(item);
+        if item_terms.is_empty() {
+            terms.push(QueryTerm {
+                qtype: QueryType::ManuscriptContains,
+                qstr: item,
+            });
+        } else {
+            terms.extend(
+                item_terms
+                    .into_iter()
+                    .filter(|term| term.qtype.is_manuscript_level()),
+            );
+        };
+    }
+
+    let mut builder = QueryBuilder::new(format!(
+        "SELECT {MANUSCRIPT_META_COLUMNS} FROM manuscript WHERE "
+    ));
+    for term in terms {
+        builder = query_term_to_sql_filter(term, builder);
+        builder.push(" AND ");
+    }
+    builder.push(" true");
+    builder.push(manuscript_order_by(order));
+    builder
+        .build_query_as::<critic_shared::ManuscriptMeta>()
+        .fetch_all(pool)
+        .await
+        .map_err(DBError::CannotGetManuscript)
+}
+
 #[derive(FromRow, Debug)]
 struct _GetPagesByQueryRow {
     manuscript_name: String,
@@ -501,14 +1816,30 @@ struct _GetPagesByQueryRow {
     transcriptions_started: i64,
     transcriptions_by_this_user: i64,
     published_by_this_user: i64,
+    locked_by: Option<String>,
 }
 
-pub async fn get_pages_by_query(
-    pool: &Pool<Postgres>,
-    query: &str,
-    this_username: &str,
-    page: i32,
-) -> Result<Vec<PageTodo>, DBError> {
+/// The `ORDER BY` clause of the todo list for `order`
+///
+/// Ties are broken by manuscript and page name, so the order is always the same.
+fn todo_order_by(order: TodoOrder) -> &'static str {
+    match order {
+        TodoOrder::AlmostDone => {
+            " ORDER BY transcriptions_published DESC, transcriptions_started ASC, manuscript_name ASC, page_name ASC "
+        }
+        TodoOrder::Untouched => {
+            " ORDER BY transcriptions_started ASC, transcriptions_published ASC, manuscript_name ASC, page_name ASC "
+        }
+        TodoOrder::Manuscript => " ORDER BY manuscript_name ASC, page_name ASC ",
+    }
+}
+
+/// The query for all pages matching `query`, in the order of the todo list, without pagination
+fn pages_by_query_builder<'a>(
+    query: &'a str,
+    this_username: &'a str,
+    order: TodoOrder,
+) -> QueryBuilder<'a, Postgres> {
     let decomposed_query = decompose_query(query);
     let mut builder = QueryBuilder::new(
         "SELECT
@@ -517,6 +1848,7 @@ pub async fn get_pages_by_query(
             page.name as page_name,
             verse_start,
             verse_end,
+            page_lock.username as locked_by,
             count(*) FILTER (WHERE transcription.id is not NULL) as transcriptions_started,
             count(*) FILTER (WHERE transcription.published) as transcriptions_published,
             count(*) FILTER (WHERE transcription.username = ",
@@ -535,6 +1867,7 @@ pub async fn get_pages_by_query(
          INNER JOIN manuscript on page.manuscript = manuscript.id
          LEFT OUTER JOIN transcription on page.id = transcription.page
          LEFT OUTER JOIN reconciliation on page.id = reconciliation.page
+         LEFT OUTER JOIN page_lock on page.id = page_lock.page AND page_lock.expires_at > now()
          WHERE
          ",
     );
@@ -546,7 +1879,9 @@ pub async fn get_pages_by_query(
     // exclude MSS with reconciliation already in progress
     builder.push(" reconciliation.id is NULL");
 
-    builder.push(" GROUP BY (manuscript_name, page.id, page_name, verse_start, verse_end) ");
+    builder.push(
+        " GROUP BY (manuscript_name, page.id, page_name, verse_start, verse_end, page_lock.username) ",
+    );
 
     // exclude MSS with two or more transcriptions, but always show MSS where the user has started
     // a transcription
@@ -555,22 +1890,13 @@ pub async fn get_pages_by_query(
     builder.push_bind(this_username);
     builder.push(") = 1) ");
 
-    builder.push(" ORDER BY transcriptions_published DESC, transcriptions_started ASC ");
-    builder.push(" LIMIT ");
-    builder.push_bind(DEFAULT_PAGINATION_SIZE);
-    builder.push(" OFFSET ");
-    builder.push_bind(page * DEFAULT_PAGINATION_SIZE);
-    builder.push(";");
-
-    let page_query_rows = builder
-        .build_query_as::<_GetPagesByQueryRow>()
-        .fetch_all(pool)
-        .await
-        .map_err(DBError::CannotGetPagesByQuery)?;
+    builder.push(todo_order_by(order));
+    builder
+}
 
-    let mut res = Vec::<PageTodo>::new();
-    for item in page_query_rows {
-        res.push(PageTodo {
+impl From<_GetPagesByQueryRow> for PageTodo {
+    fn from(item: _GetPagesByQueryRow) -> Self {
+        PageTodo {
             manuscript_name: item.manuscript_name,
             page_name: item.page_name,
             verse_start: item.verse_start,
@@ -591,9 +1917,61 @@ pub async fn get_pages_by_query(
             } else {
                 OwnStatus::None
             },
-        });
+            locked_by: item.locked_by,
+        }
     }
-    Ok(res)
+}
+
+pub async fn get_pages_by_query(
+    pool: &Pool<Postgres>,
+    query: &str,
+    this_username: &str,
+    order: TodoOrder,
+    page: i32,
+) -> Result<Vec<PageTodo>, DBError> {
+    let mut builder = pages_by_query_builder(query, this_username, order);
+    builder.push(" LIMIT ");
+    builder.push_bind(DEFAULT_PAGINATION_SIZE);
+    builder.push(" OFFSET ");
+    builder.push_bind(page * DEFAULT_PAGINATION_SIZE);
+    builder.push(";");
+
+    Ok(builder
+        .build_query_as::<_GetPagesByQueryRow>()
+        .fetch_all(pool)
+        .await
+        .map_err(DBError::CannotGetPagesByQuery)?
+        .into_iter()
+        .map(PageTodo::from)
+        .collect())
+}
+
+/// All pages matching `query`, like [`get_pages_by_query`] but without pagination
+///
+/// The query runs in its own task, which sends the pages one by one as they come in from the db,
+/// so even huge projects are never held in memory as a whole. The task stops at the first error or
+/// once the receiver is dropped.
+pub fn stream_pages_by_query(
+    pool: Pool<Postgres>,
+    query: String,
+    this_username: String,
+    order: TodoOrder,
+) -> tokio::sync::mpsc::Receiver<Result<PageTodo, DBError>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(DEFAULT_PAGINATION_SIZE as usize);
+    tokio::spawn(async move {
+        let mut builder = pages_by_query_builder(&query, &this_username, order);
+        let mut rows = builder.build_query_as::<_GetPagesByQueryRow>().fetch(&pool);
+        while let Some(row) = rows.next().await {
+            let row = row
+                .map(PageTodo::from)
+                .map_err(DBError::CannotGetPagesByQuery);
+            let failed = row.is_err();
+            if tx.send(row).await.is_err() || failed {
+                break;
+            };
+        }
+    });
+    rx
 }
 
 pub struct EditorInitialValue {
@@ -601,6 +1979,12 @@ pub struct EditorInitialValue {
     pub user_has_started: bool,
     pub verse_start: Option<i64>,
     pub verse_end: Option<i64>,
+    /// Tiny version of the page image, see [`critic_shared::PageMeta::placeholder`]
+    pub placeholder: Option<String>,
+    /// The webp versions of the page image exist
+    pub minified: bool,
+    /// Creating the webp versions failed
+    pub minification_failed: bool,
 }
 
 struct _EditorIVSeed {
@@ -610,12 +1994,22 @@ struct _EditorIVSeed {
     hand_desc: Option<String>,
     script_desc: Option<String>,
     default_language: String,
+    date: Option<String>,
+    origin: Option<String>,
+    version: i64,
     verse_start: Option<i64>,
     verse_end: Option<i64>,
+    placeholder: Option<String>,
+    minified: bool,
+    minification_failed: bool,
+    updated_at: String,
+    public: bool,
     transcriptions_by_this_user: Option<i64>,
 }
 
 /// Get the initial value for a transcription editor
+///
+/// Fails with [`DBError::PageDoesNotExist`] if there is no such page in this manuscript.
 pub async fn get_editor_initial_value(
     pool: &Pool<Postgres>,
     msname: &str,
@@ -624,15 +2018,23 @@ pub async fn get_editor_initial_value(
 ) -> Result<EditorInitialValue, DBError> {
     let seed = sqlx::query_as!(
         _EditorIVSeed,
-        "SELECT
+        r#"SELECT
             manuscript.id as manuscript_id,
             manuscript.institution,
             manuscript.collection,
             manuscript.hand_desc,
             manuscript.script_desc,
             manuscript.lang as default_language,
+            manuscript.date,
+            manuscript.origin,
+            manuscript.version,
             page.verse_start,
             page.verse_end,
+            page.placeholder,
+            page.minified,
+            page.minification_failed,
+            manuscript.updated_at::TEXT as "updated_at!",
+            manuscript.public,
             COUNT(*) FILTER (WHERE transcription.username = $3) as transcriptions_by_this_user
         FROM
             page
@@ -641,19 +2043,23 @@ pub async fn get_editor_initial_value(
         LEFT OUTER JOIN transcription
             ON page.id = transcription.page
         WHERE manuscript.title = $1 AND page.name = $2
-        GROUP BY (manuscript.id, manuscript.institution, manuscript.collection, manuscript.hand_desc, manuscript.script_desc, manuscript.lang, page.verse_start, page.verse_end)
-        ;",
+        GROUP BY (manuscript.id, manuscript.institution, manuscript.collection, manuscript.hand_desc, manuscript.script_desc, manuscript.lang, manuscript.date, manuscript.origin, manuscript.version, manuscript.updated_at, manuscript.public, page.verse_start, page.verse_end, page.placeholder, page.minified, page.minification_failed)
+        ;"#,
         msname,
         pagename,
         this_username
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await
-    .map_err(DBError::CannotGetEditorInitialValue)?;
+    .map_err(DBError::CannotGetEditorInitialValue)?
+    .ok_or(DBError::PageDoesNotExist(format!("{msname}/{pagename}")))?;
     Ok(EditorInitialValue {
         user_has_started: seed.transcriptions_by_this_user.unwrap_or_default() > 0,
         verse_start: seed.verse_start,
         verse_end: seed.verse_end,
+        placeholder: seed.placeholder,
+        minified: seed.minified,
+        minification_failed: seed.minification_failed,
         meta: ManuscriptMeta {
             id: seed.manuscript_id,
             title: msname.to_string(),
@@ -662,10 +2068,59 @@ pub async fn get_editor_initial_value(
             hand_desc: seed.hand_desc,
             script_desc: seed.script_desc,
             lang: seed.default_language,
+            date: seed.date,
+            origin: seed.origin,
+            version: seed.version,
+            // the editor does not show it
+            cover_page: None,
+            cover_minified: false,
+            updated_at: seed.updated_at,
+            public: seed.public,
         },
     })
 }
 
+/// Everyone who transcribed or reconciles the page, ordered by username
+pub async fn get_page_transcribers(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+) -> Result<Vec<PageTranscriber>, DBError> {
+    sqlx::query_as!(
+        PageTranscriber,
+        r#"SELECT
+            worked.username as "username!",
+            bool_or(worked.started) as "started!",
+            bool_or(worked.published) as "published!",
+            bool_or(worked.reconciled) as "reconciled!"
+        FROM (
+            SELECT transcription.username, true as started, transcription.published, false as reconciled
+            FROM transcription
+            INNER JOIN page
+                ON page.id = transcription.page
+            INNER JOIN manuscript
+                ON manuscript.id = page.manuscript
+            WHERE manuscript.title = $1 AND page.name = $2
+            UNION ALL
+            SELECT reconciliation.username, false, false, true
+            FROM reconciliation
+            INNER JOIN page
+                ON page.id = reconciliation.page
+            INNER JOIN manuscript
+                ON manuscript.id = page.manuscript
+            WHERE manuscript.title = $1 AND page.name = $2
+        ) AS worked
+        GROUP BY worked.username
+        ORDER BY worked.username
+        ;"#,
+        msname,
+        pagename
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetPage)
+}
+
 pub async fn add_transcription(
     pool: &Pool<Postgres>,
     msname: &str,
@@ -720,3 +2175,218 @@ pub async fn publish_transcription(
     .map(|_| ())
     .map_err(DBError::CannotPublish)
 }
+
+/// set this transcription as not published again
+///
+/// This is only possible until someone has started reconciling the page.
+pub async fn unpublish_transcription(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+
+    let Some(id) = page_id(&mut *tx, ms_meta.id, pagename).await? else {
+        return Err(DBError::PageDoesNotExist(pagename.to_string()));
+    };
+    if let Some(reconciler) =
+        sqlx::query_scalar!("SELECT username FROM reconciliation WHERE page = $1;", id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(DBError::CannotUnpublish)?
+    {
+        return Err(DBError::PageAlreadyUnderReconciliation(reconciler));
+    };
+    sqlx::query!(
+        "UPDATE transcription SET published = false WHERE page = $1 AND username = $2;",
+        id,
+        username
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(DBError::CannotUnpublish)?;
+
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
+
+/// Get the users that published a transcription for this page, oldest transcription first
+pub async fn get_published_transcribers(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+) -> Result<Vec<String>, DBError> {
+    sqlx::query_scalar!(
+        "SELECT transcription.username
+        FROM transcription
+        INNER JOIN page p ON p.id = transcription.page
+        INNER JOIN manuscript m ON m.id = p.manuscript
+        WHERE m.title = $1
+            AND p.name = $2
+            AND transcription.published
+        ORDER BY transcription.id;",
+        msname,
+        pagename
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetTranscriptions)
+}
+
+/// Get the user with the oldest published transcription for every page of a manuscript
+///
+/// Pages without a published transcription are left out.
+pub async fn get_first_published_transcribers(
+    pool: &Pool<Postgres>,
+    msname: &str,
+) -> Result<std::collections::HashMap<String, String>, DBError> {
+    Ok(sqlx::query!(
+        "SELECT DISTINCT ON (p.id) p.name, transcription.username
+        FROM transcription
+        INNER JOIN page p ON p.id = transcription.page
+        INNER JOIN manuscript m ON m.id = p.manuscript
+        WHERE m.title = $1
+            AND transcription.published
+        ORDER BY p.id, transcription.id;",
+        msname
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetTranscriptions)?
+    .into_iter()
+    .map(|row| (row.name, row.username))
+    .collect())
+}
+
+/// Count the transcriptions of `username` and list the ones not published yet
+pub async fn get_user_progress(
+    pool: &Pool<Postgres>,
+    username: &str,
+) -> Result<UserProgress, DBError> {
+    let counts = sqlx::query!(
+        r#"SELECT
+            count(*) as "pages_started!",
+            count(*) FILTER (WHERE transcription.published) as "pages_published!",
+            count(*) FILTER (WHERE transcription.published AND reconciliation.id is NULL) as "pages_pending_reconciliation!"
+        FROM transcription
+        LEFT OUTER JOIN reconciliation on transcription.page = reconciliation.page
+        WHERE transcription.username = $1;"#,
+        username
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(DBError::CannotGetUserProgress)?;
+
+    let mut in_progress = sqlx::query!(
+        "SELECT manuscript.title as manuscript_name, page.name as page_name
+        FROM transcription
+        INNER JOIN page on transcription.page = page.id
+        INNER JOIN manuscript on page.manuscript = manuscript.id
+        WHERE transcription.username = $1 AND NOT transcription.published;",
+        username
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetUserProgress)?
+    .into_iter()
+    .map(|row| (row.manuscript_name, row.page_name))
+    .collect::<Vec<_>>();
+    in_progress.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| page_name_order(&a.1, &b.1)));
+
+    Ok(UserProgress {
+        pages_started: counts.pages_started,
+        pages_published: counts.pages_published,
+        pages_pending_reconciliation: counts.pages_pending_reconciliation,
+        in_progress,
+    })
+}
+
+/// Mark a page as under reconciliation by `username`
+///
+/// Pages under reconciliation are no longer offered for transcription.
+pub async fn start_reconciliation(
+    pool: &Pool<Postgres>,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), DBError> {
+    let ms_meta = get_manuscript_meta(pool, msname).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(DBError::CannotStartTransaction)?;
+
+    let Some(id) = page_id(&mut *tx, ms_meta.id, pagename).await? else {
+        return Err(DBError::PageDoesNotExist(pagename.to_string()));
+    };
+    if let Some(reconciler) =
+        sqlx::query_scalar!("SELECT username FROM reconciliation WHERE page = $1;", id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(DBError::CannotStartReconciliation)?
+    {
+        return Err(DBError::PageAlreadyUnderReconciliation(reconciler));
+    };
+    sqlx::query!(
+        "INSERT INTO reconciliation (page, username) VALUES ($1, $2);",
+        id,
+        username
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(DBError::CannotStartReconciliation)?;
+
+    tx.commit().await.map_err(DBError::CannotCommitTransaction)
+}
+
+/// Add an entry to the audit log
+///
+/// `details` has to be a JSON object serialized to a string.
+pub async fn add_audit_entry(
+    pool: &Pool<Postgres>,
+    username: &str,
+    action: &str,
+    target: &str,
+    details: &str,
+) -> Result<(), DBError> {
+    sqlx::query!(
+        "INSERT INTO audit_log (username, action, target, details) VALUES ($1, $2, $3, $4::TEXT::JSONB);",
+        username,
+        action,
+        target,
+        details
+    )
+    .execute(pool)
+    .await
+    .map_err(DBError::CannotAddAuditEntry)?;
+    Ok(())
+}
+
+/// Get one page of audit log entries, newest first
+pub async fn get_audit_entries(
+    pool: &Pool<Postgres>,
+    page: i32,
+) -> Result<Vec<AuditEntry>, DBError> {
+    sqlx::query_as!(
+        AuditEntry,
+        r#"SELECT
+            id,
+            username,
+            action,
+            target,
+            created_at::TEXT as "created_at!",
+            details::TEXT as "details!"
+        FROM audit_log
+        ORDER BY created_at DESC, id DESC
+        LIMIT $1 OFFSET $2;"#,
+        i64::from(DEFAULT_PAGINATION_SIZE),
+        i64::from(page.max(0) * DEFAULT_PAGINATION_SIZE)
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(DBError::CannotGetAuditEntries)
+}
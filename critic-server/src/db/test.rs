@@ -1 +1,1139 @@
 //! Tests for the database connection
+
+use critic_shared::{
+    AdjacentPages, ManuscriptOrder, OwnStatus, PageTranscriber, PublishedTranscriptions, TodoOrder,
+    UserProgress,
+};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    auth::{NormalizedTokenResponse, UserInfo},
+    config::UploadQuotaConfig,
+};
+
+use super::{
+    acquire_lock, add_manuscript, add_page, add_transcription, add_uploaded_page, check_lock,
+    count_pages_to_minify, get_adjacent_pages, get_all_pages, get_editor_initial_value,
+    get_failed_minifications, get_first_published_transcribers, get_manuscript_meta,
+    get_manuscript_pages, get_manuscript_title, get_manuscripts_by_query, get_page_to_minify,
+    get_page_transcribers, get_pages_by_query, get_user_progress, insert_or_update_user_session,
+    listen_for_new_pages, mark_pages_minification_failed, mark_pages_minified, page_name_order,
+    publish_transcription, release_lock, rename_page, retry_failed_minifications,
+    retry_page_minification, set_manuscript_cover, set_manuscript_public, set_page_verse_ranges,
+    start_reconciliation, stream_pages_by_query, unpublish_transcription, update_ms_meta,
+    user_exists, validate_manuscript_title, validate_page_name, validate_username, DBError,
+    DEFAULT_PAGINATION_SIZE,
+};
+
+async fn add_user(pool: &Pool<Postgres>, username: &str) {
+    sqlx::query(
+        "INSERT INTO user_session (id, username, access_token, expires_at)
+        VALUES (1, $1, 'token', now());",
+    )
+    .bind(username)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// get the published count and own status for the only page in the db
+async fn status_for(pool: &Pool<Postgres>, username: &str) -> (PublishedTranscriptions, OwnStatus) {
+    let pages = get_pages_by_query(pool, "", username, TodoOrder::default(), 0)
+        .await
+        .unwrap();
+    assert_eq!(pages.len(), 1);
+    (pages[0].transcriptions_published, pages[0].this_user_status)
+}
+
+#[sqlx::test]
+async fn publishing_is_reflected_in_page_query(pool: Pool<Postgres>) {
+    add_user(&pool, "alice").await;
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "page", "ms").await.unwrap();
+    add_transcription(&pool, "ms", "page", "alice")
+        .await
+        .unwrap();
+    assert_eq!(
+        status_for(&pool, "alice").await,
+        (PublishedTranscriptions::None, OwnStatus::Started)
+    );
+
+    publish_transcription(&pool, "ms", "page", "alice")
+        .await
+        .unwrap();
+    assert_eq!(
+        status_for(&pool, "alice").await,
+        (PublishedTranscriptions::One, OwnStatus::Published)
+    );
+
+    unpublish_transcription(&pool, "ms", "page", "alice")
+        .await
+        .unwrap();
+    assert_eq!(
+        status_for(&pool, "alice").await,
+        (PublishedTranscriptions::None, OwnStatus::Started)
+    );
+}
+
+#[sqlx::test]
+async fn cannot_unpublish_under_reconciliation(pool: Pool<Postgres>) {
+    add_user(&pool, "alice").await;
+    add_user(&pool, "bob").await;
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "page", "ms").await.unwrap();
+    add_transcription(&pool, "ms", "page", "alice")
+        .await
+        .unwrap();
+    publish_transcription(&pool, "ms", "page", "alice")
+        .await
+        .unwrap();
+    start_reconciliation(&pool, "ms", "page", "bob")
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        unpublish_transcription(&pool, "ms", "page", "alice").await,
+        Err(DBError::PageAlreadyUnderReconciliation(reconciler)) if reconciler == "bob"
+    ));
+}
+
+#[test]
+fn page_names_are_ordered_numerically() {
+    let mut names = vec![
+        "folio10-recto",
+        "folio2-verso",
+        "folio2-recto",
+        "folio02-recto",
+        "cover",
+        "folio1",
+    ];
+    names.sort_by(|a, b| page_name_order(a, b));
+    assert_eq!(
+        names,
+        vec![
+            "cover",
+            "folio1",
+            "folio02-recto",
+            "folio2-recto",
+            "folio2-verso",
+            "folio10-recto",
+        ]
+    );
+}
+
+#[sqlx::test]
+async fn adjacent_pages(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["f10", "f2", "f1"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+
+    assert_eq!(
+        get_adjacent_pages(&pool, "ms", "f1").await.unwrap(),
+        AdjacentPages {
+            previous: None,
+            next: Some("f2".to_string()),
+        }
+    );
+    assert_eq!(
+        get_adjacent_pages(&pool, "ms", "f2").await.unwrap(),
+        AdjacentPages {
+            previous: Some("f1".to_string()),
+            next: Some("f10".to_string()),
+        }
+    );
+    assert_eq!(
+        get_adjacent_pages(&pool, "ms", "f10").await.unwrap(),
+        AdjacentPages {
+            previous: Some("f2".to_string()),
+            next: None,
+        }
+    );
+    assert!(matches!(
+        get_adjacent_pages(&pool, "ms", "f3").await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+}
+
+#[sqlx::test]
+async fn page_meta_shows_the_minification_state(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["done", "failed", "waiting"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+    let id = |name: &str| {
+        let pool = pool.clone();
+        let name = name.to_string();
+        async move {
+            get_manuscript_pages(&pool, "ms")
+                .await
+                .unwrap()
+                .into_iter()
+                .find(|page| page.name == name)
+                .unwrap()
+                .id
+        }
+    };
+    mark_pages_minified(&pool, &[(id("done").await, "placeholder".to_string())])
+        .await
+        .unwrap();
+    mark_pages_minification_failed(&pool, &[id("failed").await])
+        .await
+        .unwrap();
+
+    for page in get_manuscript_pages(&pool, "ms").await.unwrap() {
+        let expected = match page.name.as_str() {
+            "done" => (true, false),
+            "failed" => (false, true),
+            _ => (false, false),
+        };
+        assert_eq!(
+            (page.minified, page.minification_failed),
+            expected,
+            "{}",
+            page.name
+        );
+    }
+    // only pages waiting for minification are handed out, with their state
+    let to_minify = get_page_to_minify(&pool, 10).await.unwrap();
+    assert_eq!(to_minify.len(), 1);
+    assert_eq!(to_minify[0].1.name, "waiting");
+    assert!(!to_minify[0].1.minified && !to_minify[0].1.minification_failed);
+}
+
+#[sqlx::test]
+async fn mark_pages_in_batches(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["a", "b", "c", "d"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+    let ids = |names: &'static [&'static str]| {
+        let pool = pool.clone();
+        async move {
+            get_manuscript_pages(&pool, "ms")
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|page| names.contains(&page.name.as_str()))
+                .map(|page| page.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let minified_pages = ids(&["a", "b"])
+        .await
+        .into_iter()
+        .map(|id| (id, format!("placeholder-{id}")))
+        .collect::<Vec<_>>();
+    mark_pages_minified(&pool, &minified_pages).await.unwrap();
+    mark_pages_minification_failed(&pool, &ids(&["c"]).await)
+        .await
+        .unwrap();
+    // empty batches are fine
+    mark_pages_minified(&pool, &[]).await.unwrap();
+
+    assert_eq!(count_pages_to_minify(&pool).await.unwrap(), 1);
+    let mut minified = get_all_pages(&pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|(_, _, minified)| *minified)
+        .map(|(_, page, _)| page)
+        .collect::<Vec<_>>();
+    minified.sort();
+    assert_eq!(minified, vec!["a", "b"]);
+    // placeholders are stored only for minified pages
+    for page in get_manuscript_pages(&pool, "ms").await.unwrap() {
+        match page.name.as_str() {
+            "a" | "b" => assert_eq!(page.placeholder, Some(format!("placeholder-{}", page.id))),
+            _ => assert_eq!(page.placeholder, None),
+        }
+    }
+}
+
+#[sqlx::test]
+async fn failed_minifications_are_retried(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["a", "b", "c"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+    let pages = get_manuscript_pages(&pool, "ms").await.unwrap();
+    mark_pages_minified(&pool, &[(pages[0].id, String::new())])
+        .await
+        .unwrap();
+    mark_pages_minification_failed(&pool, &[pages[1].id, pages[2].id])
+        .await
+        .unwrap();
+    assert_eq!(count_pages_to_minify(&pool).await.unwrap(), 0);
+
+    assert_eq!(retry_failed_minifications(&pool).await.unwrap(), 2);
+    assert_eq!(count_pages_to_minify(&pool).await.unwrap(), 2);
+    // nothing left to retry
+    assert_eq!(retry_failed_minifications(&pool).await.unwrap(), 0);
+}
+
+#[sqlx::test]
+async fn failed_minifications_are_listed_and_retried_per_page(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["a", "b", "c"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+    let pages = get_manuscript_pages(&pool, "ms").await.unwrap();
+    mark_pages_minification_failed(&pool, &[pages[1].id, pages[2].id])
+        .await
+        .unwrap();
+    let failed_pages = || {
+        let pool = pool.clone();
+        async move {
+            get_failed_minifications(&pool, 0)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|failed| failed.page)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(failed_pages().await, vec!["b", "c"]);
+
+    let retried = retry_page_minification(&pool, pages[1].id).await.unwrap();
+    assert_eq!(
+        (retried.manuscript.as_str(), retried.page.as_str()),
+        ("ms", "b")
+    );
+    assert_eq!(failed_pages().await, vec!["c"]);
+    assert_eq!(count_pages_to_minify(&pool).await.unwrap(), 2);
+    // only failed pages can be retried
+    assert!(matches!(
+        retry_page_minification(&pool, pages[0].id).await,
+        Err(DBError::MinificationNotFailed(id)) if id == pages[0].id
+    ));
+    assert!(matches!(
+        retry_page_minification(&pool, pages[2].id + 1000).await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+    // further pages of the list are empty
+    assert!(get_failed_minifications(&pool, 1).await.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn manuscripts_by_query(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "Codex A", Some("hbo-Hebr"))
+        .await
+        .unwrap();
+    add_manuscript(&pool, "Codex B", Some("arc-Hebr"))
+        .await
+        .unwrap();
+    add_manuscript(&pool, "Fragment", Some("hbo-Hebr"))
+        .await
+        .unwrap();
+    let titles = |query: &'static str| {
+        let pool = pool.clone();
+        async move {
+            get_manuscripts_by_query(&pool, query, ManuscriptOrder::TitleAsc)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|ms| ms.title)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(titles("").await, vec!["Codex A", "Codex B", "Fragment"]);
+    assert_eq!(titles("lang=hbo-Hebr").await, vec!["Codex A", "Fragment"]);
+    assert_eq!(titles("ms:Codex lang=hbo-Hebr").await, vec!["Codex A"]);
+    assert_eq!(titles("Codex lang:Hebr").await, vec!["Codex A", "Codex B"]);
+    assert_eq!(titles("ms=Fragment lang:arc").await, Vec::<String>::new());
+    // page terms do not restrict manuscripts
+    assert_eq!(titles("ms:Codex page=1").await, vec!["Codex A", "Codex B"]);
+}
+
+#[sqlx::test]
+async fn bulk_verse_ranges_are_atomic(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    add_page(&pool, "1v", "ms").await.unwrap();
+    let mut verses = Vec::new();
+    for _ in 0..4 {
+        verses.push(
+            sqlx::query_scalar!("INSERT INTO verse DEFAULT VALUES RETURNING id;")
+                .fetch_one(&pool)
+                .await
+                .unwrap(),
+        );
+    }
+    let ranges = |pool: Pool<Postgres>| async move {
+        get_manuscript_pages(&pool, "ms")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|page| (page.verse_start, page.verse_end))
+            .collect::<Vec<_>>()
+    };
+    let pages = get_manuscript_pages(&pool, "ms").await.unwrap();
+    let (first, second) = (pages[0].id, pages[1].id);
+
+    set_page_verse_ranges(
+        &pool,
+        "ms",
+        &[
+            (first, Some(verses[0]), Some(verses[1])),
+            (second, Some(verses[2]), None),
+        ],
+    )
+    .await
+    .unwrap();
+    let expected = vec![(Some(verses[0]), Some(verses[1])), (Some(verses[2]), None)];
+    assert_eq!(ranges(pool.clone()).await, expected);
+
+    // the second update references a verse that does not exist, so the first is rolled back
+    assert!(matches!(
+        set_page_verse_ranges(
+            &pool,
+            "ms",
+            &[
+                (first, Some(verses[2]), Some(verses[3])),
+                (second, Some(verses[3] + 1000), None),
+            ],
+        )
+        .await,
+        Err(DBError::CannotSetVerseRanges(_))
+    ));
+    assert_eq!(ranges(pool.clone()).await, expected);
+
+    // the same for an unknown page
+    assert!(matches!(
+        set_page_verse_ranges(
+            &pool,
+            "ms",
+            &[(first, None, None), (second + 1000, None, None)]
+        )
+        .await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+    assert_eq!(ranges(pool.clone()).await, expected);
+
+    // ranges ending before they start are rejected before changing anything
+    assert!(matches!(
+        set_page_verse_ranges(
+            &pool,
+            "ms",
+            &[(first, None, None), (second, Some(verses[3]), Some(verses[2]))],
+        )
+        .await,
+        Err(DBError::InvalidVerseRange(id)) if id == second
+    ));
+    assert_eq!(ranges(pool.clone()).await, expected);
+
+    // pages of another manuscript cannot be changed through this one
+    add_manuscript(&pool, "other", None).await.unwrap();
+    assert!(matches!(
+        set_page_verse_ranges(&pool, "other", &[(first, None, None)]).await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+    assert_eq!(ranges(pool.clone()).await, expected);
+}
+
+#[sqlx::test]
+async fn user_progress(pool: Pool<Postgres>) {
+    add_user(&pool, "alice").await;
+    add_user(&pool, "bob").await;
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["1r", "1v", "2r", "10r", "11r"] {
+        add_page(&pool, page, "ms").await.unwrap();
+        add_transcription(&pool, "ms", page, "alice").await.unwrap();
+    }
+    for page in ["1r", "1v"] {
+        publish_transcription(&pool, "ms", page, "alice")
+            .await
+            .unwrap();
+    }
+    start_reconciliation(&pool, "ms", "1r", "bob")
+        .await
+        .unwrap();
+    // transcriptions of other users are not counted
+    add_transcription(&pool, "ms", "1r", "bob").await.unwrap();
+
+    let progress = get_user_progress(&pool, "alice").await.unwrap();
+    assert_eq!(progress.pages_started, 5);
+    assert_eq!(progress.pages_published, 2);
+    assert_eq!(progress.pages_pending_reconciliation, 1);
+    assert_eq!(
+        progress.in_progress,
+        ["2r", "10r", "11r"]
+            .map(|page| ("ms".to_string(), page.to_string()))
+            .to_vec()
+    );
+
+    assert_eq!(
+        get_user_progress(&pool, "carol").await.unwrap(),
+        UserProgress::default()
+    );
+}
+
+#[sqlx::test]
+async fn page_transcribers(pool: Pool<Postgres>) {
+    for user in ["alice", "bob", "carol"] {
+        add_user(&pool, user).await;
+    }
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    add_page(&pool, "1v", "ms").await.unwrap();
+    add_transcription(&pool, "ms", "1r", "bob").await.unwrap();
+    add_transcription(&pool, "ms", "1r", "alice").await.unwrap();
+    publish_transcription(&pool, "ms", "1r", "alice")
+        .await
+        .unwrap();
+    start_reconciliation(&pool, "ms", "1r", "carol")
+        .await
+        .unwrap();
+    // other pages are not included
+    add_transcription(&pool, "ms", "1v", "carol").await.unwrap();
+
+    let transcriber = |username: &str, started, published, reconciled| PageTranscriber {
+        username: username.to_string(),
+        started,
+        published,
+        reconciled,
+    };
+    assert_eq!(
+        get_page_transcribers(&pool, "ms", "1r").await.unwrap(),
+        vec![
+            transcriber("alice", true, true, false),
+            transcriber("bob", true, false, false),
+            transcriber("carol", false, false, true),
+        ]
+    );
+    assert_eq!(
+        get_page_transcribers(&pool, "ms", "2r").await.unwrap(),
+        vec![]
+    );
+}
+
+#[sqlx::test]
+async fn stale_manuscript_update_is_rejected(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    // two admins open the same manuscript
+    let meta = get_manuscript_meta(&pool, "ms").await.unwrap();
+    let mut first_edit = meta.clone();
+    first_edit.institution = Some("first".to_string());
+    let mut second_edit = meta;
+    second_edit.institution = Some("second".to_string());
+    second_edit.title = "renamed".to_string();
+
+    let new_version = update_ms_meta(&pool, &first_edit).await.unwrap();
+    assert_eq!(new_version, first_edit.version + 1);
+    assert!(matches!(
+        update_ms_meta(&pool, &second_edit).await,
+        Err(DBError::StaleUpdate(title)) if title == "ms"
+    ));
+    let stored = get_manuscript_meta(&pool, "ms").await.unwrap();
+    assert_eq!(stored.institution.as_deref(), Some("first"));
+    assert_eq!(stored.version, new_version);
+    assert_eq!(get_manuscript_title(&pool, stored.id).await.unwrap(), "ms");
+    assert!(matches!(
+        get_manuscript_title(&pool, stored.id + 1000).await,
+        Err(DBError::ManuscriptDoesNotExist(_))
+    ));
+}
+
+#[test]
+fn malicious_manuscript_titles_are_rejected() {
+    for title in [
+        "",
+        " ms",
+        "ms\n",
+        "../ms",
+        "..",
+        ".",
+        "ms/../../etc",
+        "a\\b",
+        "ms\0",
+        "ms\u{7}",
+    ] {
+        assert!(
+            matches!(
+                validate_manuscript_title(title),
+                Err(DBError::InvalidManuscriptTitle(_))
+            ),
+            "{title:?} was accepted"
+        );
+    }
+    for title in [
+        "ML115",
+        "Codex Leningradensis",
+        "Sassoon 1053",
+        "v.1",
+        "כתב יד",
+    ] {
+        assert!(
+            validate_manuscript_title(title).is_ok(),
+            "{title:?} was rejected"
+        );
+    }
+}
+
+#[test]
+fn usernames_must_be_file_names() {
+    for username in ["", "..", "../admin", "a/b", "a\\b", ".hidden", "user\0"] {
+        assert!(
+            matches!(
+                validate_username(username),
+                Err(DBError::InvalidUsername(_))
+            ),
+            "{username:?} was accepted"
+        );
+    }
+    for username in ["octocat", "john.doe", "jane_doe-2"] {
+        assert!(
+            validate_username(username).is_ok(),
+            "{username:?} was rejected"
+        );
+    }
+}
+
+#[sqlx::test]
+async fn manuscript_titles_are_validated(pool: Pool<Postgres>) {
+    assert!(matches!(
+        add_manuscript(&pool, "../ms", None).await,
+        Err(DBError::InvalidManuscriptTitle(_))
+    ));
+    add_manuscript(&pool, "Codex", None).await.unwrap();
+    assert!(matches!(
+        add_manuscript(&pool, "CODEX", None).await,
+        Err(DBError::ManuscriptTitleTaken(existing)) if existing == "Codex"
+    ));
+    add_manuscript(&pool, "other", None).await.unwrap();
+
+    let mut meta = get_manuscript_meta(&pool, "other").await.unwrap();
+    meta.title = "codex".to_string();
+    assert!(matches!(
+        update_ms_meta(&pool, &meta).await,
+        Err(DBError::ManuscriptTitleTaken(_))
+    ));
+    meta.title = "other/..".to_string();
+    assert!(matches!(
+        update_ms_meta(&pool, &meta).await,
+        Err(DBError::InvalidManuscriptTitle(_))
+    ));
+    // changing only the case of its own title is fine
+    meta.title = "Other".to_string();
+    update_ms_meta(&pool, &meta).await.unwrap();
+}
+
+#[sqlx::test]
+async fn page_names_are_validated(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for pagename in ["", "..", "../../etc", "1r/..", "1r\\x", "1r\0"] {
+        assert!(
+            matches!(
+                add_page(&pool, pagename, "ms").await,
+                Err(DBError::InvalidPageName(_))
+            ),
+            "{pagename:?} was accepted"
+        );
+    }
+    assert!(validate_page_name("f. 1r").is_ok());
+    add_page(&pool, "1r", "ms").await.unwrap();
+    assert!(matches!(
+        rename_page(&pool, "/nonexistent", "ms", "1r", "../1r").await,
+        Err(DBError::InvalidPageName(_))
+    ));
+}
+
+#[sqlx::test]
+async fn manuscripts_are_sorted(pool: Pool<Postgres>) {
+    for (msname, created_at) in [
+        ("A", "2026-01-02"),
+        ("B", "2026-01-03"),
+        ("C", "2026-01-01"),
+    ] {
+        add_manuscript(&pool, msname, None).await.unwrap();
+        sqlx::query("UPDATE manuscript SET created_at = $1::DATE WHERE title = $2;")
+            .bind(created_at)
+            .bind(msname)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    let titles = |order: ManuscriptOrder| {
+        let pool = pool.clone();
+        async move {
+            get_manuscripts_by_query(&pool, "", order)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|ms| ms.title)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(titles(ManuscriptOrder::TitleAsc).await, vec!["A", "B", "C"]);
+    assert_eq!(
+        titles(ManuscriptOrder::TitleDesc).await,
+        vec!["C", "B", "A"]
+    );
+    assert_eq!(
+        titles(ManuscriptOrder::NewestFirst).await,
+        vec!["B", "A", "C"]
+    );
+}
+
+#[sqlx::test]
+async fn todo_list_is_sorted(pool: Pool<Postgres>) {
+    add_user(&pool, "alice").await;
+    add_user(&pool, "bob").await;
+    add_manuscript(&pool, "a", None).await.unwrap();
+    add_manuscript(&pool, "b", None).await.unwrap();
+    // a/1: untouched, a/2: one started, b/1: two started and one published
+    for (msname, pagename) in [("a", "1"), ("a", "2"), ("b", "1")] {
+        add_page(&pool, pagename, msname).await.unwrap();
+    }
+    add_transcription(&pool, "a", "2", "alice").await.unwrap();
+    add_transcription(&pool, "b", "1", "alice").await.unwrap();
+    add_transcription(&pool, "b", "1", "bob").await.unwrap();
+    publish_transcription(&pool, "b", "1", "bob").await.unwrap();
+
+    let pages = |order: TodoOrder| {
+        let pool = pool.clone();
+        async move {
+            get_pages_by_query(&pool, "", "carol", order, 0)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|page| format!("{}/{}", page.manuscript_name, page.page_name))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        pages(TodoOrder::AlmostDone).await,
+        vec!["b/1", "a/1", "a/2"]
+    );
+    assert_eq!(pages(TodoOrder::Untouched).await, vec!["a/1", "a/2", "b/1"]);
+    assert_eq!(
+        pages(TodoOrder::Manuscript).await,
+        vec!["a/1", "a/2", "b/1"]
+    );
+}
+
+#[test]
+fn todo_order_keys_round_trip() {
+    for order in TodoOrder::ALL {
+        assert_eq!(TodoOrder::from_key(order.key()), Some(order));
+    }
+    assert_eq!(TodoOrder::from_key("page.id; DROP TABLE page"), None);
+}
+
+#[sqlx::test]
+async fn page_locks(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    let locked_by = || {
+        let pool = pool.clone();
+        async move {
+            get_pages_by_query(&pool, "", "carol", TodoOrder::default(), 0)
+                .await
+                .unwrap()[0]
+                .locked_by
+                .clone()
+        }
+    };
+    assert_eq!(locked_by().await, None);
+
+    acquire_lock(&pool, "ms", "1r", "alice", 60).await.unwrap();
+    assert_eq!(locked_by().await.as_deref(), Some("alice"));
+    assert!(matches!(
+        acquire_lock(&pool, "ms", "1r", "bob", 60).await,
+        Err(DBError::PageLocked(holder)) if holder == "alice"
+    ));
+    // refreshing the own lock is fine
+    acquire_lock(&pool, "ms", "1r", "alice", 60).await.unwrap();
+    // checking does not claim the page
+    check_lock(&pool, "ms", "1r", "alice").await.unwrap();
+    assert!(matches!(
+        check_lock(&pool, "ms", "1r", "bob").await,
+        Err(DBError::PageLocked(holder)) if holder == "alice"
+    ));
+    assert_eq!(locked_by().await.as_deref(), Some("alice"));
+    // bob cannot release alices lock
+    release_lock(&pool, "ms", "1r", "bob").await.unwrap();
+    assert_eq!(locked_by().await.as_deref(), Some("alice"));
+    release_lock(&pool, "ms", "1r", "alice").await.unwrap();
+    assert_eq!(locked_by().await, None);
+
+    acquire_lock(&pool, "ms", "1r", "bob", 60).await.unwrap();
+    assert!(matches!(
+        acquire_lock(&pool, "ms", "2r", "bob", 60).await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+}
+
+#[sqlx::test]
+async fn expired_page_locks_are_taken_over(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    acquire_lock(&pool, "ms", "1r", "alice", 60).await.unwrap();
+    // as if alices editor crashed a while ago
+    sqlx::query("UPDATE page_lock SET expires_at = now() - interval '1 minute';")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_pages_by_query(&pool, "", "bob", TodoOrder::default(), 0)
+            .await
+            .unwrap()[0]
+            .locked_by,
+        None
+    );
+    check_lock(&pool, "ms", "1r", "bob").await.unwrap();
+    acquire_lock(&pool, "ms", "1r", "bob", 60).await.unwrap();
+    assert!(matches!(
+        acquire_lock(&pool, "ms", "1r", "alice", 60).await,
+        Err(DBError::PageLocked(holder)) if holder == "bob"
+    ));
+}
+
+#[sqlx::test]
+async fn all_pages_can_be_streamed(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for idx in 0..DEFAULT_PAGINATION_SIZE + 5 {
+        add_page(&pool, &format!("p{idx}"), "ms").await.unwrap();
+    }
+    add_manuscript(&pool, "other", None).await.unwrap();
+    add_page(&pool, "p1", "other").await.unwrap();
+
+    assert_eq!(
+        get_pages_by_query(&pool, "ms:ms", "alice", TodoOrder::default(), 0)
+            .await
+            .unwrap()
+            .len(),
+        DEFAULT_PAGINATION_SIZE as usize
+    );
+    let mut pages = stream_pages_by_query(
+        pool.clone(),
+        "ms:ms".to_string(),
+        "alice".to_string(),
+        TodoOrder::default(),
+    );
+    let mut count = 0;
+    while let Some(page) = pages.recv().await {
+        assert_eq!(page.unwrap().manuscript_name, "ms");
+        count += 1;
+    }
+    assert_eq!(count, DEFAULT_PAGINATION_SIZE + 5);
+}
+
+#[sqlx::test]
+async fn adding_pages_notifies_listeners(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let mut listener = listen_for_new_pages(&pool).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(5), listener.recv())
+        .await
+        .expect("adding a page notifies")
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn uploads_past_the_quota_are_rejected(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let quota = UploadQuotaConfig {
+        daily_bytes: Some(250),
+        daily_pages: Some(3),
+    };
+    let upload = |pagename: &'static str, username: &'static str, bytes: u64| {
+        let pool = pool.clone();
+        let quota = &quota;
+        async move {
+            // every upload is a different image
+            let hash = format!("{username}-{pagename}-{bytes}");
+            add_uploaded_page(&pool, pagename, "ms", username, bytes, &hash, quota).await
+        }
+    };
+
+    upload("1", "alice", 100).await.unwrap();
+    upload("2", "alice", 100).await.unwrap();
+    assert!(matches!(
+        upload("3", "alice", 100).await,
+        Err(DBError::UploadQuotaExceeded(_))
+    ));
+    // the rejected file is neither added nor counted
+    upload("3", "alice", 50).await.unwrap();
+    assert!(matches!(
+        upload("4", "alice", 0).await,
+        Err(DBError::UploadQuotaExceeded(_))
+    ));
+    // quotas are per user
+    upload("4", "bob", 100).await.unwrap();
+
+    let pages = get_manuscript_pages(&pool, "ms")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|page| page.name)
+        .collect::<Vec<_>>();
+    assert_eq!(pages, vec!["1", "2", "3", "4"]);
+}
+
+#[sqlx::test]
+async fn duplicate_images_are_detected(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_manuscript(&pool, "other", None).await.unwrap();
+    let quota = UploadQuotaConfig::default();
+    add_uploaded_page(&pool, "1r", "ms", "alice", 10, "hash", &quota)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        add_uploaded_page(&pool, "1v", "ms", "alice", 10, "hash", &quota).await,
+        Err(DBError::DuplicatePageImage(existing)) if existing == "1r"
+    ));
+    assert_eq!(get_manuscript_pages(&pool, "ms").await.unwrap().len(), 1);
+    // other manuscripts may have the same image
+    add_uploaded_page(&pool, "1r", "other", "alice", 10, "hash", &quota)
+        .await
+        .unwrap();
+    // pages without a hash (added before hashes were recorded) never collide
+    add_page(&pool, "2r", "ms").await.unwrap();
+    add_page(&pool, "2v", "ms").await.unwrap();
+}
+
+#[sqlx::test]
+async fn manuscript_cover(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let cover = || {
+        let pool = pool.clone();
+        async move { get_manuscript_meta(&pool, "ms").await.unwrap().cover_page }
+    };
+    assert_eq!(cover().await, None);
+
+    // the page uploaded first is the default
+    add_page(&pool, "2r", "ms").await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    assert_eq!(cover().await.as_deref(), Some("2r"));
+
+    set_manuscript_cover(&pool, "ms", Some("1r")).await.unwrap();
+    assert_eq!(cover().await.as_deref(), Some("1r"));
+    assert_eq!(
+        get_manuscripts_by_query(&pool, "", ManuscriptOrder::TitleAsc)
+            .await
+            .unwrap()[0]
+            .cover_page
+            .as_deref(),
+        Some("1r")
+    );
+    assert!(matches!(
+        set_manuscript_cover(&pool, "ms", Some("3r")).await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+    set_manuscript_cover(&pool, "ms", None).await.unwrap();
+    assert_eq!(cover().await.as_deref(), Some("2r"));
+
+    // the cover follows the minification of the page it shows
+    assert!(
+        !get_manuscript_meta(&pool, "ms")
+            .await
+            .unwrap()
+            .cover_minified
+    );
+    let cover_id = get_manuscript_pages(&pool, "ms")
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|page| page.name == "2r")
+        .unwrap()
+        .id;
+    mark_pages_minified(&pool, &[(cover_id, "placeholder".to_string())])
+        .await
+        .unwrap();
+    assert!(
+        get_manuscript_meta(&pool, "ms")
+            .await
+            .unwrap()
+            .cover_minified
+    );
+    assert!(
+        get_manuscripts_by_query(&pool, "", ManuscriptOrder::TitleAsc)
+            .await
+            .unwrap()[0]
+            .cover_minified
+    );
+    set_manuscript_cover(&pool, "ms", Some("1r")).await.unwrap();
+    assert!(
+        !get_manuscript_meta(&pool, "ms")
+            .await
+            .unwrap()
+            .cover_minified
+    );
+}
+
+#[sqlx::test]
+async fn updating_a_manuscript_bumps_updated_at(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let timestamps = || {
+        sqlx::query_as::<_, (String, String)>(
+            "SELECT created_at::TEXT, updated_at::TEXT FROM manuscript WHERE title = 'ms';",
+        )
+        .fetch_one(&pool)
+    };
+    let (created_before, updated_before) = timestamps().await.unwrap();
+
+    let mut meta = get_manuscript_meta(&pool, "ms").await.unwrap();
+    assert_eq!(meta.updated_at, updated_before);
+    meta.institution = Some("Bodleian".to_string());
+    update_ms_meta(&pool, &meta).await.unwrap();
+
+    let (created_after, updated_after) = timestamps().await.unwrap();
+    assert_eq!(created_after, created_before);
+    assert_ne!(updated_after, updated_before);
+    assert_eq!(
+        get_manuscript_meta(&pool, "ms").await.unwrap().updated_at,
+        updated_after
+    );
+}
+
+#[sqlx::test]
+async fn manuscripts_are_private_until_made_public(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    assert!(!get_manuscript_meta(&pool, "ms").await.unwrap().public);
+
+    set_manuscript_public(&pool, "ms", true).await.unwrap();
+    assert!(get_manuscript_meta(&pool, "ms").await.unwrap().public);
+    // editing the metadata does not touch it
+    let meta = get_manuscript_meta(&pool, "ms").await.unwrap();
+    update_ms_meta(
+        &pool,
+        &critic_shared::ManuscriptMeta {
+            public: false,
+            ..meta
+        },
+    )
+    .await
+    .unwrap();
+    assert!(get_manuscript_meta(&pool, "ms").await.unwrap().public);
+
+    assert!(matches!(
+        set_manuscript_public(&pool, "nope", true).await,
+        Err(DBError::ManuscriptDoesNotExist(_))
+    ));
+}
+
+#[sqlx::test]
+async fn editor_initial_value_for_a_missing_page(pool: Pool<Postgres>) {
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    add_page(&pool, "1r", "ms").await.unwrap();
+    let initial = get_editor_initial_value(&pool, "ms", "1r", "user")
+        .await
+        .unwrap();
+    assert!(!initial.minified && !initial.minification_failed);
+    assert_eq!(initial.placeholder, None);
+    assert!(matches!(
+        get_editor_initial_value(&pool, "ms", "2r", "user").await,
+        Err(DBError::PageDoesNotExist(page)) if page == "ms/2r"
+    ));
+    assert!(matches!(
+        get_editor_initial_value(&pool, "other", "1r", "user").await,
+        Err(DBError::PageDoesNotExist(_))
+    ));
+}
+
+fn login_as(id: i32, subject: &str, login: &str) -> (UserInfo, NormalizedTokenResponse) {
+    (
+        UserInfo {
+            id,
+            subject: subject.to_string(),
+            login: login.to_string(),
+        },
+        NormalizedTokenResponse {
+            access_token: format!("token-{subject}"),
+            refresh_token: None,
+            expires_at: time::OffsetDateTime::now_utc(),
+        },
+    )
+}
+
+#[sqlx::test]
+async fn usernames_stay_with_their_subject(pool: Pool<Postgres>) {
+    let (info, token) = login_as(5, "alice-sub", "alice");
+    let alice = insert_or_update_user_session(&pool, info, token)
+        .await
+        .unwrap();
+    assert_eq!(alice.subject.as_deref(), Some("alice-sub"));
+    // logging in again updates the session
+    let (info, token) = login_as(5, "alice-sub", "alice");
+    insert_or_update_user_session(&pool, info, token)
+        .await
+        .unwrap();
+
+    // another account calling itself alice, even with a colliding id
+    let (info, token) = login_as(5, "mallory-sub", "alice");
+    assert!(matches!(
+        insert_or_update_user_session(&pool, info, token).await,
+        Err(DBError::UsernameTaken(name)) if name == "alice"
+    ));
+    let access_token: String =
+        sqlx::query_scalar("SELECT access_token FROM user_session WHERE username = 'alice';")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(access_token, "token-alice-sub");
+}
+
+#[sqlx::test]
+async fn sessions_without_subject_are_claimed_by_their_id(pool: Pool<Postgres>) {
+    // stored before subjects were, with id 1
+    add_user(&pool, "bob").await;
+    let (info, token) = login_as(2, "mallory-sub", "bob");
+    assert!(matches!(
+        insert_or_update_user_session(&pool, info, token).await,
+        Err(DBError::UsernameTaken(_))
+    ));
+    let (info, token) = login_as(1, "bob-sub", "bob");
+    let bob = insert_or_update_user_session(&pool, info, token)
+        .await
+        .unwrap();
+    assert_eq!(bob.subject.as_deref(), Some("bob-sub"));
+}
+
+#[sqlx::test]
+async fn first_published_transcribers_of_a_manuscript(pool: Pool<Postgres>) {
+    add_user(&pool, "alice").await;
+    add_user(&pool, "bob").await;
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    for page in ["1r", "1v", "2r"] {
+        add_page(&pool, page, "ms").await.unwrap();
+    }
+    for (page, username) in [
+        ("1r", "bob"),
+        ("1r", "alice"),
+        ("1v", "alice"),
+        ("2r", "bob"),
+    ] {
+        add_transcription(&pool, "ms", page, username)
+            .await
+            .unwrap();
+    }
+    for (page, username) in [("1r", "alice"), ("1r", "bob"), ("1v", "alice")] {
+        publish_transcription(&pool, "ms", page, username)
+            .await
+            .unwrap();
+    }
+    let transcribers = get_first_published_transcribers(&pool, "ms").await.unwrap();
+    // the oldest transcription, not the first one published
+    assert_eq!(transcribers["1r"], "bob");
+    assert_eq!(transcribers["1v"], "alice");
+    // not published yet
+    assert!(!transcribers.contains_key("2r"));
+    assert!(get_first_published_transcribers(&pool, "other")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[sqlx::test]
+async fn users_exist_once_they_logged_in(pool: Pool<Postgres>) {
+    assert!(!user_exists(&pool, "alice").await.unwrap());
+    add_user(&pool, "alice").await;
+    assert!(user_exists(&pool, "alice").await.unwrap());
+    assert!(!user_exists(&pool, "bob").await.unwrap());
+}
@@ -0,0 +1,550 @@
+//! Exporting transcriptions to formats used by external tools
+
+use std::{convert::Infallible, fmt::Write, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use critic_format::streamed::Block;
+use critic_shared::{OwnStatus, PageTodo, PublishedTranscriptions, TodoOrder};
+use futures_util::{stream, StreamExt};
+use quick_xml::escape::escape;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    auth::AuthSession,
+    config::Config,
+    db::{
+        get_first_published_transcribers, get_published_transcribers, stream_pages_by_query,
+        DBError,
+    },
+    transcription_store::{read_raw_transcription, read_transcription_from_disk},
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// The router handling all exports
+pub fn export_router() -> axum::Router {
+    axum::Router::new()
+        .route(
+            &format!(
+                "{}/{{msname}}/{{pagename}}/{{username}}",
+                critic_shared::urls::TEI_EXPORT_API_ENDPOINT
+            ),
+            axum::routing::get(tei_export),
+        )
+        .route(
+            &format!(
+                "{}/{{msname}}",
+                critic_shared::urls::TEI_EXPORT_API_ENDPOINT
+            ),
+            axum::routing::get(tei_manuscript_export),
+        )
+        .route(
+            &format!(
+                "{}/{{msname}}/{{pagename}}/{{username}}",
+                critic_shared::urls::XML_EXPORT_API_ENDPOINT
+            ),
+            axum::routing::get(xml_export),
+        )
+        .route(
+            critic_shared::urls::TODO_CSV_EXPORT_API_ENDPOINT,
+            axum::routing::get(todo_csv_export),
+        )
+}
+
+/// Export the published transcription of `username` for a page as TEI P5
+async fn tei_export(
+    Extension(config): Extension<Arc<Config>>,
+    Path((msname, pagename, username)): Path<(String, String, String)>,
+) -> Response {
+    let transcribers = match get_published_transcribers(&config.db, &msname, &pagename).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("Failed to get transcriptions for {msname}/{pagename}: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if !transcribers.contains(&username) {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("{username} has not published a transcription for {msname}/{pagename}."),
+        )
+            .into_response();
+    };
+    let default_language = match config
+        .manuscript_cache
+        .manuscript_meta(&config.db, &msname)
+        .await
+    {
+        Ok(x) => x.lang,
+        Err(e @ DBError::ManuscriptDoesNotExist(_)) => {
+            return (StatusCode::NOT_FOUND, e.to_string()).into_response();
+        }
+        Err(e) => {
+            tracing::warn!("Failed to get manuscript {msname}: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    let blocks = match read_transcription_from_disk(
+        &config.data_directory,
+        &msname,
+        &pagename,
+        &username,
+        &default_language,
+    ) {
+        Ok((blocks, _pagename)) => blocks,
+        Err(e) => {
+            tracing::warn!("Failed to read transcription /{msname}/{pagename}/{username}: {e}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read transcription from disk.",
+            )
+                .into_response();
+        }
+    };
+    (
+        [(header::CONTENT_TYPE, "application/tei+xml")],
+        blocks_to_tei(&blocks, &msname, &pagename, &username),
+    )
+        .into_response()
+}
+
+/// Download a transcription exactly as it is stored, in the critic format
+///
+/// Users get their own transcriptions, published or not, and the published ones of everybody.
+async fn xml_export(
+    Extension(config): Extension<Arc<Config>>,
+    auth_session: AuthSession,
+    Path((msname, pagename, username)): Path<(String, String, String)>,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if user.username != username {
+        match get_published_transcribers(&config.db, &msname, &pagename).await {
+            Ok(transcribers) if transcribers.contains(&username) => {}
+            Ok(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    format!(
+                        "{username} has not published a transcription for {msname}/{pagename}."
+                    ),
+                )
+                    .into_response();
+            }
+            Err(e) => {
+                tracing::warn!("Failed to get transcriptions for {msname}/{pagename}: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+        };
+    };
+    match read_raw_transcription(&config.data_directory, &msname, &pagename, &username) {
+        Ok(Some(content)) => (
+            [
+                (header::CONTENT_TYPE, "application/xml".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    xml_content_disposition(&msname, &pagename, &username),
+                ),
+            ],
+            content,
+        )
+            .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            format!("There is no transcription of {msname}/{pagename} by {username}."),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to read transcription /{msname}/{pagename}/{username}: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read transcription from disk.",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Download as `<manuscript>_<page>_<user>.xml`, percent-encoded because names need not be ASCII
+pub fn xml_content_disposition(msname: &str, pagename: &str, username: &str) -> String {
+    format!(
+        "attachment; filename*=UTF-8''{}",
+        urlencoding::encode(&format!("{msname}_{pagename}_{username}.xml"))
+    )
+}
+
+/// Export all pages of a manuscript in reading order as a single TEI P5 document
+///
+/// Each page is taken from its oldest published transcription. Pages without a published
+/// transcription are exported as a `<gap>`. The document is streamed page by page.
+async fn tei_manuscript_export(
+    Extension(config): Extension<Arc<Config>>,
+    Path(msname): Path<String>,
+) -> Response {
+    let manuscript = match config
+        .manuscript_cache
+        .manuscript(&config.db, &msname)
+        .await
+    {
+        Ok(x) => x,
+        Err(e @ DBError::ManuscriptDoesNotExist(_)) => {
+            return (StatusCode::NOT_FOUND, e.to_string()).into_response();
+        }
+        Err(e) => {
+            tracing::warn!("Failed to get manuscript {msname}: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    // only the names are collected up front, the transcriptions are read while streaming
+    let mut first_transcribers = match get_first_published_transcribers(&config.db, &msname).await {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("Failed to get transcriptions for {msname}: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    let pages = manuscript
+        .pages
+        .into_iter()
+        .map(|page| {
+            let username = first_transcribers.remove(&page.name);
+            (page.name, username)
+        })
+        .collect::<Vec<_>>();
+    let mut transcribers = pages
+        .iter()
+        .filter_map(|(_, username)| username.as_deref())
+        .collect::<Vec<_>>();
+    transcribers.sort_unstable();
+    transcribers.dedup();
+    let header = manuscript_tei_header(&msname, &transcribers);
+
+    let default_language = manuscript.meta.lang;
+    let surfaces = stream::iter(pages).then(move |(pagename, username)| {
+        let data_directory = config.data_directory.clone();
+        let msname = msname.clone();
+        let default_language = default_language.clone();
+        async move {
+            let Some(username) = username else {
+                return missing_page_to_tei(&pagename, "not transcribed");
+            };
+            // reading and parsing the file blocks
+            let unreadable = missing_page_to_tei(&pagename, "transcription unreadable");
+            tokio::task::spawn_blocking(move || {
+                match read_transcription_from_disk(
+                    &data_directory,
+                    &msname,
+                    &pagename,
+                    &username,
+                    &default_language,
+                ) {
+                    Ok((blocks, _pagename)) => page_to_tei(&blocks, &pagename, Some(&username)),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to read transcription /{msname}/{pagename}/{username}: {e}"
+                        );
+                        missing_page_to_tei(&pagename, "transcription unreadable")
+                    }
+                }
+            })
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to join the task reading a transcription: {e}");
+                unreadable
+            })
+        }
+    });
+    let body = stream::once(async move { header })
+        .chain(surfaces)
+        .chain(stream::once(async { MANUSCRIPT_TEI_FOOTER.to_string() }))
+        .map(Ok::<_, Infallible>);
+    (
+        [(header::CONTENT_TYPE, "application/tei+xml")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Write `name="value"` with an escaped value
+fn attribute(out: &mut String, name: &str, value: &str) {
+    let _ = write!(out, " {name}=\"{}\"", escape(value));
+}
+
+/// Write the `xml:lang` attribute unless `lang` is empty
+fn lang_attribute(out: &mut String, lang: &str) {
+    if !lang.is_empty() {
+        attribute(out, "xml:lang", lang);
+    };
+}
+
+/// Serialize a single block that is not a break into TEI
+fn block_to_tei(out: &mut String, block: &Block) {
+    match block {
+        Block::Text(paragraph) => {
+            out.push_str("<seg");
+            lang_attribute(out, &paragraph.lang);
+            let _ = write!(out, ">{}</seg>", escape(&paragraph.content));
+        }
+        Block::Lacuna(lacuna) => {
+            out.push_str("<gap");
+            if !lacuna.reason.is_empty() {
+                attribute(out, "reason", &lacuna.reason);
+            };
+            attribute(out, "quantity", &lacuna.n.to_string());
+            attribute(out, "unit", &lacuna.unit.name().to_lowercase());
+            if let Some(cert) = &lacuna.cert {
+                attribute(out, "cert", cert);
+            };
+            out.push_str("/>");
+        }
+        Block::Abbreviation(abbreviation) => {
+            out.push_str("<choice><abbr");
+            lang_attribute(out, &abbreviation.surface_lang);
+            let _ = write!(out, ">{}</abbr><expan", escape(&abbreviation.surface));
+            lang_attribute(out, &abbreviation.expansion_lang);
+            let _ = write!(out, ">{}</expan></choice>", escape(&abbreviation.expansion));
+        }
+        Block::Correction(correction) => {
+            // every version is a reading, in the order in which they were written
+            out.push_str("<app>");
+            for (idx, version) in correction.versions.iter().enumerate() {
+                out.push_str("<rdg");
+                attribute(out, "n", &(idx + 1).to_string());
+                if let Some(hand) = &version.hand {
+                    attribute(out, "hand", hand);
+                };
+                lang_attribute(out, &version.lang);
+                let _ = write!(out, ">{}</rdg>", escape(&version.content));
+            }
+            out.push_str("</app>");
+        }
+        Block::Uncertain(uncertain) => {
+            out.push_str("<unclear");
+            if !uncertain.agent.is_empty() {
+                attribute(out, "agent", &uncertain.agent);
+            };
+            if let Some(cert) = &uncertain.cert {
+                attribute(out, "cert", cert);
+            };
+            lang_attribute(out, &uncertain.lang);
+            let _ = write!(out, ">{}</unclear>", escape(&uncertain.content));
+        }
+        Block::Anchor(anchor) => {
+            out.push_str("<milestone");
+            attribute(out, "unit", "verse");
+            attribute(out, "type", &anchor.anchor_type);
+            attribute(out, "n", &anchor.anchor_id);
+            out.push_str("/>");
+        }
+        Block::Space(space) => {
+            out.push_str("<space");
+            attribute(out, "quantity", &space.quantity.to_string());
+            attribute(out, "unit", &space.unit.name().to_lowercase());
+            out.push_str("/>");
+        }
+        Block::Break(_) => {
+            // breaks are handled by the caller, because they close and open elements
+        }
+    }
+}
+
+/// Everything up to and including the opening `<sourceDoc>`
+fn tei_header(out: &mut String, title: &str, msname: &str, transcribers: &[&str]) {
+    out.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    out.push_str(r#"<TEI xmlns="http://www.tei-c.org/ns/1.0">"#);
+    let _ = write!(
+        out,
+        "<teiHeader><fileDesc><titleStmt><title>{}</title>",
+        escape(title)
+    );
+    for username in transcribers {
+        let _ = write!(
+            out,
+            "<respStmt><resp>Transcribed by</resp><name>{}</name></respStmt>",
+            escape(*username)
+        );
+    }
+    let _ = write!(
+        out,
+        "</titleStmt>\
+        <publicationStmt><p>Exported from critic</p></publicationStmt>\
+        <sourceDesc><msDesc><msIdentifier><idno>{}</idno></msIdentifier></msDesc></sourceDesc>\
+        </fileDesc></teiHeader><sourceDoc>",
+        escape(msname),
+    );
+}
+
+/// The start of the TEI document for a whole manuscript, see [`MANUSCRIPT_TEI_FOOTER`]
+fn manuscript_tei_header(msname: &str, transcribers: &[&str]) -> String {
+    let mut out = String::new();
+    tei_header(&mut out, msname, msname, transcribers);
+    out
+}
+
+/// Closes what [`manuscript_tei_header`] opened
+const MANUSCRIPT_TEI_FOOTER: &str = "</sourceDoc></TEI>";
+
+/// A page without a transcription, as a `<surface>` that only contains a `<gap>`
+fn missing_page_to_tei(pagename: &str, reason: &str) -> String {
+    let mut out = String::new();
+    out.push_str("<surface");
+    attribute(&mut out, "n", pagename);
+    out.push_str("><gap");
+    attribute(&mut out, "reason", reason);
+    out.push_str("/></surface>");
+    out
+}
+
+/// Serialize the transcription of a single page as `<surface>`
+///
+/// Columns are `<zone>`s and lines are `<line>`s.
+fn page_to_tei(blocks: &[Block], pagename: &str, username: Option<&str>) -> String {
+    let mut out = String::new();
+    out.push_str("<surface");
+    attribute(&mut out, "n", pagename);
+    if let Some(username) = username {
+        attribute(&mut out, "resp", username);
+    };
+    out.push_str(r#"><zone type="column"><line>"#);
+    for block in blocks {
+        match block {
+            Block::Break(break_type) if break_type.name() == "Column" => {
+                out.push_str(r#"</line></zone><zone type="column"><line>"#);
+            }
+            Block::Break(_) => {
+                out.push_str("</line><line>");
+            }
+            _ => block_to_tei(&mut out, block),
+        }
+    }
+    out.push_str("</line></zone></surface>");
+    out
+}
+
+/// Serialize a transcription of a page to a TEI P5 document
+///
+/// The page is a `<surface>` in the `<sourceDoc>`, see [`page_to_tei`].
+pub fn blocks_to_tei(blocks: &[Block], msname: &str, pagename: &str, username: &str) -> String {
+    let mut out = String::new();
+    tei_header(
+        &mut out,
+        &format!("{msname} - {pagename}"),
+        msname,
+        &[username],
+    );
+    out.push_str(&page_to_tei(blocks, pagename, None));
+    out.push_str("</sourceDoc></TEI>");
+    out
+}
+
+/// The first line of the todo list CSV
+pub const TODO_CSV_HEADER: &str =
+    "manuscript,page,verse_start,verse_end,started,published,your_status\r\n";
+
+/// Quote a CSV field if needed
+///
+/// Fields that spreadsheets would run as a formula get a leading `'`, so a page named e.g.
+/// `=HYPERLINK(...)` is shown as text.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// A single line of the todo list CSV, see [`TODO_CSV_HEADER`]
+pub fn page_todo_to_csv(page: &PageTodo) -> String {
+    let published = match page.transcriptions_published {
+        PublishedTranscriptions::None => "0",
+        PublishedTranscriptions::One => "1",
+        PublishedTranscriptions::Two => "2",
+        PublishedTranscriptions::More => ">2",
+    };
+    let status = match page.this_user_status {
+        OwnStatus::None => "",
+        OwnStatus::Started => "started",
+        OwnStatus::Published => "published",
+    };
+    format!(
+        "{},{},{},{},{},{published},{status}\r\n",
+        csv_field(&page.manuscript_name),
+        csv_field(&page.page_name),
+        csv_field(page.verse_start.as_deref().unwrap_or_default()),
+        csv_field(page.verse_end.as_deref().unwrap_or_default()),
+        page.transcriptions_started,
+    )
+}
+
+/// The search filters of the todo list
+#[derive(Deserialize)]
+struct TodoQuery {
+    #[serde(default)]
+    query: String,
+    /// The key of a [`TodoOrder`]
+    order: Option<String>,
+}
+
+/// Export all pages of the todo list matching the search filters as CSV
+///
+/// Unlike the page list, this is not paginated. The rows are streamed as they come in from the db.
+/// A db error midway aborts the response, so the client sees a failed download instead of a
+/// truncated file.
+async fn todo_csv_export(
+    Extension(config): Extension<Arc<Config>>,
+    auth_session: AuthSession,
+    Query(TodoQuery { query, order }): Query<TodoQuery>,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let order = match order.as_deref().map(TodoOrder::from_key) {
+        None => TodoOrder::default(),
+        Some(Some(order)) => order,
+        Some(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Unknown sort order for the page list",
+            )
+                .into_response();
+        }
+    };
+    let pages = stream_pages_by_query(config.db.clone(), query, user.username, order);
+    // None once the error was sent
+    let rows = stream::unfold(Some(pages), |pages| async move {
+        let mut pages = pages?;
+        match pages.recv().await? {
+            Ok(page) => Some((Ok(page_todo_to_csv(&page)), Some(pages))),
+            Err(e) => {
+                tracing::warn!("Failed exporting the page list: {e}");
+                Some((Err(e), None))
+            }
+        }
+    });
+    let body = stream::once(async { Ok(TODO_CSV_HEADER.to_string()) }).chain(rows);
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"todo.csv\"",
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
@@ -0,0 +1,212 @@
+use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+use critic_shared::{OwnStatus, PageTodo, PublishedTranscriptions};
+use quick_xml::{events::Event, Reader};
+
+use super::{
+    blocks_to_tei, manuscript_tei_header, missing_page_to_tei, page_to_tei, page_todo_to_csv,
+    xml_content_disposition, MANUSCRIPT_TEI_FOOTER, TODO_CSV_HEADER,
+};
+
+fn block(block_type: BlockType, content: &str) -> Block {
+    Block::from_type_lang_and_content(block_type, "hbo-Hebr".to_string(), content.to_string())
+}
+
+/// Parse the document and return the names of all elements in document order
+fn element_names(tei: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(tei);
+    let mut names = Vec::new();
+    loop {
+        match reader.read_event().expect("export is well-formed xml") {
+            Event::Start(e) | Event::Empty(e) => {
+                names.push(String::from_utf8(e.name().as_ref().to_vec()).unwrap());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    names
+}
+
+/// All raw (still escaped) text content of the elements with this name
+fn texts_of(tei: &str, element: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(tei);
+    let mut texts = Vec::new();
+    let mut inside = false;
+    loop {
+        match reader.read_event().expect("export is well-formed xml") {
+            Event::Start(e) if e.name().as_ref() == element.as_bytes() => inside = true,
+            Event::End(e) if e.name().as_ref() == element.as_bytes() => inside = false,
+            Event::Text(t) if inside => texts.push(String::from_utf8(t.to_vec()).unwrap()),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    texts
+}
+
+#[test]
+fn empty_transcription() {
+    let tei = blocks_to_tei(&[], "ms", "p1", "user");
+    let names = element_names(&tei);
+    assert_eq!(names[0], "TEI");
+    assert!(names.contains(&"sourceDoc".to_string()));
+    assert_eq!(names.iter().filter(|n| *n == "line").count(), 1);
+}
+
+#[test]
+fn text_is_escaped_and_preserved() {
+    let tei = blocks_to_tei(&[block(BlockType::Text, "a < b & c")], "ms", "p1", "user");
+    assert!(tei.contains(r#"<seg xml:lang="hbo-Hebr">a &lt; b &amp; c</seg>"#));
+    assert_eq!(
+        element_names(&tei).iter().filter(|n| *n == "seg").count(),
+        1
+    );
+}
+
+#[test]
+fn breaks_open_lines_and_zones() {
+    let line_break = block(BlockType::Break, "");
+    let tei = blocks_to_tei(
+        &[
+            block(BlockType::Text, "first"),
+            line_break,
+            block(BlockType::Text, "second"),
+        ],
+        "ms",
+        "p1",
+        "user",
+    );
+    let names = element_names(&tei);
+    assert_eq!(names.iter().filter(|n| *n == "line").count(), 2);
+    assert_eq!(names.iter().filter(|n| *n == "zone").count(), 1);
+    assert_eq!(
+        texts_of(&tei, "seg"),
+        vec!["first".to_string(), "second".to_string()]
+    );
+}
+
+#[test]
+fn blocks_map_to_tei_elements() {
+    let tei = blocks_to_tei(
+        &[
+            block(BlockType::Lacuna, ""),
+            block(BlockType::Abbreviation, "abbr"),
+            block(BlockType::Correction, "corrected"),
+            block(BlockType::Uncertain, "unclear"),
+            block(BlockType::Anchor, ""),
+            block(BlockType::Space, ""),
+        ],
+        "ms",
+        "p1",
+        "user",
+    );
+    let names = element_names(&tei);
+    for expected in [
+        "gap",
+        "choice",
+        "abbr",
+        "expan",
+        "app",
+        "rdg",
+        "unclear",
+        "milestone",
+        "space",
+    ] {
+        assert!(
+            names.contains(&expected.to_string()),
+            "{expected} missing in {tei}"
+        );
+    }
+    assert_eq!(texts_of(&tei, "abbr"), vec!["abbr".to_string()]);
+    assert_eq!(texts_of(&tei, "rdg"), vec!["corrected".to_string()]);
+    assert_eq!(texts_of(&tei, "unclear"), vec!["unclear".to_string()]);
+}
+
+#[test]
+fn manuscript_export_concatenates_pages() {
+    let tei = [
+        manuscript_tei_header("ms", &["alice", "bob"]),
+        page_to_tei(&[block(BlockType::Text, "first")], "p1", Some("alice")),
+        missing_page_to_tei("p2", "not transcribed"),
+        page_to_tei(&[block(BlockType::Text, "third")], "p3", Some("bob")),
+        MANUSCRIPT_TEI_FOOTER.to_string(),
+    ]
+    .concat();
+    let names = element_names(&tei);
+    assert_eq!(names.iter().filter(|n| *n == "surface").count(), 3);
+    assert_eq!(names.iter().filter(|n| *n == "respStmt").count(), 2);
+    assert_eq!(
+        texts_of(&tei, "seg"),
+        vec!["first".to_string(), "third".to_string()]
+    );
+    // pages stay in order, with a gap for the missing one
+    let p1 = tei.find(r#"<surface n="p1" resp="alice">"#).unwrap();
+    let p2 = tei
+        .find(r#"<surface n="p2"><gap reason="not transcribed"/></surface>"#)
+        .unwrap();
+    let p3 = tei.find(r#"<surface n="p3" resp="bob">"#).unwrap();
+    assert!(p1 < p2 && p2 < p3);
+}
+
+#[test]
+fn todo_csv_rows() {
+    let page = PageTodo {
+        manuscript_name: "Codex, \"the\" one".to_string(),
+        page_name: "1r".to_string(),
+        verse_start: Some("10".to_string()),
+        verse_end: None,
+        transcriptions_started: 3,
+        transcriptions_published: PublishedTranscriptions::More,
+        this_user_status: OwnStatus::Started,
+        locked_by: None,
+    };
+    assert_eq!(
+        page_todo_to_csv(&page),
+        "\"Codex, \"\"the\"\" one\",1r,10,,3,>2,started\r\n"
+    );
+    // one field per column
+    assert_eq!(TODO_CSV_HEADER.split(',').count(), 7);
+}
+
+#[test]
+fn todo_csv_fields_are_not_run_as_formulas() {
+    let page = |manuscript_name: &str, page_name: &str| PageTodo {
+        manuscript_name: manuscript_name.to_string(),
+        page_name: page_name.to_string(),
+        verse_start: None,
+        verse_end: None,
+        transcriptions_started: 0,
+        transcriptions_published: PublishedTranscriptions::None,
+        this_user_status: OwnStatus::None,
+        locked_by: None,
+    };
+    assert_eq!(
+        page_todo_to_csv(&page("=HYPERLINK(\"http://example.com\")", "+1r")),
+        "\"'=HYPERLINK(\"\"http://example.com\"\")\",'+1r,,,0,0,\r\n"
+    );
+    assert_eq!(
+        page_todo_to_csv(&page("-ms", "@1r")),
+        "'-ms,'@1r,,,0,0,\r\n"
+    );
+    assert_eq!(
+        page_todo_to_csv(&page("\tms", "\r1r")),
+        "'\tms,\"'\r1r\",,,0,0,\r\n"
+    );
+    // only the start of a field matters
+    assert_eq!(
+        page_todo_to_csv(&page("ms", "1r=2v")),
+        "ms,1r=2v,,,0,0,\r\n"
+    );
+}
+
+#[test]
+fn xml_download_names_are_percent_encoded() {
+    assert_eq!(
+        xml_content_disposition("ms", "p1", "user"),
+        "attachment; filename*=UTF-8''ms_p1_user.xml"
+    );
+    assert_eq!(
+        xml_content_disposition("ms \"a\"", "דף", "user"),
+        "attachment; filename*=UTF-8''ms%20%22a%22_%D7%93%D7%A3_user.xml"
+    );
+}
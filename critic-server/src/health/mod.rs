@@ -0,0 +1,113 @@
+//! Liveness and readiness endpoints for reverse proxies and orchestrators
+//!
+//! [`HEALTH_URL`](critic_shared::urls::HEALTH_URL) only shows that the web server answers.
+//! [`READINESS_URL`](critic_shared::urls::READINESS_URL) additionally checks that the db answers,
+//! the data directory is writable and the minification service is running.
+//!
+//! Both are unauthenticated and must be merged outside of the `login_required!` layer.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use axum::{
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use critic_shared::urls::{HEALTH_URL, READINESS_URL};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::{config::Config, db::ping};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Is the minification service currently running?
+static MINIFICATION_ALIVE: AtomicBool = AtomicBool::new(false);
+
+/// Marks the minification service as alive for as long as it is held
+///
+/// Dropping it (also when the service panics) marks the service as dead again.
+pub struct MinificationAlive;
+impl MinificationAlive {
+    pub fn new() -> Self {
+        MINIFICATION_ALIVE.store(true, Ordering::Relaxed);
+        Self
+    }
+}
+impl Default for MinificationAlive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for MinificationAlive {
+    fn drop(&mut self) {
+        MINIFICATION_ALIVE.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Body returned by both endpoints
+#[derive(Serialize)]
+struct HealthStatus {
+    status: &'static str,
+    /// Everything that failed for readiness
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+/// Check that we can create and remove a file in the data directory
+fn check_data_directory(data_directory: &str) -> Result<(), std::io::Error> {
+    let path = format!("{data_directory}/.readyz");
+    std::fs::write(&path, b"")?;
+    std::fs::remove_file(&path)
+}
+
+/// The router for health and readiness
+pub fn health_router() -> axum::Router {
+    axum::Router::new()
+        .route(HEALTH_URL, axum::routing::get(health))
+        .route(READINESS_URL, axum::routing::get(readiness))
+}
+
+/// The web server is up
+async fn health() -> Response {
+    Json(HealthStatus {
+        status: "ok",
+        errors: vec![],
+    })
+    .into_response()
+}
+
+/// The server can actually do its work
+async fn readiness(Extension(config): Extension<Arc<Config>>) -> Response {
+    let mut errors = Vec::new();
+    if let Err(e) = ping(&config.db).await {
+        errors.push(e.to_string());
+    };
+    if let Err(e) = check_data_directory(&config.data_directory) {
+        errors.push(format!("Data directory is not writable: {e}"));
+    };
+    if !MINIFICATION_ALIVE.load(Ordering::Relaxed) {
+        errors.push("The minification service is not running.".to_string());
+    };
+    if errors.is_empty() {
+        Json(HealthStatus {
+            status: "ok",
+            errors,
+        })
+        .into_response()
+    } else {
+        tracing::warn!("Not ready: {}", errors.join(" "));
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthStatus {
+                status: "unavailable",
+                errors,
+            }),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,25 @@
+use std::sync::atomic::Ordering;
+
+use super::{check_data_directory, MinificationAlive, MINIFICATION_ALIVE};
+
+#[test]
+fn minification_alive_is_reset_on_drop() {
+    let guard = MinificationAlive::new();
+    assert!(MINIFICATION_ALIVE.load(Ordering::Relaxed));
+    drop(guard);
+    assert!(!MINIFICATION_ALIVE.load(Ordering::Relaxed));
+}
+
+#[test]
+fn data_directory_check() {
+    let dir = std::env::temp_dir().join(format!("critic-health-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_str = dir.to_string_lossy().to_string();
+    // does not exist yet
+    assert!(check_data_directory(&dir_str).is_err());
+    std::fs::create_dir_all(&dir).unwrap();
+    assert!(check_data_directory(&dir_str).is_ok());
+    // the probe file is cleaned up again
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}
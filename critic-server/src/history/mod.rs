@@ -0,0 +1,255 @@
+//! Optional history of transcriptions, kept in a git repository
+//!
+//! The repository lives in the transcription directory. The history service periodically looks
+//! for transcriptions that changed since its last run and commits each of them on its own, with
+//! the transcriber as author. Rapid saves of the same page in between two runs therefore end up
+//! in a single commit.
+//!
+//! Failures are logged, but never keep a transcription from being saved.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+use critic_shared::urls::TRANSCRIPTION_BASE_LOCATION;
+
+use crate::{config::Config, signal_handler::InShutdown, transcription_store::PUBLISHED_DIRECTORY};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Problems that can occur while committing to the history
+#[derive(Debug)]
+enum HistoryError {
+    /// Cannot create the directory for the repository
+    CreateDir(std::io::Error),
+    /// Cannot run git at all
+    Spawn(std::io::Error),
+    /// git ran but failed: (arguments, stderr)
+    Git(String, String),
+}
+impl core::fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::CreateDir(e) => {
+                write!(f, "Cannot create the transcription directory: {e}.")
+            }
+            Self::Spawn(e) => {
+                write!(f, "Cannot run git: {e}.")
+            }
+            Self::Git(args, stderr) => {
+                write!(f, "git {args} failed: {stderr}")
+            }
+        }
+    }
+}
+impl core::error::Error for HistoryError {}
+
+/// A single changed path in the repository
+#[derive(Debug, PartialEq)]
+enum Change {
+    /// A transcription was created or saved
+    Transcription {
+        path: String,
+        msname: String,
+        pagename: String,
+        username: String,
+    },
+    /// Anything else, e.g. transcriptions removed because their page was renamed or deleted
+    Other { path: String },
+}
+
+/// Interpret the output of `git status --porcelain -z`
+fn parse_status(output: &[u8]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut entries = output
+        .split(|b| *b == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| String::from_utf8_lossy(entry).to_string());
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        };
+        let (status, path) = entry.split_at(3);
+        let path = path.to_string();
+        // renames and copies are followed by the path they came from
+        if status.starts_with('R') || status.starts_with('C') {
+            entries.next();
+        };
+        // published transcriptions are copies of transcriptions that are in the history already
+        if path.split('/').any(|part| part == PUBLISHED_DIRECTORY) {
+            continue;
+        };
+        if status.contains('D') {
+            changes.push(Change::Other { path });
+            continue;
+        };
+        let parts = path.split('/').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [msname, pagename, file] if file.ends_with(".xml") && file.len() > 4 => {
+                changes.push(Change::Transcription {
+                    msname: msname.to_string(),
+                    pagename: pagename.to_string(),
+                    username: file[..file.len() - 4].to_string(),
+                    path,
+                });
+            }
+            _ => {
+                changes.push(Change::Other { path });
+            }
+        }
+    }
+    changes
+}
+
+/// Run git in the repository and return its stdout
+fn git(repository: &Path, args: &[&str]) -> Result<Vec<u8>, HistoryError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args([
+            "-c",
+            "user.name=critic",
+            "-c",
+            "user.email=critic@localhost",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .output()
+        .map_err(HistoryError::Spawn)?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(HistoryError::Git(
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Create the repository unless it exists already
+fn ensure_repository(repository: &Path) -> Result<(), HistoryError> {
+    std::fs::create_dir_all(repository).map_err(HistoryError::CreateDir)?;
+    if !repository.join(".git").exists() {
+        git(repository, &["init", "--quiet"])?;
+        tracing::info!(
+            "Created transcription history repository in {}.",
+            repository.to_string_lossy()
+        );
+    };
+    Ok(())
+}
+
+/// Commit all changes in the repository, one commit per saved transcription
+///
+/// Returns the number of commits made.
+fn commit_changes(repository: &Path) -> Result<usize, HistoryError> {
+    let status = git(
+        repository,
+        &["status", "--porcelain", "-z", "--untracked-files=all"],
+    )?;
+    let changes = parse_status(&status);
+    let mut commits = 0;
+
+    let other_paths = changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Other { path } => Some(path.as_str()),
+            Change::Transcription { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    if !other_paths.is_empty() {
+        let mut args = vec!["add", "--all", "--"];
+        args.extend(other_paths);
+        git(repository, &args)?;
+        git(
+            repository,
+            &[
+                "commit",
+                "--quiet",
+                "-m",
+                "update transcriptions of renamed or deleted pages",
+            ],
+        )?;
+        commits += 1;
+    };
+
+    for change in changes {
+        let Change::Transcription {
+            path,
+            msname,
+            pagename,
+            username,
+        } = change
+        else {
+            continue;
+        };
+        git(repository, &["add", "--all", "--", &path])?;
+        git(
+            repository,
+            &[
+                "commit",
+                "--quiet",
+                "-m",
+                &format!("transcribe {msname}/{pagename} by {username}"),
+                "--author",
+                &format!("{username} <{username}@users.noreply.github.com>"),
+            ],
+        )?;
+        commits += 1;
+    }
+    Ok(commits)
+}
+
+/// Commit all pending changes without blocking the runtime, logging any failure
+async fn update_history(repository: PathBuf) {
+    match tokio::task::spawn_blocking(move || {
+        ensure_repository(&repository)?;
+        commit_changes(&repository)
+    })
+    .await
+    {
+        Ok(Ok(0)) => {}
+        Ok(Ok(commits)) => {
+            tracing::debug!("Added {commits} commits to the transcription history.");
+        }
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to update the transcription history: {e}");
+        }
+        Err(e) => {
+            tracing::warn!("Failed to join the transcription history task: {e}");
+        }
+    };
+}
+
+/// Run the history service
+///
+/// Returns immediately if the history is disabled in the config.
+pub async fn run_history(
+    config: Arc<Config>,
+    mut watcher: tokio::sync::watch::Receiver<InShutdown>,
+) {
+    if !config.history.enabled {
+        tracing::debug!("Transcription history is disabled.");
+        return;
+    };
+    tracing::debug!("Starting the transcription history service");
+    let mut repository = PathBuf::from(&config.data_directory);
+    repository.push(&TRANSCRIPTION_BASE_LOCATION[1..]);
+    loop {
+        let in_shutdown = tokio::select! {
+            _ = watcher.changed() => true,
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.history.batch_seconds)) => false,
+        };
+        // commit once more on shutdown, so that no save is left out of the history
+        update_history(repository.clone()).await;
+        if in_shutdown {
+            tracing::debug!("Shutting down transcription history service now.");
+            return;
+        };
+    }
+}
@@ -0,0 +1,68 @@
+use super::{parse_status, Change};
+
+#[test]
+fn empty_status() {
+    assert_eq!(parse_status(b""), vec![]);
+}
+
+#[test]
+fn saved_transcriptions() {
+    assert_eq!(
+        parse_status(b"?? ms/p1/alice.xml\0 M ms/p 2/bob.xml\0"),
+        vec![
+            Change::Transcription {
+                path: "ms/p1/alice.xml".to_string(),
+                msname: "ms".to_string(),
+                pagename: "p1".to_string(),
+                username: "alice".to_string(),
+            },
+            Change::Transcription {
+                path: "ms/p 2/bob.xml".to_string(),
+                msname: "ms".to_string(),
+                pagename: "p 2".to_string(),
+                username: "bob".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn deletions_and_unknown_files() {
+    assert_eq!(
+        parse_status(b" D ms/p1/alice.xml\0?? notes.txt\0"),
+        vec![
+            Change::Other {
+                path: "ms/p1/alice.xml".to_string()
+            },
+            Change::Other {
+                path: "notes.txt".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn renames_skip_the_original_path() {
+    assert_eq!(
+        parse_status(b"R  ms/new/alice.xml\0ms/old/alice.xml\0"),
+        vec![Change::Transcription {
+            path: "ms/new/alice.xml".to_string(),
+            msname: "ms".to_string(),
+            pagename: "new".to_string(),
+            username: "alice".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn published_transcriptions_are_left_out() {
+    assert_eq!(
+        parse_status(b"?? ms/p1/.published/alice.xml\0 M ms/p1/alice.xml\0"),
+        vec![Change::Transcription {
+            path: "ms/p1/alice.xml".to_string(),
+            msname: "ms".to_string(),
+            pagename: "p1".to_string(),
+            username: "alice".to_string(),
+        }]
+    );
+}
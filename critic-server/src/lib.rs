@@ -2,11 +2,22 @@
 //!
 //! Also contains some axum routes that are static or directly linked to external APIs (like the
 //! oauth flow).
+pub mod api;
+pub mod audit;
 pub mod auth;
+pub mod cleanup;
 pub mod config;
 pub mod db;
-pub mod github;
+pub mod export;
+pub mod health;
+pub mod history;
+pub mod manuscript_cache;
 pub mod minification;
+pub mod monitoring;
+pub mod plain_text;
+pub mod presence;
+pub mod public_view;
+pub mod request_id;
 pub mod signal_handler;
 pub mod static_files;
 pub mod transcription_store;
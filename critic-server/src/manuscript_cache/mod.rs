@@ -0,0 +1,134 @@
+//! An in-memory cache of manuscript metadata
+//!
+//! Browsing loads the same few manuscripts over and over, while their metadata rarely changes.
+//! Only [`ManuscriptMeta`] is cached, pages are always read from the db.
+//!
+//! Everything that changes a manuscript (including adding, renaming, deleting or minifying its
+//! pages, which may change its cover page) has to call [`ManuscriptCache::invalidate`] afterwards. Changes made
+//! by other processes (e.g. the maintenance commands) are not noticed.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use critic_shared::{Manuscript, ManuscriptMeta};
+use sqlx::{Pool, Postgres};
+
+use crate::db::{get_manuscript_meta, get_manuscript_page_rows, DBError};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+#[derive(Default)]
+struct Entries {
+    /// Increased on every access, entries remember when they were last used
+    clock: u64,
+    /// Increased on every invalidation
+    ///
+    /// Metadata read from the db before an invalidation may already be stale and is not cached.
+    generation: u64,
+    /// manuscript name -> (last used, metadata)
+    by_name: HashMap<String, (u64, ManuscriptMeta)>,
+}
+
+/// The least recently used manuscripts, see the module documentation
+pub struct ManuscriptCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+// the entries themselves are not interesting in logs
+impl core::fmt::Debug for ManuscriptCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ManuscriptCache")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+impl ManuscriptCache {
+    /// A cache holding at most `capacity` manuscripts, 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The cached metadata of this manuscript, if there is any
+    fn get(&self, msname: &str) -> Option<ManuscriptMeta> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let now = entries.clock;
+        entries.by_name.get_mut(msname).map(|(last_used, meta)| {
+            *last_used = now;
+            meta.clone()
+        })
+    }
+
+    /// The current generation, to be passed to [`Self::insert`] for metadata read afterwards
+    fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Cache `meta`, unless something was invalidated since `generation`
+    fn insert(&self, meta: ManuscriptMeta, generation: u64) {
+        if self.capacity == 0 {
+            return;
+        };
+        let mut entries = self.lock();
+        if entries.generation != generation {
+            return;
+        };
+        if !entries.by_name.contains_key(&meta.title) && entries.by_name.len() >= self.capacity {
+            let least_recently_used = entries
+                .by_name
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(name, _)| name.clone());
+            if let Some(name) = least_recently_used {
+                entries.by_name.remove(&name);
+            };
+        };
+        entries.clock += 1;
+        let now = entries.clock;
+        entries.by_name.insert(meta.title.clone(), (now, meta));
+    }
+
+    /// Forget the manuscript called `msname`
+    ///
+    /// Call this after every change to the manuscript or its pages. When renaming, call it for the
+    /// old and the new name.
+    pub fn invalidate(&self, msname: &str) {
+        let mut entries = self.lock();
+        entries.generation += 1;
+        entries.by_name.remove(msname);
+    }
+
+    /// The metadata of a manuscript, from the cache if possible
+    pub async fn manuscript_meta(
+        &self,
+        pool: &Pool<Postgres>,
+        msname: &str,
+    ) -> Result<ManuscriptMeta, DBError> {
+        if let Some(meta) = self.get(msname) {
+            return Ok(meta);
+        };
+        let generation = self.generation();
+        let meta = get_manuscript_meta(pool, msname).await?;
+        self.insert(meta.clone(), generation);
+        Ok(meta)
+    }
+
+    /// Like [`crate::db::get_manuscript`], but with the metadata from the cache if possible
+    pub async fn manuscript(
+        &self,
+        pool: &Pool<Postgres>,
+        msname: &str,
+    ) -> Result<Manuscript, DBError> {
+        let meta = self.manuscript_meta(pool, msname).await?;
+        let pages = get_manuscript_page_rows(pool, meta.id).await?;
+        Ok(Manuscript { meta, pages })
+    }
+}
@@ -0,0 +1,89 @@
+use sqlx::{Pool, Postgres};
+
+use super::ManuscriptCache;
+use crate::db::{add_manuscript, add_page, get_manuscript_meta, update_ms_meta};
+
+#[sqlx::test]
+async fn cached_metadata_is_reused(pool: Pool<Postgres>) {
+    let cache = ManuscriptCache::new(4);
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let meta = cache.manuscript_meta(&pool, "ms").await.unwrap();
+
+    // changed behind the back of the cache
+    sqlx::query("UPDATE manuscript SET institution = 'Bodleian' WHERE title = 'ms';")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(cache.manuscript_meta(&pool, "ms").await.unwrap(), meta);
+}
+
+#[sqlx::test]
+async fn updates_bust_the_cache(pool: Pool<Postgres>) {
+    let cache = ManuscriptCache::new(4);
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let mut meta = cache.manuscript_meta(&pool, "ms").await.unwrap();
+
+    meta.institution = Some("Bodleian".to_string());
+    update_ms_meta(&pool, &meta).await.unwrap();
+    cache.invalidate("ms");
+    assert_eq!(
+        cache
+            .manuscript_meta(&pool, "ms")
+            .await
+            .unwrap()
+            .institution
+            .as_deref(),
+        Some("Bodleian")
+    );
+
+    // renaming needs both names invalidated
+    meta = cache.manuscript_meta(&pool, "ms").await.unwrap();
+    meta.title = "renamed".to_string();
+    update_ms_meta(&pool, &meta).await.unwrap();
+    cache.invalidate("ms");
+    cache.invalidate("renamed");
+    assert!(cache.manuscript_meta(&pool, "ms").await.is_err());
+    assert_eq!(
+        cache.manuscript_meta(&pool, "renamed").await.unwrap().title,
+        "renamed"
+    );
+
+    // the first page becomes the cover
+    add_page(&pool, "1r", "renamed").await.unwrap();
+    cache.invalidate("renamed");
+    let manuscript = cache.manuscript(&pool, "renamed").await.unwrap();
+    assert_eq!(manuscript.meta.cover_page.as_deref(), Some("1r"));
+    assert_eq!(manuscript.pages.len(), 1);
+}
+
+#[sqlx::test]
+async fn metadata_read_before_an_invalidation_is_not_cached(pool: Pool<Postgres>) {
+    let cache = ManuscriptCache::new(4);
+    add_manuscript(&pool, "ms", None).await.unwrap();
+    let generation = cache.generation();
+    let stale = get_manuscript_meta(&pool, "ms").await.unwrap();
+    // someone changes the manuscript while the stale value is on its way into the cache
+    cache.invalidate("ms");
+    cache.insert(stale, generation);
+    assert!(cache.get("ms").is_none());
+}
+
+#[sqlx::test]
+async fn least_recently_used_manuscripts_are_evicted(pool: Pool<Postgres>) {
+    let cache = ManuscriptCache::new(2);
+    for msname in ["a", "b", "c"] {
+        add_manuscript(&pool, msname, None).await.unwrap();
+    }
+    cache.manuscript_meta(&pool, "a").await.unwrap();
+    cache.manuscript_meta(&pool, "b").await.unwrap();
+    // a is now used more recently than b
+    cache.manuscript_meta(&pool, "a").await.unwrap();
+    cache.manuscript_meta(&pool, "c").await.unwrap();
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("c").is_some());
+
+    let disabled = ManuscriptCache::new(0);
+    disabled.manuscript_meta(&pool, "a").await.unwrap();
+    assert!(disabled.get("a").is_none());
+}
@@ -9,19 +9,57 @@
 //! Rescaled images are converted to webp:
 //! - at the original size (just convert so we can show images as webp)
 //! - at preview scale
+//! - at thumbnail scale (for the manuscript overview)
+//!
+//! A tiny placeholder is additionally stored in the db as `data:` url.
+//!
+//! The EXIF orientation of the original is applied to the pixels before that, so photographed
+//! pages come out upright. No metadata is written to the webp files.
+//!
+//! webp only holds 8 bit per channel, so images with more (like 16 bit TIFF masters) are
+//! converted first. Of multi-page TIFFs, only the first page is used.
+//!
+//! The minification of every page gets its own operation id. All logs about that page are written
+//! in a span carrying it.
 
-use std::{fs::remove_file, sync::Arc};
+use std::{
+    fs::remove_file,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use critic_shared::{urls::IMAGE_BASE_LOCATION, PageMeta, PREVIEW_IMAGE_WIDTH};
-use image::{imageops::resize, GenericImageView, ImageReader};
-use rayon::prelude::*;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use critic_shared::{
+    urls::IMAGE_BASE_LOCATION, PageMeta, PLACEHOLDER_IMAGE_WIDTH, PREVIEW_IMAGE_WIDTH,
+    THUMBNAIL_IMAGE_WIDTH,
+};
+use image::{
+    error::UnsupportedError, imageops::resize, metadata::Orientation, DynamicImage,
+    GenericImageView, ImageDecoder, ImageError, ImageFormat, ImageReader,
+};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuildError};
+use sqlx::postgres::PgListener;
 
 use crate::{
     config::Config,
-    db::{get_page_to_minify, mark_page_minifcation_failed, mark_page_minified},
+    db::{
+        count_pages_to_minify, get_page_to_minify, listen_for_new_pages,
+        mark_pages_minification_failed, mark_pages_minified,
+    },
+    health::MinificationAlive,
+    monitoring::{
+        MINIFICATION_DURATION, MINIFICATION_FAILURES, MINIFICATION_QUEUE_DEPTH, PAGES_MINIFIED,
+    },
     signal_handler::InShutdown,
 };
 
+// include tests
+#[cfg(test)]
+mod test;
+
 /// Problems that can occur during minification
 #[derive(Debug)]
 enum MinificationError {
@@ -31,8 +69,12 @@ enum MinificationError {
     GuessFormat(std::io::Error),
     /// Cannot decode the image
     Decode(image::ImageError),
+    /// The original is a TIFF using a feature (compression, sample format, ...) we cannot decode
+    UnsupportedTiff(UnsupportedError),
     /// Cannot save the image
     Save(image::ImageError),
+    /// Cannot encode the placeholder
+    EncodePlaceholder(image::ImageError),
 }
 impl core::fmt::Display for MinificationError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -46,20 +88,66 @@ impl core::fmt::Display for MinificationError {
             Self::Decode(e) => {
                 write!(f, "Cannot decode the image: {e}.")
             }
+            Self::UnsupportedTiff(e) => {
+                write!(f, "The TIFF uses a feature that is not supported: {e}. Please convert it to a baseline TIFF (or png) and upload it again.")
+            }
             Self::Save(e) => {
                 write!(f, "Cannot save the image: {e}.")
             }
+            Self::EncodePlaceholder(e) => {
+                write!(f, "Cannot encode the placeholder: {e}.")
+            }
         }
     }
 }
 impl core::error::Error for MinificationError {}
 
+/// The error for a failed decode of an image in `format`
+fn decode_error(format: Option<ImageFormat>, e: ImageError) -> MinificationError {
+    match (format, e) {
+        (Some(ImageFormat::Tiff), ImageError::Unsupported(e)) => {
+            MinificationError::UnsupportedTiff(e)
+        }
+        (_, e) => MinificationError::Decode(e),
+    }
+}
+
+/// Convert `img` to a color type the webp encoder can write
+///
+/// More than 8 bit per channel (16 bit and float images) are reduced to 8 bit, keeping alpha only
+/// where the image has it. Everything else is returned unchanged.
+fn into_webp_compatible(img: DynamicImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => img,
+        _ if img.color().has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()),
+        _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+    }
+}
+
+/// A tiny webp version of the image as `data:` url
+fn placeholder(img: &DynamicImage) -> Result<String, MinificationError> {
+    let target_height = (PLACEHOLDER_IMAGE_WIDTH * img.height() / img.width()).max(1);
+    let mut webp = Vec::new();
+    img.thumbnail_exact(PLACEHOLDER_IMAGE_WIDTH, target_height)
+        .write_to(
+            &mut std::io::Cursor::new(&mut webp),
+            image::ImageFormat::WebP,
+        )
+        .map_err(MinificationError::EncodePlaceholder)?;
+    Ok(format!("data:image/webp;base64,{}", BASE64.encode(webp)))
+}
+
 /// Minify a single page, blocking the thread during resizing/reading/...
+///
+/// Returns the placeholder for this page
 fn minify_page(
     data_directory: &str,
     msname: &str,
     page: &PageMeta,
-) -> Result<(), MinificationError> {
+) -> Result<String, MinificationError> {
     tracing::trace!(
         "Start minification for a new page: {} of ms {msname}",
         page.name
@@ -68,12 +156,19 @@ fn minify_page(
         "{data_directory}{IMAGE_BASE_LOCATION}/{msname}/{}",
         page.name
     );
-    let img = ImageReader::open(format!("{base_path}/original"))
+    let reader = ImageReader::open(format!("{base_path}/original"))
         .map_err(MinificationError::OpenOriginal)?
         .with_guessed_format()
-        .map_err(MinificationError::GuessFormat)?
-        .decode()
-        .map_err(MinificationError::Decode)?;
+        .map_err(MinificationError::GuessFormat)?;
+    let format = reader.format();
+    // for TIFFs, this only ever decodes the first page
+    let mut decoder = reader.into_decoder().map_err(|e| decode_error(format, e))?;
+    // the image crate does not apply this by itself
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| decode_error(format, e))?;
+    img.apply_orientation(orientation);
+    let img = into_webp_compatible(img);
+    let placeholder = placeholder(&img)?;
 
     // keep aspect ratio of the image
     let target_height = PREVIEW_IMAGE_WIDTH * img.dimensions().1 / img.dimensions().0;
@@ -88,6 +183,17 @@ fn minify_page(
     resized
         .save(format!("{base_path}/preview.webp"))
         .map_err(MinificationError::Save)?;
+    // the preview is already small, so this is a lot cheaper than going from the original
+    let thumbnail_height = (THUMBNAIL_IMAGE_WIDTH * resized.height() / resized.width()).max(1);
+    tracing::trace!("Saving Thumbnail for page: {} of ms {msname}", page.name);
+    resize(
+        &resized,
+        THUMBNAIL_IMAGE_WIDTH,
+        thumbnail_height,
+        image::imageops::FilterType::Lanczos3,
+    )
+    .save(format!("{base_path}/thumb.webp"))
+    .map_err(MinificationError::Save)?;
     tracing::trace!(
         "Saving page {} of ms {msname} as webp in original dimensions",
         page.name
@@ -103,16 +209,94 @@ fn minify_page(
     if let Err(e) = remove_file(format!("{base_path}/original")) {
         tracing::warn!("Failed to unlink original ms page file: {base_path}/original : {e}. Will not retry and leave the file orphaned.");
     };
-    Ok(())
+    Ok(placeholder)
+}
+
+/// The operation id for the next page to minify
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The span all logs about minifying `page` are written in, with a new operation id
+fn minification_span(msname: &str, page: &PageMeta) -> tracing::Span {
+    let operation_id = NEXT_OPERATION_ID.fetch_add(1, Ordering::Relaxed);
+    tracing::info_span!(
+        "minification",
+        operation_id,
+        msname,
+        page = page.name.as_str()
+    )
+}
+
+/// The threads minification runs on
+///
+/// rayon would otherwise use its global pool with one thread per core, ignoring `worker_threads`.
+fn minification_pool(worker_threads: u8) -> Result<ThreadPool, ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        // 0 would mean "one per core" to rayon
+        .num_threads(worker_threads.max(1).into())
+        .thread_name(|idx| format!("minification-{idx}"))
+        .build()
+}
+
+/// How long to only poll after listening for new pages failed
+const LISTEN_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wait until a page is added, or forever without a listener
+async fn wait_for_new_pages(listener: &mut Option<PgListener>) -> Result<(), sqlx::Error> {
+    match listener {
+        Some(listener) => listener.recv().await.map(|_| ()),
+        None => std::future::pending().await,
+    }
 }
 
 /// Run the minification service
+///
+/// Looks for pages to minify every `minification.idle_ms` when there is nothing to do. With
+/// `minification.listen`, a dedicated connection listens for new pages and adding a page wakes the
+/// service right away. Notifications sent while that connection is down are lost, which the polling
+/// makes up for. A listener that cannot be (re-)established is retried every
+/// [`LISTEN_RETRY_INTERVAL`].
 pub async fn run_minification(
     config: Arc<Config>,
     mut watcher: tokio::sync::watch::Receiver<InShutdown>,
 ) {
     tracing::debug!("Starting the minification service");
+    // reported by the readiness endpoint until this function returns or panics
+    let _alive = MinificationAlive::new();
+    let pool = match minification_pool(config.worker_threads) {
+        Ok(x) => Arc::new(x),
+        Err(e) => {
+            tracing::error!("Cannot start the minification threads, not minifying anything: {e}");
+            return;
+        }
+    };
+    let mut listener = None;
+    // the first attempt happens right away
+    let mut next_listen_attempt = Instant::now();
     loop {
+        if config.minification.listen && listener.is_none() && Instant::now() >= next_listen_attempt
+        {
+            match listen_for_new_pages(&config.db).await {
+                Ok(x) => {
+                    tracing::debug!("Listening for new pages to minify");
+                    listener = Some(x);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Only polling for pages to minify for the next {}s: {e}",
+                        LISTEN_RETRY_INTERVAL.as_secs()
+                    );
+                    next_listen_attempt = Instant::now() + LISTEN_RETRY_INTERVAL;
+                }
+            };
+        };
+        match count_pages_to_minify(&config.db).await {
+            Ok(depth) => {
+                metrics::gauge!(MINIFICATION_QUEUE_DEPTH).set(depth as f64);
+            }
+            Err(e) => {
+                tracing::debug!("Failed to count pages to minify: {e}");
+            }
+        };
         let wait_till_next_minification = match get_page_to_minify(
             &config.db,
             config.worker_threads,
@@ -122,70 +306,123 @@ pub async fn run_minification(
             Ok(pages) => {
                 if pages.is_empty() {
                     // no page to minify or error getting one - try again later
-                    tokio::time::Duration::from_secs(1)
+                    Duration::from_millis(config.minification.idle_ms)
                 } else {
                     let config_arc = config.clone();
+                    let pool_arc = pool.clone();
                     // attempt the minifications in parallel, without blocking this thread
-                    let minify_results: Vec<(Result<(), MinificationError>, String, PageMeta)> =
-                        tokio::task::spawn_blocking(move || {
+                    let minify_results: Vec<(
+                        Result<String, MinificationError>,
+                        String,
+                        PageMeta,
+                        tracing::Span,
+                    )> = tokio::task::spawn_blocking(move || {
+                        pool_arc.install(|| {
                             pages
                                 .into_par_iter()
                                 .map(|(msname, page_to_minify)| {
-                                    (
+                                    let span = minification_span(&msname, &page_to_minify);
+                                    let start = Instant::now();
+                                    let res = span.in_scope(|| {
                                         minify_page(
                                             &config_arc.data_directory,
                                             &msname,
                                             &page_to_minify,
-                                        ),
-                                        msname,
-                                        page_to_minify,
-                                    )
+                                        )
+                                    });
+                                    metrics::histogram!(MINIFICATION_DURATION)
+                                        .record(start.elapsed().as_secs_f64());
+                                    (res, msname, page_to_minify, span)
                                 })
                                 .collect::<Vec<_>>()
                         })
-                        .await
-                        .unwrap();
-                    for (res, msname, page) in minify_results {
+                    })
+                    .await
+                    .unwrap();
+                    let mut minified = Vec::new();
+                    let mut failed = Vec::new();
+                    for (res, msname, page, span) in minify_results {
                         match res {
                             Err(e) => {
-                                tracing::warn!(
-                                    "Failed to minify page {} of ms {msname}: {e}",
-                                    page.name,
-                                );
-                                if let Err(e) =
-                                    mark_page_minifcation_failed(&config.db, page.id).await
-                                {
+                                metrics::counter!(MINIFICATION_FAILURES).increment(1);
+                                span.in_scope(|| {
                                     tracing::warn!(
-                                            "Failed to mark page {} of ms {msname} minification as failed: {e}",
-                                            page.name
-                                        );
-                                };
+                                        "Failed to minify page {} of ms {msname}: {e}",
+                                        page.name,
+                                    )
+                                });
+                                failed.push((msname, page, span));
                             }
-                            Ok(()) => {
-                                // finally, mark the page as minified
-                                if let Err(e) = mark_page_minified(&config.db, page.id).await {
-                                    tracing::warn!("Failed marking page {} of ms {msname} as minified, but minification is done: {e}", page.name)
-                                };
+                            Ok(placeholder) => {
+                                metrics::counter!(PAGES_MINIFIED).increment(1);
+                                minified.push((msname, page, placeholder, span));
                             }
                         }
                     }
-                    tokio::time::Duration::from_millis(10)
+                    // one query for every page in each list
+                    if !failed.is_empty() {
+                        let ids = failed
+                            .iter()
+                            .map(|(_, page, _)| page.id)
+                            .collect::<Vec<_>>();
+                        let res = mark_pages_minification_failed(&config.db, &ids).await;
+                        // the cached metadata includes the state of the cover page
+                        for (msname, _, _) in &failed {
+                            config.manuscript_cache.invalidate(msname);
+                        }
+                        if let Err(e) = res {
+                            for (msname, page, span) in &failed {
+                                span.in_scope(|| {
+                                    tracing::warn!(
+                                        "Failed to mark page {} of ms {msname} minification as failed: {e}",
+                                        page.name
+                                    )
+                                });
+                            }
+                        };
+                    };
+                    if !minified.is_empty() {
+                        let pages = minified
+                            .iter()
+                            .map(|(_, page, placeholder, _)| (page.id, placeholder.clone()))
+                            .collect::<Vec<_>>();
+                        // finally, mark the pages as minified
+                        let res = mark_pages_minified(&config.db, &pages).await;
+                        for (msname, _, _, _) in &minified {
+                            config.manuscript_cache.invalidate(msname);
+                        }
+                        if let Err(e) = res {
+                            for (msname, page, _, span) in &minified {
+                                span.in_scope(|| {
+                                    tracing::warn!("Failed marking page {} of ms {msname} as minified, but minification is done: {e}", page.name)
+                                });
+                            }
+                        };
+                    };
+                    Duration::from_millis(config.minification.batch_pause_ms)
                 }
             }
             Err(e) => {
                 tracing::warn!("Failed to get page to minify: {e}");
                 // this may be a general problem with the DB, so we do not want to bombard it with
                 // useless requests
-                tokio::time::Duration::from_secs(5)
+                Duration::from_millis(config.minification.error_backoff_ms)
             }
         };
         // now wait a bit, or cancel the service if we are in shutdown
-        tokio::select! {
+        let listen_error = tokio::select! {
             _ = watcher.changed() => {
                 tracing::debug!("Shutting down minification service now.");
                 return;
             }
-            _ = tokio::time::sleep(wait_till_next_minification) => {}
+            _ = tokio::time::sleep(wait_till_next_minification) => None,
+            res = wait_for_new_pages(&mut listener) => res.err(),
+        };
+        if let Some(e) = listen_error {
+            // sqlx already tried to reconnect, so the db is probably gone for a while
+            tracing::warn!("Lost the connection listening for new pages: {e}");
+            listener = None;
+            next_listen_attempt = Instant::now() + LISTEN_RETRY_INTERVAL;
         };
     }
 }
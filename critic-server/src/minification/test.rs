@@ -0,0 +1,154 @@
+use critic_shared::{urls::IMAGE_BASE_LOCATION, PageMeta, THUMBNAIL_IMAGE_WIDTH};
+use image::{
+    error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
+    DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader,
+};
+use rayon::prelude::*;
+
+use super::{decode_error, minification_pool, minify_page, MinificationError};
+
+#[test]
+fn pool_size_matches_worker_threads() {
+    let pool = minification_pool(3).unwrap();
+    assert_eq!(pool.current_num_threads(), 3);
+    // parallel iterators run inside the pool use only its threads
+    assert_eq!(pool.install(rayon::current_num_threads), 3);
+    let used_threads = pool.install(|| {
+        (0..64)
+            .into_par_iter()
+            .map(|_| std::thread::current().name().map(str::to_string))
+            .collect::<std::collections::HashSet<_>>()
+    });
+    assert!(used_threads.len() <= 3);
+    assert!(used_threads.iter().all(|name| name
+        .as_deref()
+        .is_some_and(|n| n.starts_with("minification-"))));
+}
+
+#[test]
+fn zero_worker_threads_still_minify() {
+    assert_eq!(minification_pool(0).unwrap().current_num_threads(), 1);
+}
+
+/// A jpeg that is twice as wide as high, dark on the left and bright on the right, with an EXIF
+/// orientation tag saying that it has to be rotated by 90 degrees clockwise to be upright
+fn sideways_jpeg() -> Vec<u8> {
+    let img = image::GrayImage::from_fn(32, 16, |x, _| image::Luma([if x < 16 { 0 } else { 255 }]));
+    let mut jpeg = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut jpeg),
+        image::ImageFormat::Jpeg,
+    )
+    .unwrap();
+
+    // big endian TIFF header, one IFD with the single entry Orientation (0x0112) = 6
+    let exif: &[u8] = &[
+        b'E', b'x', b'i', b'f', 0, 0, //
+        b'M', b'M', 0, 42, 0, 0, 0, 8, //
+        0, 1, //
+        0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, //
+        0, 0, 0, 0,
+    ];
+    let segment_len = u16::try_from(exif.len() + 2).unwrap();
+    // put the APP1 segment right after the start of image marker
+    let mut res = jpeg[..2].to_vec();
+    res.extend([0xFF, 0xE1]);
+    res.extend(segment_len.to_be_bytes());
+    res.extend(exif);
+    res.extend(&jpeg[2..]);
+    res
+}
+
+/// A page `p1` of ms `ms` in a fresh data directory named after `test`, with `original` as its
+/// original image
+///
+/// Returns (data directory, page directory, page).
+fn page_with_original(test: &str, original: &[u8]) -> (String, String, PageMeta) {
+    let data_directory = std::env::temp_dir()
+        .join(format!("critic-minification-{test}-{}", std::process::id()))
+        .to_string_lossy()
+        .to_string();
+    let page_directory = format!("{data_directory}{IMAGE_BASE_LOCATION}/ms/p1");
+    let _ = std::fs::remove_dir_all(&data_directory);
+    std::fs::create_dir_all(&page_directory).unwrap();
+    std::fs::write(format!("{page_directory}/original"), original).unwrap();
+    let page = PageMeta {
+        id: 1,
+        manuscript_id: 1,
+        name: "p1".to_string(),
+        verse_start: None,
+        verse_end: None,
+        placeholder: None,
+        minified: false,
+        minification_failed: false,
+    };
+    (data_directory, page_directory, page)
+}
+
+#[test]
+fn exif_orientation_is_applied_and_stripped() {
+    let (data_directory, page_directory, page) = page_with_original("exif", &sideways_jpeg());
+
+    let placeholder = minify_page(&data_directory, "ms", &page).unwrap();
+    assert!(placeholder.starts_with("data:image/webp;base64,"));
+
+    let mut decoder = ImageReader::open(format!("{page_directory}/original.webp"))
+        .unwrap()
+        .into_decoder()
+        .unwrap();
+    assert_eq!(decoder.exif_metadata().unwrap(), None);
+    let upright = DynamicImage::from_decoder(decoder).unwrap().into_luma8();
+    // now higher than wide, with the formerly left half on top
+    assert_eq!(upright.dimensions(), (16, 32));
+    assert!(upright.get_pixel(8, 4).0[0] < 64);
+    assert!(upright.get_pixel(8, 28).0[0] > 192);
+
+    let preview = image::open(format!("{page_directory}/preview.webp")).unwrap();
+    assert!(preview.height() > preview.width());
+    let thumbnail = image::open(format!("{page_directory}/thumb.webp")).unwrap();
+    assert_eq!(thumbnail.width(), THUMBNAIL_IMAGE_WIDTH);
+    assert!(thumbnail.height() > thumbnail.width());
+
+    std::fs::remove_dir_all(&data_directory).unwrap();
+}
+
+#[test]
+fn sixteen_bit_tiff_is_minified() {
+    let img = image::ImageBuffer::from_fn(40, 20, |x, _| {
+        image::Rgb([if x < 20 { 0_u16 } else { u16::MAX }, 0, 0])
+    });
+    let mut tiff = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut tiff), ImageFormat::Tiff)
+        .unwrap();
+    let (data_directory, page_directory, page) = page_with_original("tiff16", &tiff);
+
+    minify_page(&data_directory, "ms", &page).unwrap();
+
+    let original = image::open(format!("{page_directory}/original.webp")).unwrap();
+    assert_eq!(original.color(), image::ColorType::Rgb8);
+    assert_eq!((original.width(), original.height()), (40, 20));
+    assert!(original.to_rgb8().get_pixel(30, 10).0[0] > 192);
+    let preview = image::open(format!("{page_directory}/preview.webp")).unwrap();
+    assert!(preview.width() > preview.height());
+    assert!(!std::path::Path::new(&format!("{page_directory}/original")).exists());
+
+    std::fs::remove_dir_all(&data_directory).unwrap();
+}
+
+#[test]
+fn unsupported_tiff_features_are_reported_as_such() {
+    let unsupported = || {
+        ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Exact(ImageFormat::Tiff),
+            UnsupportedErrorKind::GenericFeature("JPEG compression".to_string()),
+        ))
+    };
+    assert!(matches!(
+        decode_error(Some(ImageFormat::Tiff), unsupported()),
+        MinificationError::UnsupportedTiff(_)
+    ));
+    assert!(matches!(
+        decode_error(Some(ImageFormat::Png), unsupported()),
+        MinificationError::Decode(_)
+    ));
+}
@@ -0,0 +1,74 @@
+//! Prometheus metrics for the minification service and uploads
+//!
+//! The recorder is installed once at startup, after which the `metrics` macros can be used from
+//! anywhere (including rayon threads). [`metrics_router`] renders everything recorded so far.
+
+use axum::{response::IntoResponse, Extension};
+use critic_shared::urls::METRICS_URL;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder};
+
+pub use metrics_exporter_prometheus::PrometheusHandle;
+
+use crate::signal_handler::InShutdown;
+
+/// Counter: pages that were minified successfully
+pub const PAGES_MINIFIED: &str = "critic_pages_minified_total";
+/// Counter: pages for which minification failed
+pub const MINIFICATION_FAILURES: &str = "critic_minification_failures_total";
+/// Histogram: seconds spent minifying a single page
+pub const MINIFICATION_DURATION: &str = "critic_minification_duration_seconds";
+/// Gauge: pages waiting to be minified
+pub const MINIFICATION_QUEUE_DEPTH: &str = "critic_minification_queue_depth";
+/// Counter: bytes of page images stored from uploads
+pub const UPLOAD_BYTES: &str = "critic_upload_bytes_total";
+/// Counter: uploaded files that were rejected or could not be stored
+pub const UPLOAD_FAILURES: &str = "critic_upload_failures_total";
+
+/// Buckets for [`MINIFICATION_DURATION`] - large scans can take several seconds
+const MINIFICATION_DURATION_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Install the global prometheus recorder
+///
+/// Must be called once before any metric is recorded, otherwise those are lost.
+/// [`run_metrics_upkeep`] has to run for the returned handle.
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(MINIFICATION_DURATION.to_string()),
+            MINIFICATION_DURATION_BUCKETS,
+        )?
+        .install_recorder()
+}
+
+/// The router serving [`METRICS_URL`]
+///
+/// This is unauthenticated, so it is only served on the separate `metrics_addr` from the config
+/// (loopback by default), never together with the website.
+pub fn metrics_router(handle: PrometheusHandle) -> axum::Router {
+    axum::Router::new()
+        .route(METRICS_URL, axum::routing::get(metrics))
+        .layer(Extension(handle))
+}
+
+/// All metrics in the prometheus text format
+async fn metrics(Extension(handle): Extension<PrometheusHandle>) -> impl IntoResponse {
+    handle.render()
+}
+
+/// Run the upkeep for the recorder, so histograms do not grow without bounds between scrapes
+pub async fn run_metrics_upkeep(
+    handle: PrometheusHandle,
+    mut watcher: tokio::sync::watch::Receiver<InShutdown>,
+) {
+    tracing::debug!("Starting the metrics upkeep service");
+    loop {
+        handle.run_upkeep();
+        tokio::select! {
+            _ = watcher.changed() => {
+                tracing::debug!("Shutting down metrics upkeep service now.");
+                return;
+            }
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(5)) => {}
+        };
+    }
+}
@@ -0,0 +1,47 @@
+//! Flattening transcriptions to readable plain text, e.g. for searching or exporting
+//!
+//! The rules are:
+//! - text and uncertain readings are taken as they are
+//! - abbreviations are written out, using the abbreviated form only when there is no expansion
+//! - of a correction, only the last version is used, i.e. the one by the latest hand
+//! - lacunae become [`LACUNA_MARKER`], spaces a single space
+//! - every break starts a new line, a column break additionally leaves an empty line
+//! - verse anchors do not show up at all
+
+use critic_format::streamed::Block;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Stands in for text that is lost in the manuscript
+pub const LACUNA_MARKER: &str = "[…]";
+
+/// Render the blocks of a transcription as plain text, see the module docs for the rules
+pub fn render_plain_text(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Text(paragraph) => out.push_str(&paragraph.content),
+            Block::Uncertain(uncertain) => out.push_str(&uncertain.content),
+            Block::Abbreviation(abbreviation) => {
+                if abbreviation.expansion.is_empty() {
+                    out.push_str(&abbreviation.surface);
+                } else {
+                    out.push_str(&abbreviation.expansion);
+                };
+            }
+            Block::Correction(correction) => {
+                if let Some(latest) = correction.versions.last() {
+                    out.push_str(&latest.content);
+                };
+            }
+            Block::Lacuna(_) => out.push_str(LACUNA_MARKER),
+            Block::Space(_) => out.push(' '),
+            Block::Break(break_type) if break_type.name() == "Column" => out.push_str("\n\n"),
+            Block::Break(_) => out.push('\n'),
+            Block::Anchor(_) => {}
+        }
+    }
+    out
+}
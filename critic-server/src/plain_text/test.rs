@@ -0,0 +1,104 @@
+use critic_format::streamed::{
+    Abbreviation, Block, BlockType, Correction, FromTypeLangAndContent, Paragraph, Version,
+};
+
+use super::{render_plain_text, LACUNA_MARKER};
+
+fn block(block_type: BlockType, content: &str) -> Block {
+    Block::from_type_lang_and_content(block_type, "hbo-Hebr".to_string(), content.to_string())
+}
+
+fn text(content: &str) -> Block {
+    Block::Text(Paragraph {
+        lang: "hbo-Hebr".to_string(),
+        content: content.to_string(),
+    })
+}
+
+fn abbreviation(surface: &str, expansion: &str) -> Block {
+    Block::Abbreviation(Abbreviation {
+        surface_lang: "hbo-Hebr".to_string(),
+        expansion_lang: "hbo-Hebr".to_string(),
+        surface: surface.to_string(),
+        expansion: expansion.to_string(),
+    })
+}
+
+fn correction(versions: &[&str]) -> Block {
+    Block::Correction(Correction {
+        versions: versions
+            .iter()
+            .enumerate()
+            .map(|(idx, content)| Version {
+                lang: "hbo-Hebr".to_string(),
+                hand: Some(format!("hand{idx}")),
+                content: content.to_string(),
+            })
+            .collect(),
+    })
+}
+
+#[test]
+fn empty_transcription() {
+    assert_eq!(render_plain_text(&[]), "");
+}
+
+#[test]
+fn text_and_uncertain_readings_are_concatenated() {
+    assert_eq!(
+        render_plain_text(&[
+            text("first "),
+            block(BlockType::Uncertain, "unclear"),
+            text(" last")
+        ]),
+        "first unclear last"
+    );
+}
+
+#[test]
+fn breaks_start_new_lines() {
+    assert_eq!(
+        render_plain_text(&[text("first"), block(BlockType::Break, ""), text("second"),]),
+        "first\nsecond"
+    );
+}
+
+#[test]
+fn lacunae_and_spaces_are_marked() {
+    assert_eq!(
+        render_plain_text(&[
+            text("a"),
+            block(BlockType::Lacuna, ""),
+            text("b"),
+            block(BlockType::Space, ""),
+            text("c"),
+        ]),
+        format!("a{LACUNA_MARKER}b c")
+    );
+}
+
+#[test]
+fn corrections_use_the_latest_hand() {
+    assert_eq!(
+        render_plain_text(&[correction(&["first", "second", "third"])]),
+        "third"
+    );
+    assert_eq!(render_plain_text(&[correction(&[])]), "");
+}
+
+#[test]
+fn abbreviations_are_written_out() {
+    assert_eq!(
+        render_plain_text(&[abbreviation("abbr", "abbreviation")]),
+        "abbreviation"
+    );
+    assert_eq!(render_plain_text(&[abbreviation("abbr", "")]), "abbr");
+}
+
+#[test]
+fn anchors_are_left_out() {
+    assert_eq!(
+        render_plain_text(&[text("a"), block(BlockType::Anchor, ""), text("b")]),
+        "ab"
+    );
+}
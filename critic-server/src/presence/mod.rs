@@ -0,0 +1,181 @@
+//! Who currently has a page open in the transcription editor
+//!
+//! While a page is open, the editor keeps a websocket to
+//! [`PRESENCE_BASE_URL`](critic_shared::urls::PRESENCE_BASE_URL)`/<ms>/<page>` open. critic keeps
+//! track of these connections in memory and sends every connection the names of the *other* users
+//! on the same page (as JSON list) whenever they change. This is only a hint for transcribers, so
+//! nothing is stored in the db.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Path, WebSocketUpgrade,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::broadcast;
+
+use crate::auth::AuthSession;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// How many changes a connection may fall behind before it rereads the current users
+const CHANGE_BUFFER: usize = 16;
+
+/// The connections to one page
+struct PageUsers {
+    /// username -> number of open connections (a user may have the page open in several tabs)
+    connections: HashMap<String, usize>,
+    /// sends the sorted usernames whenever a user comes or goes
+    changes: broadcast::Sender<Vec<String>>,
+}
+impl PageUsers {
+    fn usernames(&self) -> Vec<String> {
+        let mut usernames = self.connections.keys().cloned().collect::<Vec<_>>();
+        usernames.sort();
+        usernames
+    }
+}
+
+/// The users on every page that is currently open
+#[derive(Default)]
+pub struct Presence {
+    /// (manuscript name, page name) -> users
+    pages: Mutex<HashMap<(String, String), PageUsers>>,
+}
+impl Presence {
+    /// Register a connection of `username` to this page
+    ///
+    /// The user counts as present until the returned handle is dropped.
+    pub fn join(&self, msname: &str, pagename: &str, username: &str) -> PresenceHandle<'_> {
+        let key = (msname.to_string(), pagename.to_string());
+        let mut pages = self.pages.lock().unwrap_or_else(|e| e.into_inner());
+        let page = pages.entry(key.clone()).or_insert_with(|| PageUsers {
+            connections: HashMap::new(),
+            changes: broadcast::channel(CHANGE_BUFFER).0,
+        });
+        let connections = page.connections.entry(username.to_string()).or_default();
+        *connections += 1;
+        // only tell the others if this is a new user, not just another tab
+        if *connections == 1 {
+            // nobody listening is fine
+            let _ = page.changes.send(page.usernames());
+        };
+        PresenceHandle {
+            presence: self,
+            key,
+            username: username.to_string(),
+            changes: page.changes.subscribe(),
+        }
+    }
+
+    /// The users currently on this page, sorted
+    pub fn users(&self, msname: &str, pagename: &str) -> Vec<String> {
+        self.pages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(msname.to_string(), pagename.to_string()))
+            .map(PageUsers::usernames)
+            .unwrap_or_default()
+    }
+
+    fn leave(&self, key: &(String, String), username: &str) {
+        let mut pages = self.pages.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(page) = pages.get_mut(key) else {
+            return;
+        };
+        if let Some(connections) = page.connections.get_mut(username) {
+            *connections -= 1;
+            if *connections == 0 {
+                page.connections.remove(username);
+                let _ = page.changes.send(page.usernames());
+            };
+        };
+        if page.connections.is_empty() {
+            pages.remove(key);
+        };
+    }
+}
+
+/// One connection of a user to a page, see [`Presence::join`]
+pub struct PresenceHandle<'a> {
+    presence: &'a Presence,
+    key: (String, String),
+    username: String,
+    /// the users on this page after every change
+    pub changes: broadcast::Receiver<Vec<String>>,
+}
+impl PresenceHandle<'_> {
+    /// The users currently on this page, sorted
+    pub fn users(&self) -> Vec<String> {
+        self.presence.users(&self.key.0, &self.key.1)
+    }
+
+    /// `users` without the user of this connection
+    pub fn others(&self, users: Vec<String>) -> Vec<String> {
+        users
+            .into_iter()
+            .filter(|user| *user != self.username)
+            .collect()
+    }
+}
+impl Drop for PresenceHandle<'_> {
+    fn drop(&mut self) {
+        self.presence.leave(&self.key, &self.username);
+    }
+}
+
+/// The users on all pages of this server
+static PRESENCE: LazyLock<Presence> = LazyLock::new(Presence::default);
+
+pub fn presence_router() -> axum::Router {
+    axum::Router::new().route("/{msname}/{pagename}", axum::routing::get(presence_socket))
+}
+
+async fn presence_socket(
+    Path((msname, pagename)): Path<(String, String)>,
+    auth_session: AuthSession,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    ws.on_upgrade(move |socket| send_presence(socket, msname, pagename, user.username))
+}
+
+/// Send the other users on this page until the socket is closed
+async fn send_presence(mut socket: WebSocket, msname: String, pagename: String, username: String) {
+    let mut handle = PRESENCE.join(&msname, &pagename, &username);
+    let mut users = handle.users();
+    loop {
+        let others = handle.others(users);
+        let Ok(json) = serde_json::to_string(&others) else {
+            return;
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            return;
+        };
+        users = loop {
+            tokio::select! {
+                changed = handle.changes.recv() => match changed {
+                    Ok(users) => break users,
+                    Err(broadcast::error::RecvError::Lagged(_)) => break handle.users(),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                msg = socket.recv() => match msg {
+                    // the client does not send anything but pings and the final close
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        };
+    }
+}
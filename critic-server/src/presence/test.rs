@@ -0,0 +1,47 @@
+use super::Presence;
+
+#[test]
+fn users_come_and_go() {
+    let presence = Presence::default();
+    let alice = presence.join("ms", "1r", "alice");
+    assert_eq!(alice.users(), vec!["alice"]);
+    assert_eq!(alice.others(alice.users()), Vec::<String>::new());
+
+    let bob = presence.join("ms", "1r", "bob");
+    assert_eq!(alice.others(alice.users()), vec!["bob"]);
+    assert_eq!(bob.others(bob.users()), vec!["alice"]);
+    // other pages are separate
+    assert!(presence.users("ms", "1v").is_empty());
+
+    drop(bob);
+    assert_eq!(presence.users("ms", "1r"), vec!["alice"]);
+    drop(alice);
+    assert!(presence.users("ms", "1r").is_empty());
+    assert!(presence.pages.lock().unwrap().is_empty());
+}
+
+#[test]
+fn a_user_with_two_tabs_stays_until_both_are_closed() {
+    let presence = Presence::default();
+    let first_tab = presence.join("ms", "1r", "alice");
+    let second_tab = presence.join("ms", "1r", "alice");
+    assert_eq!(presence.users("ms", "1r"), vec!["alice"]);
+    drop(first_tab);
+    assert_eq!(presence.users("ms", "1r"), vec!["alice"]);
+    drop(second_tab);
+    assert!(presence.users("ms", "1r").is_empty());
+}
+
+#[test]
+fn changes_are_broadcast() {
+    let presence = Presence::default();
+    let mut alice = presence.join("ms", "1r", "alice");
+    let bob = presence.join("ms", "1r", "bob");
+    assert_eq!(alice.changes.try_recv().unwrap(), vec!["alice", "bob"]);
+    // another tab of the same user is no change
+    let bob_again = presence.join("ms", "1r", "bob");
+    assert!(alice.changes.try_recv().is_err());
+    drop(bob);
+    drop(bob_again);
+    assert_eq!(alice.changes.try_recv().unwrap(), vec!["alice"]);
+}
@@ -0,0 +1,280 @@
+//! Read-only HTML pages showing published transcriptions of public manuscripts
+//!
+//! These pages do not need a login and must be merged outside of the `login_required!` layer.
+//! Manuscripts that are not marked public look exactly like manuscripts that do not exist.
+
+use std::{fmt::Write, sync::Arc};
+
+use axum::{
+    extract::Path,
+    response::{Html, IntoResponse, Response},
+    Extension,
+};
+use critic_format::streamed::Block;
+use quick_xml::escape::escape;
+use reqwest::StatusCode;
+
+use crate::{
+    config::Config,
+    db::{get_published_transcribers, DBError},
+    transcription_store::{read_published_transcription, TranscriptionStoreError},
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// The router serving the public transcription pages
+pub fn public_view_router() -> axum::Router {
+    axum::Router::new()
+        .route("/{msname}/{pagename}", axum::routing::get(public_page))
+        .route(
+            "/{msname}/{pagename}/{username}",
+            axum::routing::get(public_transcription),
+        )
+}
+
+/// The answer for everything that is not public
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        "There is no such public transcription.",
+    )
+        .into_response()
+}
+
+/// The default language of `msname` if it is public
+async fn public_manuscript_lang(config: &Config, msname: &str) -> Result<String, Response> {
+    match config
+        .manuscript_cache
+        .manuscript_meta(&config.db, msname)
+        .await
+    {
+        Ok(meta) if meta.public => Ok(meta.lang),
+        Ok(_) | Err(DBError::ManuscriptDoesNotExist(_)) => Err(not_found()),
+        Err(e) => {
+            tracing::warn!("Failed to get manuscript {msname}: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+        }
+    }
+}
+
+/// Everyone who published a transcription of the page
+async fn published_transcribers(
+    config: &Config,
+    msname: &str,
+    pagename: &str,
+) -> Result<Vec<String>, Response> {
+    get_published_transcribers(&config.db, msname, pagename)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to get transcriptions for {msname}/{pagename}: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        })
+}
+
+/// Render the published transcriptions of `usernames` for a page
+///
+/// These are the transcriptions as they were published, not what their transcribers saved since.
+async fn render_transcriptions(
+    config: &Config,
+    msname: &str,
+    pagename: &str,
+    usernames: Vec<String>,
+    default_language: &str,
+) -> Response {
+    let data_directory = config.data_directory.clone();
+    let (msname_owned, pagename_owned) = (msname.to_string(), pagename.to_string());
+    let default_language = default_language.to_string();
+    // reading and parsing the files blocks
+    let read = tokio::task::spawn_blocking(move || {
+        usernames
+            .into_iter()
+            .map(|username| {
+                read_published_transcription(
+                    &data_directory,
+                    &msname_owned,
+                    &pagename_owned,
+                    &username,
+                    &default_language,
+                )
+                .map(|(blocks, _pagename)| (username, blocks))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await
+    .map_err(TranscriptionStoreError::Join)
+    .and_then(|res| res);
+    let transcriptions = match read {
+        Ok(x) => x,
+        Err(e) => {
+            tracing::warn!("Failed to read transcriptions of /{msname}/{pagename}: {e}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read transcription from disk.",
+            )
+                .into_response();
+        }
+    };
+    Html(transcriptions_to_html(msname, pagename, &transcriptions)).into_response()
+}
+
+/// Show all published transcriptions of a page
+async fn public_page(
+    Extension(config): Extension<Arc<Config>>,
+    Path((msname, pagename)): Path<(String, String)>,
+) -> Response {
+    let default_language = match public_manuscript_lang(&config, &msname).await {
+        Ok(x) => x,
+        Err(response) => return response,
+    };
+    let transcribers = match published_transcribers(&config, &msname, &pagename).await {
+        Ok(x) if x.is_empty() => return not_found(),
+        Ok(x) => x,
+        Err(response) => return response,
+    };
+    render_transcriptions(&config, &msname, &pagename, transcribers, &default_language).await
+}
+
+/// Show the published transcription of `username` for a page
+async fn public_transcription(
+    Extension(config): Extension<Arc<Config>>,
+    Path((msname, pagename, username)): Path<(String, String, String)>,
+) -> Response {
+    let default_language = match public_manuscript_lang(&config, &msname).await {
+        Ok(x) => x,
+        Err(response) => return response,
+    };
+    match published_transcribers(&config, &msname, &pagename).await {
+        Ok(x) if x.contains(&username) => {}
+        Ok(_) => return not_found(),
+        Err(response) => return response,
+    };
+    render_transcriptions(
+        &config,
+        &msname,
+        &pagename,
+        vec![username],
+        &default_language,
+    )
+    .await
+}
+
+/// Write `lang="..."` unless `lang` is empty
+fn lang_attribute(out: &mut String, lang: &str) {
+    if !lang.is_empty() {
+        let _ = write!(out, " lang=\"{}\"", escape(lang));
+    };
+}
+
+/// Render a single block that is not a break as inline HTML
+///
+/// Verse anchors become superscript verse numbers, lacunae and uncertain readings are put in
+/// brackets and corrections show the earlier readings struck through.
+fn block_to_html(out: &mut String, block: &Block) {
+    match block {
+        Block::Text(paragraph) => {
+            out.push_str("<span");
+            lang_attribute(out, &paragraph.lang);
+            let _ = write!(out, ">{}</span>", escape(&paragraph.content));
+        }
+        Block::Lacuna(lacuna) => {
+            let mut title = format!("{} {} missing", lacuna.n, lacuna.unit.name().to_lowercase());
+            if !lacuna.reason.is_empty() {
+                let _ = write!(title, " ({})", lacuna.reason);
+            };
+            let _ = write!(
+                out,
+                "<span class=\"lacuna\" title=\"{}\">[…]</span>",
+                escape(&title)
+            );
+        }
+        Block::Abbreviation(abbreviation) => {
+            let _ = write!(out, "<abbr title=\"{}\"", escape(&abbreviation.expansion));
+            lang_attribute(out, &abbreviation.surface_lang);
+            let _ = write!(out, ">{}</abbr>", escape(&abbreviation.surface));
+        }
+        Block::Correction(correction) => {
+            out.push_str("<span class=\"correction\">");
+            let last = correction.versions.len().saturating_sub(1);
+            for (idx, version) in correction.versions.iter().enumerate() {
+                let element = if idx == last { "ins" } else { "del" };
+                let _ = write!(out, "<{element}");
+                if let Some(hand) = &version.hand {
+                    let _ = write!(out, " title=\"{}\"", escape(hand));
+                };
+                lang_attribute(out, &version.lang);
+                let _ = write!(out, ">{}</{element}>", escape(&version.content));
+            }
+            out.push_str("</span>");
+        }
+        Block::Uncertain(uncertain) => {
+            out.push_str("<span class=\"uncertain\"");
+            lang_attribute(out, &uncertain.lang);
+            let _ = write!(out, ">⸢{}⸣</span>", escape(&uncertain.content));
+        }
+        Block::Anchor(anchor) => {
+            let _ = write!(
+                out,
+                "<sup class=\"verse\">{}</sup>",
+                escape(&anchor.anchor_id)
+            );
+        }
+        Block::Space(_) => {
+            out.push_str("<span class=\"space\"> </span>");
+        }
+        Block::Break(_) => {
+            // breaks are handled by the caller, because they close and open elements
+        }
+    }
+}
+
+/// Render the blocks of one transcription, columns as `<div>`s and lines as `<p>`s
+pub fn blocks_to_html(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    out.push_str("<div class=\"column\"><p>");
+    for block in blocks {
+        match block {
+            Block::Break(break_type) if break_type.name() == "Column" => {
+                out.push_str("</p></div><div class=\"column\"><p>");
+            }
+            Block::Break(_) => {
+                out.push_str("</p><p>");
+            }
+            _ => block_to_html(&mut out, block),
+        }
+    }
+    out.push_str("</p></div>");
+    out
+}
+
+/// Keeps the page readable without the app's stylesheet
+const STYLE: &str = "body{font-family:serif;max-width:60rem;margin:2rem auto;padding:0 1rem}\
+    .page{display:flex;gap:3rem}\
+    .column p{margin:0.2rem 0;font-size:1.4rem}\
+    .verse{color:#c2410c;font-size:0.7rem}\
+    .lacuna,.uncertain{color:#475569}\
+    del{color:#94a3b8}";
+
+/// A complete HTML document showing the transcriptions of a page
+fn transcriptions_to_html(
+    msname: &str,
+    pagename: &str,
+    transcriptions: &[(impl AsRef<str>, Vec<Block>)],
+) -> String {
+    let title = escape(&format!("{msname} - {pagename}")).into_owned();
+    let mut out = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title>\
+        <style>{STYLE}</style></head><body><h1>{title}</h1>"
+    );
+    for (username, blocks) in transcriptions {
+        let _ = write!(
+            out,
+            "<section><h2>Transcribed by {}</h2><div class=\"page\" dir=\"auto\">{}</div></section>",
+            escape(username.as_ref()),
+            blocks_to_html(blocks)
+        );
+    }
+    out.push_str("</body></html>");
+    out
+}
@@ -0,0 +1,65 @@
+use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+
+use super::{blocks_to_html, transcriptions_to_html};
+
+fn block(block_type: BlockType, content: &str) -> Block {
+    Block::from_type_lang_and_content(block_type, "hbo-Hebr".to_string(), content.to_string())
+}
+
+#[test]
+fn text_is_escaped() {
+    let html = blocks_to_html(&[block(BlockType::Text, "<script>a & b</script>")]);
+    assert!(html.contains("&lt;script&gt;a &amp; b&lt;/script&gt;"));
+    assert!(!html.contains("<script>"));
+}
+
+#[test]
+fn breaks_start_new_lines() {
+    let html = blocks_to_html(&[
+        block(BlockType::Text, "first"),
+        block(BlockType::Break, ""),
+        block(BlockType::Text, "second"),
+    ]);
+    assert_eq!(html.matches("<p>").count(), 2);
+    assert_eq!(html.matches("<div class=\"column\">").count(), 1);
+    assert!(html.find("first").unwrap() < html.find("second").unwrap());
+}
+
+#[test]
+fn blocks_are_marked_up_for_reading() {
+    let html = blocks_to_html(&[
+        block(BlockType::Lacuna, ""),
+        block(BlockType::Abbreviation, "abbr"),
+        block(BlockType::Correction, "corrected"),
+        block(BlockType::Uncertain, "unclear"),
+        block(BlockType::Anchor, ""),
+        block(BlockType::Space, ""),
+    ]);
+    for expected in [
+        "[…]",
+        ">abbr</abbr>",
+        ">corrected</ins>",
+        "⸢unclear⸣",
+        "<sup class=\"verse\">",
+        "<span class=\"space\">",
+    ] {
+        assert!(html.contains(expected), "{expected} missing in {html}");
+    }
+}
+
+#[test]
+fn page_lists_every_transcriber() {
+    let html = transcriptions_to_html(
+        "ms",
+        "p<1>",
+        &[
+            ("alice", vec![block(BlockType::Text, "a")]),
+            ("bob", vec![block(BlockType::Text, "b")]),
+        ],
+    );
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>ms - p&lt;1&gt;</title>"));
+    assert!(html.contains("Transcribed by alice"));
+    assert!(html.contains("Transcribed by bob"));
+    assert_eq!(html.matches("<section>").count(), 2);
+}
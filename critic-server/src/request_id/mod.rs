@@ -0,0 +1,43 @@
+//! Correlation ids for requests
+//!
+//! Every request gets an id, taken from its `X-Request-Id` header or generated. All logs written
+//! while handling the request are in a span carrying this id, and the id is sent back in the
+//! `X-Request-Id` header of the response, so a user can report it together with a failure.
+
+use axum::{body::Body, http::Request, Router};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// The header carrying the request id
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Give every request handled by `router` a request id, see the module documentation
+pub fn with_request_ids<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    // the last layer runs first: set the id, then open the span, then copy the id to the response
+    router
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+/// The span all logs for this request are written in
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or("-");
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        path = request.uri().path(),
+    )
+}
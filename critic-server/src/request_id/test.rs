@@ -0,0 +1,36 @@
+use axum::{body::Body, http::Request, routing::get, Router};
+use tower::ServiceExt;
+
+use super::{with_request_ids, REQUEST_ID_HEADER};
+
+fn router() -> Router {
+    with_request_ids(Router::new().route("/", get(|| async { "ok" })))
+}
+
+#[tokio::test]
+async fn request_id_is_generated() {
+    let response = router()
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let id = response.headers().get(REQUEST_ID_HEADER).unwrap();
+    assert!(!id.is_empty());
+}
+
+#[tokio::test]
+async fn request_id_of_the_client_is_kept() {
+    let response = router()
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header(REQUEST_ID_HEADER, "from-the-proxy")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers().get(REQUEST_ID_HEADER).unwrap(),
+        "from-the-proxy"
+    );
+}
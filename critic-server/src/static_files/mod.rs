@@ -2,11 +2,37 @@
 //!
 //! This includes:
 //! - images
+//! - `/images/<ms>/<page>` without a file name, which redirects to the best format of the page
+//!   image the browser accepts (see [`negotiate_image_file`])
+//!
+//! [`ServeDir`] answers range requests (and advertises `Accept-Ranges`) on its own, so large
+//! originals can be fetched partially.
+
+use std::{path::Path, sync::Arc};
 
-use axum::routing::get_service;
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{ACCEPT, CACHE_CONTROL, LOCATION, VARY},
+        HeaderValue, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get_service,
+};
+use axum_login::login_required;
 use critic_shared::urls::{IMAGE_BASE_LOCATION, TRANSCRIPTION_BASE_LOCATION};
 use tower_http::services::ServeDir;
 
+use crate::{
+    auth::OauthBackend,
+    db::{validate_manuscript_title, validate_page_name},
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
 /// Creates the following directory structure if it does not exist
 /// <data_directory>
 ///     /files
@@ -18,17 +44,169 @@ fn create_data_directory_layout(data_directory: &str) -> Result<(), std::io::Err
     Ok(())
 }
 
-pub fn image_dir_router(data_directory: &str) -> Result<axum::Router, std::io::Error> {
+/// How long browsers may keep image files, and whether shared caches may keep them too
+#[derive(Clone, Copy)]
+struct CachePolicy {
+    max_age: u64,
+    /// Images need a login, so proxies must not hand them to anyone else
+    private: bool,
+}
+
+/// The `Cache-Control` value for a successful response for the image at `path`
+///
+/// The webp (and avif) files are only written once, so browsers may keep them for `max_age`
+/// seconds. Everything else (i.e. the not yet minified `original`) has to be revalidated, which is
+/// cheap thanks to `Last-Modified`.
+fn cache_control_value(path: &str, policy: CachePolicy) -> HeaderValue {
+    let is_minified = path.ends_with(".webp") || path.ends_with(".avif");
+    let scope = if policy.private { "private" } else { "public" };
+    if is_minified {
+        HeaderValue::from_str(&format!("{scope}, max-age={}", policy.max_age))
+            .expect("Cache-Control is valid ascii")
+    } else if policy.private {
+        HeaderValue::from_static("private, no-cache")
+    } else {
+        HeaderValue::from_static("no-cache")
+    }
+}
+
+/// Add `Cache-Control` to successful image responses, see [`cache_control_value`]
+async fn cache_control(
+    State(policy): State<CachePolicy>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let value = cache_control_value(request.uri().path(), policy);
+    let mut response = next.run(request).await;
+    if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+        response.headers_mut().insert(CACHE_CONTROL, value);
+    };
+    response
+}
+
+/// Does the `Accept` header explicitly accept `mime_type` (with a quality above 0)?
+fn accepts(accept: &str, mime_type: &str) -> bool {
+    accept.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        parts.next() == Some(mime_type)
+            && parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .all(|q| q.parse::<f32>().map_or(true, |q| q > 0.0))
+    })
+}
+
+/// The file in `page_directory` that should be served for a request with this `Accept` header
+///
+/// - `original.avif` if it exists and the browser accepts avif
+/// - `original.webp` once the page is minified
+/// - `original` for pages that are not minified yet
+///
+/// Returns `None` if none of them exists.
+pub fn negotiate_image_file(page_directory: &Path, accept: &str) -> Option<&'static str> {
+    let exists = |name: &str| page_directory.join(name).is_file();
+    if accepts(accept, "image/avif") && exists("original.avif") {
+        Some("original.avif")
+    } else if exists("original.webp") {
+        Some("original.webp")
+    } else if exists("original") {
+        Some("original")
+    } else {
+        None
+    }
+}
+
+/// Redirect requests for `/images/<ms>/<page>` to the best image file for this browser
+///
+/// All other requests are passed on to [`ServeDir`]. The redirect is relative, so this works no
+/// matter where the router is nested.
+async fn negotiate_image_format(
+    State(data_directory): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(rest) = request
+        .uri()
+        .path()
+        .strip_prefix(IMAGE_BASE_LOCATION)
+        .and_then(|rest| rest.strip_prefix('/'))
+    else {
+        return next.run(request).await;
+    };
+    let segments = rest.split('/').collect::<Vec<_>>();
+    let [msname, pagename] = segments.as_slice() else {
+        return next.run(request).await;
+    };
+    let (Ok(decoded_msname), Ok(decoded_pagename)) =
+        (urlencoding::decode(msname), urlencoding::decode(pagename))
+    else {
+        return next.run(request).await;
+    };
+    // something that would leave the page directory (page names may contain '.', e.g. `f. 1r`)
+    if validate_manuscript_title(&decoded_msname).is_err()
+        || validate_page_name(&decoded_pagename).is_err()
+    {
+        return next.run(request).await;
+    };
+
+    let page_directory =
+        format!("{data_directory}{IMAGE_BASE_LOCATION}/{decoded_msname}/{decoded_pagename}");
+    let accept = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let mut response = match negotiate_image_file(Path::new(&page_directory), accept) {
+        Some(file) => match HeaderValue::from_str(&format!("{pagename}/{file}")) {
+            Ok(location) => {
+                (StatusCode::TEMPORARY_REDIRECT, [(LOCATION, location)]).into_response()
+            }
+            Err(_) => StatusCode::NOT_FOUND.into_response(),
+        },
+        None => StatusCode::NOT_FOUND.into_response(),
+    };
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("Accept"));
+    response
+}
+
+/// The router serving manuscript page images
+///
+/// With `require_login`, anonymous requests are rejected with 401. The auth layer must then be
+/// applied on top of this router.
+pub fn image_dir_router(
+    data_directory: &str,
+    cache_max_age_seconds: u64,
+    require_login: bool,
+) -> Result<axum::Router, std::io::Error> {
     // create the data directory if it does not exist
     if let Err(e) = create_data_directory_layout(data_directory) {
         tracing::error!("Failed to create data directory layout: {e}");
         return Err(e);
     };
     tracing::debug!("Data directory layout is correct.");
-    Ok(axum::Router::new().nest_service(
-        IMAGE_BASE_LOCATION,
-        get_service(ServeDir::new(format!(
-            "{data_directory}{IMAGE_BASE_LOCATION}"
-        ))),
-    ))
+    let router = axum::Router::new()
+        .nest_service(
+            IMAGE_BASE_LOCATION,
+            get_service(ServeDir::new(format!(
+                "{data_directory}{IMAGE_BASE_LOCATION}"
+            ))),
+        )
+        // a route layer, so the login check below runs first
+        .route_layer(axum::middleware::from_fn_with_state(
+            Arc::<str>::from(data_directory),
+            negotiate_image_format,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            CachePolicy {
+                max_age: cache_max_age_seconds,
+                private: require_login,
+            },
+            cache_control,
+        ));
+    if require_login {
+        Ok(router.route_layer(login_required!(OauthBackend)))
+    } else {
+        Ok(router)
+    }
 }
@@ -0,0 +1,178 @@
+use axum::{
+    body::Body,
+    http::{
+        header::{ACCEPT, CACHE_CONTROL, LOCATION, VARY},
+        Request, StatusCode,
+    },
+};
+use axum_login::{
+    tower_sessions::{MemoryStore, SessionManagerLayer},
+    AuthManagerLayerBuilder,
+};
+use tower::ServiceExt;
+
+use super::{cache_control_value, image_dir_router, negotiate_image_file, CachePolicy};
+use crate::auth::{github::GithubProvider, OauthBackend};
+
+/// A data directory containing a single minified page
+fn data_directory(test_name: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("critic-static-{}-{test_name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(path.join("images/ms/p1")).unwrap();
+    std::fs::write(path.join("images/ms/p1/preview.webp"), b"webp").unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// Status of an anonymous request for the page image
+async fn anonymous_status(data_directory: &str, require_login: bool) -> StatusCode {
+    // nothing is sent to the db or the oauth server for anonymous requests
+    let db = sqlx::postgres::PgPoolOptions::new()
+        .connect_lazy("postgres://critic@localhost/critic")
+        .unwrap();
+    let client = oauth2::basic::BasicClient::new(oauth2::ClientId::new("id".to_string()))
+        .set_client_secret(oauth2::ClientSecret::new("secret".to_string()))
+        .set_auth_uri(oauth2::AuthUrl::new("https://localhost/auth".to_string()).unwrap())
+        .set_token_uri(oauth2::TokenUrl::new("https://localhost/token".to_string()).unwrap())
+        .set_redirect_uri(
+            oauth2::RedirectUrl::new("https://localhost/redirect".to_string()).unwrap(),
+        );
+    let auth_layer = AuthManagerLayerBuilder::new(
+        OauthBackend::from_parts(db, client, std::sync::Arc::new(GithubProvider::new("org"))),
+        SessionManagerLayer::new(MemoryStore::default()),
+    )
+    .build();
+    image_dir_router(data_directory, 60, require_login)
+        .unwrap()
+        .layer(auth_layer)
+        .oneshot(
+            Request::builder()
+                .uri("/images/ms/p1/preview.webp")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn anonymous_image_request_is_rejected_when_login_is_required() {
+    let dir = data_directory("protected");
+    assert_eq!(anonymous_status(&dir, true).await, StatusCode::UNAUTHORIZED);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn anonymous_image_request_is_served_when_images_are_public() {
+    let dir = data_directory("public");
+    assert_eq!(anonymous_status(&dir, false).await, StatusCode::OK);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn negotiation_prefers_accepted_formats() {
+    let base = data_directory("negotiation");
+    let dir = std::path::PathBuf::from(&base).join("images/ms/p1");
+    let chrome = "image/avif,image/webp,image/apng,image/*,*/*;q=0.8";
+    // not minified yet
+    assert_eq!(negotiate_image_file(&dir, chrome), None);
+    std::fs::write(dir.join("original"), b"png").unwrap();
+    assert_eq!(negotiate_image_file(&dir, chrome), Some("original"));
+    std::fs::write(dir.join("original.webp"), b"webp").unwrap();
+    assert_eq!(negotiate_image_file(&dir, chrome), Some("original.webp"));
+    std::fs::write(dir.join("original.avif"), b"avif").unwrap();
+    assert_eq!(negotiate_image_file(&dir, chrome), Some("original.avif"));
+    // avif has to be accepted explicitly
+    assert_eq!(negotiate_image_file(&dir, "*/*"), Some("original.webp"));
+    assert_eq!(
+        negotiate_image_file(&dir, "image/avif;q=0, image/webp"),
+        Some("original.webp")
+    );
+    let _ = std::fs::remove_dir_all(&base);
+}
+
+/// Response of an anonymous request for `uri` to a router with public images
+async fn public_response(
+    data_directory: &str,
+    uri: &str,
+    accept: &str,
+) -> axum::response::Response {
+    image_dir_router(data_directory, 60, false)
+        .unwrap()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn page_url_redirects_to_best_format() {
+    let dir = data_directory("redirect");
+    std::fs::write(format!("{dir}/images/ms/p1/original.webp"), b"webp").unwrap();
+
+    let response = public_response(&dir, "/images/ms/p1", "image/avif,*/*").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers()[LOCATION], "p1/original.webp");
+    assert_eq!(response.headers()[VARY], "Accept");
+
+    // files are still served directly
+    let response = public_response(&dir, "/images/ms/p1/original.webp", "*/*").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // pages without any full size image
+    let response = public_response(&dir, "/images/ms/p2", "*/*").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // page names may contain dots
+    std::fs::create_dir_all(format!("{dir}/images/ms/f. 1r")).unwrap();
+    std::fs::write(format!("{dir}/images/ms/f. 1r/original.webp"), b"webp").unwrap();
+    let response = public_response(&dir, "/images/ms/f.%201r", "*/*").await;
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(response.headers()[LOCATION], "f.%201r/original.webp");
+
+    // but nothing leaves the page directory
+    let response = public_response(&dir, "/images/ms/..", "*/*").await;
+    assert!(response.headers().get(LOCATION).is_none());
+    let response = public_response(&dir, "/images/ms/p1%2F..", "*/*").await;
+    assert!(response.headers().get(LOCATION).is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn images_behind_a_login_are_cached_privately() {
+    let public = CachePolicy {
+        max_age: 60,
+        private: false,
+    };
+    let private = CachePolicy {
+        max_age: 60,
+        private: true,
+    };
+    for path in ["/images/ms/p1/preview.webp", "/images/ms/p1/original.avif"] {
+        assert_eq!(cache_control_value(path, public), "public, max-age=60");
+        assert_eq!(cache_control_value(path, private), "private, max-age=60");
+    }
+    assert_eq!(
+        cache_control_value("/images/ms/p1/original", public),
+        "no-cache"
+    );
+    assert_eq!(
+        cache_control_value("/images/ms/p1/original", private),
+        "private, no-cache"
+    );
+}
+
+#[tokio::test]
+async fn public_images_are_cached_publicly() {
+    let dir = data_directory("cache-control");
+    let response = public_response(&dir, "/images/ms/p1/preview.webp", "*/*").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "public, max-age=60");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -1,12 +1,22 @@
 //! Everything related to storing raw transcriptions on disk on the server.
 
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError, Weak};
 
 use critic_format::{
     denorm::NormalizationError, destream::StreamError, streamed::Block, ConversionError,
 };
 use critic_shared::urls::TRANSCRIPTION_BASE_LOCATION;
+use sha2::{Digest, Sha256};
+
+use crate::db::{
+    validate_manuscript_title, validate_page_name, validate_username, EditorInitialValue,
+};
+
+// include tests
+#[cfg(test)]
+mod test;
 
 /// Anything that can go wrong while reading or writing Transcriptions to disk
 #[derive(Debug)]
@@ -27,6 +37,12 @@ pub enum TranscriptionStoreError {
     DeNorm(NormalizationError),
     Canonicalize(String, std::io::Error),
     PathInvalid(PathBuf),
+    /// The transcription on disk changed since it was loaded - path
+    Conflict(String),
+    /// An imported transcription is for a different page - (expected, found)
+    WrongPageName(String, String),
+    /// The blocking task accessing the transcriptions failed
+    Join(tokio::task::JoinError),
 }
 impl core::fmt::Display for TranscriptionStoreError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -68,30 +84,49 @@ impl core::fmt::Display for TranscriptionStoreError {
                     path.to_string_lossy()
                 )
             }
+            Self::Conflict(path) => {
+                write!(
+                    f,
+                    "{path} was changed since it was loaded, probably in another tab or window."
+                )
+            }
+            Self::WrongPageName(expected, found) => {
+                write!(
+                    f,
+                    "The transcription is for page {found}, but was imported for page {expected}."
+                )
+            }
+            Self::Join(e) => {
+                write!(f, "Failed to wait for the transcription task: {e}")
+            }
         }
     }
 }
 impl core::error::Error for TranscriptionStoreError {}
 
-pub fn read_transcription_from_disk(
+/// Name of the directory next to the transcriptions of a page holding what was published
+///
+/// It is hidden, so that it does not show up in the history or the search for anchors.
+pub const PUBLISHED_DIRECTORY: &str = ".published";
+
+/// The directory containing all transcriptions for a page, after basic path sanitization
+fn page_directory(
     data_directory: &str,
     msname: &str,
     pagename: &str,
-    username: &str,
-    default_language: &str,
-) -> Result<(Vec<Block>, String), TranscriptionStoreError> {
+) -> Result<PathBuf, TranscriptionStoreError> {
     // basic path sanitization
-    if msname.contains('.') || msname.contains('/') {
+    if validate_manuscript_title(msname).is_err() {
         return Err(TranscriptionStoreError::PathInvalid(msname.into()));
     };
-    if pagename.contains('.') || pagename.contains('/') {
-        return Err(TranscriptionStoreError::PathInvalid(msname.into()));
+    if validate_page_name(pagename).is_err() {
+        return Err(TranscriptionStoreError::PathInvalid(pagename.into()));
     };
     let mut path = PathBuf::new();
     path.push(data_directory);
     path.push(&TRANSCRIPTION_BASE_LOCATION[1..]);
     path.push(msname);
-    path.push(&pagename);
+    path.push(pagename);
     let abspath = std::path::absolute(&path).map_err(|e| {
         TranscriptionStoreError::Canonicalize(path.to_string_lossy().to_string(), e)
     })?;
@@ -101,8 +136,112 @@ pub fn read_transcription_from_disk(
     )) {
         return Err(TranscriptionStoreError::PathInvalid(abspath));
     };
+    Ok(path)
+}
+
+/// The file holding the transcription of `username` in `directory`, after basic path sanitization
+///
+/// `directory` is a page directory or the directory of published transcriptions in one.
+fn user_file(directory: PathBuf, username: &str) -> Result<PathBuf, TranscriptionStoreError> {
+    if validate_username(username).is_err() {
+        return Err(TranscriptionStoreError::PathInvalid(username.into()));
+    };
+    let mut path = directory;
     path.push(username);
     path.set_extension("xml");
+    Ok(path)
+}
+
+/// A fingerprint of the serialized transcription, used to detect concurrent edits
+///
+/// This is the hex encoded sha256, which stays the same across toolchains and restarts.
+pub fn transcription_version(serialized: &[u8]) -> String {
+    Sha256::digest(serialized)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// One lock per transcription file that is currently written
+static FILE_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Weak<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The lock for writing the transcription at `path`
+///
+/// Holding it makes checking the version and writing the file one step for all writers in this
+/// process.
+fn file_lock(path: &Path) -> Arc<Mutex<()>> {
+    let mut locks = FILE_LOCKS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(lock) = locks.get(path).and_then(Weak::upgrade) {
+        return lock;
+    };
+    // forget the locks nobody holds anymore
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(Mutex::new(()));
+    locks.insert(path.to_path_buf(), Arc::downgrade(&lock));
+    lock
+}
+
+/// Replace the file at `path` with `content`
+///
+/// The content is written to a hidden `.part` file next to it first and then renamed, so readers
+/// never see a half written transcription. The partial file is removed on failure.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), TranscriptionStoreError> {
+    let mut part_name = std::ffi::OsString::from(".");
+    part_name.push(path.file_name().unwrap_or_default());
+    part_name.push(".part");
+    let part_path = path.with_file_name(part_name);
+    let res = std::fs::write(&part_path, content)
+        .map_err(|e| TranscriptionStoreError::Write(part_path.to_string_lossy().to_string(), e))
+        .and_then(|()| {
+            std::fs::rename(&part_path, path)
+                .map_err(|e| TranscriptionStoreError::Write(path.to_string_lossy().to_string(), e))
+        });
+    if res.is_err() {
+        let _ = std::fs::remove_file(&part_path);
+    };
+    res
+}
+
+/// The version of the transcription currently on disk, or None if there is none yet
+pub fn read_transcription_version(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<Option<String>, TranscriptionStoreError> {
+    Ok(
+        read_raw_transcription(data_directory, msname, pagename, username)?
+            .map(|content| transcription_version(&content)),
+    )
+}
+
+/// The transcription exactly as it is stored on disk, or None if there is none
+pub fn read_raw_transcription(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<Option<Vec<u8>>, TranscriptionStoreError> {
+    let path = user_file(page_directory(data_directory, msname, pagename)?, username)?;
+    match std::fs::read(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(TranscriptionStoreError::Open(
+            path.to_string_lossy().to_string(),
+            e,
+        )),
+    }
+}
+
+pub fn read_transcription_from_disk(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+    default_language: &str,
+) -> Result<(Vec<Block>, String), TranscriptionStoreError> {
+    let path = user_file(page_directory(data_directory, msname, pagename)?, username)?;
     let file = match std::fs::File::open(&path) {
         Ok(x) => x,
         Err(e) => {
@@ -113,9 +252,173 @@ pub fn read_transcription_from_disk(
         }
     };
     let buf_reader = std::io::BufReader::new(file);
-    critic_format::page_from_xml(buf_reader, default_language).map_err(|e| match e {
+    parse_transcription(buf_reader, default_language, &path.to_string_lossy())
+}
+
+/// Where the transcription `username` published for this page is kept
+fn published_path(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<PathBuf, TranscriptionStoreError> {
+    let mut directory = page_directory(data_directory, msname, pagename)?;
+    directory.push(PUBLISHED_DIRECTORY);
+    user_file(directory, username)
+}
+
+/// Keep the transcription of `username` as it is on disk now as the one they published
+///
+/// Saves after publishing only change the transcription being edited, not what is shown as
+/// published. Fails if there is no transcription or it cannot be read back.
+pub fn snapshot_published_transcription(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+    default_language: &str,
+) -> Result<(), TranscriptionStoreError> {
+    let path = published_path(data_directory, msname, pagename, username)?;
+    let Some(content) = read_raw_transcription(data_directory, msname, pagename, username)? else {
+        return Err(TranscriptionStoreError::Open(
+            format!("/{msname}/{pagename}/{username}"),
+            std::io::ErrorKind::NotFound.into(),
+        ));
+    };
+    parse_transcription(
+        content.as_slice(),
+        default_language,
+        &format!("/{msname}/{pagename}/{username}"),
+    )?;
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).map_err(|e| {
+            TranscriptionStoreError::CreateDir(directory.to_string_lossy().to_string(), e)
+        })?;
+    };
+    write_atomically(&path, &content)
+}
+
+/// The transcription `username` published for this page, see
+/// [`snapshot_published_transcription`]
+///
+/// Transcriptions published before snapshots were kept have none, for them this is the
+/// transcription on disk.
+pub fn read_published_transcription(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+    default_language: &str,
+) -> Result<(Vec<Block>, String), TranscriptionStoreError> {
+    let path = published_path(data_directory, msname, pagename, username)?;
+    match std::fs::File::open(&path) {
+        Ok(file) => parse_transcription(
+            std::io::BufReader::new(file),
+            default_language,
+            &path.to_string_lossy(),
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => read_transcription_from_disk(
+            data_directory,
+            msname,
+            pagename,
+            username,
+            default_language,
+        ),
+        Err(e) => Err(TranscriptionStoreError::Open(
+            path.to_string_lossy().to_string(),
+            e,
+        )),
+    }
+}
+
+/// Forget what `username` published for this page, if anything
+pub fn remove_published_transcription(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), TranscriptionStoreError> {
+    let path = published_path(data_directory, msname, pagename, username)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(TranscriptionStoreError::Write(
+            path.to_string_lossy().to_string(),
+            e,
+        )),
+    }
+}
+
+/// The blocks to start a new transcription with
+///
+/// WIP - In the future, the correct part of the basetext will be found by indexing:
+/// - run OCR, find out which text is on this page
+/// - find out where the column breaks are, add the relevant basetext column-by-column
+///
+/// Until then, this only states which verses are on the page (if known).
+pub fn seed_blocks(
+    default_language: &str,
+    verse_start: Option<i64>,
+    verse_end: Option<i64>,
+) -> Vec<Block> {
+    let content = match (verse_start, verse_end) {
+        (Some(start), Some(end)) => format!("This page contains the verses {start} to {end}."),
+        (Some(start), None) => format!("This page starts with verse {start}."),
+        (None, Some(end)) => format!("This page ends with verse {end}."),
+        (None, None) => {
+            "WIP - In the future, the correct part of the basetext will automatically be put here."
+                .to_string()
+        }
+    };
+    vec![Block::Text(critic_format::streamed::Paragraph {
+        lang: default_language.to_string(),
+        content,
+    })]
+}
+
+/// The blocks `username` starts editing this page with, and the version they are based on
+///
+/// This is their transcription on disk, or the seed from [`seed_blocks`] with no version if they
+/// have not saved one yet. `initial` is what the db knows about the page.
+pub fn load_for_editor(
+    data_directory: &str,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+    initial: &EditorInitialValue,
+) -> Result<(Vec<Block>, Option<String>), TranscriptionStoreError> {
+    let default_language = &initial.meta.lang;
+    // get the version first - if the file changes while reading, the next save conflicts
+    let version = read_transcription_version(data_directory, msname, pagename, username)?;
+    match read_transcription_from_disk(data_directory, msname, pagename, username, default_language)
+    {
+        Ok((blocks, _pagename)) => Ok((blocks, version)),
+        // nothing saved yet
+        Err(TranscriptionStoreError::Open(_, e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            // expected for pages started from the todo list
+            if initial.user_has_started {
+                tracing::debug!(
+                    "Transcription /{msname}/{pagename}/{username} is in the db but not on disk, starting from scratch."
+                );
+            };
+            Ok((
+                seed_blocks(default_language, initial.verse_start, initial.verse_end),
+                None,
+            ))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse a transcription in the critic format, `source` names where it came from in errors
+fn parse_transcription(
+    reader: impl std::io::BufRead,
+    default_language: &str,
+    source: &str,
+) -> Result<(Vec<Block>, String), TranscriptionStoreError> {
+    critic_format::page_from_xml(reader, default_language).map_err(|e| match e {
         ConversionError::DeSer(de_err) => {
-            TranscriptionStoreError::Deser(path.to_string_lossy().to_string(), de_err)
+            TranscriptionStoreError::Deser(source.to_string(), de_err)
         }
         ConversionError::Norm(norm_err) => TranscriptionStoreError::Norm(norm_err),
         ConversionError::Stream(stream_err) => TranscriptionStoreError::Stream(stream_err),
@@ -125,48 +428,61 @@ pub fn read_transcription_from_disk(
     })
 }
 
-/// We have already checked that we really want to save this transcription data.
-/// Write it to disk.
-pub fn write_transcription_to_disk(
-    data: Vec<Block>,
+/// Store a transcription that was written outside of critic, e.g. before a project moved here
+///
+/// `xml` has to be a valid page in the critic format named `pagename`. `source` (usually the file
+/// name) is used in error messages. An existing transcription by `username` is never overwritten,
+/// that fails with [`TranscriptionStoreError::Conflict`].
+///
+/// Returns the version of the newly written transcription.
+pub fn import_transcription(
+    xml: &[u8],
+    source: &str,
     data_directory: &str,
     msname: &str,
-    pagename: String,
+    pagename: &str,
     username: &str,
-) -> Result<(), TranscriptionStoreError> {
-    // basic path sanitization
-    if msname.contains('.') || msname.contains('/') {
-        return Err(TranscriptionStoreError::PathInvalid(msname.into()));
-    };
-    if pagename.contains('.') || pagename.contains('/') {
-        return Err(TranscriptionStoreError::PathInvalid(msname.into()));
+    default_language: &str,
+) -> Result<String, TranscriptionStoreError> {
+    let (blocks, name) = parse_transcription(xml, default_language, source)?;
+    if name != pagename {
+        return Err(TranscriptionStoreError::WrongPageName(
+            pagename.to_string(),
+            name,
+        ));
     };
+    write_transcription_to_disk(
+        blocks,
+        data_directory,
+        msname,
+        pagename.to_string(),
+        username,
+        None,
+    )
+}
+
+/// Check whether any transcription on disk contains an anchor in the versification scheme with this
+/// `shorthand`
+pub fn versification_scheme_in_use(
+    data_directory: &str,
+    shorthand: &str,
+) -> Result<bool, TranscriptionStoreError> {
     let mut path = PathBuf::new();
     path.push(data_directory);
     path.push(&TRANSCRIPTION_BASE_LOCATION[1..]);
-    path.push(msname);
-    path.push(&pagename);
-    let abspath = std::path::absolute(&path).map_err(|e| {
-        TranscriptionStoreError::Canonicalize(path.to_string_lossy().to_string(), e)
-    })?;
-    if !abspath.to_string_lossy().contains(&format!(
-        "{}{}/{}/{}",
-        data_directory, TRANSCRIPTION_BASE_LOCATION, msname, pagename
-    )) {
-        return Err(TranscriptionStoreError::PathInvalid(abspath));
-    };
-    std::fs::create_dir_all(&path)
-        .map_err(|e| TranscriptionStoreError::CreateDir(path.to_string_lossy().to_string(), e))?;
-    path.push(username);
-    path.set_extension("xml");
-    let mut file = match std::fs::OpenOptions::new()
-        .read(false)
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&path)
-    {
+    // anchor ids look like A_V_<shorthand>_<verse>
+    let needle = format!("A_V_{shorthand}_");
+    dir_contains(&path, &needle)
+}
+
+/// Recursively check whether any file below `path` contains `needle`
+fn dir_contains(path: &std::path::Path, needle: &str) -> Result<bool, TranscriptionStoreError> {
+    let entries = match std::fs::read_dir(path) {
         Ok(x) => x,
+        // no transcriptions were saved yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(false);
+        }
         Err(e) => {
             return Err(TranscriptionStoreError::Open(
                 path.to_string_lossy().to_string(),
@@ -174,8 +490,53 @@ pub fn write_transcription_to_disk(
             ));
         }
     };
+    for entry in entries {
+        let entry_path = entry
+            .map_err(|e| TranscriptionStoreError::Open(path.to_string_lossy().to_string(), e))?
+            .path();
+        // skip hidden entries like the history repository in `.git`
+        if entry_path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        };
+        if entry_path.is_dir() {
+            if dir_contains(&entry_path, needle)? {
+                return Ok(true);
+            };
+        } else {
+            let content = std::fs::read_to_string(&entry_path).map_err(|e| {
+                TranscriptionStoreError::Open(entry_path.to_string_lossy().to_string(), e)
+            })?;
+            if content.contains(needle) {
+                return Ok(true);
+            };
+        };
+    }
+    Ok(false)
+}
+
+/// We have already checked that we really want to save this transcription data.
+/// Write it to disk.
+///
+/// `expected_version` is the version of the transcription the user started editing from (None if
+/// they started a new one). If the file on disk has a different version, it was changed in the
+/// meantime and [`TranscriptionStoreError::Conflict`] is returned instead of overwriting it.
+///
+/// Returns the version of the newly written transcription.
+pub fn write_transcription_to_disk(
+    data: Vec<Block>,
+    data_directory: &str,
+    msname: &str,
+    pagename: String,
+    username: &str,
+    expected_version: Option<&str>,
+) -> Result<String, TranscriptionStoreError> {
+    let directory = page_directory(data_directory, msname, &pagename)?;
+    let path = user_file(directory.clone(), username)?;
 
-    let sr = critic_format::page_to_xml(data, pagename).map_err(|e| match e {
+    let sr = critic_format::page_to_xml(data, pagename.clone()).map_err(|e| match e {
         ConversionError::Ser(e) => TranscriptionStoreError::Ser(e),
         ConversionError::DeNorm(e) => TranscriptionStoreError::DeNorm(e),
         ConversionError::DeStream(e) => TranscriptionStoreError::DeStream(e),
@@ -184,7 +545,19 @@ pub fn write_transcription_to_disk(
         }
     })?;
 
-    file.write(sr.as_bytes())
-        .map(|_| ())
-        .map_err(|e| TranscriptionStoreError::Write(path.to_string_lossy().to_string(), e))
+    // nobody else may write this file between checking the version and replacing it
+    let lock = file_lock(&path);
+    let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+    let current_version = read_transcription_version(data_directory, msname, &pagename, username)?;
+    if current_version.as_deref() != expected_version {
+        return Err(TranscriptionStoreError::Conflict(
+            path.to_string_lossy().to_string(),
+        ));
+    };
+
+    std::fs::create_dir_all(&directory).map_err(|e| {
+        TranscriptionStoreError::CreateDir(directory.to_string_lossy().to_string(), e)
+    })?;
+    write_atomically(&path, sr.as_bytes())?;
+    Ok(transcription_version(sr.as_bytes()))
 }
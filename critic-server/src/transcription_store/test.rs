@@ -0,0 +1,356 @@
+use critic_format::streamed::{Block, BlockType, FromTypeLangAndContent};
+
+use critic_shared::ManuscriptMeta;
+
+use super::{
+    import_transcription, load_for_editor, read_published_transcription, read_raw_transcription,
+    read_transcription_from_disk, read_transcription_version, remove_published_transcription,
+    seed_blocks, snapshot_published_transcription, transcription_version,
+    write_transcription_to_disk, TranscriptionStoreError,
+};
+use crate::db::EditorInitialValue;
+
+/// A fresh data directory for a single test
+fn data_directory(test_name: &str) -> String {
+    let path = std::env::temp_dir().join(format!(
+        "critic-transcription-store-{}-{test_name}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&path);
+    path.to_string_lossy().to_string()
+}
+
+fn blocks(content: &str) -> Vec<Block> {
+    vec![Block::from_type_lang_and_content(
+        BlockType::Text,
+        "hbo-Hebr".to_string(),
+        content.to_string(),
+    )]
+}
+
+#[test]
+fn missing_transcription_has_no_version() {
+    let dir = data_directory("missing");
+    assert_eq!(
+        read_transcription_version(&dir, "ms", "p1", "user").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn paths_cannot_leave_the_data_directory() {
+    let dir = data_directory("traversal");
+    for (msname, pagename) in [
+        ("..", "p1"),
+        ("ms", ".."),
+        ("ms", "../../etc"),
+        ("ms/..", "p1"),
+        ("ms", "p1\0"),
+    ] {
+        assert!(
+            matches!(
+                read_transcription_version(&dir, msname, pagename, "user"),
+                Err(TranscriptionStoreError::PathInvalid(_))
+            ),
+            "{msname:?}/{pagename:?} was accepted"
+        );
+        assert!(matches!(
+            write_transcription_to_disk(
+                blocks("a"),
+                &dir,
+                msname,
+                pagename.to_string(),
+                "user",
+                None
+            ),
+            Err(TranscriptionStoreError::PathInvalid(_))
+        ));
+    }
+    for username in ["..", "../user", "a/b", ".published", ""] {
+        assert!(
+            matches!(
+                read_transcription_version(&dir, "ms", "p1", username),
+                Err(TranscriptionStoreError::PathInvalid(_))
+            ),
+            "{username:?} was accepted"
+        );
+        assert!(matches!(
+            write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), username, None),
+            Err(TranscriptionStoreError::PathInvalid(_))
+        ));
+        assert!(matches!(
+            read_published_transcription(&dir, "ms", "p1", username, "hbo-Hebr"),
+            Err(TranscriptionStoreError::PathInvalid(_))
+        ));
+    }
+    // dots are fine as long as they cannot point anywhere else
+    assert_eq!(
+        read_transcription_version(&dir, "v.1", "f. 1r", "user").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn saving_from_current_version() {
+    let dir = data_directory("current");
+    let first =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    assert_eq!(
+        read_transcription_version(&dir, "ms", "p1", "user").unwrap(),
+        Some(first.clone())
+    );
+    let second = write_transcription_to_disk(
+        blocks("b"),
+        &dir,
+        "ms",
+        "p1".to_string(),
+        "user",
+        Some(&first),
+    )
+    .unwrap();
+    assert_ne!(first, second);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn raw_transcription_is_the_stored_file() {
+    let dir = data_directory("raw");
+    assert_eq!(
+        read_raw_transcription(&dir, "ms", "p1", "user").unwrap(),
+        None
+    );
+    let version =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    let raw = read_raw_transcription(&dir, "ms", "p1", "user")
+        .unwrap()
+        .unwrap();
+    assert_eq!(transcription_version(&raw), version);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn published_transcription_does_not_change_with_later_saves() {
+    let dir = data_directory("published");
+    assert!(snapshot_published_transcription(&dir, "ms", "p1", "user", "hbo-Hebr").is_err());
+    let version =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    // published before snapshots were kept
+    assert_eq!(
+        read_published_transcription(&dir, "ms", "p1", "user", "hbo-Hebr")
+            .unwrap()
+            .0,
+        blocks("a")
+    );
+    snapshot_published_transcription(&dir, "ms", "p1", "user", "hbo-Hebr").unwrap();
+    write_transcription_to_disk(
+        blocks("b"),
+        &dir,
+        "ms",
+        "p1".to_string(),
+        "user",
+        Some(&version),
+    )
+    .unwrap();
+    assert_eq!(
+        read_published_transcription(&dir, "ms", "p1", "user", "hbo-Hebr")
+            .unwrap()
+            .0,
+        blocks("a")
+    );
+    remove_published_transcription(&dir, "ms", "p1", "user").unwrap();
+    remove_published_transcription(&dir, "ms", "p1", "user").unwrap();
+    assert_eq!(
+        read_published_transcription(&dir, "ms", "p1", "user", "hbo-Hebr")
+            .unwrap()
+            .0,
+        blocks("b")
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn version_is_hex_sha256() {
+    assert_eq!(
+        transcription_version(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn saving_leaves_no_partial_file() {
+    let dir = data_directory("partial");
+    write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None).unwrap();
+    let files: Vec<_> = std::fs::read_dir(format!("{dir}/transcriptions/ms/p1"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(files, vec!["user.xml".to_string()]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn concurrent_saves_from_the_same_version_conflict() {
+    let dir = data_directory("concurrent");
+    let first =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|idx| {
+                let (dir, first) = (&dir, &first);
+                scope.spawn(move || {
+                    write_transcription_to_disk(
+                        blocks(&format!("tab {idx}")),
+                        dir,
+                        "ms",
+                        "p1".to_string(),
+                        "user",
+                        Some(first),
+                    )
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    // exactly one tab wins, all others are told about the conflict
+    assert_eq!(results.iter().filter(|res| res.is_ok()).count(), 1);
+    assert!(results
+        .iter()
+        .filter_map(|res| res.as_ref().err())
+        .all(|e| matches!(e, TranscriptionStoreError::Conflict(_))));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn saving_from_stale_version_conflicts() {
+    let dir = data_directory("stale");
+    let first =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    // another tab saves in the meantime
+    write_transcription_to_disk(
+        blocks("b"),
+        &dir,
+        "ms",
+        "p1".to_string(),
+        "user",
+        Some(&first),
+    )
+    .unwrap();
+    assert!(matches!(
+        write_transcription_to_disk(
+            blocks("c"),
+            &dir,
+            "ms",
+            "p1".to_string(),
+            "user",
+            Some(&first),
+        ),
+        Err(TranscriptionStoreError::Conflict(_))
+    ));
+    // a new transcription must not overwrite one that was created in the meantime
+    assert!(matches!(
+        write_transcription_to_disk(blocks("d"), &dir, "ms", "p1".to_string(), "user", None),
+        Err(TranscriptionStoreError::Conflict(_))
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn importing_transcriptions() {
+    let dir = data_directory("import");
+    let xml = critic_format::page_to_xml(blocks("a"), "p1".to_string()).unwrap();
+    assert!(matches!(
+        import_transcription(xml.as_bytes(), "p2.xml", &dir, "ms", "p2", "user", "hbo-Hebr"),
+        Err(TranscriptionStoreError::WrongPageName(expected, found)) if expected == "p2" && found == "p1"
+    ));
+    assert!(matches!(
+        import_transcription(b"<not-a-page>", "p1.xml", &dir, "ms", "p1", "user", "hbo-Hebr"),
+        Err(TranscriptionStoreError::Deser(source, _)) if source == "p1.xml"
+    ));
+    let version = import_transcription(
+        xml.as_bytes(),
+        "p1.xml",
+        &dir,
+        "ms",
+        "p1",
+        "user",
+        "hbo-Hebr",
+    )
+    .unwrap();
+    assert_eq!(
+        read_transcription_version(&dir, "ms", "p1", "user").unwrap(),
+        Some(version)
+    );
+    assert_eq!(
+        read_transcription_from_disk(&dir, "ms", "p1", "user", "hbo-Hebr")
+            .unwrap()
+            .0,
+        blocks("a")
+    );
+    // existing work is never overwritten by an import
+    assert!(matches!(
+        import_transcription(
+            xml.as_bytes(),
+            "p1.xml",
+            &dir,
+            "ms",
+            "p1",
+            "user",
+            "hbo-Hebr"
+        ),
+        Err(TranscriptionStoreError::Conflict(_))
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn initial_value(user_has_started: bool) -> EditorInitialValue {
+    EditorInitialValue {
+        meta: ManuscriptMeta {
+            id: 1,
+            title: "ms".to_string(),
+            institution: None,
+            collection: None,
+            hand_desc: None,
+            script_desc: None,
+            lang: "hbo-Hebr".to_string(),
+            date: None,
+            origin: None,
+            version: 0,
+            cover_page: None,
+            cover_minified: false,
+            updated_at: String::new(),
+            public: false,
+        },
+        user_has_started,
+        verse_start: Some(1001001),
+        verse_end: Some(1001005),
+        placeholder: None,
+        minified: true,
+        minification_failed: false,
+    }
+}
+
+#[test]
+fn editor_starts_with_saved_blocks_or_seed() {
+    let dir = data_directory("editor");
+    let (seeded, version) =
+        load_for_editor(&dir, "ms", "p1", "user", &initial_value(false)).unwrap();
+    assert_eq!(
+        seeded,
+        seed_blocks("hbo-Hebr", Some(1001001), Some(1001005))
+    );
+    assert_eq!(version, None);
+
+    let saved =
+        write_transcription_to_disk(blocks("a"), &dir, "ms", "p1".to_string(), "user", None)
+            .unwrap();
+    let (loaded, version) =
+        load_for_editor(&dir, "ms", "p1", "user", &initial_value(true)).unwrap();
+    assert_eq!(loaded, blocks("a"));
+    assert_eq!(version, Some(saved));
+    let _ = std::fs::remove_dir_all(&dir);
+}
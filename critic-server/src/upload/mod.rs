@@ -1,18 +1,179 @@
 //! Endpoints for uploading stuff to the server
 
-use std::sync::Arc;
+use std::{
+    io::{Cursor, Read},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use axum::{
-    extract::{DefaultBodyLimit, Multipart, Path},
-    response::IntoResponse,
+    body::Bytes,
+    extract::{multipart::Field, DefaultBodyLimit, Multipart, Path},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
 use critic_shared::{
-    urls::IMAGE_BASE_LOCATION, FileTransferResponse, ALLOWED_IMAGE_EXTENSIONS, MAX_BODY_SIZE,
+    urls::IMAGE_BASE_LOCATION, ConversionFailure, ConversionStage, FileTransferResponse,
+    ARCHIVE_EXTENSION, MAX_BODY_SIZE, PDF_EXTENSION, TRANSCRIPTION_EXTENSION,
 };
+use image::{ImageFormat, ImageReader};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
 use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::{
+    audit::record_audit,
+    auth::{user_may, Action, AuthSession, AuthenticatedUser},
+    config::Config,
+    db::{
+        add_transcription, add_uploaded_page, delete_page, page_exists, user_exists,
+        validate_page_name, validate_username, DBError,
+    },
+    monitoring::{UPLOAD_BYTES, UPLOAD_FAILURES},
+    transcription_store::{import_transcription, TranscriptionStoreError},
+};
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// How many uploads are currently being processed
+static UPLOADS_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+/// Cleared once the server starts shutting down, new uploads are refused from then on
+static ACCEPTING_UPLOADS: AtomicBool = AtomicBool::new(true);
+
+/// Marks an upload as in flight for as long as it is held, so shutdown waits for it
+struct UploadInFlight;
+impl UploadInFlight {
+    /// Register a new upload, or get the response to send if we are already shutting down
+    fn start() -> Result<Self, Response> {
+        if !ACCEPTING_UPLOADS.load(Ordering::SeqCst) {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The server is shutting down, please retry the upload later.",
+            )
+                .into_response());
+        };
+        UPLOADS_IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        Ok(Self)
+    }
+}
+impl Drop for UploadInFlight {
+    fn drop(&mut self) {
+        UPLOADS_IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Stop accepting new uploads and wait until all running uploads are done, or `max_wait` elapsed
+///
+/// Returns the number of uploads still running when giving up.
+pub async fn drain_uploads(max_wait: Duration) -> usize {
+    ACCEPTING_UPLOADS.store(false, Ordering::SeqCst);
+    let start = Instant::now();
+    loop {
+        let in_flight = UPLOADS_IN_FLIGHT.load(Ordering::SeqCst);
+        if in_flight == 0 || start.elapsed() >= max_wait {
+            return in_flight;
+        };
+        tracing::debug!("Waiting for {in_flight} uploads to finish before shutting down.");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Write `data` as the `original` file in `directory_path`
+///
+/// The data is written to `original.part` first and only renamed once complete, so an interrupted
+/// write never leaves a truncated `original` behind. The partial file is removed on failure.
+fn write_original(directory_path: &str, data: &[u8]) -> Result<(), std::io::Error> {
+    let part_path = format!("{directory_path}/original.part");
+    let res = std::fs::write(&part_path, data)
+        .and_then(|()| std::fs::rename(&part_path, format!("{directory_path}/original")));
+    if res.is_err() {
+        let _ = std::fs::remove_file(&part_path);
+    };
+    res
+}
+
+/// Reasons an uploaded file is not accepted as a page image
+#[derive(Debug)]
+pub enum ImageValidationError {
+    /// The file is empty
+    Empty,
+    /// The image format cannot be guessed from the content
+    GuessFormat(std::io::Error),
+    /// The content is an image of a different format than the extension says - (extension, format)
+    FormatMismatch(String, ImageFormat),
+    /// The image header cannot be decoded
+    Decode(image::ImageError),
+    /// The image is smaller then the configured minimum - (width, height, minimum)
+    TooSmall(u32, u32, u32),
+}
+impl core::fmt::Display for ImageValidationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Empty => {
+                write!(f, "The file is empty")
+            }
+            Self::GuessFormat(e) => {
+                write!(f, "Cannot guess the image format: {e}")
+            }
+            Self::FormatMismatch(extension, format) => {
+                write!(
+                    f,
+                    "The file name ends in .{extension}, but the file is a {format:?} image"
+                )
+            }
+            Self::Decode(e) => {
+                write!(f, "The file is not a readable image: {e}")
+            }
+            Self::TooSmall(width, height, min) => {
+                write!(
+                    f,
+                    "The image is {width}x{height} pixels, but must be at least {min}x{min} pixels"
+                )
+            }
+        }
+    }
+}
+impl core::error::Error for ImageValidationError {}
 
-use crate::{auth::AuthSession, config::Config, db::add_page, github::user_is_member};
+/// Make sure that `data` is a decodable image of at least `min_dimension` pixels in each direction
+///
+/// The format is detected from the content (not the file name) and has to be the one `extension`
+/// stands for. This only decodes the image header, the full decode happens during minification.
+/// Returns the dimensions of the image.
+pub fn validate_image(
+    data: &[u8],
+    extension: &str,
+    min_dimension: u32,
+) -> Result<(u32, u32), ImageValidationError> {
+    if data.is_empty() {
+        return Err(ImageValidationError::Empty);
+    };
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(ImageValidationError::GuessFormat)?;
+    // content that is no known image at all fails to decode below
+    if let Some(format) = reader.format() {
+        if ImageFormat::from_extension(extension) != Some(format) {
+            return Err(ImageValidationError::FormatMismatch(
+                extension.to_string(),
+                format,
+            ));
+        };
+    };
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(ImageValidationError::Decode)?;
+    if width < min_dimension || height < min_dimension {
+        return Err(ImageValidationError::TooSmall(width, height, min_dimension));
+    };
+    Ok((width, height))
+}
 
 /// The router handling all file uploads
 pub fn upload_router() -> axum::Router {
@@ -24,9 +185,234 @@ pub fn upload_router() -> axum::Router {
             ),
             axum::routing::post(page_upload),
         )
+        .route(
+            &format!(
+                "{}/{{msname}}",
+                critic_shared::urls::ARCHIVE_UPLOAD_API_ENDPOINT
+            ),
+            axum::routing::post(archive_upload),
+        )
+        .route(
+            &format!(
+                "{}/{{msname}}",
+                critic_shared::urls::PDF_UPLOAD_API_ENDPOINT
+            ),
+            axum::routing::post(pdf_upload),
+        )
+        .route(
+            &format!(
+                "{}/{{msname}}/{{username}}",
+                critic_shared::urls::TRANSCRIPTION_UPLOAD_API_ENDPOINT
+            ),
+            axum::routing::post(transcription_upload),
+        )
+        .route(
+            &format!(
+                "{}/{{msname}}/{{username}}",
+                critic_shared::urls::TRANSCRIPTION_ARCHIVE_UPLOAD_API_ENDPOINT
+            ),
+            axum::routing::post(transcription_archive_upload),
+        )
         .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
 }
 
+/// Read the complete content of a multipart field, but at most `max_size` bytes
+///
+/// The field is read chunk by chunk and abandoned as soon as it grows past `max_size`, so a single
+/// oversized file never has to fit into memory.
+/// Fails with a message for the [`FileTransferResponse`] when the body cannot be read, e.g. because
+/// the client aborted the upload mid-field.
+async fn read_field_data(mut field: Field<'_>, max_size: usize) -> Result<Bytes, String> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read the file from the request: {e}."))?
+    {
+        if data.len() + chunk.len() > max_size {
+            return Err(format!(
+                "{} is larger than the allowed {max_size} bytes.",
+                field.file_name().unwrap_or("The file")
+            ));
+        };
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data.into())
+}
+
+/// What happened to an uploaded page image that was not rejected
+#[derive(Debug, PartialEq, Eq)]
+enum StoreOutcome {
+    Saved,
+    /// The manuscript already has this image, so it was not saved again - message for the user
+    Duplicate(String),
+}
+
+/// The SHA-256 of `data` as lowercase hex, used to recognize images uploaded twice
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Add a page to the db and write its image as `original` to the data directory
+///
+/// Images the manuscript already has are skipped. The page counts towards the upload quota of
+/// `username`; files over the quota are not saved.
+/// When writing the file fails, the page is removed from the db again, so the upload can simply be
+/// retried. Successful uploads are recorded in the audit log for `username`.
+async fn store_page(
+    config: &Config,
+    username: &str,
+    msname: &str,
+    base_name: &str,
+    data: &[u8],
+) -> Result<StoreOutcome, String> {
+    // the page name becomes a directory name, so it must not point anywhere else
+    if let Err(e) = validate_page_name(base_name) {
+        tracing::warn!("{username} tried to upload {base_name:?} for {msname}: {e}");
+        return Err(format!("{base_name:?} cannot be used as a page: {e}"));
+    };
+    // try insert into the DB first
+    match add_uploaded_page(
+        &config.db,
+        base_name,
+        msname,
+        username,
+        data.len() as u64,
+        &content_hash(data),
+        &config.upload_quota,
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(e @ DBError::DuplicatePageImage(_)) => {
+            tracing::info!("{username} uploaded {base_name} for {msname} again: {e}");
+            return Ok(StoreOutcome::Duplicate(format!(
+                "{base_name} was skipped: {e}"
+            )));
+        }
+        Err(e @ DBError::UploadQuotaExceeded(_)) => {
+            tracing::info!(
+                "{username} is over the upload quota, not saving {base_name} for {msname}."
+            );
+            return Err(format!("{e} {base_name} was not saved."));
+        }
+        Err(e) => {
+            tracing::warn!("Failed to insert new page {base_name} for {msname} into the db: {e}");
+            return Err(format!("Failed to insert new page into the db: {e}."));
+        }
+    };
+    // the first page becomes the cover
+    config.manuscript_cache.invalidate(msname);
+    // that worked - now deal with the file system
+    let directory_path = format!(
+        "{}{}/{msname}/{base_name}",
+        config.data_directory, IMAGE_BASE_LOCATION
+    );
+    let fs_res = match std::fs::create_dir_all(&directory_path) {
+        Ok(()) => write_original(&directory_path, data).map_err(|e| {
+            tracing::warn!("Unable to write manuscript page to file: {e}");
+            "Failed to write Page to file.".to_string()
+        }),
+        Err(e) => Err(format!(
+            "Failed to crate directory to put new page into: {e}."
+        )),
+    };
+    if fs_res.is_ok() {
+        metrics::counter!(UPLOAD_BYTES).increment(data.len() as u64);
+        record_audit(
+            &config.db,
+            username,
+            "upload_page",
+            &format!("{msname}/{base_name}"),
+            serde_json::json!({ "bytes": data.len() }),
+        )
+        .await;
+    };
+    if fs_res.is_err() {
+        // do not leave a page without an image in the db
+        if let Err(e) =
+            delete_page(&config.db, &config.data_directory, base_name, msname, false).await
+        {
+            tracing::error!(
+                "Failed to remove page {base_name} for {msname} from the db after the image could not be saved: {e}"
+            );
+        };
+        config.manuscript_cache.invalidate(msname);
+    };
+    fs_res.map(|()| StoreOutcome::Saved)
+}
+
+/// Make sure the user of this session may do `action`
+///
+/// Returns the response to send instead if they may not.
+async fn authorize_session(
+    config: Arc<Config>,
+    auth_session: AuthSession,
+    action: Action,
+) -> Result<AuthenticatedUser, Response> {
+    let Some(user) = auth_session.user else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+    match user_may(config, &user, action).await {
+        Ok(true) => Ok(user),
+        Ok(false) => Err(StatusCode::UNAUTHORIZED.into_response()),
+        Err(e) => {
+            tracing::warn!("Unable to get user membership for {}: {e}", user.username);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+        }
+    }
+}
+
+/// Split a file name into base name and extension
+///
+/// The file name has to contain exactly one dot, and the extension has to be in `allowed_extensions`.
+fn split_file_name(
+    file_name: &str,
+    allowed_extensions: &[impl AsRef<str>],
+) -> Result<(String, String), String> {
+    let mut dot_split = file_name.split('.');
+    let base_name = match dot_split.next() {
+        Some(x) => x.to_string(),
+        None => {
+            return Err("Filename did not contain a basename.".to_string());
+        }
+    };
+    let extension = match dot_split.next() {
+        Some(x) => x.to_string(),
+        None => {
+            return Err("Filename did not contain an extension.".to_string());
+        }
+    };
+    if !allowed_extensions
+        .iter()
+        .any(|allowed| allowed.as_ref() == extension)
+    {
+        return Err("Extension is not allowed.".to_string());
+    };
+    if dot_split.next().is_some() {
+        return Err("Filename did not contain exactly one dot.".to_string());
+    };
+    Ok((base_name, extension))
+}
+
+/// Turn the collected results into the response for the client, counting the failed files
+fn transfer_response(results: FileTransferResponse) -> Response {
+    metrics::counter!(UPLOAD_FAILURES)
+        .increment(results.err.iter().filter(|e| e.is_some()).count() as u64);
+    (
+        if results.err.iter().all(|e| e.is_none()) {
+            StatusCode::OK
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        },
+        Json(results),
+    )
+        .into_response()
+}
+
 /// Upload several pages for a manuscript
 pub async fn page_upload(
     Extension(config): Extension<Arc<Config>>,
@@ -34,18 +420,16 @@ pub async fn page_upload(
     auth_session: AuthSession,
     mut mpart: Multipart,
 ) -> impl IntoResponse {
-    let Some(user) = auth_session.user else {
-        return StatusCode::UNAUTHORIZED.into_response();
+    let user = match authorize_session(config.clone(), auth_session, Action::Upload).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
     };
-    match user_is_member(config.clone(), &user).await {
-        Ok(true) => {}
-        Ok(false) => return StatusCode::UNAUTHORIZED.into_response(),
-        Err(e) => {
-            tracing::warn!(
-                "Unable to get github user membership for {}: {e}",
-                user.username
-            );
-            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    let _in_flight = match UploadInFlight::start() {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
         }
     };
 
@@ -58,61 +442,758 @@ pub async fn page_upload(
                     results.push_err("The file name must be set for each file.".to_string());
                     continue;
                 };
-                let mut dot_split = file_name.split('.');
-                let base_name = match dot_split.next() {
-                    Some(x) => x.to_string(),
-                    None => {
-                        results.push_err("Filename did not contain a basename.".to_string());
+                let (base_name, extension) =
+                    match split_file_name(file_name, &config.allowed_image_extensions) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            results.push_err(e);
+                            continue;
+                        }
+                    };
+
+                let data = match read_field_data(field, config.max_image_size).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed reading page {base_name}.{extension} for {msname} from {}: {e}",
+                            user.username
+                        );
+                        results.push_err(e);
                         continue;
                     }
                 };
-                let extension = match dot_split.next() {
-                    Some(x) => x.to_string(),
-                    None => {
-                        results.push_err("Filename did not contain an extension.".to_string());
-                        continue;
+
+                // validate before touching the DB, so a bad file never leaves a page row behind
+                if let Err(e) = validate_image(&data, &extension, config.min_image_dimension) {
+                    tracing::info!(
+                        "{} uploaded invalid page image {base_name}.{extension} for {msname}: {e}",
+                        user.username
+                    );
+                    results.push_err(format!("{e}."));
+                    continue;
+                };
+
+                match store_page(&config, &user.username, &msname, &base_name, &data).await {
+                    Ok(StoreOutcome::Saved) => {
+                        tracing::info!(
+                            "{} saved new page for {msname}: {base_name}.{extension}.",
+                            user.username
+                        );
+                        results.push_ok();
                     }
+                    Ok(StoreOutcome::Duplicate(note)) => results.push_skipped(note),
+                    Err(e) => results.push_err(e),
                 };
-                if !ALLOWED_IMAGE_EXTENSIONS.contains(&extension.as_str()) {
-                    results.push_err("Extension is not allowed.".to_string());
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                tracing::warn!("Failed reading one of the multipart fields: {e}");
+                tracing::warn!("logged in user: {}", user.username);
+                // the body is broken from here on, there is nothing more we can read
+                break;
+            }
+        };
+    }
+    transfer_response(results)
+}
+
+/// How many bytes all entries of a single zip archive may expand to together
+const MAX_EXTRACTED_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Read all entries from a zip archive
+///
+/// Returns (entry name, content or error) for each file in the archive. Directory entries are
+/// skipped, entry names containing a path (or `..`) are rejected to prevent zip-slip.
+/// Entries larger than `max_entry_size` are rejected. If all entries together expand to more than
+/// `max_total_size` bytes, the whole archive is rejected, so that a zip bomb cannot exhaust memory.
+/// This is blocking and should be called from a blocking task.
+fn read_zip_entries(
+    data: &[u8],
+    max_entry_size: u64,
+    max_total_size: u64,
+) -> Result<Vec<(String, Result<Vec<u8>, String>)>, String> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| format!("The file is not a valid zip archive: {e}."))?;
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut total_size = 0_u64;
+    for idx in 0..archive.len() {
+        let mut entry = match archive.by_index(idx) {
+            Ok(x) => x,
+            Err(e) => {
+                entries.push((
+                    format!("entry {idx}"),
+                    Err(format!("Cannot read entry from the archive: {e}.")),
+                ));
+                continue;
+            }
+        };
+        if entry.is_dir() {
+            continue;
+        };
+        let name = entry.name().to_string();
+        if name.contains('/') || name.contains('\\') || name.contains("..") {
+            entries.push((
+                name,
+                Err("Entries must not be nested in directories.".to_string()),
+            ));
+            continue;
+        };
+        // never trust the size in the archive, stop decompressing one byte after the limit instead
+        let limit = max_entry_size.min(max_total_size - total_size);
+        let mut content = Vec::new();
+        if let Err(e) = (&mut entry)
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut content)
+        {
+            entries.push((name, Err(format!("Cannot decompress entry: {e}."))));
+            continue;
+        };
+        let size = content.len() as u64;
+        if size > max_entry_size {
+            entries.push((
+                name,
+                Err(format!(
+                    "The entry is larger than the limit of {max_entry_size} bytes."
+                )),
+            ));
+            continue;
+        };
+        if size > limit {
+            return Err(format!(
+                "The archive expands to more than the limit of {max_total_size} bytes."
+            ));
+        };
+        total_size += size;
+        entries.push((name, Ok(content)));
+    }
+    Ok(entries)
+}
+
+/// Upload zip archives for a manuscript, each entry in an archive becoming one page
+///
+/// The response contains one result per entry in the archives.
+pub async fn archive_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Path(msname): Path<String>,
+    auth_session: AuthSession,
+    mut mpart: Multipart,
+) -> impl IntoResponse {
+    let user = match authorize_session(config.clone(), auth_session, Action::Upload).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let _in_flight = match UploadInFlight::start() {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let mut results = FileTransferResponse::new();
+    loop {
+        match mpart.next_field().await {
+            Ok(Some(field)) => {
+                let Some(file_name) = field.file_name() else {
+                    results.push_err("The file name must be set for each file.".to_string());
                     continue;
                 };
-                if dot_split.next().is_some() {
-                    results.push_err("Filename did not contain exactly one dot.".to_string());
+                let archive_name = file_name.to_string();
+                if let Err(e) = split_file_name(&archive_name, &[ARCHIVE_EXTENSION]) {
+                    results.push_err(e);
                     continue;
                 };
+                let data = match read_field_data(field, MAX_BODY_SIZE).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed reading archive {archive_name} for {msname} from {}: {e}",
+                            user.username
+                        );
+                        results.push_err(e);
+                        continue;
+                    }
+                };
+                // decompression is blocking, do not stall the runtime with it
+                // each entry becomes a page image, so it is limited like one
+                let max_entry_size = config.max_image_size as u64;
+                let entries = match tokio::task::spawn_blocking(move || {
+                    read_zip_entries(&data, max_entry_size, MAX_EXTRACTED_ARCHIVE_SIZE)
+                })
+                .await
+                {
+                    Ok(Ok(x)) => x,
+                    Ok(Err(e)) => {
+                        results.push_err(format!("{archive_name}: {e}"));
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to join the archive extraction task: {e}");
+                        results.push_err(format!("{archive_name}: Failed to extract archive."));
+                        continue;
+                    }
+                };
+                for (entry_name, content) in entries {
+                    let content = match content {
+                        Ok(x) => x,
+                        Err(e) => {
+                            results.push_err(format!("{archive_name}/{entry_name}: {e}"));
+                            continue;
+                        }
+                    };
+                    let (base_name, extension) =
+                        match split_file_name(&entry_name, &config.allowed_image_extensions) {
+                            Ok(x) => x,
+                            Err(e) => {
+                                results.push_err(format!("{archive_name}/{entry_name}: {e}"));
+                                continue;
+                            }
+                        };
+                    if let Err(e) = validate_image(&content, &extension, config.min_image_dimension)
+                    {
+                        results.push_err(format!("{archive_name}/{entry_name}: {e}."));
+                        continue;
+                    };
+                    match store_page(&config, &user.username, &msname, &base_name, &content).await {
+                        Ok(StoreOutcome::Saved) => {
+                            tracing::info!(
+                                "{} saved new page for {msname} from {archive_name}: {entry_name}.",
+                                user.username
+                            );
+                            results.push_ok();
+                        }
+                        Ok(StoreOutcome::Duplicate(note)) => {
+                            results.push_skipped(format!("{archive_name}/{entry_name}: {note}"));
+                        }
+                        Err(e) => results.push_err(format!("{archive_name}/{entry_name}: {e}")),
+                    };
+                }
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                tracing::warn!("Failed reading one of the multipart fields: {e}");
+                tracing::warn!("logged in user: {}", user.username);
+                break;
+            }
+        };
+    }
+    transfer_response(results)
+}
 
-                let data = field.bytes().await.unwrap();
+/// The name of the page with index `idx` (counting from 0) in the PDF `pdf_stem`.pdf
+///
+/// The PDF's name is part of the page name, so that several PDFs can be uploaded to one
+/// manuscript.
+fn pdf_page_name(pdf_stem: &str, idx: usize) -> String {
+    format!("{pdf_stem}_p{:03}", idx + 1)
+}
 
-                // try insert into the DB first
-                if let Err(e) = add_page(&config.db, &base_name, &msname).await {
-                    tracing::warn!(
-                        "Failed to insert new page {base_name} for {msname} into the db: {e}"
-                    );
-                    results.push_err(format!("Failed to insert new page into the db: {e}."));
+/// Rasterize all pages of a PDF to png, sending (page name, png or error) for each page to `tx`
+///
+/// Pages are named after the PDF, see [`pdf_page_name`].
+/// This is blocking and should be called from a blocking task. Pages are rendered one after the
+/// other and only sent on when the receiver has room, so large PDFs are never held in memory as a
+/// whole.
+///
+/// Pages whose bitmap would exceed [`MAX_PDF_PAGE_PIXELS`] at `dpi` are refused without rendering.
+fn rasterize_pdf(
+    data: &[u8],
+    pdf_stem: &str,
+    dpi: u16,
+    tx: tokio::sync::mpsc::Sender<(String, Result<Vec<u8>, String>)>,
+) -> Result<(), String> {
+    let pdfium = Pdfium::new(
+        Pdfium::bind_to_system_library()
+            .map_err(|e| format!("PDF rendering is not available on this server: {e}."))?,
+    );
+    let document = pdfium
+        .load_pdf_from_byte_slice(data, None)
+        .map_err(|e| format!("The file is not a readable PDF: {e}."))?;
+    // PDF user space has 72 units per inch
+    let render_config = PdfRenderConfig::new().scale_page_by_factor(f32::from(dpi) / 72.0);
+    for (idx, page) in document.pages().iter().enumerate() {
+        let page_name = pdf_page_name(pdf_stem, idx);
+        let png_res = check_pdf_page_size(page.width().value, page.height().value, dpi)
+            .and_then(|()| {
+                page.render_with_config(&render_config)
+                    .map_err(|e| format!("Cannot render page: {e}."))
+            })
+            .and_then(|bitmap| {
+                let mut png = Cursor::new(Vec::new());
+                bitmap
+                    .as_image()
+                    .write_to(&mut png, ImageFormat::Png)
+                    .map(|_| png.into_inner())
+                    .map_err(|e| format!("Cannot encode page as png: {e}."))
+            });
+        if tx.blocking_send((page_name, png_res)).is_err() {
+            // the receiver is gone, nobody cares about the remaining pages
+            break;
+        };
+    }
+    Ok(())
+}
+
+/// How many pixels a single PDF page may have once rasterized
+///
+/// Rendering allocates an uncompressed bitmap of 4 bytes per pixel, so this caps that allocation
+/// at 400 MiB no matter what the page claims its size is.
+const MAX_PDF_PAGE_PIXELS: u64 = 100 * 1024 * 1024;
+
+/// Check that a page of `width` x `height` points rendered at `dpi` stays below
+/// [`MAX_PDF_PAGE_PIXELS`]
+fn check_pdf_page_size(width: f32, height: f32, dpi: u16) -> Result<(), String> {
+    // PDF user space has 72 units per inch
+    let scale = f64::from(dpi) / 72.0;
+    let pixels = (f64::from(width) * scale).ceil() * (f64::from(height) * scale).ceil();
+    if pixels.is_finite() && pixels >= 0.0 && pixels <= MAX_PDF_PAGE_PIXELS as f64 {
+        Ok(())
+    } else {
+        Err(format!(
+            "The page is too large to render at {dpi} dpi ({width}x{height} points), try a lower pdf_dpi."
+        ))
+    }
+}
+
+/// Upload PDFs for a manuscript, each page in a PDF becoming one page
+///
+/// The response contains one result per page in the PDFs.
+pub async fn pdf_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Path(msname): Path<String>,
+    auth_session: AuthSession,
+    mut mpart: Multipart,
+) -> impl IntoResponse {
+    let user = match authorize_session(config.clone(), auth_session, Action::Upload).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let _in_flight = match UploadInFlight::start() {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let mut results = FileTransferResponse::new();
+    loop {
+        match mpart.next_field().await {
+            Ok(Some(field)) => {
+                let Some(file_name) = field.file_name() else {
+                    results.push_err("The file name must be set for each file.".to_string());
                     continue;
+                };
+                let pdf_name = file_name.to_string();
+                let pdf_stem = match split_file_name(&pdf_name, &[PDF_EXTENSION]) {
+                    Ok((base_name, _extension)) => base_name,
+                    Err(e) => {
+                        results.push_err(e);
+                        continue;
+                    }
+                };
+                let data = match read_field_data(field, MAX_BODY_SIZE).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed reading pdf {pdf_name} for {msname} from {}: {e}",
+                            user.username
+                        );
+                        results.push_err(e);
+                        continue;
+                    }
+                };
+                // rasterizing is CPU-heavy, so it runs on a blocking task and hands over pages
+                // one by one
+                let (tx, mut rx) = tokio::sync::mpsc::channel(config.worker_threads.into());
+                let dpi = config.pdf_dpi;
+                let rasterize_handle =
+                    tokio::task::spawn_blocking(move || rasterize_pdf(&data, &pdf_stem, dpi, tx));
+                while let Some((page_name, png_res)) = rx.recv().await {
+                    let png = match png_res {
+                        Ok(x) => x,
+                        Err(e) => {
+                            results.push_err(format!("{pdf_name}/{page_name}: {e}"));
+                            continue;
+                        }
+                    };
+                    if png.len() > config.max_image_size {
+                        results.push_err(format!(
+                            "{pdf_name}/{page_name}: The rendered page is larger than the allowed {} bytes, try a lower pdf_dpi.",
+                            config.max_image_size
+                        ));
+                        continue;
+                    };
+                    match store_page(&config, &user.username, &msname, &page_name, &png).await {
+                        Ok(StoreOutcome::Saved) => {
+                            tracing::info!(
+                                "{} saved new page for {msname} from {pdf_name}: {page_name}.",
+                                user.username
+                            );
+                            results.push_ok();
+                        }
+                        Ok(StoreOutcome::Duplicate(note)) => {
+                            results.push_skipped(format!("{pdf_name}/{page_name}: {note}"));
+                        }
+                        Err(e) => results.push_err(format!("{pdf_name}/{page_name}: {e}")),
+                    };
                 }
-                // that worked - now deal with the file system
-                let directory_path = format!(
-                    "{}{}/{msname}/{base_name}",
-                    config.data_directory, IMAGE_BASE_LOCATION
-                );
-                if let Err(e) = std::fs::create_dir_all(&directory_path) {
-                    results.push_err(format!(
-                        "Failed to crate directory to put new page into: {e}."
-                    ));
+                match rasterize_handle.await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        results.push_err(format!("{pdf_name}: {e}"));
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to join the pdf rasterization task: {e}");
+                        results.push_err(format!("{pdf_name}: Failed to rasterize pdf."));
+                    }
+                };
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                tracing::warn!("Failed reading one of the multipart fields: {e}");
+                tracing::warn!("logged in user: {}", user.username);
+                break;
+            }
+        };
+    }
+    transfer_response(results)
+}
+
+/// Make sure transcriptions can be imported into `msname` for `username`
+///
+/// `username` has to have logged in before. Returns the default language of the manuscript, or the
+/// response to send instead.
+async fn prepare_transcription_import(
+    config: &Config,
+    msname: &str,
+    username: &str,
+) -> Result<String, Response> {
+    // the username becomes a file name
+    if let Err(e) = validate_username(username) {
+        return Err((StatusCode::BAD_REQUEST, format!("{username}: {e}")).into_response());
+    };
+    match user_exists(&config.db, username).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("There is no user {username}."),
+            )
+                .into_response());
+        }
+        Err(e) => {
+            tracing::warn!("Failed to look up user {username} for a transcription import: {e}");
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response());
+        }
+    };
+    match config
+        .manuscript_cache
+        .manuscript_meta(&config.db, msname)
+        .await
+    {
+        Ok(x) => Ok(x.lang),
+        Err(e @ DBError::ManuscriptDoesNotExist(_)) => {
+            Err((StatusCode::NOT_FOUND, e.to_string()).into_response())
+        }
+        Err(e) => {
+            tracing::warn!("Failed to get manuscript {msname} for a transcription import: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+        }
+    }
+}
+
+/// Why an imported transcription was not stored
+#[derive(Debug, PartialEq, Eq)]
+enum ImportError {
+    /// The file is no valid transcription in the critic format - (step that failed, reason)
+    Conversion(ConversionStage, String),
+    /// Anything else - message for the user
+    Other(String),
+}
+
+/// Add the result of importing `file` to `results`
+fn push_import_result(
+    results: &mut FileTransferResponse,
+    file: String,
+    result: Result<(), ImportError>,
+) {
+    match result {
+        Ok(()) => results.push_ok(),
+        Err(ImportError::Conversion(stage, message)) => {
+            results.push_conversion_failure(ConversionFailure {
+                file,
+                stage,
+                message,
+            });
+        }
+        Err(ImportError::Other(e)) => results.push_err(format!("{file}: {e}")),
+    };
+}
+
+/// Write an imported transcription to disk and record it in the db
+///
+/// `file_name` has to be `<page>.xml` for a page that already exists. Successful imports are
+/// recorded in the audit log for `importer`.
+async fn store_transcription(
+    config: &Config,
+    importer: &str,
+    msname: &str,
+    username: &str,
+    default_language: &str,
+    file_name: &str,
+    data: &[u8],
+) -> Result<(), ImportError> {
+    let (pagename, _) =
+        split_file_name(file_name, &[TRANSCRIPTION_EXTENSION]).map_err(ImportError::Other)?;
+    match page_exists(&config.db, msname, &pagename).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(ImportError::Other(format!(
+                "The page {pagename} does not exist in {msname}."
+            )));
+        }
+        Err(e) => {
+            tracing::warn!("Failed to check whether {msname}/{pagename} exists: {e}");
+            return Err(ImportError::Other(format!(
+                "Failed to check whether the page exists: {e}."
+            )));
+        }
+    };
+    // parsing and writing the transcription is blocking file system work
+    let import_res = {
+        let data = data.to_vec();
+        let file_name = file_name.to_string();
+        let data_directory = config.data_directory.clone();
+        let msname = msname.to_string();
+        let pagename = pagename.clone();
+        let username = username.to_string();
+        let default_language = default_language.to_string();
+        tokio::task::spawn_blocking(move || {
+            import_transcription(
+                &data,
+                &file_name,
+                &data_directory,
+                &msname,
+                &pagename,
+                &username,
+                &default_language,
+            )
+        })
+        .await
+        .map_err(TranscriptionStoreError::Join)
+        .and_then(|res| res)
+    };
+    match import_res {
+        Ok(_) => {}
+        Err(TranscriptionStoreError::Conflict(_)) => {
+            return Err(ImportError::Other(format!(
+                "{username} already has a transcription for {pagename}, it is not overwritten."
+            )));
+        }
+        Err(TranscriptionStoreError::Deser(_, e)) => {
+            return Err(ImportError::Conversion(
+                ConversionStage::Deserialize,
+                e.to_string(),
+            ));
+        }
+        Err(TranscriptionStoreError::Norm(e)) => {
+            return Err(ImportError::Conversion(
+                ConversionStage::Normalize,
+                e.to_string(),
+            ));
+        }
+        Err(TranscriptionStoreError::Stream(e)) => {
+            return Err(ImportError::Conversion(
+                ConversionStage::Stream,
+                e.to_string(),
+            ));
+        }
+        Err(e) => {
+            return Err(ImportError::Other(format!("{e}.")));
+        }
+    };
+    if let Err(e) = add_transcription(&config.db, msname, &pagename, username).await {
+        tracing::error!(
+            "Imported transcription of {msname}/{pagename} by {username}, but failed to add it to the db: {e}"
+        );
+        return Err(ImportError::Other(format!(
+            "The transcription was saved, but could not be added to the db: {e}."
+        )));
+    };
+    record_audit(
+        &config.db,
+        importer,
+        "import_transcription",
+        &format!("{msname}/{pagename}"),
+        serde_json::json!({ "transcriber": username, "bytes": data.len() }),
+    )
+    .await;
+    tracing::info!("{importer} imported the transcription of {msname}/{pagename} by {username}.");
+    Ok(())
+}
+
+/// Import existing transcriptions of `username` for pages of a manuscript
+///
+/// Each file has to be named `<page>.xml` and contain that page in the critic format.
+/// The response contains one result per file.
+pub async fn transcription_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Path((msname, username)): Path<(String, String)>,
+    auth_session: AuthSession,
+    mut mpart: Multipart,
+) -> impl IntoResponse {
+    let user = match authorize_session(config.clone(), auth_session, Action::Administer).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let _in_flight = match UploadInFlight::start() {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let default_language = match prepare_transcription_import(&config, &msname, &username).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let mut results = FileTransferResponse::new();
+    loop {
+        match mpart.next_field().await {
+            Ok(Some(field)) => {
+                let Some(file_name) = field.file_name() else {
+                    results.push_err("The file name must be set for each file.".to_string());
                     continue;
                 };
-                if let Err(e) = std::fs::write(format!("{directory_path}/original"), data) {
-                    tracing::warn!("Unable to write manuscript page to file: {e}");
-                    results.push_err("Failed to write Page to file.".to_string());
+                let file_name = file_name.to_string();
+                let data = match read_field_data(field, MAX_BODY_SIZE).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        results.push_err(format!("{file_name}: {e}"));
+                        continue;
+                    }
+                };
+                let res = store_transcription(
+                    &config,
+                    &user.username,
+                    &msname,
+                    &username,
+                    &default_language,
+                    &file_name,
+                    &data,
+                )
+                .await;
+                push_import_result(&mut results, file_name, res);
+            }
+            Ok(None) => {
+                break;
+            }
+            Err(e) => {
+                tracing::warn!("Failed reading one of the multipart fields: {e}");
+                tracing::warn!("logged in user: {}", user.username);
+                break;
+            }
+        };
+    }
+    transfer_response(results)
+}
+
+/// Import zip archives of existing transcriptions of `username` for pages of a manuscript
+///
+/// Each entry in an archive is handled like a file sent to [`transcription_upload`].
+/// The response contains one result per entry in the archives.
+pub async fn transcription_archive_upload(
+    Extension(config): Extension<Arc<Config>>,
+    Path((msname, username)): Path<(String, String)>,
+    auth_session: AuthSession,
+    mut mpart: Multipart,
+) -> impl IntoResponse {
+    let user = match authorize_session(config.clone(), auth_session, Action::Administer).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let _in_flight = match UploadInFlight::start() {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+    let default_language = match prepare_transcription_import(&config, &msname, &username).await {
+        Ok(x) => x,
+        Err(response) => {
+            return response;
+        }
+    };
+
+    let mut results = FileTransferResponse::new();
+    loop {
+        match mpart.next_field().await {
+            Ok(Some(field)) => {
+                let Some(file_name) = field.file_name() else {
+                    results.push_err("The file name must be set for each file.".to_string());
                     continue;
+                };
+                let archive_name = file_name.to_string();
+                if let Err(e) = split_file_name(&archive_name, &[ARCHIVE_EXTENSION]) {
+                    results.push_err(e);
+                    continue;
+                };
+                let data = match read_field_data(field, MAX_BODY_SIZE).await {
+                    Ok(x) => x,
+                    Err(e) => {
+                        results.push_err(format!("{archive_name}: {e}"));
+                        continue;
+                    }
+                };
+                let entries = match tokio::task::spawn_blocking(move || {
+                    read_zip_entries(&data, MAX_BODY_SIZE as u64, MAX_EXTRACTED_ARCHIVE_SIZE)
+                })
+                .await
+                {
+                    Ok(Ok(x)) => x,
+                    Ok(Err(e)) => {
+                        results.push_err(format!("{archive_name}: {e}"));
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to join the archive extraction task: {e}");
+                        results.push_err(format!("{archive_name}: Failed to extract archive."));
+                        continue;
+                    }
+                };
+                for (entry_name, content) in entries {
+                    let res = match content {
+                        Ok(content) => {
+                            store_transcription(
+                                &config,
+                                &user.username,
+                                &msname,
+                                &username,
+                                &default_language,
+                                &entry_name,
+                                &content,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(ImportError::Other(e)),
+                    };
+                    push_import_result(&mut results, format!("{archive_name}/{entry_name}"), res);
                 }
-                tracing::info!(
-                    "{} saved new page for {msname}: {base_name}.{extension}.",
-                    user.username
-                );
-                results.push_ok();
             }
             Ok(None) => {
                 break;
@@ -120,16 +1201,9 @@ pub async fn page_upload(
             Err(e) => {
                 tracing::warn!("Failed reading one of the multipart fields: {e}");
                 tracing::warn!("logged in user: {}", user.username);
+                break;
             }
         };
     }
-    (
-        if results.err.iter().all(|e| e.is_none()) {
-            StatusCode::OK
-        } else {
-            StatusCode::INTERNAL_SERVER_ERROR
-        },
-        Json(results),
-    )
-        .into_response()
+    transfer_response(results)
 }
@@ -0,0 +1,316 @@
+//! Tests for upload validation
+
+use std::io::{Cursor, Write};
+
+use image::{ImageBuffer, ImageFormat, Rgb};
+
+use critic_shared::{ConversionFailure, ConversionStage, FileTransferResponse};
+
+use super::{
+    check_pdf_page_size, content_hash, push_import_result, read_zip_entries, validate_image,
+    write_original, ImageValidationError, ImportError,
+};
+
+/// A valid image of the given size and format
+fn image_bytes(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let img = ImageBuffer::from_pixel(width, height, Rgb([255_u8, 255, 255]));
+    let mut buf = Cursor::new(Vec::new());
+    img.write_to(&mut buf, format)
+        .expect("Writing image to memory works");
+    buf.into_inner()
+}
+
+/// A valid png of the given size
+fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    image_bytes(width, height, ImageFormat::Png)
+}
+
+#[test]
+fn valid_image() {
+    assert_eq!(
+        validate_image(&png_bytes(120, 200), "png", 100).unwrap(),
+        (120, 200)
+    );
+}
+
+#[test]
+fn empty_file() {
+    assert!(matches!(
+        validate_image(&[], "png", 100),
+        Err(ImageValidationError::Empty)
+    ));
+}
+
+#[test]
+fn truncated_file() {
+    let data = png_bytes(120, 200);
+    // keep the png signature, but cut off inside the header chunk
+    assert!(matches!(
+        validate_image(&data[..20], "png", 100),
+        Err(ImageValidationError::Decode(_))
+    ));
+}
+
+#[test]
+fn not_an_image() {
+    // plain text that was renamed to .png
+    let data = b"This is certainly not a png, even if the file name says so.";
+    assert!(matches!(
+        validate_image(data, "png", 100),
+        Err(ImageValidationError::Decode(_))
+    ));
+}
+
+#[test]
+fn extension_must_match_content() {
+    assert!(matches!(
+        validate_image(&png_bytes(120, 200), "jpg", 100),
+        Err(ImageValidationError::FormatMismatch(ext, ImageFormat::Png)) if ext == "jpg"
+    ));
+    let jpeg = image_bytes(120, 200, ImageFormat::Jpeg);
+    assert_eq!(validate_image(&jpeg, "jpg", 100).unwrap(), (120, 200));
+    assert_eq!(validate_image(&jpeg, "jpeg", 100).unwrap(), (120, 200));
+}
+
+#[test]
+fn tiff_is_readable() {
+    // TIFF masters can be allowed in the config, so they need to decode for minification as well
+    let tiff = image_bytes(120, 200, ImageFormat::Tiff);
+    assert_eq!(validate_image(&tiff, "tiff", 100).unwrap(), (120, 200));
+    assert_eq!(validate_image(&tiff, "tif", 100).unwrap(), (120, 200));
+    let decoded = image::ImageReader::new(Cursor::new(tiff))
+        .with_guessed_format()
+        .unwrap()
+        .decode()
+        .unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (120, 200));
+}
+
+#[test]
+fn image_too_small() {
+    assert!(matches!(
+        validate_image(&png_bytes(120, 50), "png", 100),
+        Err(ImageValidationError::TooSmall(120, 50, 100))
+    ));
+}
+
+/// A multipart request whose body ends in the middle of a file, as if the client aborted the upload
+fn aborted_multipart_request() -> axum::extract::Request {
+    let body = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"page1.png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n\
+        the first bytes of a png, but the upload stops here";
+    axum::http::Request::builder()
+        .method("POST")
+        .header("content-type", "multipart/form-data; boundary=BOUNDARY")
+        .body(axum::body::Body::from(body))
+        .expect("static request is valid")
+}
+
+#[tokio::test]
+async fn body_read_error() {
+    use axum::extract::{FromRequest, Multipart};
+
+    let mut mpart = Multipart::from_request(aborted_multipart_request(), &())
+        .await
+        .expect("multipart headers are valid");
+    let field = mpart
+        .next_field()
+        .await
+        .expect("field headers are complete")
+        .expect("there is one field");
+    assert!(super::read_field_data(field, 1024).await.is_err());
+}
+
+#[tokio::test]
+async fn oversized_field_is_abandoned_while_streaming() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::extract::{FromRequest, Multipart};
+    use futures_util::{stream, StreamExt};
+
+    // a file that never ends, so reading it completely would never finish
+    let chunks_sent = Arc::new(AtomicUsize::new(0));
+    let header = "--BOUNDARY\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"page1.png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n";
+    let body = stream::once(async move { axum::body::Bytes::from(header) })
+        .chain(stream::repeat(axum::body::Bytes::from(vec![0_u8; 1024])))
+        .inspect({
+            let chunks_sent = chunks_sent.clone();
+            move |_| {
+                chunks_sent.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .map(Ok::<_, std::convert::Infallible>);
+    let request = axum::http::Request::builder()
+        .method("POST")
+        .header("content-type", "multipart/form-data; boundary=BOUNDARY")
+        .body(axum::body::Body::from_stream(body))
+        .expect("request is valid");
+
+    let mut mpart = Multipart::from_request(request, &())
+        .await
+        .expect("multipart headers are valid");
+    let field = mpart
+        .next_field()
+        .await
+        .expect("field headers are complete")
+        .expect("there is one field");
+    let res = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        super::read_field_data(field, 16 * 1024),
+    )
+    .await
+    .expect("reading stops at the limit");
+    assert!(res
+        .unwrap_err()
+        .contains("page1.png is larger than the allowed 16384 bytes"));
+    // only a little more than the limit was pulled from the body
+    assert!(chunks_sent.load(Ordering::SeqCst) < 64);
+}
+
+#[test]
+fn original_is_written_without_leftover_part_file() {
+    let dir = std::env::temp_dir().join(format!("critic-upload-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir_str = dir.to_string_lossy().to_string();
+
+    write_original(&dir_str, b"page").unwrap();
+    assert_eq!(std::fs::read(dir.join("original")).unwrap(), b"page");
+    assert!(!dir.join("original.part").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn failed_write_leaves_nothing_behind() {
+    let dir = std::env::temp_dir().join(format!("critic-upload-missing-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(write_original(&dir.to_string_lossy(), b"page").is_err());
+    assert!(!dir.exists());
+}
+
+#[test]
+fn content_hash_is_hex_sha256() {
+    assert_eq!(
+        content_hash(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_ne!(content_hash(b"abc"), content_hash(b"abd"));
+}
+
+/// A zip archive with these (name, content) entries
+fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in entries {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn zip_entries_are_read() {
+    let data = zip_bytes(&[("p1.png", b"first"), ("p2.png", b"second")]);
+    let entries = read_zip_entries(&data, 100, 1000).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].0, "p1.png");
+    assert_eq!(entries[0].1.as_deref().unwrap(), b"first");
+    assert_eq!(entries[1].1.as_deref().unwrap(), b"second");
+}
+
+#[test]
+fn zip_slip_entries_are_rejected() {
+    let data = zip_bytes(&[
+        ("../escape.png", b"evil"),
+        ("dir/nested.png", b"nested"),
+        ("ok.png", b"fine"),
+    ]);
+    let entries = read_zip_entries(&data, 100, 1000).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].1.is_err());
+    assert!(entries[1].1.is_err());
+    assert_eq!(entries[2].1.as_deref().unwrap(), b"fine");
+}
+
+#[test]
+fn zip_bomb_entry_is_not_decompressed() {
+    // 64 MiB of zeros compress to a few kilobytes
+    let bomb = vec![0_u8; 64 * 1024 * 1024];
+    let data = zip_bytes(&[("bomb.png", &bomb), ("ok.png", b"fine")]);
+    assert!(data.len() < 1024 * 1024);
+    let entries = read_zip_entries(&data, 1024, u64::MAX).unwrap();
+    assert!(entries[0].1.as_ref().unwrap_err().contains("1024 bytes"));
+    assert_eq!(entries[1].1.as_deref().unwrap(), b"fine");
+}
+
+#[test]
+fn zip_archive_expanding_too_far_is_rejected() {
+    let page = vec![0_u8; 600];
+    let data = zip_bytes(&[("p1.png", &page), ("p2.png", &page)]);
+    // each entry is small enough, but not both together
+    assert!(read_zip_entries(&data, 1000, 1000).is_err());
+    assert_eq!(read_zip_entries(&data, 1000, 1200).unwrap().len(), 2);
+}
+
+#[test]
+fn pdf_pages_are_named_after_the_pdf() {
+    assert_eq!(pdf_page_name("scan", 0), "scan_p001");
+    assert_eq!(pdf_page_name("scan", 41), "scan_p042");
+    // a second pdf in the same manuscript does not reuse the names
+    assert_ne!(pdf_page_name("scan", 0), pdf_page_name("other", 0));
+}
+
+#[test]
+fn conversion_failures_are_reported_per_file() {
+    let mut results = FileTransferResponse::new();
+    push_import_result(&mut results, "1r.xml".to_string(), Ok(()));
+    push_import_result(
+        &mut results,
+        "old.zip/2r.xml".to_string(),
+        Err(ImportError::Conversion(
+            ConversionStage::Deserialize,
+            "missing closing tag".to_string(),
+        )),
+    );
+    push_import_result(
+        &mut results,
+        "3r.xml".to_string(),
+        Err(ImportError::Other(
+            "The page 3r does not exist in ms.".to_string(),
+        )),
+    );
+    assert_eq!(
+        results.err,
+        vec![
+            None,
+            Some("old.zip/2r.xml: missing closing tag".to_string()),
+            Some("3r.xml: The page 3r does not exist in ms.".to_string()),
+        ]
+    );
+    assert_eq!(
+        results.conversion_failures,
+        vec![ConversionFailure {
+            file: "old.zip/2r.xml".to_string(),
+            stage: ConversionStage::Deserialize,
+            message: "missing closing tag".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn oversized_pdf_pages_are_refused_before_rendering() {
+    // A4 at 300 dpi
+    assert!(check_pdf_page_size(595.0, 842.0, 300).is_ok());
+    // the largest MediaBox PDF allows, 200 inches square
+    assert!(check_pdf_page_size(14_400.0, 14_400.0, 300).is_err());
+    assert!(check_pdf_page_size(f32::NAN, 842.0, 300).is_err());
+}
@@ -0,0 +1,47 @@
+//! Information derived from language tags (like `hbo-Hebr`)
+//!
+//! Blocks carry a BCP 47 language tag. Everything that needs to know how text in a language is
+//! written should ask this module, so that the editor and exports agree.
+
+// include tests
+#[cfg(test)]
+mod test;
+
+/// Script subtags of scripts written right-to-left
+const RTL_SCRIPTS: &[&str] = &[
+    "adlm", "arab", "aran", "armi", "avst", "hebr", "mand", "mani", "nbat", "nkoo", "palm", "phli",
+    "phlp", "phnx", "prti", "rohg", "samr", "sarb", "sogd", "sogo", "syrc", "syre", "syrj", "syrn",
+    "thaa", "yezi",
+];
+
+/// Languages without a script subtag that are written right-to-left by default
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "dv", "fa", "he", "hbo", "ps", "sam", "syc", "syr", "ur", "yi",
+];
+
+/// Is text in the language `lang` written right-to-left?
+///
+/// This is decided by the script subtag (e.g. `-Hebr` or `-Arab`) if there is one, so that
+/// `ar-Latn` is left-to-right. Without a script subtag, some languages that are (almost) always
+/// written in a right-to-left script are recognized by their primary subtag. Everything else is
+/// left-to-right.
+pub fn is_rtl_script(lang: &str) -> bool {
+    let mut subtags = lang.trim().split(['-', '_']);
+    let Some(primary) = subtags.next() else {
+        return false;
+    };
+    // the script is the only subtag made up of exactly four letters
+    match subtags.find(|s| s.len() == 4 && s.chars().all(|c| c.is_ascii_alphabetic())) {
+        Some(script) => RTL_SCRIPTS.contains(&script.to_ascii_lowercase().as_str()),
+        None => RTL_LANGUAGES.contains(&primary.to_ascii_lowercase().as_str()),
+    }
+}
+
+/// The value for the html `dir` attribute of text in `lang`
+pub fn text_direction(lang: &str) -> &'static str {
+    if is_rtl_script(lang) {
+        "rtl"
+    } else {
+        "ltr"
+    }
+}
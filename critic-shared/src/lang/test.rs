@@ -0,0 +1,34 @@
+use super::{is_rtl_script, text_direction};
+
+#[test]
+fn script_subtag_decides() {
+    assert!(is_rtl_script("hbo-Hebr"));
+    assert!(is_rtl_script("ar-Arab"));
+    assert!(is_rtl_script("syc-Syrc"));
+    assert!(is_rtl_script("arc-Hebr-IL"));
+    assert!(is_rtl_script("en-hebr"));
+    assert!(!is_rtl_script("grc-Grek"));
+    assert!(!is_rtl_script("ar-Latn"));
+    assert!(!is_rtl_script("hbo-Latn"));
+}
+
+#[test]
+fn languages_without_script_subtag() {
+    assert!(is_rtl_script("he"));
+    assert!(is_rtl_script("HBO"));
+    assert!(is_rtl_script("ar-EG"));
+    assert!(!is_rtl_script("en"));
+    assert!(!is_rtl_script("grc"));
+    assert!(!is_rtl_script("en-GB"));
+    // a variant of four characters is not a script
+    assert!(!is_rtl_script("de-1901"));
+}
+
+#[test]
+fn empty_and_malformed_tags_are_ltr() {
+    assert!(!is_rtl_script(""));
+    assert!(!is_rtl_script("  "));
+    assert!(!is_rtl_script("-Hebrew"));
+    assert_eq!(text_direction(""), "ltr");
+    assert_eq!(text_direction("hbo-Hebr"), "rtl");
+}
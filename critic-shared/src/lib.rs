@@ -1,13 +1,20 @@
 //! Types and functions shared by App and Server
 
+pub mod lang;
 pub mod urls;
 
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "ssr")]
 use sqlx::FromRow;
 
-/// The extensions that we allow for page images
+/// The extensions that we allow for page images unless configured otherwise
 pub const ALLOWED_IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+/// The extension for archives containing page images
+pub const ARCHIVE_EXTENSION: &str = "zip";
+/// The extension for PDFs that are rasterized into page images
+pub const PDF_EXTENSION: &str = "pdf";
+/// The extension for imported transcriptions
+pub const TRANSCRIPTION_EXTENSION: &str = "xml";
 /// Max body size for POST-requests in bytes
 ///
 /// Please note changes to this value in the README under `Reverse Proxying critic`
@@ -17,14 +24,100 @@ pub const MAX_BODY_SIZE: usize = 150 * 1024 * 1024;
 /// the height will be calculated to keep the same aspect ratio
 pub const PREVIEW_IMAGE_WIDTH: u32 = 720;
 
+/// width of the thumbnails in the manuscript overview
+/// the height will be calculated to keep the same aspect ratio
+pub const THUMBNAIL_IMAGE_WIDTH: u32 = 160;
+
+/// width of the tiny placeholder shown while the preview loads
+pub const PLACEHOLDER_IMAGE_WIDTH: u32 = 32;
+
+/// How the site presents itself, set in the `[branding]` section of the config
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Branding {
+    /// Shown as the title of every page
+    #[serde(default = "default_site_title")]
+    pub title: String,
+    /// The logo in the navigation bar
+    #[serde(default = "default_logo_url")]
+    pub logo_url: String,
+    /// The heading on the home page
+    #[serde(default = "default_welcome_message")]
+    pub welcome_message: String,
+}
+impl Default for Branding {
+    fn default() -> Self {
+        Self {
+            title: default_site_title(),
+            logo_url: default_logo_url(),
+            welcome_message: default_welcome_message(),
+        }
+    }
+}
+fn default_site_title() -> String {
+    "critic - textual criticism".to_string()
+}
+fn default_logo_url() -> String {
+    "/logo.webp".to_string()
+}
+fn default_welcome_message() -> String {
+    "Welcome to Critic".to_string()
+}
+
+/// The logged in user and what they may do in critic
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct UserProfile {
+    pub username: String,
+    /// Member of the group configured for the oauth provider
+    pub member: bool,
+    pub may_transcribe: bool,
+    pub may_upload: bool,
+    pub may_administer: bool,
+}
+
+/// The step in which converting an imported transcription failed, see
+/// `critic_format::ConversionError`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionStage {
+    /// The file is no well-formed XML in the critic format
+    Deserialize,
+    /// The content cannot be normalized into a page
+    Normalize,
+    /// The page cannot be turned into editor blocks
+    Stream,
+}
+
+/// An imported transcription that could not be converted
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ConversionFailure {
+    /// The file (or `<archive>/<entry>`) the transcription came from
+    pub file: String,
+    pub stage: ConversionStage,
+    /// What the converter reported
+    pub message: String,
+}
+
 /// Response from the backend after file uploads
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct FileTransferResponse {
     pub err: Vec<Option<String>>,
+    /// Notes on files that were not saved, but not because of an error (e.g. duplicates)
+    ///
+    /// These files have no error in `err`.
+    #[serde(default)]
+    pub skipped: Vec<String>,
+    /// Details on imported transcriptions that could not be converted
+    ///
+    /// These files also have their error in `err`.
+    #[serde(default)]
+    pub conversion_failures: Vec<ConversionFailure>,
 }
 impl FileTransferResponse {
     pub fn new() -> Self {
-        Self { err: Vec::new() }
+        Self {
+            err: Vec::new(),
+            skipped: Vec::new(),
+            conversion_failures: Vec::new(),
+        }
     }
 
     /// A single file was uploaded ok
@@ -44,6 +137,23 @@ impl FileTransferResponse {
         self.err
             .extend(std::iter::repeat_n(Some(error), batch_size));
     }
+    /// The next file was deliberately not saved, for the reason in `note`
+    pub fn push_skipped(&mut self, note: String) {
+        self.err.push(None);
+        self.skipped.push(note);
+    }
+    /// The next file is a transcription that could not be converted
+    pub fn push_conversion_failure(&mut self, failure: ConversionFailure) {
+        self.err
+            .push(Some(format!("{}: {}", failure.file, failure.message)));
+        self.conversion_failures.push(failure);
+    }
+    /// Add the results of `other` after the ones already present
+    pub fn append(&mut self, other: Self) {
+        self.err.extend(other.err);
+        self.skipped.extend(other.skipped);
+        self.conversion_failures.extend(other.conversion_failures);
+    }
 }
 impl Extend<Option<String>> for FileTransferResponse {
     fn extend<T: IntoIterator<Item = Option<String>>>(&mut self, iter: T) {
@@ -51,8 +161,14 @@ impl Extend<Option<String>> for FileTransferResponse {
     }
 }
 
+/// Versification schemes (by full name) that are always present
+///
+/// These are inserted in `202507071848_versification_scheme.up.sql`, cannot be changed in the admin
+/// interface and are shown while the other schemes are still loading.
+pub const STATIC_VERSIFICATION_SCHEMES: &[&str] = &["Present", "Common"];
+
 /// The names of a versification scheme
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 pub struct VersificationScheme {
     pub id: i64,
@@ -73,9 +189,105 @@ pub struct ManuscriptMeta {
     pub collection: Option<String>,
     pub hand_desc: Option<String>,
     pub script_desc: Option<String>,
-    // TODO add this as editable in admin page
+    /// Default language of this manuscript
     pub lang: String,
-    // TODO also add:
+    /// When this manuscript was written
+    pub date: Option<String>,
+    /// Where this manuscript was written
+    pub origin: Option<String>,
+    /// Incremented on every update of the metadata, used to detect concurrent edits
+    pub version: i64,
+    /// Name of the page representing this manuscript, the first page unless one was chosen
+    ///
+    /// None if the manuscript has no pages.
+    pub cover_page: Option<String>,
+    /// The webp versions of the cover page image exist, see [`PageMeta::minified`]
+    ///
+    /// Not sent back when editing the metadata.
+    #[serde(default)]
+    pub cover_minified: bool,
+    /// When the manuscript was last changed, as formatted by the db
+    ///
+    /// Not sent back when editing the metadata.
+    #[serde(default)]
+    pub updated_at: String,
+    /// Can the published transcriptions be read without logging in?
+    ///
+    /// Not sent back when editing the metadata, it is changed on its own.
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// How a list of manuscripts is sorted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ManuscriptOrder {
+    TitleAsc,
+    TitleDesc,
+    /// Most recently added first
+    #[default]
+    NewestFirst,
+}
+impl ManuscriptOrder {
+    pub const ALL: [Self; 3] = [Self::NewestFirst, Self::TitleAsc, Self::TitleDesc];
+
+    /// The value used for this order in forms
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::TitleAsc => "title-asc",
+            Self::TitleDesc => "title-desc",
+            Self::NewestFirst => "newest",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.key() == key)
+    }
+
+    /// The text shown to the user
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TitleAsc => "Title (A-Z)",
+            Self::TitleDesc => "Title (Z-A)",
+            Self::NewestFirst => "Recently added",
+        }
+    }
+}
+
+/// How the transcription todo list is sorted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TodoOrder {
+    /// Pages with the most published transcriptions first, then the fewest started ones
+    #[default]
+    AlmostDone,
+    /// Pages with the fewest started transcriptions first
+    Untouched,
+    /// By manuscript and page name, ignoring progress
+    Manuscript,
+}
+impl TodoOrder {
+    pub const ALL: [Self; 3] = [Self::AlmostDone, Self::Untouched, Self::Manuscript];
+
+    /// The value used for this order in forms and urls
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::AlmostDone => "almost-done",
+            Self::Untouched => "untouched",
+            Self::Manuscript => "manuscript",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.key() == key)
+    }
+
+    /// The text shown to the user
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::AlmostDone => "Almost done first",
+            Self::Untouched => "Untouched first",
+            Self::Manuscript => "Manuscript and page",
+        }
+    }
 }
 
 /// complete information for a manuscript, including its pages
@@ -95,6 +307,63 @@ pub struct PageMeta {
     pub name: String,
     pub verse_start: Option<i64>,
     pub verse_end: Option<i64>,
+    /// A tiny version of the page image as `data:` url, for pages minified with a placeholder
+    pub placeholder: Option<String>,
+    /// The webp versions of the page image exist
+    pub minified: bool,
+    /// Creating the webp versions failed, they will not exist until it is retried
+    pub minification_failed: bool,
+}
+
+/// The neighbours of a page in its manuscript, see `critic_server::db::get_adjacent_pages`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct AdjacentPages {
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+/// How far a user got with their transcriptions, see `critic_server::db::get_user_progress`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+pub struct UserProgress {
+    /// Pages the user has started a transcription for (including published ones)
+    pub pages_started: i64,
+    pub pages_published: i64,
+    /// Published pages that nobody started reconciling yet
+    pub pages_pending_reconciliation: i64,
+    /// (manuscript name, page name) of the unpublished transcriptions in reading order
+    pub in_progress: Vec<(String, String)>,
+}
+
+/// What one user did on a page, see `critic_server::db::get_page_transcribers`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct PageTranscriber {
+    pub username: String,
+    /// The user has a transcription for this page
+    pub started: bool,
+    pub published: bool,
+    /// The user is reconciling this page
+    pub reconciled: bool,
+}
+
+/// A page the minification service failed to minify
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct FailedMinification {
+    pub page_id: i64,
+    pub manuscript: String,
+    pub page: String,
+}
+
+/// One entry of the audit log, see `critic_server::audit::record_audit`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub username: String,
+    pub action: String,
+    pub target: String,
+    /// When the action happened, as rendered by postgres
+    pub created_at: String,
+    /// Additional information about the action as JSON object
+    pub details: String,
 }
 
 /// This provides context through the entire app. When ShowHelp(true) is present, some components
@@ -130,6 +399,8 @@ pub struct PageTodo {
     pub transcriptions_started: u8,
     pub transcriptions_published: PublishedTranscriptions,
     pub this_user_status: OwnStatus,
+    /// The user currently claiming this page, if page locking is enabled
+    pub locked_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
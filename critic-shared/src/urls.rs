@@ -16,3 +16,43 @@ pub const UPLOAD_BASE_URL: &str = "/upload";
 /// The api endpoint where new manuscript pages should be uploaded to
 /// The manuscriptname these pages belong to will be appended after this string (and a /)
 pub const PAGE_UPLOAD_API_ENDPOINT: &str = "/v1/page";
+/// The api endpoint where zip archives of manuscript pages should be uploaded to
+/// The manuscriptname these pages belong to will be appended after this string (and a /)
+pub const ARCHIVE_UPLOAD_API_ENDPOINT: &str = "/v1/archive";
+/// The api endpoint where PDFs of manuscript pages should be uploaded to
+/// The manuscriptname these pages belong to will be appended after this string (and a /)
+pub const PDF_UPLOAD_API_ENDPOINT: &str = "/v1/pdf";
+/// The api endpoint where existing transcriptions (as xml in the critic format) should be uploaded to
+/// The manuscriptname and the username of the transcriber will be appended after this string
+/// (separated by /)
+pub const TRANSCRIPTION_UPLOAD_API_ENDPOINT: &str = "/v1/transcription";
+/// The api endpoint where zip archives of existing transcriptions should be uploaded to
+/// The manuscriptname and the username of the transcriber will be appended after this string
+/// (separated by /)
+pub const TRANSCRIPTION_ARCHIVE_UPLOAD_API_ENDPOINT: &str = "/v1/transcription-archive";
+/// The base url for exporting data to other formats
+pub const EXPORT_BASE_URL: &str = "/export";
+/// The api endpoint where published transcriptions can be downloaded as TEI P5
+/// The manuscriptname, pagename and username will be appended after this string (separated by /)
+pub const TEI_EXPORT_API_ENDPOINT: &str = "/v1/tei";
+/// The api endpoint where transcriptions can be downloaded as stored, in the critic format
+/// The manuscriptname, pagename and username will be appended after this string (separated by /)
+pub const XML_EXPORT_API_ENDPOINT: &str = "/v1/xml";
+/// The api endpoint where the list of pages to transcribe can be downloaded as CSV
+/// The search filters can be given as `?query=...`, like in the page list
+pub const TODO_CSV_EXPORT_API_ENDPOINT: &str = "/v1/todo.csv";
+/// The base url for the read-only JSON api for external tools
+pub const JSON_API_BASE_URL: &str = "/api/v1";
+/// Websocket telling the transcription editor who else has the same page open
+/// The manuscriptname and pagename will be appended after this string (separated by /)
+pub const PRESENCE_BASE_URL: &str = "/presence";
+/// Read-only view of published transcriptions of public manuscripts, reachable without login
+/// The manuscriptname and pagename will be appended after this string, optionally followed by the
+/// username of one transcriber (separated by /)
+pub const PUBLIC_VIEW_BASE_URL: &str = "/view";
+/// Liveness endpoint, answers as long as the web server runs
+pub const HEALTH_URL: &str = "/healthz";
+/// Readiness endpoint, checks the db, the data directory and the minification service
+pub const READINESS_URL: &str = "/readyz";
+/// Prometheus metrics endpoint
+pub const METRICS_URL: &str = "/metrics";
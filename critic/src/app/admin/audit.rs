@@ -0,0 +1,108 @@
+//! Showing the audit log: who changed what
+
+use critic_components::DEFAULT_BUTTON_CLASSES;
+use critic_shared::AuditEntry;
+use leptos::prelude::*;
+
+#[cfg(feature = "ssr")]
+use super::authorize_maintainer;
+use crate::app::TopLevelPosition;
+
+/// Get one page of the audit log, newest entries first
+#[server]
+async fn get_audit_log(page: i32) -> Result<Vec<AuditEntry>, ServerFnError> {
+    let (config, _user) = authorize_maintainer("read the audit log").await?;
+    critic_server::db::get_audit_entries(&config.db, page)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+#[component]
+fn AuditRow(entry: AuditEntry) -> impl IntoView {
+    view! {
+        <div class="table-row odd:bg-slate-800 even:bg-slate-600">
+            <div class="table-cell p-2 whitespace-nowrap">{entry.created_at}</div>
+            <div class="table-cell p-2">{entry.username}</div>
+            <div class="table-cell p-2 font-mono">{entry.action}</div>
+            <div class="table-cell p-2">{entry.target}</div>
+            <div class="table-cell p-2 font-mono text-sm">{entry.details}</div>
+        </div>
+    }
+}
+
+/// Page through the audit log
+#[component]
+pub fn AuditLog() -> impl IntoView {
+    let set_top_level_pos =
+        use_context::<WriteSignal<TopLevelPosition>>().expect("App provides TopLevelPosition");
+    *set_top_level_pos.write() = TopLevelPosition::Admin;
+
+    let page = RwSignal::new(0);
+    let entries = Resource::new(move || page.get(), get_audit_log);
+
+    view! {
+        <div class="flex h-full flex-col">
+            <div class="flex flex-row justify-center">
+                <h1 class="p-10 text-6xl font-semibold">Audit Log</h1>
+            </div>
+            <div class="flex flex-row justify-center">
+                <div class="w-3/4">
+                    <div class="flex flex-row justify-between">
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            type="button"
+                            disabled=move || page.get() == 0
+                            on:click=move |_| page.update(|p| *p = (*p - 1).max(0))
+                        >
+                            "Newer"
+                        </button>
+                        <p class="m-2 text-slate-300">"Page " {move || page.get() + 1}</p>
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            type="button"
+                            on:click=move |_| page.update(|p| *p += 1)
+                        >
+                            "Older"
+                        </button>
+                    </div>
+                    <div class="table w-full text-xl">
+                        <div class="table-header-group font-bold">
+                            <div class="table-row">
+                                <div class="table-cell p-2">"Time"</div>
+                                <div class="table-cell p-2">"User"</div>
+                                <div class="table-cell p-2">"Action"</div>
+                                <div class="table-cell p-2">"Target"</div>
+                                <div class="table-cell p-2">"Details"</div>
+                            </div>
+                        </div>
+                        <div class="table-row-group">
+                            <Transition fallback=|| {
+                                view! { <p>"Loading audit log..."</p> }
+                            }>
+                                {move || {
+                                    entries
+                                        .get()
+                                        .map(|entries_res| match entries_res {
+                                            Ok(entries) => {
+                                                entries
+                                                    .into_iter()
+                                                    .map(|entry| view! { <AuditRow entry=entry /> })
+                                                    .collect_view()
+                                                    .into_any()
+                                            }
+                                            Err(e) => {
+                                                view! {
+                                                    <p>"Unable to get the audit log: " {e.to_string()}</p>
+                                                }
+                                                    .into_any()
+                                            }
+                                        })
+                                }}
+                            </Transition>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -7,58 +7,137 @@
 // query params
 // @msq=search-term-to-find-ms
 
+use critic_components::confirm::ConfirmDialog;
 use critic_components::filetransfer::TransferPage;
+use critic_components::toast::{use_toasts, ToastKind};
 use critic_components::{DEFAULT_BUTTON_CLASSES, TEXTAREA_DEFAULT_COLS, TEXTAREA_DEFAULT_ROWS};
-use critic_shared::urls::{IMAGE_BASE_LOCATION, STATIC_BASE_URL};
-use critic_shared::{ManuscriptMeta, PREVIEW_IMAGE_WIDTH};
-use leptos::either::Either;
+use critic_shared::urls::{IMAGE_BASE_LOCATION, PUBLIC_VIEW_BASE_URL, STATIC_BASE_URL};
+use critic_shared::{
+    ManuscriptMeta, ManuscriptOrder, PageMeta, PageTranscriber, PREVIEW_IMAGE_WIDTH,
+    THUMBNAIL_IMAGE_WIDTH,
+};
+use leptos::either::{Either, EitherOf3};
+use leptos::ev::keydown;
+use leptos::html::{Ul, A};
 use leptos::prelude::*;
 use leptos_router::components::Outlet;
 use leptos_router::hooks::{query_signal, use_params};
+use leptos_use::{signal_debounced, use_event_listener};
+use web_sys::wasm_bindgen::JsCast;
 
-use crate::app::shared::{MsParams, PageParams};
+use crate::app::error::AppError;
+use crate::app::me::{profile_allows, Profile};
+use crate::app::shared::{MinificationBadge, MsParams, PageParams};
 use crate::app::EmptyError;
 
+/// Get the manuscripts matching `query` (`ms:`, `ms=`, `lang:` and `lang=` terms, as in the
+/// transcription todo list) sorted by `order`
 #[server]
-async fn get_manuscripts() -> Result<Vec<critic_shared::ManuscriptMeta>, ServerFnError> {
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
-    critic_server::db::get_manuscripts(&config.db)
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))
+async fn get_manuscripts(
+    query: Option<String>,
+    order: ManuscriptOrder,
+) -> Result<Vec<critic_shared::ManuscriptMeta>, AppError> {
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>().ok_or(
+        AppError::Internal("Unable to get config from context".to_string()),
+    )?;
+    Ok(
+        critic_server::db::get_manuscripts_by_query(&config.db, &query.unwrap_or_default(), order)
+            .await?,
+    )
 }
 
 #[server]
-async fn add_manuscript(msname: String) -> Result<(), ServerFnError> {
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
+async fn add_manuscript(msname: String) -> Result<(), AppError> {
+    let (config, _, user) = crate::app::me::logged_in().await?;
+    critic_server::db::add_manuscript(&config.db, &msname, None).await?;
+    config.manuscript_cache.invalidate(&msname);
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "add_manuscript",
+        &msname,
+        serde_json::json!({}),
+    )
+    .await;
     // after adding the new manuscript, redirect to its own page
     leptos_axum::redirect(&format!("/admin/manuscripts/{msname}"));
-    critic_server::db::add_manuscript(&config.db, &msname, None)
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))
+    Ok(())
+}
+
+/// Move through the links of `list` with the arrow keys
+///
+/// Every `<li>` of the list is expected to contain one link. Enter opens the focused link as usual.
+/// Handled keys are not passed on, so the global shortcuts never see them.
+fn use_list_keyboard_navigation(list: NodeRef<Ul>) {
+    let _cleanup = use_event_listener(list, keydown, move |ev| {
+        if ev.ctrl_key() || ev.alt_key() || ev.meta_key() {
+            return;
+        };
+        let forward = match ev.key().as_str() {
+            "ArrowDown" => true,
+            "ArrowUp" => false,
+            _ => return,
+        };
+        let Some(list) = list.get_untracked() else {
+            return;
+        };
+        ev.prevent_default();
+        ev.stop_propagation();
+        let current_item = document()
+            .active_element()
+            .and_then(|focused| focused.closest("li").ok().flatten())
+            .filter(|item| list.contains(Some(item)));
+        let next_item = match (current_item, forward) {
+            (Some(item), true) => item.next_element_sibling(),
+            (Some(item), false) => item.previous_element_sibling(),
+            (None, true) => list.first_element_child(),
+            (None, false) => list.last_element_child(),
+        };
+        if let Some(link) = next_item
+            .and_then(|item| item.query_selector("a").ok().flatten())
+            .and_then(|link| link.dyn_into::<web_sys::HtmlElement>().ok())
+        {
+            let _ = link.focus();
+        };
+    });
+}
+
+/// Scroll the link in `link_ref` into view once it is mounted
+fn scroll_into_view_when_mounted(link_ref: NodeRef<A>) {
+    Effect::new(move || {
+        if let Some(link) = link_ref.get() {
+            let options = web_sys::ScrollIntoViewOptions::new();
+            options.set_block(web_sys::ScrollLogicalPosition::Nearest);
+            link.scroll_into_view_with_scroll_into_view_options(&options);
+        };
+    });
 }
 
+/// How long to wait after the last keystroke in the manuscript search before asking the server
+const MANUSCRIPT_SEARCH_DEBOUNCE_MS: f64 = 300.0;
+
 #[component]
 pub fn ManuscriptList() -> impl IntoView {
     let (query, set_query) = query_signal::<String>("msq");
+    // the url follows every keystroke, the search only once typing stops
+    let debounced_query = signal_debounced(query, MANUSCRIPT_SEARCH_DEBOUNCE_MS);
+    // newest first, so freshly added manuscripts are at the top
+    let order = RwSignal::new(ManuscriptOrder::default());
 
     // this can be toggled to force a reload for manuscripts
-    let manuscript_list = OnceResource::new(async {
-        get_manuscripts()
-            .await
-            .map_err(|e| ServerFnError::new(format!("Unable to get manuscript information: {e}")))
-    });
+    let manuscript_list = Resource::new(
+        move || (debounced_query.get(), order.get()),
+        async |(msquery, order)| {
+            get_manuscripts(msquery, order).await.map_err(|e| {
+                AppError::Internal(format!("Unable to get manuscript information: {e}"))
+            })
+        },
+    );
     let new_manuscript_open = RwSignal::new(false);
 
-    let add_manuscript_srvact = ServerAction::<AddManuscript>::new();
-
-    let new_manuscript_error = move || match add_manuscript_srvact.value().get() {
-        Some(Err(e)) => Some(e.to_string()),
-        _ => None,
-    };
-
     let new_msname_ref = NodeRef::new();
+    let ms_list_ref = NodeRef::<Ul>::new();
+    use_list_keyboard_navigation(ms_list_ref);
     view! {
         <div id="ManuscriptList-wrapper" class="h-full flex flex-row justify-start">
             // the left sidebar containing the different manuscripts
@@ -67,9 +146,6 @@ pub fn ManuscriptList() -> impl IntoView {
                 class="flex flex-col justify-start w-1/4 overflow-auto border-r-2 border-slate-600"
             >
                 // the search bar, new-manuscript-button and actual list
-                <div id="new-manuscript-error" class="bg-red-200">
-                    {new_manuscript_error}
-                </div>
                 <div
                     id="new-manuscript-button"
                     class=(
@@ -96,8 +172,18 @@ pub fn ManuscriptList() -> impl IntoView {
                         on:submit=move |ev| {
                             ev.prevent_default();
                             let new_msname = new_msname_ref.get().expect("input field exists");
+                            let toasts = use_toasts();
                             leptos::task::spawn_local(async move {
-                                let _res = add_manuscript(new_msname.value()).await;
+                                let msname = new_msname.value();
+                                let (kind, message) = match add_manuscript(msname.clone()).await {
+                                    Ok(()) => (ToastKind::Success, format!("Added {msname}.")),
+                                    Err(e) => {
+                                        (ToastKind::Error, format!("Could not add {msname}: {e}"))
+                                    }
+                                };
+                                if let Some(toasts) = toasts {
+                                    toasts.push(kind, message);
+                                };
                             });
                             new_manuscript_open.update(|x| *x ^= true);
                         }
@@ -141,6 +227,7 @@ pub fn ManuscriptList() -> impl IntoView {
                         class="w-0 grow mr-2 ml-1 border-0 font-mono text-slate-400"
                         type="search"
                         name="msq"
+                        placeholder="<name> lang:<>"
                         value=move || query.get()
                         on:input:target=move |ev| {
                             let current_value = ev.target().value();
@@ -155,6 +242,30 @@ pub fn ManuscriptList() -> impl IntoView {
                         }
                     />
                 </div>
+                <div class="flex flex-row justify-between mx-4 mb-2 text-sm">
+                    <label for="ms-order">"Sort by"</label>
+                    <select
+                        id="ms-order"
+                        class="border border-slate-500 rounded-md"
+                        on:change:target=move |ev| {
+                            if let Some(new_order) = ManuscriptOrder::from_key(&ev.target().value()) {
+                                order.set(new_order);
+                            };
+                        }
+                    >
+                        {ManuscriptOrder::ALL
+                            .map(|option| {
+                                view! {
+                                    <option
+                                        value=option.key()
+                                        selected=move || order.get() == option
+                                    >
+                                        {option.label()}
+                                    </option>
+                                }
+                            })}
+                    </select>
+                </div>
 
                 <ErrorBoundary fallback=|errors| {
                     view! {
@@ -175,7 +286,7 @@ pub fn ManuscriptList() -> impl IntoView {
                     <Transition fallback=|| view! { <p>"Loading manuscripts..."</p> }>
                         // list of manuscripts
                         <div id="ms-list-wrapper" class="flex flex-col justify-start h-0 grow">
-                            <ul>
+                            <ul node_ref=ms_list_ref>
                                 {move || {
                                     manuscript_list
                                         .get()
@@ -183,18 +294,7 @@ pub fn ManuscriptList() -> impl IntoView {
                                             info_res
                                                 .map(|info: Vec<critic_shared::ManuscriptMeta>| {
                                                     info.into_iter()
-                                                        .filter_map(|ms| {
-                                                            if !query
-                                                                .with(|msquery: &Option<String>| {
-                                                                    if let Some(searched_name) = msquery {
-                                                                        ms.title.contains(searched_name)
-                                                                    } else {
-                                                                        true
-                                                                    }
-                                                                })
-                                                            {
-                                                                return None;
-                                                            }
+                                                        .map(|ms| {
                                                             let ms_params = use_params::<MsParams>();
                                                             let this_title = ms.title.clone();
                                                             let is_selected = move || {
@@ -204,46 +304,68 @@ pub fn ManuscriptList() -> impl IntoView {
                                                                         param.msname.is_some_and(|param| param == this_title)
                                                                     })
                                                             };
-                                                            Some(
-                                                                // we do not want to show MSS that the
-                                                                // user did not search for
-                                                                view! {
-                                                                    <li class="flex">
-                                                                        // keep query parameter if one is set
-                                                                        {if let Some(query_name) = query.get() {
-                                                                            Either::Left(
-                                                                                view! {
-                                                                                    <a
-                                                                                        href=format!(
-                                                                                            "/admin/manuscripts/{}?msq={}",
-                                                                                            ms.title,
-                                                                                            query_name,
-                                                                                        )
-                                                                                        class="w-0 grow my-2 bg-slate-600 p-2 text-center font-serif text-lg shadow-sm hover:bg-slate-500"
-                                                                                        class=(["shadow-sky-600"], !is_selected())
-                                                                                        class=(["shadow-slate-300", "text-sky-300"], is_selected())
-                                                                                    >
-                                                                                        {ms.title.clone()}
-                                                                                    </a>
-                                                                                },
-                                                                            )
-                                                                        } else {
-                                                                            Either::Right(
-                                                                                view! {
-                                                                                    <a
-                                                                                        href=format!("/admin/manuscripts/{}", ms.title)
-                                                                                        class="w-0 grow my-2 bg-slate-600 p-2 text-center font-serif text-lg shadow-sm hover:bg-slate-500"
-                                                                                        class=(["shadow-sky-600"], !is_selected())
-                                                                                        class=(["shadow-slate-300", "text-sky-300"], is_selected())
-                                                                                    >
-                                                                                        {ms.title.clone()}
-                                                                                    </a>
-                                                                                },
-                                                                            )
-                                                                        }}
-                                                                    </li>
-                                                                },
-                                                            )
+                                                            let link_ref = NodeRef::<A>::new();
+                                                            if is_selected() {
+                                                                scroll_into_view_when_mounted(link_ref);
+                                                            };
+                                                            view! {
+                                                                <li class="flex">
+                                                                    // the cover has no preview until it is minified
+                                                                    {ms
+                                                                        .cover_page
+                                                                        .clone()
+                                                                        .filter(|_| ms.cover_minified)
+                                                                        .map(|cover| {
+                                                                            view! {
+                                                                                <img
+                                                                                    class="my-2 h-12 w-9 object-cover"
+                                                                                    alt=""
+                                                                                    loading="lazy"
+                                                                                    src=format!(
+                                                                                        "{STATIC_BASE_URL}{IMAGE_BASE_LOCATION}/{}/{cover}/preview.webp",
+                                                                                        ms.title,
+                                                                                    )
+                                                                                />
+                                                                            }
+                                                                        })}
+                                                                    // keep query parameter if one is set
+                                                                    {if let Some(query_name) = query.get() {
+                                                                        Either::Left(
+                                                                            view! {
+                                                                                <a
+                                                                                    node_ref=link_ref
+                                                                                    aria-current=is_selected().then_some("page")
+                                                                                    href=format!(
+                                                                                        "/admin/manuscripts/{}?msq={}",
+                                                                                        ms.title,
+                                                                                        query_name,
+                                                                                    )
+                                                                                    class="w-0 grow my-2 bg-slate-600 p-2 text-center font-serif text-lg shadow-sm hover:bg-slate-500"
+                                                                                    class=(["shadow-sky-600"], !is_selected())
+                                                                                    class=(["shadow-slate-300", "text-sky-300"], is_selected())
+                                                                                >
+                                                                                    {ms.title.clone()}
+                                                                                </a>
+                                                                            },
+                                                                        )
+                                                                    } else {
+                                                                        Either::Right(
+                                                                            view! {
+                                                                                <a
+                                                                                    node_ref=link_ref
+                                                                                    aria-current=is_selected().then_some("page")
+                                                                                    href=format!("/admin/manuscripts/{}", ms.title)
+                                                                                    class="w-0 grow my-2 bg-slate-600 p-2 text-center font-serif text-lg shadow-sm hover:bg-slate-500"
+                                                                                    class=(["shadow-sky-600"], !is_selected())
+                                                                                    class=(["shadow-slate-300", "text-sky-300"], is_selected())
+                                                                                >
+                                                                                    {ms.title.clone()}
+                                                                                </a>
+                                                                            },
+                                                                        )
+                                                                    }}
+                                                                </li>
+                                                            }
                                                         })
                                                         .collect_view()
                                                 })
@@ -262,21 +384,343 @@ pub fn ManuscriptList() -> impl IntoView {
 }
 
 #[server]
-pub async fn get_manuscript_by_name(
+pub async fn get_manuscript_by_name(msname: String) -> Result<critic_shared::Manuscript, AppError> {
+    let config: std::sync::Arc<critic_server::config::Config> = use_context().ok_or(
+        AppError::Internal("Unable to get config from context".to_string()),
+    )?;
+    Ok(config
+        .manuscript_cache
+        .manuscript(&config.db, &msname)
+        .await?)
+}
+
+/// Set the first and last verse for many pages of a manuscript at once
+///
+/// `updates` contains (page id, verse start, verse end).
+#[server]
+async fn set_page_verse_ranges(
     msname: String,
-) -> Result<critic_shared::Manuscript, ServerFnError> {
-    let config: std::sync::Arc<critic_server::config::Config> =
-        use_context().ok_or(ServerFnError::new("Unable to get config from context"))?;
-    let res = critic_server::db::get_manuscript(&config.db, &msname).await;
-    match res {
-        Ok(x) => Ok(x),
-        Err(e @ critic_server::db::DBError::ManuscriptDoesNotExist(_)) => {
-            Err(ServerFnError::new(e.to_string()))
-        }
-        Err(e) => {
-            tracing::warn!("Failed loading manuscript meta: {e}");
-            Err(ServerFnError::new(e.to_string()))
+    updates: Vec<(i64, Option<i64>, Option<i64>)>,
+) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("set verse ranges").await?;
+    if let Err(e) = critic_server::db::set_page_verse_ranges(&config.db, &msname, &updates).await {
+        tracing::warn!("Failed to set verse ranges for pages of {msname}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!(
+        "User {} set verse ranges for {} pages of MS {msname}.",
+        user.username,
+        updates.len()
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "set_page_verse_ranges",
+        &msname,
+        serde_json::json!({ "updates": updates }),
+    )
+    .await;
+    Ok(())
+}
+
+/// Parse the content of a verse input, where an empty input means "unknown"
+fn parse_verse(input: &str) -> Result<Option<i64>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    };
+    input
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| format!("{input} is not a verse id."))
+}
+
+/// Edit the verse ranges of all pages of a manuscript in one grid
+#[component]
+fn VerseRangeGrid(msname: String, pages: Vec<PageMeta>) -> impl IntoView {
+    let save_act = ServerAction::<SetPageVerseRanges>::new();
+    let parse_error = RwSignal::new(None::<String>);
+    let rows = pages
+        .into_iter()
+        .map(|page| {
+            (
+                page.id,
+                page.name,
+                page.verse_start,
+                page.verse_end,
+                RwSignal::new(page.verse_start.map(|v| v.to_string()).unwrap_or_default()),
+                RwSignal::new(page.verse_end.map(|v| v.to_string()).unwrap_or_default()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let rows = StoredValue::new(rows);
+
+    // only send the pages that actually changed
+    let save = move |_| {
+        let mut updates = Vec::new();
+        for (id, name, old_start, old_end, start, end) in rows.get_value() {
+            let parsed =
+                parse_verse(&start.get()).and_then(|s| parse_verse(&end.get()).map(|e| (s, e)));
+            match parsed {
+                Ok((new_start, new_end)) => {
+                    if (new_start, new_end) != (old_start, old_end) {
+                        updates.push((id, new_start, new_end));
+                    };
+                }
+                Err(e) => {
+                    parse_error.set(Some(format!("Page {name}: {e}")));
+                    return;
+                }
+            };
         }
+        parse_error.set(None);
+        if !updates.is_empty() {
+            save_act.dispatch(SetPageVerseRanges {
+                msname: msname.clone(),
+                updates,
+            });
+        };
+    };
+
+    let action_message = move || {
+        parse_error.get().or_else(|| match save_act.value().get() {
+            Some(Err(e)) => Some(e.to_string()),
+            _ => None,
+        })
+    };
+
+    view! {
+        <div class="bg-red-200 text-black">{action_message}</div>
+        <Show when=move || matches!(save_act.value().get(), Some(Ok(())))>
+            <p class="bg-green-200 text-black">"Verse ranges saved."</p>
+        </Show>
+        <div class="table w-full text-lg">
+            <div class="table-header-group font-bold">
+                <div class="table-row">
+                    <div class="table-cell p-2">"Page"</div>
+                    <div class="table-cell p-2">"First verse"</div>
+                    <div class="table-cell p-2">"Last verse"</div>
+                </div>
+            </div>
+            <div class="table-row-group">
+                {rows
+                    .get_value()
+                    .into_iter()
+                    .map(|(_, name, _, _, start, end)| {
+                        view! {
+                            <div class="table-row odd:bg-slate-800 even:bg-slate-600">
+                                <div class="table-cell p-2 font-serif">{name}</div>
+                                <div class="table-cell p-2">
+                                    <input
+                                        class="w-full bg-inherit"
+                                        inputmode="numeric"
+                                        prop:value=move || start.get()
+                                        on:input:target=move |ev| start.set(ev.target().value())
+                                    />
+                                </div>
+                                <div class="table-cell p-2">
+                                    <input
+                                        class="w-full bg-inherit"
+                                        inputmode="numeric"
+                                        prop:value=move || end.get()
+                                        on:input:target=move |ev| end.set(ev.target().value())
+                                    />
+                                </div>
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </div>
+        <div class="flex justify-around mt-2">
+            <button class=format!("w-2/5 {DEFAULT_BUTTON_CLASSES}") type="button" on:click=save>
+                "Save verse ranges"
+            </button>
+        </div>
+    }
+}
+
+/// Choose the page representing a manuscript in the manuscript list
+#[server]
+async fn set_manuscript_cover(msname: String, pagename: String) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("choose the cover page").await?;
+    let res = critic_server::db::set_manuscript_cover(&config.db, &msname, Some(&pagename)).await;
+    config.manuscript_cache.invalidate(&msname);
+    if let Err(e) = res {
+        tracing::warn!("Failed to set {pagename} as cover of {msname}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!(
+        "User {} set page {pagename} as cover of MS {msname}.",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "set_manuscript_cover",
+        &msname,
+        serde_json::json!({ "page": pagename }),
+    )
+    .await;
+    Ok(())
+}
+
+#[server]
+async fn set_manuscript_public(msname: String, public: bool) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("make manuscripts public").await?;
+    let res = critic_server::db::set_manuscript_public(&config.db, &msname, public).await;
+    config.manuscript_cache.invalidate(&msname);
+    if let Err(e) = res {
+        tracing::warn!("Failed to set public={public} for {msname}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!(
+        "User {} set public={public} for MS {msname}.",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "set_manuscript_public",
+        &msname,
+        serde_json::json!({ "public": public }),
+    )
+    .await;
+    Ok(())
+}
+
+/// Checkbox making the published transcriptions of a manuscript readable without login
+#[component]
+fn PublicToggle(msname: String, public: bool) -> impl IntoView {
+    let set_public_act = ServerAction::<SetManuscriptPublic>::new();
+    let view_url = format!("{PUBLIC_VIEW_BASE_URL}/{msname}/<page>");
+
+    view! {
+        <div class="flex flex-col items-center text-sm">
+            <div>
+                <input
+                    id="ms-public"
+                    type="checkbox"
+                    checked=public
+                    on:change:target=move |ev| {
+                        set_public_act
+                            .dispatch(SetManuscriptPublic {
+                                msname: msname.clone(),
+                                public: ev.target().checked(),
+                            });
+                    }
+                />
+                <label class="ml-2" for="ms-public">
+                    "Public: published transcriptions can be read without login at "
+                    <span class="font-mono">{view_url}</span>
+                </label>
+            </div>
+            {move || match set_public_act.value().get() {
+                Some(Err(e)) => {
+                    Some(view! { <div class="bg-red-200 text-black">{e.to_string()}</div> })
+                }
+                _ => None,
+            }}
+        </div>
+    }
+}
+
+/// Select for the cover page of a manuscript, `cover` being the current one
+#[component]
+fn CoverSelect(msname: String, pages: Vec<PageMeta>, cover: Option<String>) -> impl IntoView {
+    let set_cover_act = ServerAction::<SetManuscriptCover>::new();
+
+    view! {
+        <div class="flex flex-col mx-2 text-sm">
+            <label for="ms-cover">"Cover page"</label>
+            <select
+                id="ms-cover"
+                class="border border-slate-500 rounded-md"
+                on:change:target=move |ev| {
+                    set_cover_act
+                        .dispatch(SetManuscriptCover {
+                            msname: msname.clone(),
+                            pagename: ev.target().value(),
+                        });
+                }
+            >
+                {pages
+                    .into_iter()
+                    .map(|page| {
+                        let selected = cover.as_ref() == Some(&page.name);
+                        view! {
+                            <option value=page.name.clone() selected=selected>
+                                {page.name}
+                            </option>
+                        }
+                    })
+                    .collect_view()}
+            </select>
+            {move || match set_cover_act.value().get() {
+                Some(Err(e)) => {
+                    Some(view! { <div class="bg-red-200 text-black">{e.to_string()}</div> })
+                }
+                _ => None,
+            }}
+        </div>
+    }
+}
+
+/// Thumbnails of all pages of a manuscript, each linking to its page
+///
+/// Pages that are not minified yet have no thumbnail and only show their name and state. Pages
+/// minified before thumbnails were generated show their preview instead, and have no placeholder
+/// to show while it loads.
+#[component]
+fn ThumbnailGrid(msname: String, pages: Vec<PageMeta>, show: RwSignal<bool>) -> impl IntoView {
+    view! {
+        <div class="flex flex-wrap justify-center gap-2">
+            {pages
+                .into_iter()
+                .map(|page| {
+                    let image_base = format!(
+                        "{STATIC_BASE_URL}{IMAGE_BASE_LOCATION}/{msname}/{}",
+                        page.name,
+                    );
+                    let preview_src = format!("{image_base}/preview.webp");
+                    let thumbnail_src = RwSignal::new(format!("{image_base}/thumb.webp"));
+                    view! {
+                        <a
+                            class="flex flex-col items-center bg-slate-600 p-1 shadow-sm shadow-sky-600 hover:bg-slate-500"
+                            href=format!("/admin/manuscripts/{msname}/{}", page.name)
+                            on:click=move |_| show.set(false)
+                        >
+                            {page
+                                .minified
+                                .then(|| {
+                                    let placeholder = page.placeholder.clone();
+                                    view! {
+                                        <img
+                                            class="bg-cover"
+                                            alt=format!("Thumbnail for {}", page.name)
+                                            src=thumbnail_src
+                                            on:error=move |_| {
+                                                if thumbnail_src.get_untracked() != preview_src {
+                                                    thumbnail_src.set(preview_src.clone());
+                                                }
+                                            }
+                                            width=THUMBNAIL_IMAGE_WIDTH
+                                            loading="lazy"
+                                            style:background-image=placeholder
+                                                .map(|url| format!("url({url})"))
+                                        />
+                                    }
+                                })}
+                            <p class="font-serif">{page.name.clone()}</p>
+                            <MinificationBadge
+                                minified=page.minified
+                                minification_failed=page.minification_failed
+                            />
+                        </a>
+                    }
+                })
+                .collect_view()}
+        </div>
     }
 }
 
@@ -291,15 +735,18 @@ pub fn Manuscript() -> impl IntoView {
     // now get manuscript from the db
     let manuscript_info = Resource::new(msname, async |name_opt| {
         if let Some(name) = name_opt {
-            get_manuscript_by_name(name).await.map_err(|e| {
-                ServerFnError::new(format!("Unable to get manuscript information: {e}"))
-            })
+            get_manuscript_by_name(name).await
         } else {
-            Err(ServerFnError::new(
+            Err(AppError::NotFound(
                 "No manuscript passed in the URL".to_string(),
             ))
         }
     });
+    // pages can be renamed or deleted from the page view, which then needs to refresh the page list
+    provide_context(manuscript_info);
+    let page_list_ref = NodeRef::<Ul>::new();
+    use_list_keyboard_navigation(page_list_ref);
+    let profile = use_context::<Profile>().expect("App provides the user profile");
 
     view! {
         <Transition fallback=|| {
@@ -309,12 +756,39 @@ pub fn Manuscript() -> impl IntoView {
                 manuscript_info
                     .get()
                     .map(|info_res| match info_res {
-                        Err(e) => Either::Left(view! { <div>{e.to_string()}</div> }),
+                        Err(AppError::NotFound(msg)) => {
+                            EitherOf3::A(
+                                view! {
+                                    <div class="m-4">
+                                        <p class="text-xl">"Manuscript not found"</p>
+                                        <p class="text-slate-400">{msg}</p>
+                                    </div>
+                                },
+                            )
+                        }
+                        Err(e) => {
+                            EitherOf3::B(
+                                view! {
+                                    <div class="m-4 bg-red-200 text-black">
+                                        "Unable to get manuscript information: " {e.to_string()}
+                                    </div>
+                                },
+                            )
+                        }
                         Ok(info) => {
                             let show_page_upload = RwSignal::new(false);
+                            let show_verse_ranges = RwSignal::new(false);
+                            let show_thumbnails = RwSignal::new(false);
                             let msname = info.meta.title.clone();
                             let ms_name = msname.clone();
-                            Either::Right(
+                            let ms_name_ranges = msname.clone();
+                            let pages_for_ranges = info.pages.clone();
+                            let ms_name_thumbnails = msname.clone();
+                            let pages_for_thumbnails = info.pages.clone();
+                            let ms_name_cover = msname.clone();
+                            let pages_for_cover = info.pages.clone();
+                            let cover = info.meta.cover_page.clone();
+                            EitherOf3::C(
                                 view! {
                                     <div
                                         id="Manuscript-wrapper"
@@ -345,6 +819,52 @@ pub fn Manuscript() -> impl IntoView {
                                                     </div>
                                                 </div>
                                             </Show>
+                                            // the same for the verse range grid
+                                            <Show when=move || show_verse_ranges.get() fallback=|| {}>
+                                                <div class="z-20 absolute inset-0 bg-stone-100/60 backdrop-blur-[4px]">
+                                                    <div class="relative inset-1/12 w-10/12 max-h-10/12 overflow-y-auto">
+                                                        <div class="bg-slate-700 rounded-lg p-2">
+                                                            <VerseRangeGrid
+                                                                msname=ms_name_ranges.clone()
+                                                                pages=pages_for_ranges.clone()
+                                                            />
+                                                        </div>
+                                                        <div class="flex justify-around">
+                                                            <button
+                                                                class="text-slate-50 bg-slate-700 hover:bg-slate-800 rounded-lg text-center p-3 mt-1"
+                                                                on:click=move |_| {
+                                                                    show_verse_ranges.set(false);
+                                                                    manuscript_info.refetch();
+                                                                }
+                                                            >
+                                                                Done
+                                                            </button>
+                                                        </div>
+                                                    </div>
+                                                </div>
+                                            </Show>
+                                            // and for the thumbnails
+                                            <Show when=move || show_thumbnails.get() fallback=|| {}>
+                                                <div class="z-20 absolute inset-0 bg-stone-100/60 backdrop-blur-[4px]">
+                                                    <div class="relative inset-1/12 w-10/12 max-h-10/12 overflow-y-auto">
+                                                        <div class="bg-slate-700 rounded-lg p-2">
+                                                            <ThumbnailGrid
+                                                                msname=ms_name_thumbnails.clone()
+                                                                pages=pages_for_thumbnails.clone()
+                                                                show=show_thumbnails
+                                                            />
+                                                        </div>
+                                                        <div class="flex justify-around">
+                                                            <button
+                                                                class="text-slate-50 bg-slate-700 hover:bg-slate-800 rounded-lg text-center p-3 mt-1"
+                                                                on:click=move |_| show_thumbnails.set(false)
+                                                            >
+                                                                Close
+                                                            </button>
+                                                        </div>
+                                                    </div>
+                                                </div>
+                                            </Show>
                                             <div
                                                 id="manuscript-pageinfo-wrapper"
                                                 class="flex justify-start min-h-96 max-h-full"
@@ -354,18 +874,50 @@ pub fn Manuscript() -> impl IntoView {
                                                     id="manuscript-pagelist-wrapper"
                                                     class="flex h-full w-44 flex-col justify-start border-r-2 border-slate-600"
                                                 >
+                                                    <Show when=move || {
+                                                        profile_allows(profile, |p| p.may_upload)
+                                                    }>
+                                                        <div class="flex justify-center">
+                                                            <button
+                                                                class="text-md m-2 rounded-2xl bg-slate-600 p-2 text-center font-bold text-slate-50 shadow-sm shadow-sky-600 hover:bg-slate-500"
+                                                                on:click=move |_| {
+                                                                    show_page_upload.update(|x| *x ^= true);
+                                                                }
+                                                            >
+                                                                "Add Pages"
+                                                            </button>
+                                                        </div>
+                                                    </Show>
                                                     <div class="flex justify-center">
                                                         <button
                                                             class="text-md m-2 rounded-2xl bg-slate-600 p-2 text-center font-bold text-slate-50 shadow-sm shadow-sky-600 hover:bg-slate-500"
                                                             on:click=move |_| {
-                                                                show_page_upload.update(|x| *x ^= true);
+                                                                show_verse_ranges.update(|x| *x ^= true);
                                                             }
                                                         >
-                                                            "Add Pages"
+                                                            "Verse Ranges"
                                                         </button>
                                                     </div>
+                                                    <div class="flex justify-center">
+                                                        <button
+                                                            class="text-md m-2 rounded-2xl bg-slate-600 p-2 text-center font-bold text-slate-50 shadow-sm shadow-sky-600 hover:bg-slate-500"
+                                                            on:click=move |_| {
+                                                                show_thumbnails.update(|x| *x ^= true);
+                                                            }
+                                                        >
+                                                            "Thumbnails"
+                                                        </button>
+                                                    </div>
+                                                    <CoverSelect
+                                                        msname=ms_name_cover
+                                                        pages=pages_for_cover
+                                                        cover=cover
+                                                    />
                                                     // list over all pages
-                                                    <ul class="h-0 grow overflow-y-auto no-scrollbar">
+                                                    <ul
+                                                        node_ref=page_list_ref
+                                                        class="h-0 grow overflow-y-auto no-scrollbar"
+                                                    >
                                                         {info
                                                             .pages
                                                             .into_iter()
@@ -378,9 +930,27 @@ pub fn Manuscript() -> impl IntoView {
                                                                             param.pagename.is_some_and(|param| param == page_name)
                                                                         })
                                                                 };
+                                                                let link_ref = NodeRef::<A>::new();
+                                                                if is_selected() {
+                                                                    scroll_into_view_when_mounted(link_ref);
+                                                                };
                                                                 view! {
                                                                     <li class="flex">
+                                                                        {page
+                                                                            .placeholder
+                                                                            .clone()
+                                                                            .map(|placeholder| {
+                                                                                view! {
+                                                                                    <img
+                                                                                        class="my-1 h-11 w-8 object-cover"
+                                                                                        alt=""
+                                                                                        src=placeholder
+                                                                                    />
+                                                                                }
+                                                                            })}
                                                                         <a
+                                                                            node_ref=link_ref
+                                                                            aria-current=is_selected().then_some("page")
                                                                             class="my-1 w-0 grow bg-slate-600 p-2 text-center font-serif text-lg shadow-sm hover:bg-slate-500"
                                                                             class=(["shadow-slate-300", "text-sky-300"], is_selected())
                                                                             class=(["shadow-sky-600"], !is_selected())
@@ -390,6 +960,11 @@ pub fn Manuscript() -> impl IntoView {
                                                                             )
                                                                         >
                                                                             {page.name.clone()}
+                                                                            <MinificationBadge
+                                                                                minified=page.minified
+                                                                                minification_failed=page
+                                                                                    .minification_failed
+                                                                            />
                                                                         </a>
                                                                         {if let (Some(start), Some(end)) = (
                                                                             page.verse_start,
@@ -491,52 +1066,50 @@ pub fn MMetaInput(
 }
 
 /// TODO: correctly rename file directory
+///
+/// Returns the new version of the manuscript metadata.
 #[server]
-async fn update_ms_metadata(data: ManuscriptMeta, old_title: String) -> Result<(), ServerFnError> {
-    use critic_server::auth::AuthSession;
-    use critic_server::github::user_is_member;
+async fn update_ms_metadata(data: ManuscriptMeta) -> Result<i64, AppError> {
     use critic_shared::urls::IMAGE_BASE_LOCATION;
-    use leptos_axum::extract;
 
-    let auth_session = match extract::<AuthSession>().await {
-        Ok(x) => x,
-        Err(e) => {
-            let msg = format!("Failed to get AuthSession: {e}");
-            tracing::warn!(msg);
-            return Err(ServerFnError::new(msg));
-        }
+    let (config, user) = super::authorize_maintainer("update MS metadata").await?;
+    if data.title.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "The manuscript title must not be empty.".to_string(),
+        ));
     };
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
-
-    let Some(user) = auth_session.user else {
-        return Err(ServerFnError::new("No usersession available"));
+    if data.lang.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "The manuscript language must not be empty.".to_string(),
+        ));
     };
-    match user_is_member(config.clone(), &user).await {
-        Ok(true) => {}
-        Ok(false) => {
-            return Err(ServerFnError::new(
-                "Unauthorized: Need to be Org member to update MS metadata.",
-            ));
-        }
+    // the update fails if the manuscript changed after this, so this is the title it is renamed from
+    let old_title = critic_server::db::get_manuscript_title(&config.db, data.id).await?;
+    // change the MS in the db
+    let res = critic_server::db::update_ms_meta(&config.db, &data).await;
+    config.manuscript_cache.invalidate(&old_title);
+    config.manuscript_cache.invalidate(&data.title);
+    let new_version = match res {
+        Ok(x) => x,
         Err(e) => {
             tracing::warn!(
-                "Unable to get github user membership for {}: {e}",
-                user.username
+                "Failed to update manuscript metadata for ms with id {}: {e}",
+                data.id
             );
-            return Err(ServerFnError::new(e.to_string()));
+            return Err(e.into());
         }
     };
-    // change the MS in the db
-    if let Err(e) = critic_server::db::update_ms_meta(&config.db, &data).await {
-        tracing::warn!(
-            "Failed to update manuscript metadata for ms with id {}",
-            data.id
-        );
-        return Err(ServerFnError::new(e.to_string()));
-    };
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "update_ms_meta",
+        &data.title,
+        serde_json::json!({ "old_title": old_title, "meta": data }),
+    )
+    .await;
     // rename the image directory for the MS if it was renamed
-    if data.title != old_title {
+    // titles stored before they were validated must not point anywhere else on disk either
+    if data.title != old_title && critic_server::db::validate_manuscript_title(&old_title).is_ok() {
         let base_path = format!("{}{IMAGE_BASE_LOCATION}", &config.data_directory);
         let old_path = format!("{base_path}/{old_title}");
         let new_path = format!("{base_path}/{}", data.title);
@@ -559,9 +1132,93 @@ async fn update_ms_metadata(data: ManuscriptMeta, old_title: String) -> Result<(
         // But I don't really know how to change that behavior.
         leptos_axum::redirect(&format!("/admin/manuscripts/{}", data.title));
     };
+    Ok(new_version)
+}
+
+/// Delete a manuscript with all its pages and transcriptions
+#[server]
+async fn delete_manuscript(msname: String, force: bool) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("delete manuscripts").await?;
+    let res =
+        critic_server::db::delete_manuscript(&config.db, &config.data_directory, &msname, force)
+            .await;
+    config.manuscript_cache.invalidate(&msname);
+    if let Err(e) = res {
+        tracing::warn!("Failed to delete manuscript {msname}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!("User {} deleted MS {msname}.", user.username);
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "delete_manuscript",
+        &msname,
+        serde_json::json!({ "force": force }),
+    )
+    .await;
+    leptos_axum::redirect("/admin/manuscripts");
     Ok(())
 }
 
+/// Delete a manuscript after asking for confirmation
+#[component]
+fn DeleteManuscriptForm(msname: String) -> impl IntoView {
+    let srvact = ServerAction::<DeleteManuscript>::new();
+    let confirm_open = RwSignal::new(false);
+    let force = RwSignal::new(false);
+
+    let delete_error = move || match srvact.value().get() {
+        Some(Err(e)) => Some(e.to_string()),
+        _ => None,
+    };
+
+    view! {
+        <details class="col-span-2 border border-slate-500 rounded-lg p-2 mt-4">
+            <summary>Delete this manuscript</summary>
+            <div class="border border-slate-500 bg-red-700/40 mb-2">
+                <div class="p-4 pt-2 pb-2">
+                    <p>
+                        "Warning! Deleting a manuscript removes all of its pages, page images and transcriptions. This cannot be undone."
+                    </p>
+                </div>
+            </div>
+            <div class="bg-red-200 text-black">{delete_error}</div>
+            <div class="grid grid-cols-2">
+                <label for="delete-force">"Also delete published transcriptions"</label>
+                <input
+                    id="delete-force"
+                    type="checkbox"
+                    prop:checked=move || force.get()
+                    on:change:target=move |ev| {
+                        force.set(ev.target().checked());
+                    }
+                />
+            </div>
+            <div class="flex justify-around mt-2">
+                <button
+                    class=format!("w-2/5 {DEFAULT_BUTTON_CLASSES}")
+                    type="button"
+                    on:click=move |_| confirm_open.set(true)
+                >
+                    "Delete manuscript"
+                </button>
+            </div>
+            <ConfirmDialog
+                open=confirm_open
+                message=format!("Really delete {msname}?")
+                confirm_label="Yes, delete"
+                on_confirm=move |()| {
+                    srvact
+                        .dispatch(DeleteManuscript {
+                            msname: msname.clone(),
+                            force: force.get(),
+                        });
+                }
+            />
+        </details>
+    }
+}
+
 /// Show meta-information for an individual manuscript
 #[component]
 fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
@@ -569,14 +1226,30 @@ fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
     let collection = RwSignal::new(meta.collection.clone());
     let hand_desc = RwSignal::new(meta.hand_desc.clone());
     let script_desc = RwSignal::new(meta.script_desc.clone());
+    let lang = RwSignal::new(Some(meta.lang.clone()));
+    let date = RwSignal::new(meta.date.clone());
+    let origin = RwSignal::new(meta.origin.clone());
     let new_name = RwSignal::new(meta.title.clone());
     let institution_save = RwSignal::new(meta.institution);
     let collection_save = RwSignal::new(meta.collection);
     let hand_desc_save = RwSignal::new(meta.hand_desc);
     let script_desc_save = RwSignal::new(meta.script_desc);
+    let lang_save = RwSignal::new(Some(meta.lang));
+    let date_save = RwSignal::new(meta.date);
+    let origin_save = RwSignal::new(meta.origin);
     let new_name_save = RwSignal::new(meta.title.clone());
+    let delete_name = meta.title.clone();
+    // the version the edits in this form are based on
+    let version = RwSignal::new(meta.version);
+    let manuscript_info = use_context::<Resource<Result<critic_shared::Manuscript, AppError>>>();
 
     let srvact = ServerAction::<UpdateMsMetadata>::new();
+    // saving twice without reloading is fine, the second save is based on the first one
+    Effect::new(move |_| {
+        if let Some(Ok(new_version)) = srvact.value().get() {
+            version.set(new_version);
+        };
+    });
 
     view! {
         <div class="p-6 border-2 border-slate-500">
@@ -584,10 +1257,16 @@ fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
             <h1 class="m-4 p-2 text-3xl text-center">
                 "Manuscript "<span class="font-bold">{meta.title.clone()}</span>
             </h1>
+            <p class="text-center text-sm">"Last changed: "{meta.updated_at}</p>
+            <PublicToggle msname=meta.title.clone() public=meta.public />
             <ActionForm action=srvact>
                 <div class="flex justify-around flex-col">
                     <input type="hidden" name="data[id]" value=meta.id />
-                    <input type="hidden" name="old_title" value=meta.title />
+                    <input
+                        type="hidden"
+                        name="data[version]"
+                        prop:value=move || version.get().to_string()
+                    />
                     <MMetaInput
                         name="data[institution]"
                         signal=institution
@@ -598,6 +1277,15 @@ fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
                     <MMetaInput name="data[collection]" signal=collection>
                         Collection:
                     </MMetaInput>
+                    <MMetaInput name="data[lang]" signal=lang>
+                        Default language:
+                    </MMetaInput>
+                    <MMetaInput name="data[date]" signal=date>
+                        Date of writing:
+                    </MMetaInput>
+                    <MMetaInput name="data[origin]" signal=origin>
+                        Place of origin:
+                    </MMetaInput>
                     <MMetaTextArea name="data[hand_desc]" signal=hand_desc>
                         Scribal hands in use:
                     </MMetaTextArea>
@@ -637,6 +1325,9 @@ fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
                                 *collection.write() = collection_save.get();
                                 *hand_desc.write() = hand_desc_save.get();
                                 *script_desc.write() = script_desc_save.get();
+                                *lang.write() = lang_save.get();
+                                *date.write() = date_save.get();
+                                *origin.write() = origin_save.get();
                                 *new_name.write() = new_name_save.get();
                             }
                         >
@@ -653,23 +1344,352 @@ fn ManuscriptMeta(meta: critic_shared::ManuscriptMeta) -> impl IntoView {
                                 *collection_save.write() = collection_save.get();
                                 *hand_desc_save.write() = hand_desc.get();
                                 *script_desc_save.write() = script_desc.get();
+                                *lang_save.write() = lang.get();
+                                *date_save.write() = date.get();
+                                *origin_save.write() = origin.get();
                                 *new_name_save.write() = new_name_save.get();
                             }
                         >
                             Save changes
                         </button>
                     </div>
+                    {move || match srvact.value().get() {
+                        Some(Err(AppError::Conflict(msg))) => {
+                            Some(
+                                EitherOf3::A(
+                                    view! {
+                                        <div class="mt-2 p-2 bg-red-200 text-black">
+                                            <p>{msg}</p>
+                                            <p>
+                                                "Reload the manuscript to see the current metadata. Your edits in this form will be lost."
+                                            </p>
+                                            <button
+                                                type="button"
+                                                class=DEFAULT_BUTTON_CLASSES
+                                                on:click=move |_| {
+                                                    if let Some(info) = manuscript_info {
+                                                        info.refetch();
+                                                    }
+                                                }
+                                            >
+                                                "Reload"
+                                            </button>
+                                        </div>
+                                    },
+                                ),
+                            )
+                        }
+                        Some(Err(e)) => {
+                            Some(
+                                EitherOf3::B(
+                                    view! {
+                                        <div class="mt-2 p-2 bg-red-200 text-black">
+                                            {e.to_string()}
+                                        </div>
+                                    },
+                                ),
+                            )
+                        }
+                        Some(Ok(_)) => Some(EitherOf3::C(view! { <p class="mt-2">"Saved."</p> })),
+                        None => None,
+                    }}
                 </div>
             </ActionForm>
+            <DeleteManuscriptForm msname=delete_name />
         </div>
     }
 }
 
+/// Rename a page of a manuscript
+#[server]
+async fn rename_page(
+    msname: String,
+    old_pagename: String,
+    new_pagename: String,
+) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("rename pages").await?;
+    let new_pagename = new_pagename.trim();
+    if new_pagename.is_empty() {
+        return Err(ServerFnError::new("The page name must not be empty."));
+    };
+    critic_server::db::validate_page_name(new_pagename)?;
+    let res = critic_server::db::rename_page(
+        &config.db,
+        &config.data_directory,
+        &msname,
+        &old_pagename,
+        new_pagename,
+    )
+    .await;
+    // the page may have been the cover
+    config.manuscript_cache.invalidate(&msname);
+    if let Err(e) = res {
+        tracing::warn!("Failed to rename page {old_pagename} of {msname} to {new_pagename}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!(
+        "User {} renamed page {old_pagename} of MS {msname} to {new_pagename}.",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "rename_page",
+        &format!("{msname}/{old_pagename}"),
+        serde_json::json!({ "new_name": new_pagename }),
+    )
+    .await;
+    leptos_axum::redirect(&format!("/admin/manuscripts/{msname}/{new_pagename}"));
+    Ok(())
+}
+
+/// Delete a page of a manuscript
+#[server]
+async fn delete_page(msname: String, pagename: String, force: bool) -> Result<(), ServerFnError> {
+    let (config, user) = super::authorize_maintainer("delete pages").await?;
+    let res = critic_server::db::delete_page(
+        &config.db,
+        &config.data_directory,
+        &pagename,
+        &msname,
+        force,
+    )
+    .await;
+    // the page may have been the cover
+    config.manuscript_cache.invalidate(&msname);
+    if let Err(e) = res {
+        tracing::warn!("Failed to delete page {pagename} of {msname}: {e}");
+        return Err(ServerFnError::new(e.to_string()));
+    };
+    tracing::info!(
+        "User {} deleted page {pagename} of MS {msname}.",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "delete_page",
+        &format!("{msname}/{pagename}"),
+        serde_json::json!({ "force": force }),
+    )
+    .await;
+    leptos_axum::redirect(&format!("/admin/manuscripts/{msname}"));
+    Ok(())
+}
+
+/// Rename or delete a single page
+#[component]
+fn PageActions(msname: String, pagename: String) -> impl IntoView {
+    let rename_act = ServerAction::<RenamePage>::new();
+    let delete_act = ServerAction::<DeletePage>::new();
+    let new_pagename = RwSignal::new(pagename.clone());
+    let confirm_open = RwSignal::new(false);
+    let force = RwSignal::new(false);
+
+    // the page list needs to show the new state
+    let manuscript_info = use_context::<Resource<Result<critic_shared::Manuscript, AppError>>>();
+    Effect::new(move || {
+        if matches!(rename_act.value().get(), Some(Ok(())))
+            || matches!(delete_act.value().get(), Some(Ok(())))
+        {
+            if let Some(info) = manuscript_info {
+                info.refetch();
+            };
+        };
+    });
+
+    let action_error = move || {
+        [rename_act.value().get(), delete_act.value().get()]
+            .into_iter()
+            .find_map(|res| match res {
+                Some(Err(e)) => Some(e.to_string()),
+                _ => None,
+            })
+    };
+
+    view! {
+        <div class="bg-red-200 text-black">{action_error}</div>
+        <details class="border border-slate-500 rounded-lg p-2 m-2">
+            <summary>Rename this page</summary>
+            <div class="grid grid-cols-2">
+                <label for="page-new-name">"New page name"</label>
+                <input
+                    id="page-new-name"
+                    class="bg-slate-700 border border-slate-500"
+                    prop:value=move || new_pagename.get()
+                    on:input:target=move |ev| {
+                        new_pagename.set(ev.target().value());
+                    }
+                />
+            </div>
+            <div class="flex justify-around mt-2">
+                <button
+                    class=format!("w-2/5 {DEFAULT_BUTTON_CLASSES}")
+                    type="button"
+                    on:click={
+                        let msname = msname.clone();
+                        let pagename = pagename.clone();
+                        move |_| {
+                            rename_act
+                                .dispatch(RenamePage {
+                                    msname: msname.clone(),
+                                    old_pagename: pagename.clone(),
+                                    new_pagename: new_pagename.get(),
+                                });
+                        }
+                    }
+                >
+                    "Rename page"
+                </button>
+            </div>
+        </details>
+        <details class="border border-slate-500 rounded-lg p-2 m-2">
+            <summary>Delete this page</summary>
+            <div class="border border-slate-500 bg-red-700/40 mb-2">
+                <div class="p-4 pt-2 pb-2">
+                    <p>
+                        "Warning! Deleting a page removes its image and all transcriptions for it. This cannot be undone."
+                    </p>
+                </div>
+            </div>
+            <div class="grid grid-cols-2">
+                <label for="page-delete-force">"Also delete transcriptions"</label>
+                <input
+                    id="page-delete-force"
+                    type="checkbox"
+                    prop:checked=move || force.get()
+                    on:change:target=move |ev| {
+                        force.set(ev.target().checked());
+                    }
+                />
+            </div>
+            <div class="flex justify-around mt-2">
+                <button
+                    class=format!("w-2/5 {DEFAULT_BUTTON_CLASSES}")
+                    type="button"
+                    on:click=move |_| confirm_open.set(true)
+                >
+                    "Delete page"
+                </button>
+            </div>
+            <ConfirmDialog
+                open=confirm_open
+                message=format!("Really delete {pagename}?")
+                confirm_label="Yes, delete"
+                on_confirm=move |()| {
+                    delete_act
+                        .dispatch(DeletePage {
+                            msname: msname.clone(),
+                            pagename: pagename.clone(),
+                            force: force.get(),
+                        });
+                }
+            />
+        </details>
+    }
+}
+
+/// Everyone who worked on a page
+///
+/// Only maintainers can see this, other users only ever see counts.
+#[server]
+async fn get_page_transcribers(
+    msname: String,
+    pagename: String,
+) -> Result<Vec<PageTranscriber>, ServerFnError> {
+    let (config, _) = super::authorize_maintainer("see who worked on a page").await?;
+    critic_server::db::get_page_transcribers(&config.db, &msname, &pagename)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Table of the users who worked on a page
+#[component]
+fn PageTranscribers(msname: String, pagename: String) -> impl IntoView {
+    let transcribers = Resource::new(
+        move || (msname.clone(), pagename.clone()),
+        |(msname, pagename)| get_page_transcribers(msname, pagename),
+    );
+    let mark = |done: bool| if done { "yes" } else { "-" };
+
+    view! {
+        <details class="border border-slate-500 rounded-lg p-2 m-2">
+            <summary>Transcribers</summary>
+            <Suspense fallback=|| {
+                view! { "Loading transcribers..." }
+            }>
+                {move || {
+                    transcribers
+                        .get()
+                        .map(|res| match res {
+                            Err(e) => {
+                                Either::Left(
+                                    view! {
+                                        <div class="bg-red-200 text-black">{e.to_string()}</div>
+                                    },
+                                )
+                            }
+                            Ok(transcribers) if transcribers.is_empty() => {
+                                Either::Right(
+                                    Either::Left(view! { <p>"Nobody worked on this page yet."</p> }),
+                                )
+                            }
+                            Ok(transcribers) => {
+                                Either::Right(
+                                    Either::Right(
+                                        view! {
+                                            <div class="table w-full">
+                                                <div class="table-header-group font-bold">
+                                                    <div class="table-row">
+                                                        <div class="table-cell p-2">"User"</div>
+                                                        <div class="table-cell p-2">"Started"</div>
+                                                        <div class="table-cell p-2">"Published"</div>
+                                                        <div class="table-cell p-2">"Reconciling"</div>
+                                                    </div>
+                                                </div>
+                                                <div class="table-row-group">
+                                                    {transcribers
+                                                        .into_iter()
+                                                        .map(|t| {
+                                                            view! {
+                                                                <div class="table-row odd:bg-slate-800 even:bg-slate-600">
+                                                                    <div class="table-cell p-2">{t.username}</div>
+                                                                    <div class="table-cell p-2">{mark(t.started)}</div>
+                                                                    <div class="table-cell p-2">{mark(t.published)}</div>
+                                                                    <div class="table-cell p-2">
+                                                                        {mark(t.reconciled)}
+                                                                    </div>
+                                                                </div>
+                                                            }
+                                                        })
+                                                        .collect_view()}
+                                                </div>
+                                            </div>
+                                        },
+                                    ),
+                                )
+                            }
+                        })
+                }}
+            </Suspense>
+        </details>
+    }
+}
+
 /// show information for a complete page
 #[component]
 pub fn Page() -> impl IntoView {
     let ms_params = use_params::<MsParams>();
     let page_params = use_params::<PageParams>();
+    // (minified, minification failed) for this page, once the page list is loaded
+    let manuscript_info = use_context::<Resource<Result<critic_shared::Manuscript, AppError>>>();
+    let minification_state = move |pagename: &str| {
+        manuscript_info
+            .and_then(|info| info.get())
+            .and_then(Result::ok)
+            .and_then(|ms| ms.pages.into_iter().find(|page| page.name == pagename))
+            .map(|page| (page.minified, page.minification_failed))
+    };
 
     view! {
         <ErrorBoundary fallback=|_errors| {
@@ -699,7 +1719,7 @@ pub fn Page() -> impl IntoView {
                                     <button class=DEFAULT_BUTTON_CLASSES>Edit - TODO</button>
                                     <a
                                         class=DEFAULT_BUTTON_CLASSES
-                                        href=format!("{image_base}/original.webp")
+                                        href=image_base.clone()
                                         target="_blank"
                                     >
                                         View Original
@@ -713,12 +1733,41 @@ pub fn Page() -> impl IntoView {
                                         Download Original
                                     </a>
                                 </div>
-                                // image preview for this page in the right hand side
-                                <img
-                                    alt=format!("Preview for {msname} - {pagename}")
-                                    src=format!("{image_base}/preview.webp")
-                                    width=PREVIEW_IMAGE_WIDTH
+                                <PageActions msname=msname.clone() pagename=pagename.clone() />
+                                <PageTranscribers
+                                    msname=msname.clone()
+                                    pagename=pagename.clone()
                                 />
+                                // image preview for this page in the right hand side
+                                {
+                                    let pagename = pagename.clone();
+                                    move || match minification_state(&pagename) {
+                                        Some((false, minification_failed)) => {
+                                            Either::Left(
+                                                view! {
+                                                    <p class="m-4 text-center">
+                                                        "There is no preview for this page yet."
+                                                        <MinificationBadge
+                                                            minified=false
+                                                            minification_failed=minification_failed
+                                                        />
+                                                    </p>
+                                                },
+                                            )
+                                        }
+                                        _ => {
+                                            Either::Right(
+                                                view! {
+                                                    <img
+                                                        alt=format!("Preview for {msname} - {pagename}")
+                                                        src=format!("{image_base}/preview.webp")
+                                                        width=PREVIEW_IMAGE_WIDTH
+                                                    />
+                                                },
+                                            )
+                                        }
+                                    }
+                                }
                             </div>
                         },
                     )
@@ -0,0 +1,171 @@
+//! Pages the minification service failed on, and retrying them
+
+use critic_components::DEFAULT_BUTTON_CLASSES;
+use critic_shared::FailedMinification;
+use leptos::prelude::*;
+
+#[cfg(feature = "ssr")]
+use super::authorize_maintainer;
+use crate::app::{error::AppError, TopLevelPosition};
+
+/// Get one page of the pages that failed to minify
+#[server]
+async fn get_failed_minifications(page: i32) -> Result<Vec<FailedMinification>, ServerFnError> {
+    let (config, _user) = authorize_maintainer("see failed minifications").await?;
+    critic_server::db::get_failed_minifications(&config.db, page)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// Let the minification service try this page again
+#[server]
+async fn retry_minification(page_id: i64) -> Result<(), AppError> {
+    let (config, user) = authorize_maintainer("retry minifications").await?;
+    // the names for the audit log come from the db, not from the client
+    let retried = critic_server::db::retry_page_minification(&config.db, page_id).await?;
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "retry_minification",
+        &format!("{}/{}", retried.manuscript, retried.page),
+        serde_json::json!({ "page_id": retried.page_id }),
+    )
+    .await;
+    Ok(())
+}
+
+#[component]
+fn FailedMinificationRow(
+    failed: FailedMinification,
+    retry: ServerAction<RetryMinification>,
+) -> impl IntoView {
+    let manuscript = failed.manuscript.clone();
+    let page = failed.page.clone();
+    view! {
+        <div class="table-row odd:bg-slate-800 even:bg-slate-600">
+            <div class="table-cell p-2">
+                <a
+                    class="underline"
+                    href=format!("/admin/manuscripts/{manuscript}/{page}")
+                >
+                    {manuscript.clone()}
+                </a>
+            </div>
+            <div class="table-cell p-2">{page.clone()}</div>
+            <div class="table-cell p-2">
+                <button
+                    class=DEFAULT_BUTTON_CLASSES
+                    type="button"
+                    on:click=move |_| {
+                        retry
+                            .dispatch(RetryMinification {
+                                page_id: failed.page_id,
+                            });
+                    }
+                >
+                    "Retry"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// Page through the pages that failed to minify
+#[component]
+pub fn FailedMinifications() -> impl IntoView {
+    let set_top_level_pos =
+        use_context::<WriteSignal<TopLevelPosition>>().expect("App provides TopLevelPosition");
+    *set_top_level_pos.write() = TopLevelPosition::Admin;
+
+    let page = RwSignal::new(0);
+    let retry = ServerAction::<RetryMinification>::new();
+    // retried pages disappear from the list
+    let failed_pages = Resource::new(
+        move || (page.get(), retry.version().get()),
+        |(page, _)| get_failed_minifications(page),
+    );
+
+    view! {
+        <div class="flex h-full flex-col">
+            <div class="flex flex-row justify-center">
+                <h1 class="p-10 text-6xl font-semibold">Failed Minifications</h1>
+            </div>
+            <div class="flex flex-row justify-center">
+                <div class="w-3/4">
+                    <p class="mb-4 text-slate-300">
+                        "These page images could not be converted to webp. The reason is in the server logs. Retrying makes the minification service try the page again."
+                    </p>
+                    <div class="bg-red-200 text-black">
+                        {move || match retry.value().get() {
+                            Some(Err(e)) => Some(format!("Unable to retry: {e}")),
+                            _ => None,
+                        }}
+                    </div>
+                    <div class="flex flex-row justify-between">
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            type="button"
+                            disabled=move || page.get() == 0
+                            on:click=move |_| page.update(|p| *p = (*p - 1).max(0))
+                        >
+                            "Previous"
+                        </button>
+                        <p class="m-2 text-slate-300">"Page " {move || page.get() + 1}</p>
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            type="button"
+                            on:click=move |_| page.update(|p| *p += 1)
+                        >
+                            "Next"
+                        </button>
+                    </div>
+                    <div class="table w-full text-xl">
+                        <div class="table-header-group font-bold">
+                            <div class="table-row">
+                                <div class="table-cell p-2">"Manuscript"</div>
+                                <div class="table-cell p-2">"Page"</div>
+                                <div class="table-cell p-2"></div>
+                            </div>
+                        </div>
+                        <div class="table-row-group">
+                            <Transition fallback=|| {
+                                view! { <p>"Loading failed minifications..."</p> }
+                            }>
+                                {move || {
+                                    failed_pages
+                                        .get()
+                                        .map(|failed_res| match failed_res {
+                                            Ok(failed_pages) if failed_pages.is_empty() => {
+                                                view! { <p>"No failed minifications."</p> }
+                                                    .into_any()
+                                            }
+                                            Ok(failed_pages) => {
+                                                failed_pages
+                                                    .into_iter()
+                                                    .map(|failed| {
+                                                        view! {
+                                                            <FailedMinificationRow failed=failed retry=retry />
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                                    .into_any()
+                                            }
+                                            Err(e) => {
+                                                view! {
+                                                    <p>
+                                                        "Unable to get failed minifications: "
+                                                        {e.to_string()}
+                                                    </p>
+                                                }
+                                                    .into_any()
+                                            }
+                                        })
+                                }}
+                            </Transition>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
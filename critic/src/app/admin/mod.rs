@@ -6,7 +6,39 @@ use leptos_router::path;
 
 use crate::app::TopLevelPosition;
 
+mod audit;
 mod manuscripts;
+mod minification;
+mod versification;
+
+/// Get the config and the user for this request if the user may administer critic
+///
+/// `purpose` completes the error message for other users, e.g. "manage versification schemes".
+/// Server functions returning a [`ServerFnError`] get the message of the
+/// [`AppError`](crate::app::error::AppError).
+#[cfg(feature = "ssr")]
+async fn authorize_maintainer(
+    purpose: &str,
+) -> Result<
+    (
+        std::sync::Arc<critic_server::config::Config>,
+        critic_server::auth::AuthenticatedUser,
+    ),
+    crate::app::error::AppError,
+> {
+    use crate::app::error::AppError;
+    use critic_server::auth::{user_may, Action};
+
+    let (config, _, user) = crate::app::me::logged_in().await?;
+    match user_may(config.clone(), &user, Action::Administer).await {
+        Ok(true) => Ok((config, user)),
+        Ok(false) => Err(AppError::Unauthorized(format!("Not allowed to {purpose}."))),
+        Err(e) => {
+            tracing::warn!("Unable to get user membership for {}: {e}", user.username);
+            Err(AppError::Internal(e.to_string()))
+        }
+    }
+}
 
 #[component]
 pub fn AdminLanding() -> impl IntoView {
@@ -70,6 +102,52 @@ pub fn AdminLanding() -> impl IntoView {
                         </div>
                         <p class="ml-12 list-disc text-xl">Manage Versification Schemes</p>
                     </a>
+                    <a
+                        href="/admin/audit"
+                        class="rounded-4xl border-2 border-sky-600 bg-slate-700 p-8 shadow-lg shadow-sky-600 hover:bg-slate-600 hover:shadow-xl"
+                    >
+                        <div class="flex flex-row justify-start">
+                            <svg
+                                xmlns="http://www.w3.org/2000/svg"
+                                fill="none"
+                                viewBox="0 0 24 24"
+                                stroke-width="1.5"
+                                stroke="currentColor"
+                                class="size-14"
+                            >
+                                <path
+                                    stroke-linecap="round"
+                                    stroke-linejoin="round"
+                                    d="M12 6v6h4.5m4.5 0a9 9 0 1 1-18 0 9 9 0 0 1 18 0Z"
+                                />
+                            </svg>
+                            <h2 class="mt-3 mb-4 ml-2 text-4xl font-bold">Audit Log</h2>
+                        </div>
+                        <p class="ml-12 list-disc text-xl">See who changed what</p>
+                    </a>
+                    <a
+                        href="/admin/minification"
+                        class="rounded-4xl border-2 border-sky-600 bg-slate-700 p-8 shadow-lg shadow-sky-600 hover:bg-slate-600 hover:shadow-xl"
+                    >
+                        <div class="flex flex-row justify-start">
+                            <svg
+                                xmlns="http://www.w3.org/2000/svg"
+                                fill="none"
+                                viewBox="0 0 24 24"
+                                stroke-width="1.5"
+                                stroke="currentColor"
+                                class="size-14"
+                            >
+                                <path
+                                    stroke-linecap="round"
+                                    stroke-linejoin="round"
+                                    d="M12 9v3.75m-9.303 3.376c-.866 1.5.217 3.374 1.948 3.374h14.71c1.73 0 2.813-1.874 1.948-3.374L13.949 3.378c-.866-1.5-3.032-1.5-3.898 0L2.697 16.126ZM12 15.75h.007v.008H12v-.008Z"
+                                />
+                            </svg>
+                            <h2 class="mt-3 mb-4 ml-2 text-4xl font-bold">Minification</h2>
+                        </div>
+                        <p class="ml-12 list-disc text-xl">Retry page images that failed to convert</p>
+                    </a>
                 </div>
             </div>
         </div>
@@ -90,6 +168,9 @@ pub fn AdminRouter() -> impl MatchNestedRoutes + Clone {
             </ParentRoute>
             <Route path=path!("") view=manuscripts::ManuscriptLanding />
         </ParentRoute>
+        <Route path=path!("versification") view=versification::VersificationSchemes />
+        <Route path=path!("audit") view=audit::AuditLog />
+        <Route path=path!("minification") view=minification::FailedMinifications />
     }
     .into_inner()
 }
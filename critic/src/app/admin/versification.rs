@@ -0,0 +1,320 @@
+//! Adding, editing and removing versification schemes
+
+use critic_components::DEFAULT_BUTTON_CLASSES;
+use critic_shared::{VersificationScheme, STATIC_VERSIFICATION_SCHEMES};
+use leptos::prelude::*;
+
+#[cfg(feature = "ssr")]
+use super::authorize_maintainer;
+use crate::app::TopLevelPosition;
+
+/// Make sure both names of a versification scheme are usable
+#[cfg(feature = "ssr")]
+fn validate_scheme_names(full_name: &str, shorthand: &str) -> Result<(), ServerFnError> {
+    if full_name.trim().is_empty() || shorthand.trim().is_empty() {
+        return Err(ServerFnError::new(
+            "Full name and shorthand must not be empty.",
+        ));
+    };
+    // the shorthand is part of anchor ids like A_V_<shorthand>_<verse>
+    if !shorthand.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ServerFnError::new(
+            "The shorthand may only contain ASCII letters and digits.",
+        ));
+    };
+    Ok(())
+}
+
+#[server]
+async fn list_versification_schemes() -> Result<Vec<VersificationScheme>, ServerFnError> {
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
+        .ok_or(ServerFnError::new("Unable to get config from context"))?;
+    critic_server::db::get_versification_schemes(&config.db)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+#[server]
+async fn add_versification_scheme(
+    full_name: String,
+    shorthand: String,
+) -> Result<(), ServerFnError> {
+    let (config, user) = authorize_maintainer("manage versification schemes").await?;
+    validate_scheme_names(&full_name, &shorthand)?;
+    critic_server::db::add_versification_scheme(&config.db, full_name.trim(), shorthand.trim())
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    tracing::info!(
+        "User {} added versification scheme {full_name} ({shorthand}).",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "add_versification_scheme",
+        full_name.trim(),
+        serde_json::json!({ "shorthand": shorthand.trim() }),
+    )
+    .await;
+    Ok(())
+}
+
+#[server]
+async fn update_versification_scheme(scheme: VersificationScheme) -> Result<(), ServerFnError> {
+    let (config, user) = authorize_maintainer("manage versification schemes").await?;
+    validate_scheme_names(&scheme.full_name, &scheme.shorthand)?;
+    let scheme = VersificationScheme {
+        id: scheme.id,
+        full_name: scheme.full_name.trim().to_string(),
+        shorthand: scheme.shorthand.trim().to_string(),
+    };
+    critic_server::db::update_versification_scheme(&config.db, &config.data_directory, &scheme)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    tracing::info!(
+        "User {} changed versification scheme {} to {} ({}).",
+        user.username,
+        scheme.id,
+        scheme.full_name,
+        scheme.shorthand
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "update_versification_scheme",
+        &scheme.id.to_string(),
+        serde_json::json!({ "full_name": scheme.full_name, "shorthand": scheme.shorthand }),
+    )
+    .await;
+    Ok(())
+}
+
+#[server]
+async fn delete_versification_scheme(id: i64) -> Result<(), ServerFnError> {
+    let (config, user) = authorize_maintainer("manage versification schemes").await?;
+    critic_server::db::delete_versification_scheme(&config.db, &config.data_directory, id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    tracing::info!("User {} deleted versification scheme {id}.", user.username);
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "delete_versification_scheme",
+        &id.to_string(),
+        serde_json::json!({}),
+    )
+    .await;
+    Ok(())
+}
+
+/// A single versification scheme that can be edited or deleted unless it is static
+#[component]
+fn SchemeRow(
+    scheme: VersificationScheme,
+    update_act: ServerAction<UpdateVersificationScheme>,
+    delete_act: ServerAction<DeleteVersificationScheme>,
+) -> impl IntoView {
+    let is_static = STATIC_VERSIFICATION_SCHEMES.contains(&scheme.full_name.as_str());
+    let full_name = RwSignal::new(scheme.full_name.clone());
+    let shorthand = RwSignal::new(scheme.shorthand.clone());
+    let id = scheme.id;
+
+    view! {
+        <div class="table-row odd:bg-slate-800 even:bg-slate-600">
+            <div class="table-cell p-2">
+                <input
+                    class="w-full bg-inherit"
+                    disabled=is_static
+                    prop:value=move || full_name.get()
+                    on:input:target=move |ev| {
+                        full_name.set(ev.target().value());
+                    }
+                />
+            </div>
+            <div class="table-cell p-2">
+                <input
+                    class="w-full bg-inherit font-mono"
+                    disabled=is_static
+                    prop:value=move || shorthand.get()
+                    on:input:target=move |ev| {
+                        shorthand.set(ev.target().value());
+                    }
+                />
+            </div>
+            <div class="table-cell p-2">
+                {if is_static {
+                    view! { <p class="text-slate-400">"built in"</p> }.into_any()
+                } else {
+                    view! {
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            type="button"
+                            on:click=move |_| {
+                                update_act
+                                    .dispatch(UpdateVersificationScheme {
+                                        scheme: VersificationScheme {
+                                            id,
+                                            full_name: full_name.get(),
+                                            shorthand: shorthand.get(),
+                                        },
+                                    });
+                            }
+                        >
+                            "Save"
+                        </button>
+                        <button
+                            class=format!("bg-red-700/60 {DEFAULT_BUTTON_CLASSES}")
+                            type="button"
+                            on:click=move |_| {
+                                delete_act.dispatch(DeleteVersificationScheme { id });
+                            }
+                        >
+                            "Delete"
+                        </button>
+                    }
+                        .into_any()
+                }}
+            </div>
+        </div>
+    }
+}
+
+/// List, add, edit and delete versification schemes
+#[component]
+pub fn VersificationSchemes() -> impl IntoView {
+    let set_top_level_pos =
+        use_context::<WriteSignal<TopLevelPosition>>().expect("App provides TopLevelPosition");
+    *set_top_level_pos.write() = TopLevelPosition::Admin;
+
+    let add_act = ServerAction::<AddVersificationScheme>::new();
+    let update_act = ServerAction::<UpdateVersificationScheme>::new();
+    let delete_act = ServerAction::<DeleteVersificationScheme>::new();
+
+    // reload the schemes whenever one of the actions went through
+    let schemes = Resource::new(
+        move || {
+            (
+                add_act.version().get(),
+                update_act.version().get(),
+                delete_act.version().get(),
+            )
+        },
+        async |_| list_versification_schemes().await,
+    );
+
+    let action_error = move || {
+        [
+            add_act.value().get(),
+            update_act.value().get(),
+            delete_act.value().get(),
+        ]
+        .into_iter()
+        .find_map(|res| match res {
+            Some(Err(e)) => Some(e.to_string()),
+            _ => None,
+        })
+    };
+
+    let new_full_name = RwSignal::new(String::new());
+    let new_shorthand = RwSignal::new(String::new());
+
+    view! {
+        <div class="flex h-full flex-col">
+            <div class="flex flex-row justify-center">
+                <h1 class="p-10 text-6xl font-semibold">Versification Schemes</h1>
+            </div>
+            <div class="flex flex-row justify-center">
+                <div class="w-3/4">
+                    <div class="bg-red-200 text-black">{action_error}</div>
+                    <p class="m-2 text-slate-300">
+                        "Schemes that are used by anchors in transcriptions or by verse mappings cannot be changed or deleted."
+                    </p>
+                    <div class="table w-full text-xl">
+                        <div class="table-header-group font-bold">
+                            <div class="table-row">
+                                <div class="table-cell p-2">"Full Name"</div>
+                                <div class="table-cell p-2">"Shorthand"</div>
+                                <div class="table-cell p-2"></div>
+                            </div>
+                        </div>
+                        <div class="table-row-group">
+                            <Transition fallback=|| {
+                                view! { <p>"Loading versification schemes..."</p> }
+                            }>
+                                {move || {
+                                    schemes
+                                        .get()
+                                        .map(|schemes_res| match schemes_res {
+                                            Ok(schemes) => {
+                                                schemes
+                                                    .into_iter()
+                                                    .map(|scheme| {
+                                                        view! {
+                                                            <SchemeRow
+                                                                scheme=scheme
+                                                                update_act=update_act
+                                                                delete_act=delete_act
+                                                            />
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                                    .into_any()
+                                            }
+                                            Err(e) => {
+                                                view! {
+                                                    <p>
+                                                        "Unable to get versification schemes: "
+                                                        {e.to_string()}
+                                                    </p>
+                                                }
+                                                    .into_any()
+                                            }
+                                        })
+                                }}
+                            </Transition>
+                            <div class="table-row">
+                                <div class="table-cell p-2">
+                                    <input
+                                        class="w-full"
+                                        placeholder="Full name"
+                                        prop:value=move || new_full_name.get()
+                                        on:input:target=move |ev| {
+                                            new_full_name.set(ev.target().value());
+                                        }
+                                    />
+                                </div>
+                                <div class="table-cell p-2">
+                                    <input
+                                        class="w-full font-mono"
+                                        placeholder="Shorthand"
+                                        prop:value=move || new_shorthand.get()
+                                        on:input:target=move |ev| {
+                                            new_shorthand.set(ev.target().value());
+                                        }
+                                    />
+                                </div>
+                                <div class="table-cell p-2">
+                                    <button
+                                        class=DEFAULT_BUTTON_CLASSES
+                                        type="button"
+                                        on:click=move |_| {
+                                            add_act
+                                                .dispatch(AddVersificationScheme {
+                                                    full_name: new_full_name.get(),
+                                                    shorthand: new_shorthand.get(),
+                                                });
+                                            new_full_name.set(String::new());
+                                            new_shorthand.set(String::new());
+                                        }
+                                    >
+                                        "Add"
+                                    </button>
+                                </div>
+                            </div>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
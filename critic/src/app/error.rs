@@ -0,0 +1,107 @@
+//! The error type returned by server functions
+//!
+//! Server functions that only returned `ServerFnError::new(e.to_string())` left the frontend with
+//! nothing but a message. [`AppError`] keeps the kind of problem, so e.g. a manuscript that does
+//! not exist can be shown differently from a db that is down.
+
+use leptos::server_fn::{
+    codec::JsonEncoding,
+    error::{FromServerFnError, ServerFnErrorErr},
+};
+use serde::{Deserialize, Serialize};
+
+/// What went wrong in a server function, with a message for the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppError {
+    /// The requested thing does not exist
+    NotFound(String),
+    /// The request itself is invalid, e.g. a required field is empty
+    BadRequest(String),
+    /// The request conflicts with the current state, e.g. the name is already taken
+    Conflict(String),
+    /// The user is not logged in or may not do this
+    Unauthorized(String),
+    /// Anything else - the db is down, the request could not be sent, ...
+    Internal(String),
+}
+impl AppError {
+    /// The message, without the kind of error
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(msg)
+            | Self::BadRequest(msg)
+            | Self::Conflict(msg)
+            | Self::Unauthorized(msg)
+            | Self::Internal(msg) => msg,
+        }
+    }
+}
+impl core::fmt::Display for AppError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::NotFound(msg) => {
+                write!(f, "Not found: {msg}")
+            }
+            Self::BadRequest(msg) => {
+                write!(f, "Invalid request: {msg}")
+            }
+            Self::Conflict(msg) => {
+                write!(f, "Conflict: {msg}")
+            }
+            Self::Unauthorized(msg) => {
+                write!(f, "Unauthorized: {msg}")
+            }
+            Self::Internal(msg) => {
+                write!(f, "{msg}")
+            }
+        }
+    }
+}
+impl std::error::Error for AppError {}
+impl FromServerFnError for AppError {
+    type Encoder = JsonEncoding;
+
+    /// Failures of the server function machinery itself (e.g. the request could not be sent)
+    fn from_server_fn_error(value: ServerFnErrorErr) -> Self {
+        Self::Internal(value.to_string())
+    }
+}
+#[cfg(feature = "ssr")]
+impl From<critic_server::db::DBError> for AppError {
+    fn from(value: critic_server::db::DBError) -> Self {
+        use critic_server::db::DBError;
+        let is_unique_violation = |e: &sqlx::Error| {
+            e.as_database_error()
+                .is_some_and(|db_err| db_err.is_unique_violation())
+        };
+        match value {
+            DBError::ManuscriptDoesNotExist(_)
+            | DBError::PageDoesNotExist(_)
+            | DBError::VersificationSchemeDoesNotExist(_) => Self::NotFound(value.to_string()),
+            DBError::PageAlreadyExists
+            | DBError::PageHasTranscriptions(_)
+            | DBError::ManuscriptHasPublishedTranscriptions(_)
+            | DBError::VersificationSchemeInUse(_)
+            | DBError::VersificationSchemeChanged(_)
+            | DBError::StaticVersificationScheme(_)
+            | DBError::PageAlreadyUnderReconciliation(_)
+            | DBError::InvalidVerseRange(_)
+            | DBError::MinificationNotFailed(_)
+            | DBError::StaleUpdate(_)
+            | DBError::PageLocked(_)
+            | DBError::ManuscriptTitleTaken(_) => Self::Conflict(value.to_string()),
+            DBError::InvalidManuscriptTitle(_)
+            | DBError::InvalidPageName(_)
+            | DBError::InvalidUsername(_) => Self::BadRequest(value.to_string()),
+            DBError::CannotAddManuscript(ref e) | DBError::CannotInsertPage(ref e)
+                if is_unique_violation(e) =>
+            {
+                Self::Conflict(value.to_string())
+            }
+            _ => {
+                tracing::warn!("Database error in a server function: {value}");
+                Self::Internal(value.to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! The progress of the logged in user
+
+use critic_shared::{UserProfile, UserProgress};
+use leptos::prelude::*;
+
+use crate::app::TopLevelPosition;
+
+/// Get the config, the auth session and the logged in user for this request
+///
+/// Fails if nobody is logged in. Server functions returning a [`ServerFnError`] get the message of
+/// the [`AppError`](crate::app::error::AppError).
+#[cfg(feature = "ssr")]
+pub(crate) async fn logged_in() -> Result<
+    (
+        std::sync::Arc<critic_server::config::Config>,
+        critic_server::auth::AuthSession,
+        critic_server::auth::AuthenticatedUser,
+    ),
+    crate::app::error::AppError,
+> {
+    use crate::app::error::AppError;
+    use critic_server::auth::AuthSession;
+    use leptos_axum::extract;
+
+    let auth_session = match extract::<AuthSession>().await {
+        Ok(x) => x,
+        Err(e) => {
+            let msg = format!("Failed to get AuthSession: {e}");
+            tracing::warn!(msg);
+            return Err(AppError::Internal(msg));
+        }
+    };
+    let Some(user) = auth_session.user.clone() else {
+        return Err(AppError::Unauthorized(
+            "No usersession available".to_string(),
+        ));
+    };
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>().ok_or(
+        AppError::Internal("Unable to get config from context".to_string()),
+    )?;
+    Ok((config, auth_session, user))
+}
+
+#[server]
+async fn get_own_progress() -> Result<UserProgress, ServerFnError> {
+    let (config, _, user) = logged_in().await?;
+    critic_server::db::get_user_progress(&config.db, &user.username)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// The logged in user and what they may do
+///
+/// The result is cached in the session, so the oauth provider is only asked once per login.
+#[server]
+pub async fn whoami() -> Result<UserProfile, ServerFnError> {
+    let (config, auth_session, user) = logged_in().await?;
+    critic_server::auth::user_profile(config, &auth_session.session, &user)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to get the profile of {}: {e}", user.username);
+            ServerFnError::new(e.to_string())
+        })
+}
+
+/// The profile of the logged in user, loaded once by the App
+pub type Profile = OnceResource<Result<UserProfile, ServerFnError>>;
+
+/// Does the loaded profile satisfy `check`?
+///
+/// False while loading and when the profile cannot be loaded. This only hides controls, the
+/// server checks permissions again for every request.
+pub fn profile_allows(profile: Profile, check: impl Fn(&UserProfile) -> bool) -> bool {
+    profile
+        .get()
+        .and_then(Result::ok)
+        .is_some_and(|profile| check(&profile))
+}
+
+/// A single number with a label below it
+#[component]
+fn Stat(value: i64, label: &'static str) -> impl IntoView {
+    view! {
+        <div class="flex flex-col items-center rounded-4xl border-2 border-sky-600 bg-slate-700 p-8 shadow-lg shadow-sky-600">
+            <p class="text-6xl font-bold">{value}</p>
+            <p class="mt-2 text-xl text-slate-300">{label}</p>
+        </div>
+    }
+}
+
+/// Show the statistics for the own transcriptions and the pages still in progress
+#[component]
+pub fn OwnProgress() -> impl IntoView {
+    let set_top_level_pos =
+        use_context::<WriteSignal<TopLevelPosition>>().expect("App provides TopLevelPosition");
+    *set_top_level_pos.write() = TopLevelPosition::Me;
+
+    let progress = Resource::new(|| (), async |_| get_own_progress().await);
+
+    view! {
+        <div class="flex h-full flex-col">
+            <div class="flex flex-row justify-center">
+                <h1 class="p-10 text-6xl font-semibold">My Progress</h1>
+            </div>
+            <Transition fallback=|| {
+                view! { <p>"Loading your progress..."</p> }
+            }>
+                {move || {
+                    progress
+                        .get()
+                        .map(|progress_res| match progress_res {
+                            Ok(progress) => {
+                                view! {
+                                    <div class="flex flex-row justify-center">
+                                        <div class="grid w-3/4 grid-cols-3 gap-8">
+                                            <Stat
+                                                value=progress.pages_started
+                                                label="pages started"
+                                            />
+                                            <Stat
+                                                value=progress.pages_published
+                                                label="pages published"
+                                            />
+                                            <Stat
+                                                value=progress.pages_pending_reconciliation
+                                                label="pages waiting for reconciliation"
+                                            />
+                                        </div>
+                                    </div>
+                                    <div class="flex flex-row justify-center">
+                                        <div class="w-3/4">
+                                            <h2 class="mt-10 mb-4 text-4xl font-bold">
+                                                "In progress"
+                                            </h2>
+                                            {if progress.in_progress.is_empty() {
+                                                view! {
+                                                    <p class="text-slate-300">
+                                                        "You have no unpublished transcriptions."
+                                                    </p>
+                                                }
+                                                    .into_any()
+                                            } else {
+                                                view! {
+                                                    <div class="table w-full text-xl">
+                                                        {progress
+                                                            .in_progress
+                                                            .into_iter()
+                                                            .map(|(msname, pagename)| {
+                                                                view! {
+                                                                    <a
+                                                                        href=format!("/transcribe/{msname}/{pagename}")
+                                                                        class="table-row odd:bg-slate-800 even:bg-slate-600 hover:bg-sky-900"
+                                                                    >
+                                                                        <div class="table-cell p-2">{msname.clone()}</div>
+                                                                        <div class="table-cell p-2">{pagename.clone()}</div>
+                                                                    </a>
+                                                                }
+                                                            })
+                                                            .collect_view()}
+                                                    </div>
+                                                }
+                                                    .into_any()
+                                            }}
+                                        </div>
+                                    </div>
+                                }
+                                    .into_any()
+                            }
+                            Err(e) => {
+                                view! { <p>"Unable to get your progress: " {e.to_string()}</p> }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Transition>
+        </div>
+    }
+}
@@ -1,4 +1,5 @@
-use critic_shared::ShowHelp;
+use critic_components::toast::{provide_toasts, ToastStack};
+use critic_shared::{Branding, ShowHelp};
 use leptos::{ev::keydown, prelude::*};
 use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
 use leptos_router::{
@@ -8,9 +9,14 @@ use leptos_router::{
 
 use leptos_use::{use_document, use_event_listener};
 
+use me::{profile_allows, whoami, OwnProgress, Profile};
+use reconcile::ReconcileEditor;
 use transcribe::{editor::TranscribeEditor, todo::TranscribeTodoList};
 
 mod admin;
+pub mod error;
+mod me;
+mod reconcile;
 pub mod shared;
 mod transcribe;
 
@@ -41,11 +47,27 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
     }
 }
 
+/// The branding from the config, loaded once by the App
+type SiteBranding = OnceResource<Result<Branding, ServerFnError>>;
+
+#[server]
+async fn get_branding() -> Result<Branding, ServerFnError> {
+    let config: std::sync::Arc<critic_server::config::Config> =
+        use_context().ok_or(ServerFnError::new("Unable to get config from context"))?;
+    Ok(config.branding.clone())
+}
+
+/// The loaded branding, or the default one if it cannot be loaded
+fn current_branding(branding: SiteBranding) -> Branding {
+    branding.get().and_then(Result::ok).unwrap_or_default()
+}
+
 #[derive(Clone, PartialEq, Eq)]
 enum TopLevelPosition {
     Admin,
     Transcribe,
     Reconcile,
+    Me,
     None,
 }
 
@@ -80,10 +102,14 @@ fn NavBar(top_level_pos: ReadSignal<TopLevelPosition>) -> impl IntoView {
     let navbar_help_button_classes = "p-2 pl-4 pr-4 text-slate-50 hover:bg-slate-500 bg-slate-600 rounded-2xl text-2xl font-bold m-2 text-center shadow-md shadow-orange-400/70";
 
     let help_active = use_context::<RwSignal<ShowHelp>>().expect("App provides show-help context");
+    let profile = use_context::<Profile>().expect("App provides the user profile");
+    let branding = use_context::<SiteBranding>().expect("App provides the branding");
     view! {
         <nav class="flex flex-row justify-around bg-black border-b-4 border-slate-600">
             <a href="/logo">
-                <img alt="logo" src="/logo.webp" />
+                <Transition fallback=|| ()>
+                    <img alt="logo" src=move || current_branding(branding).logo_url />
+                </Transition>
             </a>
             <NavBarButton
                 to="/transcribe"
@@ -99,12 +125,19 @@ fn NavBar(top_level_pos: ReadSignal<TopLevelPosition>) -> impl IntoView {
             >
                 Reconcile
             </NavBarButton>
-            <NavBarButton
-                to="/admin"
-                top_level_pos=top_level_pos
-                active_state=&TopLevelPosition::Admin
-            >
-                Administer
+            <Transition fallback=|| ()>
+                <Show when=move || profile_allows(profile, |p| p.may_administer)>
+                    <NavBarButton
+                        to="/admin"
+                        top_level_pos=top_level_pos
+                        active_state=&TopLevelPosition::Admin
+                    >
+                        Administer
+                    </NavBarButton>
+                </Show>
+            </Transition>
+            <NavBarButton to="/me" top_level_pos=top_level_pos active_state=&TopLevelPosition::Me>
+                My Progress
             </NavBarButton>
             <span
                 on:click=move |_| { help_active.update(|a| a.toggle()) }
@@ -136,6 +169,11 @@ pub fn App() -> impl IntoView {
         }
     });
     provide_context(help_active);
+    provide_toasts();
+    let profile: Profile = OnceResource::new(whoami());
+    provide_context(profile);
+    let branding: SiteBranding = OnceResource::new(get_branding());
+    provide_context(branding);
 
     // will be set on page load by the top level routes
     let (top_level_pos, set_top_level_pos) = signal(TopLevelPosition::None);
@@ -147,7 +185,9 @@ pub fn App() -> impl IntoView {
         <Stylesheet id="leptos" href="/pkg/critic.css" />
 
         // sets the document title
-        <Title text="critic - textual criticism" />
+        <Transition fallback=|| ()>
+            <Title text=move || current_branding(branding).title />
+        </Transition>
 
         <div class="h-screen w-screen flex flex-col bg-slate-900 text-white">
             // Router
@@ -158,6 +198,8 @@ pub fn App() -> impl IntoView {
                         <Route path=StaticSegment("") view=HomePage />
                         <Route path=path!("transcribe") view=TranscribeTodoList />
                         <Route path=path!("transcribe/:msname/:pagename") view=TranscribeEditor />
+                        <Route path=path!("reconcile/:msname/:pagename") view=ReconcileEditor />
+                        <Route path=path!("me") view=OwnProgress />
                         <ParentRoute
                             path=path!("admin")
                             view=|| {
@@ -170,6 +212,7 @@ pub fn App() -> impl IntoView {
                     </Routes>
                 </main>
             </Router>
+            <ToastStack />
         </div>
     }
 }
@@ -178,11 +221,16 @@ pub fn App() -> impl IntoView {
 #[component]
 fn HomePage() -> impl IntoView {
     let show_help = use_context::<RwSignal<ShowHelp>>().expect("Main page provides ShowHelp");
+    let branding = use_context::<SiteBranding>().expect("App provides the branding");
 
     view! {
         <div class="flex flex-row justify-center">
             <div>
-                <h1 class="p-10 text-6xl font-semibold">Welcome to Critic</h1>
+                <h1 class="p-10 text-6xl font-semibold">
+                    <Transition fallback=|| ()>
+                        {move || current_branding(branding).welcome_message}
+                    </Transition>
+                </h1>
                 <div class="relative pt-6 text-lg">
                     <p class="text-center">
                         "On many pages, you can press"
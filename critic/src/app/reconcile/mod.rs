@@ -0,0 +1,215 @@
+//! Components and server functions to reconcile two published transcriptions of a page
+
+use critic_components::{
+    reconcile::{AlignedBlock, AlignmentView},
+    DEFAULT_BUTTON_CLASSES,
+};
+use leptos::prelude::*;
+use leptos_router::hooks::use_params;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    shared::{MsParams, PageParams},
+    TopLevelPosition,
+};
+
+/// Two published transcriptions of a page aligned against each other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reconciliation {
+    /// The user who published the left transcription
+    pub left_user: String,
+    /// The user who published the right transcription
+    pub right_user: String,
+    pub alignment: Vec<AlignedBlock>,
+}
+
+#[server]
+async fn get_reconciliation(
+    msname: String,
+    pagename: String,
+) -> Result<Reconciliation, ServerFnError> {
+    use critic_server::{
+        auth::AuthSession, db::get_published_transcribers,
+        transcription_store::read_transcription_from_disk,
+    };
+    use leptos_axum::extract;
+
+    let auth_session = match extract::<AuthSession>().await {
+        Ok(x) => x,
+        Err(e) => {
+            let msg = format!("Failed to get AuthSession: {e}");
+            tracing::warn!(msg);
+            return Err(ServerFnError::new(msg));
+        }
+    };
+    if auth_session.user.is_none() {
+        return Err(ServerFnError::new("No usersession available"));
+    };
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
+        .ok_or(ServerFnError::new("Unable to get config from context"))?;
+
+    let transcribers = get_published_transcribers(&config.db, &msname, &pagename)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let [left_user, right_user, ..] = transcribers.as_slice() else {
+        return Err(ServerFnError::new(format!(
+            "Reconciliation needs two published transcriptions, but {msname}/{pagename} has {}.",
+            transcribers.len()
+        )));
+    };
+    let default_language = config
+        .manuscript_cache
+        .manuscript_meta(&config.db, &msname)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .lang;
+
+    let read_blocks = |username: &str| {
+        read_transcription_from_disk(
+            &config.data_directory,
+            &msname,
+            &pagename,
+            username,
+            &default_language,
+        )
+        .map(|(blocks, _pagename)| blocks)
+        .map_err(|e| {
+            tracing::warn!("Failed to read transcription /{msname}/{pagename}/{username}: {e}");
+            ServerFnError::new(format!(
+                "Transcription /{msname}/{pagename}/{username} is not readable from disk: {e}"
+            ))
+        })
+    };
+    let left = read_blocks(left_user)?;
+    let right = read_blocks(right_user)?;
+
+    Ok(Reconciliation {
+        left_user: left_user.clone(),
+        right_user: right_user.clone(),
+        alignment: critic_components::reconcile::align_blocks(&left, &right),
+    })
+}
+
+#[server]
+async fn start_reconciliation(msname: String, pagename: String) -> Result<(), ServerFnError> {
+    use critic_server::auth::AuthSession;
+    use leptos_axum::extract;
+
+    let auth_session = match extract::<AuthSession>().await {
+        Ok(x) => x,
+        Err(e) => {
+            let msg = format!("Failed to get AuthSession: {e}");
+            tracing::warn!(msg);
+            return Err(ServerFnError::new(msg));
+        }
+    };
+    let Some(user) = auth_session.user else {
+        return Err(ServerFnError::new("No usersession available"));
+    };
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
+        .ok_or(ServerFnError::new("Unable to get config from context"))?;
+    crate::app::transcribe::authorize_transcriber(config.clone(), &user).await?;
+
+    critic_server::db::start_reconciliation(&config.db, &msname, &pagename, &user.username)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    tracing::info!(
+        "User {} started reconciling {msname}/{pagename}.",
+        user.username
+    );
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "start_reconciliation",
+        &format!("{msname}/{pagename}"),
+        serde_json::json!({}),
+    )
+    .await;
+    Ok(())
+}
+
+/// Show the differences between two transcriptions of a page
+#[component]
+pub fn ReconcileEditor() -> impl IntoView {
+    let set_top_level_pos =
+        use_context::<WriteSignal<TopLevelPosition>>().expect("App provides TopLevelPosition");
+    *set_top_level_pos.write() = TopLevelPosition::Reconcile;
+
+    let ms_params = use_params::<MsParams>();
+    let page_params = use_params::<PageParams>();
+    let names = move || {
+        (
+            ms_params.get().ok().and_then(|p| p.msname),
+            page_params.get().ok().and_then(|p| p.pagename),
+        )
+    };
+
+    let reconciliation = Resource::new(names, async |(msname, pagename)| {
+        if let (Some(msname), Some(pagename)) = (msname, pagename) {
+            get_reconciliation(msname, pagename).await
+        } else {
+            Err(ServerFnError::new(
+                "Failed to get manuscript name and page name from the url.",
+            ))
+        }
+    });
+
+    let start_act = ServerAction::<StartReconciliation>::new();
+    let start_message = move || match start_act.value().get() {
+        Some(Ok(())) => Some("This page is now marked as under reconciliation.".to_string()),
+        Some(Err(e)) => Some(e.to_string()),
+        None => None,
+    };
+
+    view! {
+        <div class="flex h-full flex-col overflow-y-auto">
+            <div class="flex flex-row justify-center">
+                <h1 class="p-10 text-6xl font-semibold">
+                    "Reconcile "
+                    {move || {
+                        let (msname, pagename) = names();
+                        format!("{} - {}", msname.unwrap_or_default(), pagename.unwrap_or_default())
+                    }}
+                </h1>
+            </div>
+            <div class="flex flex-row justify-center">
+                <div class="w-4/5">
+                    <div class="flex justify-center">
+                        <button
+                            class=DEFAULT_BUTTON_CLASSES
+                            on:click=move |_| {
+                                if let (Some(msname), Some(pagename)) = names() {
+                                    start_act.dispatch(StartReconciliation { msname, pagename });
+                                }
+                            }
+                        >
+                            "Start reconciling this page"
+                        </button>
+                    </div>
+                    <p class="text-center text-orange-400">{start_message}</p>
+                    <Transition fallback=|| {
+                        view! { <p>"Loading transcriptions..."</p> }
+                    }>
+                        {move || {
+                            reconciliation
+                                .get()
+                                .map(|res| match res {
+                                    Ok(rec) => {
+                                        view! {
+                                            <AlignmentView
+                                                alignment=rec.alignment
+                                                left_name=rec.left_user
+                                                right_name=rec.right_user
+                                            />
+                                        }
+                                            .into_any()
+                                    }
+                                    Err(e) => view! { <p>{e.to_string()}</p> }.into_any(),
+                                })
+                        }}
+                    </Transition>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1,6 +1,6 @@
 //! Shared Types and functions accross the app
 
-use leptos::prelude::*;
+use leptos::{either::Either, prelude::*};
 use leptos_router::params::Params;
 
 #[derive(Params, Clone, PartialEq)]
@@ -12,3 +12,29 @@ pub struct MsParams {
 pub struct PageParams {
     pub pagename: Option<String>,
 }
+
+/// Marks pages whose webp images do not exist (yet), nothing for minified pages
+#[component]
+pub fn MinificationBadge(minified: bool, minification_failed: bool) -> impl IntoView {
+    if minification_failed {
+        Some(Either::Left(view! {
+            <span
+                class="m-1 rounded-md bg-red-700 px-1 text-sm"
+                title="The images for this page could not be created, retry under Minification."
+            >
+                "processing failed"
+            </span>
+        }))
+    } else if !minified {
+        Some(Either::Right(view! {
+            <span
+                class="m-1 rounded-md bg-slate-500 px-1 text-sm"
+                title="The images for this page are still being created."
+            >
+                "processing…"
+            </span>
+        }))
+    } else {
+        None
+    }
+}
@@ -3,144 +3,350 @@
 //! this shows the editor, the publish button, rendering to html and xml and so on
 
 use critic_components::{
+    confirm::ConfirmDialog,
     editor::{blocks::EditorBlock, Editor},
+    imageviewer::ImageViewer,
+    toast::{push_toast, ToastKind},
     xmleditor::{XmlEditor, XmlState},
+    DEFAULT_BUTTON_CLASSES,
 };
 use critic_format::streamed::Block;
 use critic_shared::{
     urls::{IMAGE_BASE_LOCATION, STATIC_BASE_URL},
-    ShowHelp,
+    AdjacentPages, ManuscriptMeta, ShowHelp,
 };
 use leptos::{
     either::{Either, EitherOf3},
+    ev::{beforeunload, click, keydown},
     prelude::*,
 };
-use leptos_router::hooks::use_params;
+use leptos_router::hooks::{use_navigate, use_params};
+use leptos_use::{
+    use_document, use_event_listener, use_event_listener_with_options, use_window,
+    UseEventListenerOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use web_sys::wasm_bindgen::JsCast;
 
+use super::{lock::PageLock, presence::PagePresence};
 use crate::app::{
-    shared::{MsParams, PageParams},
+    error::AppError,
+    shared::{MinificationBadge, MsParams, PageParams},
     EmptyError, TopLevelPosition,
 };
 
-/// WIP.
-/// Get the starting information for this page.
-/// If the user has started this transcription already, use that.
-/// Otherwise, the initial content is approximated using Basetext-Indexing:
-/// - run OCR, find out which text is on this page
-/// - find out where the column breaks are, add the relevant basetext column-by-column
+/// Load the transcription of this page by the current user for editing
+///
+/// If the user has not saved a transcription for this page yet, an empty seed for the verses on
+/// this page is returned instead.
 /// Result is
-///     (Manuscript to initialize the editor with, default-language)
+///     (blocks to initialize the editor with, default-language, version of the transcription
+///     on disk if the user has started it, first and last verse on the page if this is a new
+///     transcription and both are known)
+///
+/// The editor itself uses [`get_editor_bootstrap`], this is kept for existing clients.
 #[server]
-async fn get_initial_ms(
+pub async fn load_transcription(
     msname: String,
     pagename: String,
-) -> Result<(Vec<Block>, String), ServerFnError> {
-    use critic_format::streamed::Block;
-    use critic_server::{
-        auth::AuthSession, db::get_editor_initial_value,
-        transcription_store::read_transcription_from_disk,
-    };
-    use leptos_axum::extract;
-    let auth_session = match extract::<AuthSession>().await {
-        Ok(x) => x,
-        Err(e) => {
-            let msg = format!("Failed to get AuthSession: {e}");
-            tracing::warn!(msg);
-            return Err(ServerFnError::new(msg));
-        }
-    };
-    let Some(user) = auth_session.user else {
-        return Err(ServerFnError::new("No usersession available"));
-    };
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
-    let initial_seed = get_editor_initial_value(&config.db, &msname, &pagename, &user.username)
+) -> Result<(Vec<Block>, String, Option<String>, Option<(i64, i64)>), ServerFnError> {
+    let bootstrap = get_editor_bootstrap(msname, pagename)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let verses = bootstrap.seed_verses();
+    Ok((
+        bootstrap.blocks,
+        bootstrap.meta.lang,
+        bootstrap.version,
+        verses,
+    ))
+}
 
-    // TODO: first get default language from the DB
-    let default_language = initial_seed.meta.lang;
-
-    if initial_seed.user_has_started {
-        Ok((
-                read_transcription_from_disk(&config.data_directory, &msname, &pagename, &user.username, &default_language)
-                    .map(|(blocks, _pagename)| blocks)
-                    .map_err(|e| ServerFnError::new(format!("Transcription /{msname}/{pagename}/{} should exist but is not readable from disk: {e}", user.username)))?,
-                default_language))
-    } else {
-        // TODO - do the whole indexing and find the right place in the base text
-        // WIP
-        Ok((
-            vec![
-                Block::Text(critic_format::streamed::Paragraph {
-                    lang: "".to_string(),
-                    content: "WIP - In the future, the correct part of the basetext will automatically be put here.".to_string()})
-            ],
-            default_language
-        ))
+/// Everything the transcription editor needs to start, see [`get_editor_bootstrap`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorBootstrap {
+    pub meta: ManuscriptMeta,
+    pub verse_start: Option<i64>,
+    pub verse_end: Option<i64>,
+    /// Tiny version of the page image, shown while the image loads
+    pub placeholder: Option<String>,
+    /// The webp versions of the page image exist
+    pub minified: bool,
+    /// Creating the webp versions failed
+    pub minification_failed: bool,
+    /// Whether the current user started a transcription of this page
+    pub user_has_started: bool,
+    /// The transcription of the current user, or a seed if they have not saved one yet
+    pub blocks: Vec<Block>,
+    /// Version of the transcription on disk, None if `blocks` is a seed
+    pub version: Option<String>,
+}
+impl EditorBootstrap {
+    /// First and last verse on the page, if this is a new transcription and both are known
+    fn seed_verses(&self) -> Option<(i64, i64)> {
+        if self.version.is_none() {
+            self.verse_start.zip(self.verse_end)
+        } else {
+            None
+        }
     }
 }
 
+/// Load the manuscript, page and transcription of the current user for the editor in one go
+///
+/// Like [`load_transcription`], but also returns what the db knows about the page.
+/// Fails with [`AppError::NotFound`] if there is no such page, and with
+/// [`AppError::Unauthorized`] if the user may not transcribe.
+#[server]
+pub async fn get_editor_bootstrap(
+    msname: String,
+    pagename: String,
+) -> Result<EditorBootstrap, AppError> {
+    use critic_server::{db::get_editor_initial_value, transcription_store::load_for_editor};
+
+    let (config, user) = super::current_transcriber().await?;
+    let initial = get_editor_initial_value(&config.db, &msname, &pagename, &user.username).await?;
+    let (blocks, version) = load_for_editor(
+        &config.data_directory,
+        &msname,
+        &pagename,
+        &user.username,
+        &initial,
+    )
+    .map_err(|e| {
+        AppError::Internal(format!(
+            "Transcription /{msname}/{pagename}/{} is not readable from disk: {e}",
+            user.username
+        ))
+    })?;
+    Ok(EditorBootstrap {
+        meta: initial.meta,
+        verse_start: initial.verse_start,
+        verse_end: initial.verse_end,
+        placeholder: initial.placeholder,
+        minified: initial.minified,
+        minification_failed: initial.minification_failed,
+        user_has_started: initial.user_has_started,
+        blocks,
+        version,
+    })
+}
+
+/// What happened when saving a transcription
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SaveOutcome {
+    /// The transcription was saved and now has this version
+    Saved(String),
+    /// The transcription on disk was changed since it was loaded and was not overwritten
+    Conflict,
+    /// The page (or its manuscript) does not exist (anymore) - reason
+    NotFound(String),
+    /// The user may not save a transcription for this page - reason
+    NotAllowed(String),
+    /// The blocks cannot be turned into a valid transcription - reason
+    Invalid(String),
+}
+
+/// Save a transcription of this page for the current user, unless it was changed since `version`
+/// was loaded
+///
+/// The transcription is written to disk and recorded in the db, so that the user shows up as having
+/// started this page.
 #[server]
 pub async fn save_transcription(
     blocks: Vec<Block>,
     msname: String,
     pagename: String,
-) -> Result<(), ServerFnError> {
-    use critic_server::{auth::AuthSession, transcription_store::write_transcription_to_disk};
-    use leptos_axum::extract;
+    version: Option<String>,
+) -> Result<SaveOutcome, ServerFnError> {
+    use critic_components::editor::anchors::{describe_anchor_problems, find_anchor_problems};
+    use critic_server::transcription_store::{
+        write_transcription_to_disk, TranscriptionStoreError,
+    };
 
-    let auth_session = match extract::<AuthSession>().await {
-        Ok(x) => x,
-        Err(e) => {
-            let msg = format!("Failed to get AuthSession: {e}");
-            tracing::warn!(msg);
-            return Err(ServerFnError::new(msg));
-        }
+    let (config, user) = super::current_transcriber().await?;
+
+    // do not leave files behind for pages that do not exist
+    if !critic_server::db::page_exists(&config.db, &msname, &pagename).await? {
+        return Ok(SaveOutcome::NotFound(format!(
+            "The page {pagename} does not exist in {msname}."
+        )));
     };
-    let Some(user) = auth_session.user else {
-        return Err(ServerFnError::new("No usersession available"));
+
+    if let Some(message) = describe_anchor_problems(&find_anchor_problems(&blocks)) {
+        return Ok(SaveOutcome::Invalid(message));
+    };
+
+    // saving also keeps the lock alive, in case the heartbeat of the editor got lost
+    if config.locking.enabled {
+        match critic_server::db::acquire_lock(
+            &config.db,
+            &msname,
+            &pagename,
+            &user.username,
+            config.locking.ttl_seconds,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(e @ critic_server::db::DBError::PageLocked(_)) => {
+                return Ok(SaveOutcome::NotAllowed(e.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
     };
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
 
-    write_transcription_to_disk(
+    let new_version = match write_transcription_to_disk(
         blocks,
         &config.data_directory,
         &msname,
         pagename.to_string(),
         &user.username,
-    )?;
+        version.as_deref(),
+    ) {
+        Ok(x) => x,
+        Err(TranscriptionStoreError::Conflict(path)) => {
+            tracing::info!("Refusing to overwrite {path} that changed since it was loaded.");
+            return Ok(SaveOutcome::Conflict);
+        }
+        Err(e @ TranscriptionStoreError::PathInvalid(_)) => {
+            return Ok(SaveOutcome::NotAllowed(e.to_string()));
+        }
+        Err(
+            e @ (TranscriptionStoreError::Ser(_)
+            | TranscriptionStoreError::DeNorm(_)
+            | TranscriptionStoreError::DeStream(_)),
+        ) => {
+            return Ok(SaveOutcome::Invalid(e.to_string()));
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
     // save the fact that this transcription exists to the DB
     critic_server::db::add_transcription(&config.db, &msname, &pagename, &user.username).await?;
-    Ok(())
+    Ok(SaveOutcome::Saved(new_version))
+}
+
+/// Save the blocks and keep track of the version of the transcription on disk
+///
+/// Sets `conflict` if the transcription was changed elsewhere in the meantime.
+async fn save_versioned(
+    blocks: Vec<Block>,
+    msname: String,
+    pagename: String,
+    version: RwSignal<Option<String>>,
+    conflict: RwSignal<bool>,
+) -> Result<(), ServerFnError> {
+    match save_transcription(blocks, msname, pagename, version.get_untracked()).await? {
+        SaveOutcome::Saved(new_version) => {
+            version.set(Some(new_version));
+            Ok(())
+        }
+        SaveOutcome::Conflict => {
+            conflict.set(true);
+            Err(ServerFnError::new(
+                "This transcription was changed in another tab or window since you loaded it.",
+            ))
+        }
+        SaveOutcome::NotFound(reason) => Err(ServerFnError::new(format!(
+            "This transcription cannot be saved: {reason}"
+        ))),
+        SaveOutcome::NotAllowed(reason) => Err(ServerFnError::new(format!(
+            "You may not save this transcription: {reason}"
+        ))),
+        SaveOutcome::Invalid(reason) => Err(ServerFnError::new(format!(
+            "This transcription is not valid: {reason}"
+        ))),
+    }
 }
 
 #[server]
 pub async fn publish_transcription(msname: String, pagename: String) -> Result<(), ServerFnError> {
-    use critic_server::auth::AuthSession;
-    use leptos_axum::extract;
+    let (config, user) = super::current_transcriber().await?;
+    super::check_page_lock(&config, &msname, &pagename, &user.username).await?;
 
-    let auth_session = match extract::<AuthSession>().await {
-        Ok(x) => x,
-        Err(e) => {
-            let msg = format!("Failed to get AuthSession: {e}");
-            tracing::warn!(msg);
-            return Err(ServerFnError::new(msg));
-        }
-    };
-    let Some(user) = auth_session.user else {
-        return Err(ServerFnError::new("No usersession available"));
+    // only publish what is actually on disk and can be read back, later saves do not change it
+    let default_language =
+        critic_server::db::get_editor_initial_value(&config.db, &msname, &pagename, &user.username)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?
+            .meta
+            .lang;
+    if let Err(e) = critic_server::transcription_store::snapshot_published_transcription(
+        &config.data_directory,
+        &msname,
+        &pagename,
+        &user.username,
+        &default_language,
+    ) {
+        return Err(ServerFnError::new(format!(
+            "Save a valid transcription before publishing it: {e}"
+        )));
     };
-    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
-        .ok_or(ServerFnError::new("Unable to get config from context"))?;
 
     critic_server::db::publish_transcription(&config.db, &msname, &pagename, &user.username)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "publish_transcription",
+        &format!("{msname}/{pagename}"),
+        serde_json::json!({}),
+    )
+    .await;
     Ok(())
 }
 
+/// Take back a published transcription
+///
+/// This is only possible as long as nobody started reconciling the page.
+#[server]
+pub async fn unpublish_transcription(
+    msname: String,
+    pagename: String,
+) -> Result<(), ServerFnError> {
+    let (config, user) = super::current_transcriber().await?;
+    super::check_page_lock(&config, &msname, &pagename, &user.username).await?;
+
+    critic_server::db::unpublish_transcription(&config.db, &msname, &pagename, &user.username)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    // it is not shown anymore either way, publishing again takes a new snapshot
+    if let Err(e) = critic_server::transcription_store::remove_published_transcription(
+        &config.data_directory,
+        &msname,
+        &pagename,
+        &user.username,
+    ) {
+        tracing::warn!("Failed to remove published transcription /{msname}/{pagename}: {e}");
+    };
+    critic_server::audit::record_audit(
+        &config.db,
+        &user.username,
+        "unpublish_transcription",
+        &format!("{msname}/{pagename}"),
+        serde_json::json!({}),
+    )
+    .await;
+    Ok(())
+}
+
+/// Get the pages before and after this one in the manuscript
+#[server]
+pub async fn get_adjacent_pages(
+    msname: String,
+    pagename: String,
+) -> Result<AdjacentPages, ServerFnError> {
+    let config = use_context::<std::sync::Arc<critic_server::config::Config>>()
+        .ok_or(ServerFnError::new("Unable to get config from context"))?;
+    critic_server::db::get_adjacent_pages(&config.db, &msname, &pagename)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
+
 /// The main component for the transcription editor page
 #[component]
 pub fn TranscribeEditor() -> impl IntoView {
@@ -166,21 +372,40 @@ pub fn TranscribeEditor() -> impl IntoView {
                 .and_then(|x| x.pagename.clone()),
         )
     };
+    // the same, but rerun when navigating to another page
+    let both_names_tracked = move || {
+        (
+            ms_param.read().as_ref().ok().and_then(|x| x.msname.clone()),
+            page_param
+                .read()
+                .as_ref()
+                .ok()
+                .and_then(|x| x.pagename.clone()),
+        )
+    };
+    let navigate = use_navigate();
     // get initial state from the server
-    let blocks_res = Resource::new(both_names, async |(ms_name_opt, page_name_opt)| {
+    let blocks_res = Resource::new(both_names_tracked, async |(ms_name_opt, page_name_opt)| {
         if let (Some(x), Some(y)) = (ms_name_opt, page_name_opt) {
-            get_initial_ms(x, y).await
+            get_editor_bootstrap(x, y).await
         } else {
-            Err(ServerFnError::new(
-                "Did not get both Manuscript and Page name to fetch initial data.",
+            Err(AppError::NotFound(
+                "Did not get both Manuscript and Page name to fetch initial data.".to_string(),
             ))
         }
     });
+    let adjacent_res = Resource::new(both_names_tracked, async |(ms_name_opt, page_name_opt)| {
+        if let (Some(x), Some(y)) = (ms_name_opt, page_name_opt) {
+            get_adjacent_pages(x, y).await
+        } else {
+            Ok(AdjacentPages::default())
+        }
+    });
 
     view! {
         <div class="flex h-full flex-col">
             <h1 class="p-10 text-center text-6xl font-semibold">
-                "Transcribing "{move || both_names().1}
+                "Transcribing "{move || both_names_tracked().1}
             </h1>
             // show links to the image
             <ErrorBoundary fallback=|_errors| {
@@ -191,8 +416,9 @@ pub fn TranscribeEditor() -> impl IntoView {
                         ms_param.get().map(|p| p.msname).unwrap_or(None),
                         page_param.get().map(|p| p.pagename).unwrap_or(None),
                     ) {
+                        // the server picks the best format the browser supports
                         let image_link = format!(
-                            "{STATIC_BASE_URL}{IMAGE_BASE_LOCATION}/{msname}/{pagename}/original.webp",
+                            "{STATIC_BASE_URL}{IMAGE_BASE_LOCATION}/{msname}/{pagename}",
                         );
                         Ok(
                             view! {
@@ -238,12 +464,44 @@ pub fn TranscribeEditor() -> impl IntoView {
                 <Transition fallback=|| {
                     view! { <p>"Loading manuscripts..."</p> }
                 }>
+                    // a mistyped or outdated link, not an error
+                    {move || match blocks_res.get() {
+                        Some(Err(AppError::NotFound(msg))) => {
+                            Some(
+                                view! {
+                                    <div class="m-4">
+                                        <p class="text-xl">"No such page"</p>
+                                        <p class="text-slate-400">{msg}</p>
+                                    </div>
+                                },
+                            )
+                        }
+                        _ => None,
+                    }}
                     {move || {
                         blocks_res
                             .get()
+                            .filter(|res| !matches!(res, Err(AppError::NotFound(_))))
                             .map(|blocks_or_err| {
                                 blocks_or_err
-                                    .map(|(blocks, default_lang)| {
+                                    .map(|bootstrap| {
+                                        let verses = bootstrap.seed_verses();
+                                        let EditorBootstrap {
+                                            meta,
+                                            blocks,
+                                            version: initial_version,
+                                            placeholder,
+                                            minified,
+                                            minification_failed,
+                                            ..
+                                        } = bootstrap;
+                                        let default_lang = meta.lang;
+                                        // version of the transcription on disk that this editor
+                                        // is based on
+                                        let version = RwSignal::new(initial_version);
+                                        let conflict = RwSignal::new(false);
+                                        let reload_confirm_open = RwSignal::new(false);
+                                        let loaded_blocks = StoredValue::new(blocks.clone());
                                         let blocks = RwSignal::new(
                                             blocks
                                                 .into_iter()
@@ -264,7 +522,13 @@ pub fn TranscribeEditor() -> impl IntoView {
                                                 .collect();
                                             async move {
                                                 if let (Some(msname), Some(pagename)) = both_names() {
-                                                    save_transcription(blocks_dehydrated, msname, pagename)
+                                                    save_versioned(
+                                                            blocks_dehydrated,
+                                                            msname,
+                                                            pagename,
+                                                            version,
+                                                            conflict,
+                                                        )
                                                         .await
                                                 } else {
                                                     Ok(())
@@ -280,10 +544,12 @@ pub fn TranscribeEditor() -> impl IntoView {
                                                 .collect();
                                             async move {
                                                 if let (Some(msname), Some(pagename)) = both_names() {
-                                                    save_transcription(
+                                                    save_versioned(
                                                             blocks_dehydrated,
                                                             msname.clone(),
                                                             pagename.clone(),
+                                                            version,
+                                                            conflict,
                                                         )
                                                         .await?;
                                                     publish_transcription(msname, pagename).await
@@ -292,17 +558,130 @@ pub fn TranscribeEditor() -> impl IntoView {
                                                 }
                                             }
                                         });
-                                        both_names()
-                                            .1
-                                            .map(|pagename| {
+                                        let navigate = navigate.clone();
+                                        // save changes (but do not start a transcription by
+                                        // merely passing through), then go to the other page
+                                        let go_to_page_action = Action::new_local(move |
+                                            target: &String|
+                                        {
+                                            let target = target.clone();
+                                            let current: Vec<Block> = blocks
+                                                .with_untracked(|b| {
+                                                    b.iter().map(|b| b.inner.clone().into()).collect()
+                                                });
+                                            let changed = loaded_blocks
+                                                .with_value(|loaded| *loaded != current);
+                                            let navigate = navigate.clone();
+                                            async move {
+                                                let (Some(msname), Some(pagename)) = both_names() else {
+                                                    return Ok(());
+                                                };
+                                                if changed {
+                                                    save_versioned(
+                                                            current,
+                                                            msname.clone(),
+                                                            pagename,
+                                                            version,
+                                                            conflict,
+                                                        )
+                                                        .await?;
+                                                }
+                                                navigate(
+                                                    &format!("/transcribe/{msname}/{target}"),
+                                                    Default::default(),
+                                                );
+                                                Ok(())
+                                            }
+                                        });
+                                        let (msname, pagename) = both_names();
+                                        msname
+                                            .zip(pagename)
+                                            .map(|(msname, pagename)| {
                                                 view! {
-                                                    <EditorWithTabs
-                                                        blocks=blocks
-                                                        default_language=default_lang
-                                                        on_save=save_state_action
-                                                        on_publish=publish_action
-                                                        pagename=pagename
+                                                    {move || {
+                                                        adjacent_res
+                                                            .get()
+                                                            .and_then(Result::ok)
+                                                            .map(|adjacent| {
+                                                                view! {
+                                                                    <PageNavigation
+                                                                        adjacent=adjacent
+                                                                        on_navigate=go_to_page_action
+                                                                    />
+                                                                }
+                                                            })
+                                                    }}
+                                                    <PageLock
+                                                        msname=msname.clone()
+                                                        pagename=pagename.clone()
+                                                    />
+                                                    <PagePresence
+                                                        msname=msname.clone()
+                                                        pagename=pagename.clone()
                                                     />
+                                                    <Show when=move || conflict.get()>
+                                                        <div class="mx-16 flex justify-between border border-slate-500 bg-red-700/40 p-2">
+                                                            <p class="flex flex-col justify-center">
+                                                                "This transcription was saved from another tab or window since you loaded it. Saving here would overwrite those changes."
+                                                            </p>
+                                                            <button
+                                                                class=DEFAULT_BUTTON_CLASSES
+                                                                on:click=move |_| reload_confirm_open.set(true)
+                                                            >
+                                                                "Reload (discards your changes here)"
+                                                            </button>
+                                                            <button
+                                                                class=DEFAULT_BUTTON_CLASSES
+                                                                on:click=move |_| conflict.set(false)
+                                                            >
+                                                                "Dismiss"
+                                                            </button>
+                                                        </div>
+                                                    </Show>
+                                                    <ConfirmDialog
+                                                        open=reload_confirm_open
+                                                        message=DISCARD_CHANGES_QUESTION
+                                                        confirm_label="Reload"
+                                                        on_confirm=move |()| blocks_res.refetch()
+                                                    />
+                                                    <div class="grid grid-cols-2">
+                                                        <div class="ml-16 my-4">
+                                                            {if minified {
+                                                                Either::Left(
+                                                                    view! {
+                                                                        <ImageViewer
+                                                                            msname=msname.clone()
+                                                                            pagename=pagename.clone()
+                                                                            placeholder=placeholder
+                                                                        />
+                                                                    },
+                                                                )
+                                                            } else {
+                                                                Either::Right(
+                                                                    view! {
+                                                                        <p class="text-slate-400">
+                                                                            "There is no image for this page yet."
+                                                                        </p>
+                                                                        <MinificationBadge
+                                                                            minified=minified
+                                                                            minification_failed=minification_failed
+                                                                        />
+                                                                    },
+                                                                )
+                                                            }}
+                                                        </div>
+                                                        <div>
+                                                            <EditorWithTabs
+                                                                blocks=blocks
+                                                                default_language=default_lang
+                                                                on_save=save_state_action
+                                                                on_publish=publish_action
+                                                                msname=msname
+                                                                pagename=pagename
+                                                                verses=verses
+                                                            />
+                                                        </div>
+                                                    </div>
                                                 }
                                             })
                                     })
@@ -347,6 +726,17 @@ const SHORTCUT_DESCRIPTIONS: &[(&str, &str, &str)] = &[
         "Delete the selection, marking the end of a line or column",
     ),
     ("c", "Check", "XML only: check that XML is valid."),
+    (
+        "p",
+        "Previous page",
+        "Save and go to the previous page of this manuscript",
+    ),
+    (
+        "n",
+        "Next page",
+        "Save and go to the next page of this manuscript",
+    ),
+    ("h", "Help", "Show or hide this overview"),
 ];
 
 #[component]
@@ -366,10 +756,14 @@ fn HelpOverlay(active: RwSignal<ShowHelp>) -> impl IntoView {
                 <p>
                     "You can use the normal Editor, view an approximated render of what you have entered so far, or edit the XML directly. Remember that when you edit XML, you need to convert it to the normal editor before saving or publishing to make sure the data is correct."
                 </p>
+                <p>
+                    "Your transcription is also saved automatically shortly after you stop editing, unless the XML has not been checked."
+                </p>
                 <p>
                     "You can use these keyboard shortcuts: "
                     <span class="text-2xl">ctrl + alt +</span>"..."
                 </p>
+                <p>"Press Escape or click anywhere to close this overview."</p>
                 <table class="table-fixed flex justify-around">
                     <tbody>
                         {SHORTCUT_DESCRIPTIONS
@@ -391,6 +785,324 @@ fn HelpOverlay(active: RwSignal<ShowHelp>) -> impl IntoView {
     }
 }
 
+/// How long after the last edit the transcription is saved automatically
+const DEFAULT_AUTOSAVE_DELAY: Duration = Duration::from_secs(30);
+
+/// Prefix of the session storage key holding the language of new blocks for a manuscript
+const NEW_BLOCK_LANGUAGE_KEY_PREFIX: &str = "critic-new-block-language-";
+
+/// The language new blocks are created with, starting out as `default_language`
+///
+/// Once changed, the language is kept in the session storage for the rest of the browser session,
+/// so it also applies to the other pages of the manuscript `msname`.
+fn use_new_block_language(msname: &str, default_language: String) -> RwSignal<String> {
+    let language = RwSignal::new(default_language.clone());
+    let key = format!("{NEW_BLOCK_LANGUAGE_KEY_PREFIX}{msname}");
+    // effects only run in the browser, where the session storage exists
+    Effect::new(move |restored: Option<()>| {
+        let storage = window().session_storage().ok().flatten();
+        if restored.is_none() {
+            if let Some(stored) = storage
+                .as_ref()
+                .and_then(|s| s.get_item(&key).ok().flatten())
+            {
+                language.set(stored);
+            };
+        };
+        let current = language.get();
+        if let Some(storage) = storage {
+            // only remember deliberate changes, so a changed manuscript default still applies
+            let res = if current == default_language {
+                storage.remove_item(&key)
+            } else {
+                storage.set_item(&key, &current)
+            };
+            if res.is_err() {
+                push_toast(
+                    ToastKind::Error,
+                    "Cannot remember the language of new blocks for this session.",
+                );
+            };
+        };
+    });
+    language
+}
+
+/// What [`use_autosave`] knows about the saved state of the blocks
+#[derive(Clone, Copy)]
+struct SaveState {
+    /// Local time of the last successful save (autosave or manual)
+    last_saved_at: ReadSignal<Option<String>>,
+    /// Do the blocks differ from what was last saved successfully?
+    unsaved_changes: ReadSignal<bool>,
+}
+
+/// Save the blocks with `on_save` once they have not been edited for `delay`
+///
+/// Autosave is skipped while another save is pending, while the XML editor contains XML that was
+/// not successfully checked and when nothing changed since the last successful save.
+fn use_autosave(
+    blocks: RwSignal<Vec<EditorBlock>>,
+    xml_state: RwSignal<XmlState>,
+    on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
+    delay: Duration,
+) -> SaveState {
+    let dehydrate = |blocks: &[EditorBlock]| {
+        blocks
+            .iter()
+            .map(|b| b.inner.clone().into())
+            .collect::<Vec<Block>>()
+    };
+    // what the server has, as far as we know
+    let saved = StoredValue::new(blocks.with_untracked(|b| dehydrate(b)));
+    // what is currently being saved
+    let in_flight = StoredValue::new(None::<Vec<Block>>);
+    let (last_saved_at, set_last_saved_at) = signal(None::<String>);
+    let (unsaved_changes, set_unsaved_changes) = signal(false);
+    // incremented on every edit, so that only the timer started by the last edit saves
+    let generation = StoredValue::new(0_usize);
+
+    // remember what is being saved, whoever dispatched the save
+    Effect::new(move |_| {
+        if let Some(input) = on_save.input().read().as_ref() {
+            in_flight.set_value(Some(dehydrate(input)));
+        };
+    });
+    Effect::new(move |_| {
+        if let Some(Ok(())) = *on_save.value().read() {
+            if let Some(just_saved) = in_flight.get_value() {
+                // the user may have continued editing while the save was running
+                set_unsaved_changes.set(blocks.with_untracked(|b| dehydrate(b)) != just_saved);
+                saved.set_value(just_saved);
+                in_flight.set_value(None);
+            };
+            set_last_saved_at.set(Some(
+                web_sys::js_sys::Date::new_0()
+                    .to_locale_time_string("default")
+                    .into(),
+            ));
+        };
+    });
+
+    Effect::new(move |previous: Option<()>| {
+        blocks.with(|b| b.iter().for_each(|block| block.inner.track()));
+        // the first run only subscribes, it is not an edit
+        if previous.is_none() {
+            return;
+        };
+        let current = blocks.with_untracked(|b| dehydrate(b));
+        set_unsaved_changes.set(saved.with_value(|s| *s != current));
+        generation.update_value(|g| *g += 1);
+        let this_generation = generation.get_value();
+        schedule_autosave(delay, move || {
+            if generation.get_value() != this_generation {
+                // a later edit started its own timer
+                return false;
+            };
+            if on_save.pending().get_untracked() {
+                // try again once the running save is done
+                return true;
+            };
+            if !matches!(xml_state.get_untracked(), XmlState::Checked) {
+                return false;
+            };
+            let current = blocks.with_untracked(|b| dehydrate(b));
+            if saved.with_value(|s| *s == current) {
+                return false;
+            };
+            on_save.dispatch(blocks.get_untracked());
+            false
+        });
+    });
+
+    SaveState {
+        last_saved_at,
+        unsaved_changes,
+    }
+}
+
+/// Asked before reloading a transcription that was saved elsewhere, dropping the changes here
+const DISCARD_CHANGES_QUESTION: &str =
+    "Reload the transcription as it was saved elsewhere? Your changes here will be lost.";
+
+/// Shown when leaving the editor with unsaved changes
+const UNSAVED_CHANGES_WARNING: &str =
+    "There are unsaved changes in this transcription. Leave anyway and lose them?";
+
+/// Ask for confirmation before leaving the editor while `dirty`
+///
+/// This covers closing or reloading the tab as well as following links (e.g. in the navbar).
+/// Both listeners are removed together with the editor. Links are followed once the user confirms
+/// in a [`ConfirmDialog`], closing the tab is confirmed by the browser.
+#[component]
+fn LeaveGuard(dirty: Signal<bool>) -> impl IntoView {
+    let confirm_open = RwSignal::new(false);
+    // the link the user wants to follow while the dialog is open
+    let pending_link = StoredValue::new_local(None::<web_sys::HtmlElement>);
+    // set once the user agreed to leave, so that they are not asked again on the way out
+    let leave_confirmed = StoredValue::new(false);
+    let _cleanup_unload = use_event_listener(use_window(), beforeunload, move |ev| {
+        if dirty.get_untracked() && !leave_confirmed.get_value() {
+            // browsers show their own generic message instead
+            ev.prevent_default();
+            ev.set_return_value(UNSAVED_CHANGES_WARNING);
+        };
+    });
+    // capture, so that this runs before the router handles the click
+    let _cleanup_click = use_event_listener_with_options(
+        use_document(),
+        click,
+        move |ev| {
+            if !dirty.get_untracked() || leave_confirmed.get_value() || ev.default_prevented() {
+                return;
+            };
+            // modifier clicks open the link elsewhere or save it, the editor stays open
+            if ev.button() != 0 || ev.ctrl_key() || ev.meta_key() || ev.shift_key() || ev.alt_key()
+            {
+                return;
+            };
+            let Some(link) = ev
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|el| el.closest("a[href]").ok().flatten())
+            else {
+                return;
+            };
+            // the editor stays open when the link opens in another tab or is a download
+            if link
+                .get_attribute("target")
+                .is_some_and(|target| target != "_self")
+                || link.has_attribute("download")
+            {
+                return;
+            };
+            ev.prevent_default();
+            ev.stop_propagation();
+            pending_link.set_value(link.dyn_into::<web_sys::HtmlElement>().ok());
+            confirm_open.set(true);
+        },
+        UseEventListenerOptions::default().capture(true),
+    );
+
+    view! {
+        <ConfirmDialog
+            open=confirm_open
+            message=UNSAVED_CHANGES_WARNING
+            confirm_label="Leave"
+            on_confirm=move |()| {
+                if let Some(link) = pending_link.get_value() {
+                    leave_confirmed.set_value(true);
+                    link.click();
+                };
+            }
+            on_cancel=move |()| pending_link.set_value(None)
+        />
+    }
+}
+
+/// Run `attempt` after `delay`, and again after each further `delay` for as long as it returns
+/// true
+fn schedule_autosave(delay: Duration, attempt: impl Fn() -> bool + Clone + 'static) {
+    set_timeout(
+        move || {
+            if attempt() {
+                schedule_autosave(delay, attempt);
+            };
+        },
+        delay,
+    );
+}
+
+/// Shows whether and when the transcription was last saved
+#[component]
+fn SaveIndicator(
+    on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
+    save_state: SaveState,
+) -> impl IntoView {
+    view! {
+        <p class="px-2 text-sm text-slate-400">
+            {move || {
+                if on_save.pending().get() {
+                    return "Saving...".to_string();
+                }
+                let saved = match (on_save.value().get(), save_state.last_saved_at.get()) {
+                    (Some(Err(e)), _) => format!("Saving failed: {e}"),
+                    (_, Some(time)) => format!("Last saved at {time}"),
+                    (_, None) => "Not saved since loading".to_string(),
+                };
+                if save_state.unsaved_changes.get() {
+                    format!("{saved} - unsaved changes")
+                } else {
+                    saved
+                }
+            }}
+        </p>
+    }
+}
+
+/// Links to the previous and next page of the manuscript
+///
+/// Pages are ordered by name, with numbers compared by value (`folio2` before `folio10`).
+#[component]
+fn PageNavigation(
+    adjacent: AdjacentPages,
+    on_navigate: Action<String, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let AdjacentPages { previous, next } = adjacent;
+    let previous = StoredValue::new(previous);
+    let next = StoredValue::new(next);
+    let go_to = move |target: StoredValue<Option<String>>| {
+        if let Some(pagename) = target.get_value() {
+            if !on_navigate.pending().get_untracked() {
+                on_navigate.dispatch(pagename);
+            };
+        };
+    };
+    // <ctrl>-<alt>-P - previous page, <ctrl>-<alt>-N - next page
+    let _cleanup = use_event_listener(use_document(), keydown, move |evt| {
+        if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 80 {
+            evt.prevent_default();
+            go_to(previous);
+        } else if evt.alt_key() && evt.ctrl_key() && evt.key_code() == 78 {
+            evt.prevent_default();
+            go_to(next);
+        }
+    });
+
+    view! {
+        <div class="mx-16 flex justify-between">
+            <button
+                class=DEFAULT_BUTTON_CLASSES
+                class=("opacity-50", move || previous.with_value(Option::is_none))
+                disabled=move || previous.with_value(Option::is_none)
+                on:click=move |_| go_to(previous)
+            >
+                {move || match previous.get_value() {
+                    Some(pagename) => format!("← {pagename}"),
+                    None => "This is the first page".to_string(),
+                }}
+            </button>
+            <p class="flex flex-col justify-center text-red-400">
+                {move || match on_navigate.value().get() {
+                    Some(Err(e)) => e.to_string(),
+                    _ => String::new(),
+                }}
+            </p>
+            <button
+                class=DEFAULT_BUTTON_CLASSES
+                class=("opacity-50", move || next.with_value(Option::is_none))
+                disabled=move || next.with_value(Option::is_none)
+                on:click=move |_| go_to(next)
+            >
+                {move || match next.get_value() {
+                    Some(pagename) => format!("{pagename} →"),
+                    None => "This is the last page".to_string(),
+                }}
+            </button>
+        </div>
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum EditorTabs {
     Block,
@@ -405,36 +1117,70 @@ fn EditorWithTabs(
     default_language: String,
     on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
     on_publish: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
+    msname: String,
     pagename: String,
+    /// First and last verse on the page, to offer seeding anchors for a new transcription
+    verses: Option<(i64, i64)>,
+    /// Save automatically once the blocks have not been edited for this long
+    #[prop(default = DEFAULT_AUTOSAVE_DELAY)]
+    autosave_delay: Duration,
 ) -> impl IntoView {
     let help_active: RwSignal<ShowHelp> = use_context().expect("Root mounts ShowHelp context");
     let tab_active = RwSignal::new(EditorTabs::Block);
 
     let xml_state = RwSignal::new(XmlState::Checked);
+    let save_state = use_autosave(blocks, xml_state, on_save, autosave_delay);
+    // XML that was not applied to the blocks yet would be lost as well
+    let dirty = Signal::derive(move || {
+        save_state.unsaved_changes.get() || !matches!(xml_state.get(), XmlState::Checked)
+    });
+    // successful saves are shown by the save indicator, autosave would make them too noisy here
+    Effect::new(move |_| {
+        if let Some(Err(e)) = on_save.value().get() {
+            push_toast(ToastKind::Error, format!("Saving failed: {e}"));
+        };
+    });
+    Effect::new(move |_| match on_publish.value().get() {
+        Some(Ok(())) => push_toast(ToastKind::Success, "Transcription published."),
+        Some(Err(e)) => push_toast(ToastKind::Error, format!("Publishing failed: {e}")),
+        None => {}
+    });
+    // kept here, so that it survives switching tabs
+    let new_block_language = use_new_block_language(&msname, default_language);
 
     view! {
         <div class="mx-16 my-4 flex flex-col h-full bg-slate-800 relative">
             <HelpOverlay active=help_active />
+            <LeaveGuard dirty=dirty />
             <div class="text-red">
                 <p>
                     {move || match xml_state.get() {
-                        XmlState::Checked | XmlState::Unchecked => Either::Left(()),
-                        XmlState::Err(e) => Either::Right(e),
+                        XmlState::Checked
+                        | XmlState::Unchecked
+                        | XmlState::AwaitingConfirmation(_) => Either::Left(()),
+                        XmlState::Err(e) => Either::Right(e.to_string()),
                     }}
                 </p>
             </div>
-            <TabSwitcher xml_state=xml_state tab_active=tab_active />
+            <TabSwitcher
+                xml_state=xml_state
+                tab_active=tab_active
+                unsaved_changes=save_state.unsaved_changes
+                on_save=on_save
+                blocks=blocks
+            />
+            <SaveIndicator on_save=on_save save_state=save_state />
             {move || {
                 tab_active
                     .with(|tab| match tab {
                         EditorTabs::Block => {
-                            let lang_cloned = default_language.clone();
                             EitherOf3::A(
                                 view! {
                                     <Editor
                                         blocks=blocks
-                                        default_language=lang_cloned
+                                        language=new_block_language
                                         on_save=on_save
+                                        verses=verses
                                     />
                                 },
                             )
@@ -471,8 +1217,20 @@ fn EditorWithTabs(
     }
 }
 
+/// Asked before opening the XML editor with unsaved changes
+const SAVE_BEFORE_XML_QUESTION: &str =
+    "There are unsaved changes in this transcription. Save them and switch to the XML editor?";
+
 #[component]
-fn TabSwitcher(xml_state: RwSignal<XmlState>, tab_active: RwSignal<EditorTabs>) -> impl IntoView {
+fn TabSwitcher(
+    xml_state: RwSignal<XmlState>,
+    tab_active: RwSignal<EditorTabs>,
+    /// Unsaved changes are saved before the XML editor is opened
+    unsaved_changes: ReadSignal<bool>,
+    on_save: Action<Vec<EditorBlock>, Result<(), ServerFnError>>,
+    blocks: RwSignal<Vec<EditorBlock>>,
+) -> impl IntoView {
+    let save_confirm_open = RwSignal::new(false);
     view! {
         <div id="editor-tab-header" class="mb-4 p-2 pb-0 border-b border-slate-600">
             <button
@@ -481,10 +1239,10 @@ fn TabSwitcher(xml_state: RwSignal<XmlState>, tab_active: RwSignal<EditorTabs>)
                         XmlState::Checked => {
                             tab_active.set(EditorTabs::Block);
                         }
-                        XmlState::Err(_) => {}
+                        XmlState::Err(_) | XmlState::AwaitingConfirmation(_) => {}
                         XmlState::Unchecked => {
                             xml_state
-                                .set(XmlState::Err("You need to check the XML first.".to_string()));
+                                .set(XmlState::Err("You need to check the XML first.".to_string().into()));
                         }
                     }
                 }
@@ -499,10 +1257,10 @@ fn TabSwitcher(xml_state: RwSignal<XmlState>, tab_active: RwSignal<EditorTabs>)
                         XmlState::Checked => {
                             tab_active.set(EditorTabs::Render);
                         }
-                        XmlState::Err(_) => {}
+                        XmlState::Err(_) | XmlState::AwaitingConfirmation(_) => {}
                         XmlState::Unchecked => {
                             xml_state
-                                .set(XmlState::Err("You need to check the XML first.".to_string()));
+                                .set(XmlState::Err("You need to check the XML first.".to_string().into()));
                         }
                     }
                 }
@@ -513,13 +1271,29 @@ fn TabSwitcher(xml_state: RwSignal<XmlState>, tab_active: RwSignal<EditorTabs>)
             </button>
             <button
                 on:click=move |_| {
-                    tab_active.set(EditorTabs::Xml);
+                    if tab_active.get() == EditorTabs::Xml {
+                        return;
+                    }
+                    if unsaved_changes.get_untracked() {
+                        save_confirm_open.set(true);
+                    } else {
+                        tab_active.set(EditorTabs::Xml);
+                    }
                 }
                 class="mx-2 mb-0 p-2 hover:bg-slate-500 rounded-t-lg"
                 class=("bg-sky-600/30", move || tab_active.get() == EditorTabs::Xml)
             >
                 XML
             </button>
+            <ConfirmDialog
+                open=save_confirm_open
+                message=SAVE_BEFORE_XML_QUESTION
+                confirm_label="Save and switch"
+                on_confirm=move |()| {
+                    on_save.dispatch(blocks.get_untracked());
+                    tab_active.set(EditorTabs::Xml);
+                }
+            />
         </div>
     }
 }
@@ -567,6 +1341,15 @@ fn PublishButton(
                                 },
                             )
                         }
+                        XmlState::AwaitingConfirmation(_) => {
+                            Either::Right(
+                                view! {
+                                    <span class="w-96 text-2xl m-2 rounded-2xl bg-slate-600 p-2 text-center font-bold text-slate-50">
+                                        "Apply or discard your XML changes before publishing!"
+                                    </span>
+                                },
+                            )
+                        }
                     })
             }}
         </div>
@@ -0,0 +1,118 @@
+//! Claims a page while it is open in the editor, see `[locking]` in the config
+
+use leptos::prelude::*;
+use std::time::Duration;
+
+/// Claim the page for the current user, who has to be allowed to transcribe
+///
+/// Returns how often (in seconds) the claim has to be renewed, or None if this project does not
+/// lock pages.
+#[server]
+pub async fn lock_page(msname: String, pagename: String) -> Result<Option<u64>, ServerFnError> {
+    let (config, user) = super::current_transcriber().await?;
+    if !config.locking.enabled {
+        return Ok(None);
+    };
+    critic_server::db::acquire_lock(
+        &config.db,
+        &msname,
+        &pagename,
+        &user.username,
+        config.locking.ttl_seconds,
+    )
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+    // renew well before the claim runs out, so one lost request does not lose it
+    Ok(Some((config.locking.ttl_seconds / 3).max(1)))
+}
+
+/// Give up the claim on the page, if the current user holds it
+#[server]
+pub async fn unlock_page(msname: String, pagename: String) -> Result<(), ServerFnError> {
+    let (config, _, user) = crate::app::me::logged_in().await?;
+    if !config.locking.enabled {
+        return Ok(());
+    };
+    critic_server::db::release_lock(&config.db, &msname, &pagename, &user.username)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    Ok(())
+}
+
+/// Claims the page while this component is mounted
+///
+/// Shows a warning if someone else holds the claim. Saving is refused on the server in that case,
+/// so this is only there to tell the user early.
+#[component]
+pub fn PageLock(msname: String, pagename: String) -> impl IntoView {
+    let claim_error = RwSignal::new(None::<String>);
+    let heartbeat = StoredValue::new(None::<IntervalHandle>);
+    // the first claim may still be on its way when the editor is closed
+    let unmounted = StoredValue::new(false);
+
+    // effects only run in the browser
+    Effect::new({
+        let msname = msname.clone();
+        let pagename = pagename.clone();
+        move |_| {
+            let msname = msname.clone();
+            let pagename = pagename.clone();
+            leptos::task::spawn_local(async move {
+                let interval = match lock_page(msname.clone(), pagename.clone()).await {
+                    Ok(Some(x)) => x,
+                    // this project does not lock pages
+                    Ok(None) => return,
+                    Err(e) => {
+                        claim_error.set(Some(e.to_string()));
+                        return;
+                    }
+                };
+                // the stored values are disposed of once the component is gone
+                if unmounted.try_get_value().unwrap_or(true) {
+                    let _ = unlock_page(msname, pagename).await;
+                    return;
+                };
+                claim_error.set(None);
+                let handle = set_interval_with_handle(
+                    move || {
+                        let msname = msname.clone();
+                        let pagename = pagename.clone();
+                        leptos::task::spawn_local(async move {
+                            claim_error.set(
+                                lock_page(msname, pagename)
+                                    .await
+                                    .err()
+                                    .map(|e| e.to_string()),
+                            );
+                        });
+                    },
+                    Duration::from_secs(interval),
+                );
+                heartbeat.set_value(handle.ok());
+            });
+        }
+    });
+    on_cleanup(move || {
+        unmounted.set_value(true);
+        if let Some(handle) = heartbeat.try_get_value().flatten() {
+            handle.clear();
+            leptos::task::spawn_local(async move {
+                let _ = unlock_page(msname, pagename).await;
+            });
+        };
+    });
+
+    view! {
+        {move || {
+            claim_error
+                .get()
+                .map(|e| {
+                    view! {
+                        <div class="mx-16 border border-slate-500 bg-red-700/40 p-2">
+                            <p>{e}" You will not be able to save your changes."</p>
+                        </div>
+                    }
+                })
+        }}
+    }
+}
@@ -1,2 +1,59 @@
+#[cfg(feature = "ssr")]
+use leptos::prelude::ServerFnError;
+
 pub mod editor;
+pub mod lock;
+pub mod presence;
 pub mod todo;
+
+/// Get the config and the user for this request if the user may transcribe
+#[cfg(feature = "ssr")]
+pub(crate) async fn current_transcriber() -> Result<
+    (
+        std::sync::Arc<critic_server::config::Config>,
+        critic_server::auth::AuthenticatedUser,
+    ),
+    crate::app::error::AppError,
+> {
+    let (config, _, user) = crate::app::me::logged_in().await?;
+    authorize_transcriber(config.clone(), &user).await?;
+    Ok((config, user))
+}
+
+/// Make sure no one else holds the claim on this page, if this project locks pages
+///
+/// For writes that do not come from the open editor. Saving from the editor renews the claim
+/// instead.
+#[cfg(feature = "ssr")]
+pub(crate) async fn check_page_lock(
+    config: &critic_server::config::Config,
+    msname: &str,
+    pagename: &str,
+    username: &str,
+) -> Result<(), ServerFnError> {
+    if config.locking.enabled {
+        critic_server::db::check_lock(&config.db, msname, pagename, username).await?;
+    };
+    Ok(())
+}
+
+/// Make sure `user` may start, save and publish transcriptions
+#[cfg(feature = "ssr")]
+pub(crate) async fn authorize_transcriber(
+    config: std::sync::Arc<critic_server::config::Config>,
+    user: &critic_server::auth::AuthenticatedUser,
+) -> Result<(), crate::app::error::AppError> {
+    use crate::app::error::AppError;
+    use critic_server::auth::{user_may, Action};
+
+    match user_may(config, user, Action::Transcribe).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(AppError::Unauthorized(
+            "Not allowed to transcribe.".to_string(),
+        )),
+        Err(e) => {
+            tracing::warn!("Unable to get user membership for {}: {e}", user.username);
+            Err(AppError::Internal(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Shows who else has a page open in the editor, see `critic_server::presence`
+
+use critic_shared::urls::PRESENCE_BASE_URL;
+use leptos::prelude::*;
+use send_wrapper::SendWrapper;
+use web_sys::{
+    js_sys,
+    wasm_bindgen::{closure::Closure, JsCast},
+    MessageEvent, WebSocket,
+};
+
+/// The websocket url for this page, relative to the current location
+fn presence_url(msname: &str, pagename: &str) -> Option<String> {
+    let location = window().location();
+    let protocol = if location.protocol().ok()? == "https:" {
+        "wss"
+    } else {
+        "ws"
+    };
+    // names may contain spaces, '#', '?' and the like
+    let msname = String::from(js_sys::encode_uri_component(msname));
+    let pagename = String::from(js_sys::encode_uri_component(pagename));
+    Some(format!(
+        "{protocol}://{}{PRESENCE_BASE_URL}/{msname}/{pagename}",
+        location.host().ok()?
+    ))
+}
+
+/// The usernames in a message from the server (a JSON list)
+fn parse_users(data: &str) -> Vec<String> {
+    js_sys::JSON::parse(data)
+        .map(|users| {
+            js_sys::Array::from(&users)
+                .iter()
+                .filter_map(|user| user.as_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn presence_message(others: &[String]) -> String {
+    match others {
+        [] => String::new(),
+        [only] => format!("{only} is also editing this page."),
+        [first @ .., last] => format!(
+            "{} and {last} are also editing this page.",
+            first.join(", ")
+        ),
+    }
+}
+
+/// Warns when other users have the same page open
+///
+/// This is only a hint: if the connection breaks, the warning simply disappears.
+#[component]
+pub fn PagePresence(msname: String, pagename: String) -> impl IntoView {
+    let others = RwSignal::new(Vec::<String>::new());
+    // effects only run in the browser
+    Effect::new(move |_| {
+        let Some(url) = presence_url(&msname, &pagename) else {
+            return;
+        };
+        let Ok(socket) = WebSocket::new(&url) else {
+            return;
+        };
+        let on_message = Closure::<dyn Fn(MessageEvent)>::new(move |ev: MessageEvent| {
+            if let Some(data) = ev.data().as_string() {
+                others.set(parse_users(&data));
+            };
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        // the closure has to live as long as the socket
+        let connection = SendWrapper::new((socket, on_message));
+        on_cleanup(move || {
+            let (socket, _on_message) = connection.take();
+            socket.set_onmessage(None);
+            let _ = socket.close();
+        });
+    });
+
+    view! {
+        <Show when=move || !others.read().is_empty()>
+            <div class="mx-16 border border-slate-500 bg-amber-700/40 p-2">
+                <p>
+                    {move || presence_message(&others.read())}
+                    " Everyone works on their own transcription, so nobody overwrites anyone else's work."
+                </p>
+            </div>
+        </Show>
+    }
+}
@@ -1,6 +1,9 @@
 //! Components and server functions to show transcripitions that are todo
 
-use critic_shared::{OwnStatus, PageTodo, PublishedTranscriptions};
+use critic_shared::{
+    urls::{EXPORT_BASE_URL, TODO_CSV_EXPORT_API_ENDPOINT},
+    OwnStatus, PageTodo, PublishedTranscriptions, TodoOrder,
+};
 use leptos::{either::Either, ev::keydown, prelude::*};
 use leptos_router::hooks::query_signal;
 use leptos_use::use_event_listener;
@@ -10,6 +13,7 @@ use crate::app::TopLevelPosition;
 #[server]
 pub async fn get_pages_by_query(
     query: String,
+    order: TodoOrder,
     page: Option<i32>,
 ) -> Result<Vec<PageTodo>, ServerFnError> {
     use critic_server::auth::AuthSession;
@@ -33,6 +37,7 @@ pub async fn get_pages_by_query(
         &config.db,
         &query,
         &user.username,
+        order,
         page.unwrap_or_default(),
     )
     .await;
@@ -45,6 +50,48 @@ pub async fn get_pages_by_query(
     }
 }
 
+/// Start a transcription of this page for the current user without opening the editor
+///
+/// Nothing is written to disk yet, the page only shows up as started. Pages that are already
+/// published by two other users or being reconciled cannot be started.
+#[server]
+pub async fn start_transcription(msname: String, pagename: String) -> Result<(), ServerFnError> {
+    let (config, user) = super::current_transcriber().await?;
+
+    if !critic_server::db::page_exists(&config.db, &msname, &pagename).await? {
+        return Err(ServerFnError::new(format!(
+            "The page {pagename} does not exist in {msname}."
+        )));
+    };
+    let transcribers =
+        critic_server::db::get_page_transcribers(&config.db, &msname, &pagename).await?;
+    if transcribers
+        .iter()
+        .any(|t| t.username == user.username && t.started)
+    {
+        // already started, nothing to do
+        return Ok(());
+    };
+    if let Some(reconciler) = transcribers.iter().find(|t| t.reconciled) {
+        return Err(ServerFnError::new(format!(
+            "This page is already being reconciled by {}.",
+            reconciler.username
+        )));
+    };
+    if transcribers.iter().filter(|t| t.published).count() >= 2 {
+        return Err(ServerFnError::new(
+            "This page already has two published transcriptions.",
+        ));
+    };
+    super::check_page_lock(&config, &msname, &pagename, &user.username).await?;
+    critic_server::db::add_transcription(&config.db, &msname, &pagename, &user.username).await?;
+    tracing::info!(
+        "User {} started a transcription of {pagename} in MS {msname} from the todo list.",
+        user.username
+    );
+    Ok(())
+}
+
 #[component]
 pub fn TranscribeTodoList() -> impl IntoView {
     let set_top_level_pos =
@@ -53,6 +100,14 @@ pub fn TranscribeTodoList() -> impl IntoView {
 
     let (query, set_query) = query_signal::<String>("psq");
     let (page, set_page) = query_signal::<i32>("page");
+    // unknown keys fall back to the default order
+    let (order_key, set_order_key) = query_signal::<String>("order");
+    let order = move || {
+        order_key
+            .get()
+            .and_then(|key| TodoOrder::from_key(&key))
+            .unwrap_or_default()
+    };
 
     // Set Query when user presses enter while focused on the search input
     let search_node_ref = NodeRef::<leptos::html::Input>::new();
@@ -64,13 +119,38 @@ pub fn TranscribeTodoList() -> impl IntoView {
     });
 
     let pages = Resource::new(
-        move || (query.get(), page.get()),
-        async |(new_query, new_page)| {
-            get_pages_by_query(new_query.unwrap_or_default(), new_page).await
+        move || (query.get(), order(), page.get()),
+        async |(new_query, new_order, new_page)| {
+            get_pages_by_query(new_query.unwrap_or_default(), new_order, new_page).await
         },
     );
     let todos_rendered = move || {
-        pages.get().map(|pages_res| pages_res.map(|pages_ok| pages_ok.into_iter().map(|page_todo| view! {
+        pages.get().map(|pages_res| pages_res.map(|pages_ok| pages_ok.into_iter().map(|page_todo| {
+        // set right away when starting from this list, the refetch then confirms it
+        let own_status = RwSignal::new(page_todo.this_user_status);
+        let start_error = RwSignal::new(None::<String>);
+        let start = {
+            let msname = page_todo.manuscript_name.clone();
+            let pagename = page_todo.page_name.clone();
+            move |ev: leptos::ev::MouseEvent| {
+                // the whole row links to the editor
+                ev.prevent_default();
+                ev.stop_propagation();
+                own_status.set(OwnStatus::Started);
+                let msname = msname.clone();
+                let pagename = pagename.clone();
+                leptos::task::spawn_local(async move {
+                    match start_transcription(msname, pagename).await {
+                        Ok(()) => pages.refetch(),
+                        Err(e) => {
+                            own_status.set(OwnStatus::None);
+                            start_error.set(Some(e.to_string()));
+                        }
+                    };
+                });
+            }
+        };
+        view! {
             <div class="table-row-group">
                 <a
                     href=format!(
@@ -138,23 +218,41 @@ pub fn TranscribeTodoList() -> impl IntoView {
                                             }
                                         }}
                                     </div>
-                                    {match page_todo.this_user_status {
-                                        OwnStatus::None => ().into_any(),
-                                        OwnStatus::Started => {
-                                            view! { <div>You have started this page.</div> }.into_any()
-                                        }
-                                        OwnStatus::Published => {
-                                            view! { <div>You have published this page.</div> }
-                                                .into_any()
-                                        }
-                                    }}
                                 }
                                     .into_any()
                             }
                         }}
+                        {move || match own_status.get() {
+                            OwnStatus::None => ().into_any(),
+                            OwnStatus::Started => {
+                                view! { <div>You have started this page.</div> }.into_any()
+                            }
+                            OwnStatus::Published => {
+                                view! { <div>You have published this page.</div> }.into_any()
+                            }
+                        }}
+                        {page_todo
+                            .locked_by
+                            .clone()
+                            .map(|username| {
+                                view! { <div class="italic">"Claimed by "{username}</div> }
+                            })}
+                        {move || {
+                            start_error
+                                .get()
+                                .map(|e| view! { <div class="bg-red-200 text-black">{e}</div> })
+                        }}
+                    </div>
+                    <div class="table-cell p-2">
+                        <Show when=move || own_status.get() == OwnStatus::None>
+                            <button class="rounded-xl p-2 text-sm hover:bg-sky-600" on:click=start.clone()>
+                                "Start"
+                            </button>
+                        </Show>
                     </div>
                 </a>
             </div>
+        }
         }).collect_view()))
     };
 
@@ -207,6 +305,41 @@ pub fn TranscribeTodoList() -> impl IntoView {
                     </svg>
                 </div>
             </div>
+            <div class="flex flex-row justify-center gap-2 text-sm">
+                <label for="todo-order">"Sort by"</label>
+                <select
+                    id="todo-order"
+                    class="border border-slate-500 rounded-md"
+                    on:change:target=move |ev| {
+                        let new_order = TodoOrder::from_key(&ev.target().value()).unwrap_or_default();
+                        set_order_key
+                            .set((new_order != TodoOrder::default()).then(|| new_order.key().to_string()));
+                        // the current result page means nothing in another order
+                        set_page.set(None);
+                    }
+                >
+                    {TodoOrder::ALL
+                        .map(|option| {
+                            view! {
+                                <option value=option.key() selected=move || order() == option>
+                                    {option.label()}
+                                </option>
+                            }
+                        })}
+                </select>
+            </div>
+            // all matching pages, not just this one page of results
+            <form
+                class="flex flex-row justify-center"
+                method="get"
+                action=format!("{EXPORT_BASE_URL}{TODO_CSV_EXPORT_API_ENDPOINT}")
+            >
+                <input type="hidden" name="query" prop:value=move || query.get().unwrap_or_default() />
+                <input type="hidden" name="order" prop:value=move || order().key() />
+                <button type="submit" class="rounded-xl p-2 text-sm hover:bg-sky-600">
+                    "Download as CSV"
+                </button>
+            </form>
             <div class="mt-8 flex min-h-24 grow flex-row justify-center overflow-y-auto mb-10 no-scrollbar">
                 <div id="page-listing" class="text-md table w-4/5">
                     <ErrorBoundary fallback=|errors| {
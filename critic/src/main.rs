@@ -2,15 +2,30 @@
 
 #![recursion_limit = "256"]
 
+// include tests
+#[cfg(all(test, feature = "ssr"))]
+mod test;
+
 #[cfg(feature = "ssr")]
 async fn shutdown_signal(
     handle: axum_server::Handle,
     mut watcher: tokio::sync::watch::Receiver<critic_server::signal_handler::InShutdown>,
+    grace_period: std::time::Duration,
 ) {
     tokio::select! {
         _ = watcher.changed() => {
+            // let running uploads finish first, so no page is cut off mid-write
+            let start = std::time::Instant::now();
+            let remaining_uploads = critic_server::upload::drain_uploads(grace_period).await;
+            if remaining_uploads > 0 {
+                tracing::warn!("Shutting down with {remaining_uploads} uploads still running.");
+            };
             tracing::debug!("Shutting down web server now.");
-            handle.graceful_shutdown(Some(std::time::Duration::from_secs(5)));
+            handle.graceful_shutdown(Some(
+                grace_period
+                    .saturating_sub(start.elapsed())
+                    .max(std::time::Duration::from_secs(1)),
+            ));
         }
     }
 }
@@ -20,6 +35,7 @@ pub async fn run_web_server(
     config: std::sync::Arc<critic_server::config::Config>,
     watcher: tokio::sync::watch::Receiver<critic_server::signal_handler::InShutdown>,
     shutdown_tx: tokio::sync::watch::Sender<critic_server::signal_handler::InShutdown>,
+    metrics_handle: critic_server::monitoring::PrometheusHandle,
 ) {
     // Generate the list of routes in your Leptos App
 
@@ -31,9 +47,14 @@ pub async fn run_web_server(
     };
     use critic::app::*;
     use critic_server::{
-        auth::GithubOauthBackend, signal_handler::InShutdown, upload::upload_router,
+        api::api_router, auth::OauthBackend, export::export_router, health::health_router,
+        monitoring::metrics_router, presence::presence_router, public_view::public_view_router,
+        request_id::with_request_ids, signal_handler::InShutdown, upload::upload_router,
+    };
+    use critic_shared::urls::{
+        EXPORT_BASE_URL, JSON_API_BASE_URL, PRESENCE_BASE_URL, PUBLIC_VIEW_BASE_URL,
+        STATIC_BASE_URL, UPLOAD_BASE_URL,
     };
-    use critic_shared::urls::{STATIC_BASE_URL, UPLOAD_BASE_URL};
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
     use time::Duration;
@@ -65,14 +86,19 @@ pub async fn run_web_server(
     // create the auth layer on top of our application core
     let session_store = MemoryStore::default();
     let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
-        .with_same_site(axum_login::tower_sessions::cookie::SameSite::Lax)
-        .with_expiry(Expiry::OnInactivity(Duration::days(1)));
-    let backend = GithubOauthBackend::new(config.clone());
+        .with_secure(config.session.cookie_secure)
+        .with_same_site(config.session.same_site.into())
+        .with_expiry(Expiry::OnInactivity(Duration::days(
+            config.session.session_expiry_days,
+        )));
+    let backend = OauthBackend::new(config.clone());
     let auth_layer = AuthManagerLayerBuilder::new(backend, session_layer).build();
 
-    let static_router = match critic_server::static_files::image_dir_router(&config.data_directory)
-    {
+    let static_router = match critic_server::static_files::image_dir_router(
+        &config.data_directory,
+        config.image_cache_max_age_seconds,
+        config.require_login_for_images,
+    ) {
         Ok(x) => x,
         Err(e) => {
             tracing::error!("Cannot recover when data directory layout is wrong: {e}.");
@@ -83,14 +109,40 @@ pub async fn run_web_server(
     };
     let app = app_core
         .nest(UPLOAD_BASE_URL, upload_router())
-        .route_layer(login_required!(GithubOauthBackend, login_url = "/login"))
+        .nest(EXPORT_BASE_URL, export_router())
+        .nest(JSON_API_BASE_URL, api_router())
+        .nest(PRESENCE_BASE_URL, presence_router())
+        .route_layer(login_required!(OauthBackend, login_url = "/login"))
         .merge(critic_server::auth::backend::auth_router())
-        .layer(auth_layer)
+        // images check the login themselves, depending on the config
         .nest(STATIC_BASE_URL, static_router)
+        // public manuscripts only, checked by the handlers
+        .nest(PUBLIC_VIEW_BASE_URL, public_view_router())
+        .layer(auth_layer)
+        // unauthenticated, for reverse proxies and orchestrators
+        .merge(health_router())
         .layer(Extension(config.clone()));
+    let app = with_request_ids(app);
 
     let shutdown_handle = axum_server::Handle::new();
-    let shutdown_future = shutdown_signal(shutdown_handle.clone(), watcher.clone());
+    let shutdown_future = shutdown_signal(
+        shutdown_handle.clone(),
+        watcher.clone(),
+        std::time::Duration::from_secs(config.shutdown_grace_seconds),
+    );
+
+    // serve the metrics separately, so they need not be publicly reachable
+    let metrics_server_future = axum_server::bind(config.metrics_addr)
+        .handle(shutdown_handle.clone())
+        .serve(metrics_router(metrics_handle).into_make_service());
+    tracing::info!("serving metrics on http://{}", config.metrics_addr);
+    let metrics_shutdown_tx = shutdown_tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = metrics_server_future.await {
+            tracing::error!("Failure while serving metrics: {e}. SHUTTING DOWN NOW.");
+            metrics_shutdown_tx.send_replace(InShutdown::Yes);
+        };
+    });
 
     // serve the main app on HTTP
     let web_server_future = axum_server::bind(config.leptos_options.site_addr)
@@ -110,15 +162,150 @@ pub async fn run_web_server(
     };
 }
 
+/// The value of `--config <path>` (or `--config=<path>`) from the command line, if given
+#[cfg(feature = "ssr")]
+fn config_path_from_args() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next();
+        };
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        };
+    }
+    None
+}
+
+/// How to call critic
+#[cfg(feature = "ssr")]
+const USAGE: &str = "Usage: critic [--config <path>] [COMMAND]
+
+Without a command, critic runs the web server.
+
+Commands:
+  --add-manuscript <name> [--lang <lang>]  add a manuscript
+  --list-manuscripts                       list all manuscripts with their language
+  --retry-minification                     let the server try again to minify pages it failed on
+  --help                                   show this message";
+
+/// What critic should do, chosen on the command line
+#[cfg(feature = "ssr")]
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    /// Run the web server and all services (the default)
+    Serve,
+    AddManuscript {
+        msname: String,
+        lang: Option<String>,
+    },
+    ListManuscripts,
+    RetryMinification,
+    Help,
+}
+
+/// The command given in `args` (without the program name)
+///
+/// `--config` is skipped here, see [`config_path_from_args`]. At most one command may be given.
+#[cfg(feature = "ssr")]
+fn command_from_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut command = Command::Serve;
+    let mut lang = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let next_command = match arg.as_str() {
+            "--config" => {
+                args.next();
+                continue;
+            }
+            x if x.starts_with("--config=") => continue,
+            "--lang" => {
+                lang = Some(args.next().ok_or("--lang needs a language")?);
+                continue;
+            }
+            "--add-manuscript" => Command::AddManuscript {
+                msname: args
+                    .next()
+                    .ok_or("--add-manuscript needs the name of the manuscript")?,
+                lang: None,
+            },
+            "--list-manuscripts" => Command::ListManuscripts,
+            "--retry-minification" => Command::RetryMinification,
+            "--help" | "-h" => Command::Help,
+            x => return Err(format!("Unknown argument: {x}")),
+        };
+        if command != Command::Serve {
+            return Err("Only one command can be given at a time".to_string());
+        };
+        command = next_command;
+    }
+    match (command, lang) {
+        (Command::AddManuscript { msname, .. }, lang) => {
+            Ok(Command::AddManuscript { msname, lang })
+        }
+        (_, Some(_)) => Err("--lang can only be used with --add-manuscript".to_string()),
+        (command, None) => Ok(command),
+    }
+}
+
+/// Run a one-off maintenance command instead of the server
+///
+/// Results are printed to stdout.
+#[cfg(feature = "ssr")]
+async fn run_command(
+    config: &critic_server::config::Config,
+    command: Command,
+) -> Result<(), critic_server::db::DBError> {
+    match command {
+        Command::Serve | Command::Help => {}
+        Command::AddManuscript { msname, lang } => {
+            critic_server::db::add_manuscript(&config.db, &msname, lang.as_deref()).await?;
+            critic_server::audit::record_audit(
+                &config.db,
+                "cli",
+                "add_manuscript",
+                &msname,
+                serde_json::json!({ "lang": lang }),
+            )
+            .await;
+            println!("Added manuscript {msname}");
+        }
+        Command::ListManuscripts => {
+            for ms in critic_server::db::get_manuscripts(&config.db).await? {
+                println!("{}\t{}", ms.title, ms.lang);
+            }
+        }
+        Command::RetryMinification => {
+            let count = critic_server::db::retry_failed_minifications(&config.db).await?;
+            println!("The server will try to minify {count} pages again");
+        }
+    };
+    Ok(())
+}
+
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
     use std::sync::Arc;
 
-    use critic_server::{minification::run_minification, signal_handler::InShutdown};
+    use critic_server::{
+        cleanup::run_cleanup, config::LogFormat, history::run_history,
+        minification::run_minification, monitoring::run_metrics_upkeep, signal_handler::InShutdown,
+    };
     use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
 
-    let config = match critic_server::config::Config::try_create().await {
+    let command = match command_from_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    let config = match critic_server::config::Config::try_create(config_path_from_args()).await {
         Ok(x) => x,
         Err(e) => {
             panic!("Error reading config: {e}.");
@@ -132,23 +319,40 @@ async fn main() {
 
     critic_server::db::migrate(&config_arc.db).await;
 
+    if command != Command::Serve {
+        if let Err(e) = run_command(&config_arc, command).await {
+            eprintln!("{e}");
+            std::process::exit(1);
+        };
+        return;
+    };
+
+    let metrics_handle = match critic_server::monitoring::install_recorder() {
+        Ok(x) => x,
+        Err(e) => {
+            panic!("Error installing the metrics recorder: {e}.");
+        }
+    };
+
     let my_crate_filter = EnvFilter::new("critic");
-    let subscriber = tracing_subscriber::registry().with(my_crate_filter).with(
-        tracing_subscriber::fmt::layer()
+    let fmt_layer = match config_arc.log_format {
+        LogFormat::Compact => tracing_subscriber::fmt::layer()
             .compact()
             .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
             .with_line_number(true)
-            .with_filter(config_arc.log_level),
-    );
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+            .with_line_number(true)
+            .boxed(),
+    };
+    let subscriber = tracing_subscriber::registry()
+        .with(my_crate_filter)
+        .with(fmt_layer.with_filter(config_arc.log_level));
     tracing::subscriber::set_global_default(subscriber).expect("static tracing config");
     tracing::debug!("Tracing enabled.");
 
-    // setup global rayon threadpool
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(config_arc.worker_threads.into())
-        .build_global()
-        .expect("First threadpool initialization.");
-
     // cancellation channel
     let (tx, rx) = tokio::sync::watch::channel(InShutdown::No);
     // start the Signal handler
@@ -160,12 +364,24 @@ async fn main() {
         config_arc.clone(),
         tx.subscribe(),
         tx.clone(),
+        metrics_handle.clone(),
     ));
-    let minification_service = tokio::task::spawn(run_minification(config_arc, tx.subscribe()));
+    let minification_service =
+        tokio::task::spawn(run_minification(config_arc.clone(), tx.subscribe()));
+    let history_service = tokio::task::spawn(run_history(config_arc.clone(), tx.subscribe()));
+    let cleanup_service = tokio::task::spawn(run_cleanup(config_arc, tx.subscribe()));
+    let metrics_upkeep_service =
+        tokio::task::spawn(run_metrics_upkeep(metrics_handle, tx.subscribe()));
 
     // Join the different services
-    let (signal_res, web_res, minification_res) =
-        tokio::join!(signal_handle, web_server, minification_service);
+    let (signal_res, web_res, minification_res, history_res, cleanup_res, metrics_upkeep_res) = tokio::join!(
+        signal_handle,
+        web_server,
+        minification_service,
+        history_service,
+        cleanup_service,
+        metrics_upkeep_service
+    );
     match signal_res {
         Ok(Ok(())) => {}
         Ok(Err(e)) => {
@@ -181,6 +397,15 @@ async fn main() {
     if let Err(e) = minification_res {
         tracing::error!("Error joining the minificaiton service: {e}");
     };
+    if let Err(e) = history_res {
+        tracing::error!("Error joining the transcription history service: {e}");
+    };
+    if let Err(e) = cleanup_res {
+        tracing::error!("Error joining the cleanup service: {e}");
+    };
+    if let Err(e) = metrics_upkeep_res {
+        tracing::error!("Error joining the metrics upkeep service: {e}");
+    };
 }
 
 #[cfg(not(feature = "ssr"))]
@@ -0,0 +1,70 @@
+use super::{command_from_args, Command};
+
+/// Parse `args` like the command line (without the program name)
+fn parse(args: &[&str]) -> Result<Command, String> {
+    command_from_args(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn serve_is_the_default() {
+    assert_eq!(parse(&[]), Ok(Command::Serve));
+    assert_eq!(parse(&["--config", "critic.toml"]), Ok(Command::Serve));
+    assert_eq!(parse(&["--config=critic.toml"]), Ok(Command::Serve));
+}
+
+#[test]
+fn every_command_is_parsed() {
+    assert_eq!(
+        parse(&["--add-manuscript", "ms"]),
+        Ok(Command::AddManuscript {
+            msname: "ms".to_string(),
+            lang: None
+        })
+    );
+    assert_eq!(
+        parse(&[
+            "--lang",
+            "grc",
+            "--add-manuscript",
+            "ms",
+            "--config",
+            "critic.toml"
+        ]),
+        Ok(Command::AddManuscript {
+            msname: "ms".to_string(),
+            lang: Some("grc".to_string())
+        })
+    );
+    assert_eq!(parse(&["--list-manuscripts"]), Ok(Command::ListManuscripts));
+    assert_eq!(
+        parse(&["--retry-minification"]),
+        Ok(Command::RetryMinification)
+    );
+    assert_eq!(parse(&["--help"]), Ok(Command::Help));
+    assert_eq!(parse(&["-h"]), Ok(Command::Help));
+}
+
+#[test]
+fn unknown_arguments_are_rejected() {
+    assert_eq!(
+        parse(&["--frobnicate"]),
+        Err("Unknown argument: --frobnicate".to_string())
+    );
+    // the config path is skipped, anything after it is not
+    assert!(parse(&["--config", "critic.toml", "serve"]).is_err());
+    assert!(parse(&["--list-manuscripts", "--help"]).is_err());
+    assert!(parse(&["--lang", "grc"]).is_err());
+    assert!(parse(&["--lang", "grc", "--list-manuscripts"]).is_err());
+}
+
+#[test]
+fn missing_values_are_rejected() {
+    assert_eq!(
+        parse(&["--add-manuscript"]),
+        Err("--add-manuscript needs the name of the manuscript".to_string())
+    );
+    assert_eq!(
+        parse(&["--add-manuscript", "ms", "--lang"]),
+        Err("--lang needs a language".to_string())
+    );
+}